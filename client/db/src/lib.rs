@@ -24,9 +24,10 @@ use codec::{Decode, Encode};
 use fp_storage::PALLET_ETHEREUM_SCHEMA_CACHE;
 use pallet_ethereum::EthereumStorageSchema;
 use parking_lot::Mutex;
-use sp_core::H256;
+use sp_core::{H160, H256};
 use sp_runtime::traits::Block as BlockT;
 use std::{
+	collections::{BTreeMap, VecDeque},
 	marker::PhantomData,
 	path::{Path, PathBuf},
 	sync::Arc,
@@ -64,16 +65,21 @@ impl DatabaseSettingsSrc {
 }
 
 pub(crate) mod columns {
-	pub const NUM_COLUMNS: u32 = 4;
+	pub const NUM_COLUMNS: u32 = 5;
 
 	pub const META: u32 = 0;
 	pub const BLOCK_MAPPING: u32 = 1;
 	pub const TRANSACTION_MAPPING: u32 = 2;
 	pub const SYNCED_MAPPING: u32 = 3;
+	pub const ADDRESS_MAPPING: u32 = 4;
 }
 
+/// Number of blocks grouped under a single key of the address index.
+const ADDRESS_BUCKET_SIZE: u32 = 256;
+
 pub(crate) mod static_keys {
 	pub const CURRENT_SYNCING_TIPS: &[u8] = b"CURRENT_SYNCING_TIPS";
+	pub const ADDRESS_INDEX_BACKFILL: &[u8] = b"ADDRESS_INDEX_BACKFILL";
 }
 
 pub struct Backend<Block: BlockT> {
@@ -141,6 +147,39 @@ impl<Block: BlockT> MetaDb<Block> {
 		Ok(())
 	}
 
+	pub fn address_index_backfill(
+		&self,
+	) -> Result<Option<AddressIndexBackfill<Block::Hash>>, String> {
+		match self.db.get(
+			crate::columns::META,
+			&crate::static_keys::ADDRESS_INDEX_BACKFILL,
+		) {
+			Some(raw) => Ok(Some(
+				Decode::decode(&mut &raw[..]).map_err(|e| format!("{:?}", e))?,
+			)),
+			None => Ok(None),
+		}
+	}
+
+	pub fn write_address_index_backfill(
+		&self,
+		backfill: AddressIndexBackfill<Block::Hash>,
+	) -> Result<(), String> {
+		let mut transaction = sp_database::Transaction::new();
+
+		transaction.set(
+			crate::columns::META,
+			crate::static_keys::ADDRESS_INDEX_BACKFILL,
+			&backfill.encode(),
+		);
+
+		self.db
+			.commit(transaction)
+			.map_err(|e| format!("{:?}", e))?;
+
+		Ok(())
+	}
+
 	pub fn ethereum_schema(&self) -> Result<Option<Vec<(EthereumStorageSchema, H256)>>, String> {
 		match self
 			.db
@@ -173,10 +212,22 @@ impl<Block: BlockT> MetaDb<Block> {
 	}
 }
 
+/// Progress of the indexing of the addresses of the blocks synced before the address index.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Encode, Decode)]
+pub enum AddressIndexBackfill<Hash> {
+	/// Blocks from this one down to genesis still have to be indexed.
+	Pending(Hash),
+	/// Blocks are indexed down to this number, older ones having no state left to index.
+	Done(u32),
+}
+
 pub struct MappingCommitment<Block: BlockT> {
 	pub block_hash: Block::Hash,
+	pub block_number: u32,
 	pub ethereum_block_hash: H256,
 	pub ethereum_transaction_hashes: Vec<H256>,
+	/// Addresses involved in each transaction, with the transaction index.
+	pub address_appearances: Vec<(H160, u32)>,
 }
 
 #[derive(Clone, Encode, Decode)]
@@ -186,6 +237,66 @@ pub struct TransactionMetadata<Block: BlockT> {
 	pub ethereum_index: u32,
 }
 
+/// A transaction in which an address appears.
+#[derive(Clone, Debug, Eq, PartialEq, Encode, Decode)]
+pub struct AddressAppearance<Block: BlockT> {
+	pub block_number: u32,
+	pub ethereum_index: u32,
+	pub block_hash: Block::Hash,
+}
+
+impl<Block: BlockT> AddressAppearance<Block> {
+	fn sort_key(&self) -> (u32, u32, Block::Hash) {
+		(self.block_number, self.ethereum_index, self.block_hash)
+	}
+}
+
+/// Appearances of an address, lazily read from the address index one bucket at a time.
+///
+/// Appearances are yielded in block order, ascending or descending, including the ones of
+/// non-canonical blocks.
+pub struct AddressAppearances<'a, Block: BlockT> {
+	mapping: &'a MappingDb<Block>,
+	address: H160,
+	start: u32,
+	descending: bool,
+	buckets: VecDeque<u32>,
+	current: VecDeque<AddressAppearance<Block>>,
+}
+
+impl<'a, Block: BlockT> Iterator for AddressAppearances<'a, Block> {
+	type Item = Result<AddressAppearance<Block>, String>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		loop {
+			let next = if self.descending {
+				self.current.pop_back()
+			} else {
+				self.current.pop_front()
+			};
+			match next {
+				Some(appearance) => {
+					let in_range = if self.descending {
+						appearance.block_number <= self.start
+					} else {
+						appearance.block_number >= self.start
+					};
+					if in_range {
+						return Some(Ok(appearance));
+					}
+				}
+				None => {
+					let bucket = self.buckets.pop_front()?;
+					match self.mapping.address_bucket(&self.address, bucket) {
+						Ok(appearances) => self.current = appearances.into(),
+						Err(e) => return Some(Err(e)),
+					}
+				}
+			}
+		}
+	}
+}
+
 pub struct MappingDb<Block: BlockT> {
 	db: Arc<dyn Database<DbHash>>,
 	write_lock: Arc<Mutex<()>>,
@@ -229,6 +340,61 @@ impl<Block: BlockT> MappingDb<Block> {
 		}
 	}
 
+	/// Iterate over the appearances of `address`, starting at block `start` (included).
+	pub fn address_appearances(
+		&self,
+		address: H160,
+		start: u32,
+		descending: bool,
+	) -> Result<AddressAppearances<Block>, String> {
+		let start_bucket = start / ADDRESS_BUCKET_SIZE;
+		let mut buckets: VecDeque<u32> = self
+			.address_buckets(&address)?
+			.into_iter()
+			.filter(|bucket| {
+				if descending {
+					*bucket <= start_bucket
+				} else {
+					*bucket >= start_bucket
+				}
+			})
+			.collect();
+		if descending {
+			buckets = buckets.into_iter().rev().collect();
+		}
+
+		Ok(AddressAppearances {
+			mapping: self,
+			address,
+			start,
+			descending,
+			buckets,
+			current: VecDeque::new(),
+		})
+	}
+
+	fn address_buckets(&self, address: &H160) -> Result<Vec<u32>, String> {
+		match self.db.get(crate::columns::ADDRESS_MAPPING, &address.encode()) {
+			Some(raw) => Ok(Vec::<u32>::decode(&mut &raw[..]).map_err(|e| format!("{:?}", e))?),
+			None => Ok(Vec::new()),
+		}
+	}
+
+	fn address_bucket(
+		&self,
+		address: &H160,
+		bucket: u32,
+	) -> Result<Vec<AddressAppearance<Block>>, String> {
+		match self
+			.db
+			.get(crate::columns::ADDRESS_MAPPING, &(address, bucket).encode())
+		{
+			Some(raw) => Ok(Vec::<AddressAppearance<Block>>::decode(&mut &raw[..])
+				.map_err(|e| format!("{:?}", e))?),
+			None => Ok(Vec::new()),
+		}
+	}
+
 	pub fn write_none(&self, block_hash: Block::Hash) -> Result<(), String> {
 		let _lock = self.write_lock.lock();
 
@@ -276,6 +442,13 @@ impl<Block: BlockT> MappingDb<Block> {
 			);
 		}
 
+		self.index_addresses(
+			&mut transaction,
+			commitment.block_hash,
+			commitment.block_number,
+			commitment.address_appearances,
+		)?;
+
 		transaction.set(
 			crate::columns::SYNCED_MAPPING,
 			&commitment.block_hash.encode(),
@@ -288,4 +461,191 @@ impl<Block: BlockT> MappingDb<Block> {
 
 		Ok(())
 	}
+
+	/// Add the address appearances of an already synced block to the address index.
+	pub fn write_address_appearances(
+		&self,
+		block_hash: Block::Hash,
+		block_number: u32,
+		address_appearances: Vec<(H160, u32)>,
+	) -> Result<(), String> {
+		let _lock = self.write_lock.lock();
+
+		let mut transaction = sp_database::Transaction::new();
+
+		self.index_addresses(&mut transaction, block_hash, block_number, address_appearances)?;
+
+		self.db
+			.commit(transaction)
+			.map_err(|e| format!("{:?}", e))?;
+
+		Ok(())
+	}
+
+	fn index_addresses(
+		&self,
+		transaction: &mut sp_database::Transaction<DbHash>,
+		block_hash: Block::Hash,
+		block_number: u32,
+		address_appearances: Vec<(H160, u32)>,
+	) -> Result<(), String> {
+		let mut appearances = BTreeMap::<H160, Vec<u32>>::new();
+		for (address, ethereum_index) in address_appearances {
+			appearances.entry(address).or_default().push(ethereum_index);
+		}
+		let bucket = block_number / ADDRESS_BUCKET_SIZE;
+		for (address, indices) in appearances {
+			let mut buckets = self.address_buckets(&address)?;
+			if let Err(position) = buckets.binary_search(&bucket) {
+				buckets.insert(position, bucket);
+				transaction.set(
+					crate::columns::ADDRESS_MAPPING,
+					&address.encode(),
+					&buckets.encode(),
+				);
+			}
+
+			let mut entries = self.address_bucket(&address, bucket)?;
+			for ethereum_index in indices {
+				let appearance = AddressAppearance::<Block> {
+					block_number,
+					ethereum_index,
+					block_hash,
+				};
+				if let Err(position) =
+					entries.binary_search_by_key(&appearance.sort_key(), |e| e.sort_key())
+				{
+					entries.insert(position, appearance);
+				}
+			}
+			transaction.set(
+				crate::columns::ADDRESS_MAPPING,
+				&(address, bucket).encode(),
+				&entries.encode(),
+			);
+		}
+
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use sp_runtime::testing::{Block as TestBlock, ExtrinsicWrapper};
+
+	type Block = TestBlock<ExtrinsicWrapper<u64>>;
+
+	fn open_backend(dir: &tempfile::TempDir) -> Backend<Block> {
+		Backend::new(&DatabaseSettings {
+			source: DatabaseSettingsSrc::RocksDb { path: dir.path().to_path_buf(), cache_size: 0 },
+		})
+		.unwrap()
+	}
+
+	fn hash(block_number: u32, fork: u8) -> H256 {
+		let mut hash = H256::from_low_u64_be(block_number.into());
+		hash.0[0] = fork;
+		hash
+	}
+
+	fn appearances(
+		backend: &Backend<Block>,
+		address: H160,
+		start: u32,
+		descending: bool,
+	) -> Vec<(u32, u32, u8)> {
+		backend
+			.mapping()
+			.address_appearances(address, start, descending)
+			.unwrap()
+			.map(|appearance| {
+				let appearance = appearance.unwrap();
+				(appearance.block_number, appearance.ethereum_index, appearance.block_hash.0[0])
+			})
+			.collect()
+	}
+
+	#[test]
+	fn address_appearances_are_grouped_in_buckets() {
+		let dir = tempfile::tempdir().unwrap();
+		let backend = open_backend(&dir);
+		let address = H160::repeat_byte(1);
+		for block_number in [600, 1, 255, 256] {
+			backend
+				.mapping()
+				.write_address_appearances(hash(block_number, 0), block_number, vec![(address, 0)])
+				.unwrap();
+		}
+
+		assert_eq!(backend.mapping().address_buckets(&address).unwrap(), vec![0, 1, 2]);
+		let numbers = |bucket| {
+			backend
+				.mapping()
+				.address_bucket(&address, bucket)
+				.unwrap()
+				.into_iter()
+				.map(|appearance| appearance.block_number)
+				.collect::<Vec<_>>()
+		};
+		assert_eq!(numbers(0), vec![1, 255]);
+		assert_eq!(numbers(1), vec![256]);
+		assert_eq!(numbers(2), vec![600]);
+		assert!(backend.mapping().address_buckets(&H160::repeat_byte(2)).unwrap().is_empty());
+	}
+
+	#[test]
+	fn address_appearances_iterate_from_start() {
+		let dir = tempfile::tempdir().unwrap();
+		let backend = open_backend(&dir);
+		let address = H160::repeat_byte(1);
+		let other = H160::repeat_byte(2);
+		backend
+			.mapping()
+			.write_hashes(MappingCommitment {
+				block_hash: hash(3, 0),
+				block_number: 3,
+				ethereum_block_hash: H256::repeat_byte(3),
+				ethereum_transaction_hashes: vec![H256::repeat_byte(30), H256::repeat_byte(31)],
+				address_appearances: vec![(address, 1), (other, 0), (address, 0), (address, 1)],
+			})
+			.unwrap();
+		for (block_number, fork) in [(300, 0), (300, 1), (700, 0)] {
+			backend
+				.mapping()
+				.write_address_appearances(
+					hash(block_number, fork),
+					block_number,
+					vec![(address, 0)],
+				)
+				.unwrap();
+		}
+
+		assert_eq!(
+			appearances(&backend, address, 0, false),
+			vec![(3, 0, 0), (3, 1, 0), (300, 0, 0), (300, 0, 1), (700, 0, 0)]
+		);
+		assert_eq!(
+			appearances(&backend, address, 300, false),
+			vec![(300, 0, 0), (300, 0, 1), (700, 0, 0)]
+		);
+		assert_eq!(
+			appearances(&backend, address, 699, true),
+			vec![(300, 0, 1), (300, 0, 0), (3, 1, 0), (3, 0, 0)]
+		);
+		assert_eq!(appearances(&backend, address, 2, true), vec![]);
+		assert_eq!(appearances(&backend, address, 701, false), vec![]);
+		assert_eq!(appearances(&backend, other, u32::max_value(), true), vec![(3, 0, 0)]);
+	}
+
+	#[test]
+	fn address_index_backfill_is_stored() {
+		let dir = tempfile::tempdir().unwrap();
+		let backend = open_backend(&dir);
+		assert_eq!(backend.meta().address_index_backfill().unwrap(), None);
+
+		let pending = AddressIndexBackfill::Pending(hash(5, 0));
+		backend.meta().write_address_index_backfill(pending).unwrap();
+		assert_eq!(backend.meta().address_index_backfill().unwrap(), Some(pending));
+	}
 }
@@ -25,23 +25,77 @@ use fp_rpc::EthereumRuntimeRPCApi;
 use sc_client_api::BlockOf;
 use sp_api::{ApiExt, ProvideRuntimeApi};
 use sp_blockchain::HeaderBackend;
+use sp_core::H160;
 use sp_runtime::{
 	generic::BlockId,
-	traits::{Block as BlockT, Header as HeaderT, Zero},
+	traits::{Block as BlockT, Header as HeaderT, UniqueSaturatedInto, Zero},
 };
 
-pub fn sync_block<Block: BlockT>(
+/// Addresses involved in the Ethereum transactions of a block, with the transaction index.
+///
+/// The transaction statuses are read from the runtime state of the block, which fails if the
+/// state is not available anymore (e.g. pruned).
+fn address_appearances<Block: BlockT, C>(
+	client: &C,
+	header: &Block::Header,
+) -> Result<Vec<(H160, u32)>, String>
+where
+	C: ProvideRuntimeApi<Block> + Send + Sync + HeaderBackend<Block> + BlockOf,
+	C::Api: EthereumRuntimeRPCApi<Block>,
+{
+	let statuses = match client
+		.runtime_api()
+		.current_transaction_statuses(&BlockId::Hash(header.hash()))
+		.map_err(|e| format!("{:?}", e))?
+	{
+		Some(statuses) => statuses,
+		None => return Ok(Vec::new()),
+	};
+
+	let mut appearances = Vec::new();
+	for status in statuses {
+		let index = status.transaction_index;
+		appearances.push((status.from, index));
+		if let Some(to) = status.to {
+			appearances.push((to, index));
+		}
+		if let Some(contract_address) = status.contract_address {
+			appearances.push((contract_address, index));
+		}
+		for log in status.logs {
+			appearances.push((log.address, index));
+		}
+	}
+	Ok(appearances)
+}
+
+pub fn sync_block<Block: BlockT, C>(
+	client: &C,
 	backend: &fc_db::Backend<Block>,
 	header: &Block::Header,
-) -> Result<(), String> {
+) -> Result<(), String>
+where
+	C: ProvideRuntimeApi<Block> + Send + Sync + HeaderBackend<Block> + BlockOf,
+	C::Api: EthereumRuntimeRPCApi<Block>,
+{
 	match fp_consensus::find_log(header.digest()) {
 		Ok(log) => {
 			let post_hashes = log.into_hashes();
 
 			let mapping_commitment = fc_db::MappingCommitment {
 				block_hash: header.hash(),
+				block_number: (*header.number()).unique_saturated_into(),
 				ethereum_block_hash: post_hashes.block_hash,
 				ethereum_transaction_hashes: post_hashes.transaction_hashes,
+				address_appearances: address_appearances(client, header).unwrap_or_else(|e| {
+					log::debug!(
+						target: "mapping-sync",
+						"Transaction statuses of block {:?} not available, skipping address index: {:?}",
+						header.hash(),
+						e,
+					);
+					Vec::new()
+				}),
 			};
 			backend.mapping().write_hashes(mapping_commitment)?;
 
@@ -83,8 +137,10 @@ where
 			.hash();
 		let mapping_commitment = fc_db::MappingCommitment::<Block> {
 			block_hash: header.hash(),
+			block_number: 0,
 			ethereum_block_hash: block_hash,
 			ethereum_transaction_hashes: Vec::new(),
+			address_appearances: Vec::new(),
 		};
 		backend.mapping().write_hashes(mapping_commitment)?;
 	} else {
@@ -157,7 +213,7 @@ where
 		{
 			return Ok(false);
 		}
-		sync_block(frontier_backend, &operating_header)?;
+		sync_block(client, frontier_backend, &operating_header)?;
 
 		current_syncing_tips.push(*operating_header.parent_hash());
 		frontier_backend
@@ -188,3 +244,200 @@ where
 
 	Ok(synced_any)
 }
+
+/// Index the addresses of up to `limit` blocks synced before the address index existed, walking
+/// the canonical chain down from the best block at the time of the first run.
+///
+/// Stops at genesis or at the first block whose state is not available anymore, recording how
+/// far back the index goes. Returns whether blocks are left to index.
+pub fn backfill_address_index<Block: BlockT, C, B>(
+	client: &C,
+	substrate_backend: &B,
+	frontier_backend: &fc_db::Backend<Block>,
+	limit: usize,
+) -> Result<bool, String>
+where
+	C: ProvideRuntimeApi<Block> + Send + Sync + HeaderBackend<Block> + BlockOf,
+	C::Api: EthereumRuntimeRPCApi<Block>,
+	B: sp_blockchain::HeaderBackend<Block>,
+{
+	backfill_address_index_with(
+		frontier_backend,
+		limit,
+		|| client.info().best_hash,
+		|hash| {
+			substrate_backend
+				.header(BlockId::Hash(hash))
+				.map_err(|e| format!("{:?}", e))?
+				.ok_or("Header not found".to_string())
+		},
+		|header| address_appearances(client, header),
+	)
+}
+
+/// `backfill_address_index`, with the best block hash, the headers and the address appearances
+/// of the blocks given by `best_hash`, `header` and `appearances`.
+fn backfill_address_index_with<Block: BlockT>(
+	frontier_backend: &fc_db::Backend<Block>,
+	limit: usize,
+	best_hash: impl FnOnce() -> Block::Hash,
+	header: impl Fn(Block::Hash) -> Result<Block::Header, String>,
+	appearances: impl Fn(&Block::Header) -> Result<Vec<(H160, u32)>, String>,
+) -> Result<bool, String> {
+	let mut next = match frontier_backend.meta().address_index_backfill()? {
+		Some(fc_db::AddressIndexBackfill::Pending(hash)) => hash,
+		Some(fc_db::AddressIndexBackfill::Done(_)) => return Ok(false),
+		None => best_hash(),
+	};
+
+	for _ in 0..limit {
+		let header = header(next)?;
+		let number: u32 = (*header.number()).unique_saturated_into();
+
+		let oldest = match appearances(&header) {
+			Ok(appearances) => {
+				frontier_backend.mapping().write_address_appearances(
+					header.hash(),
+					number,
+					appearances,
+				)?;
+				if number > 0 {
+					next = *header.parent_hash();
+					continue;
+				}
+				0
+			}
+			Err(e) => {
+				log::debug!(
+					target: "mapping-sync",
+					"Transaction statuses of block {:?} not available: {:?}",
+					header.hash(),
+					e,
+				);
+				number.saturating_add(1)
+			}
+		};
+
+		log::info!(
+			target: "mapping-sync",
+			"Address index covers the blocks from #{}",
+			oldest,
+		);
+		frontier_backend
+			.meta()
+			.write_address_index_backfill(fc_db::AddressIndexBackfill::Done(oldest))?;
+		return Ok(false);
+	}
+
+	frontier_backend
+		.meta()
+		.write_address_index_backfill(fc_db::AddressIndexBackfill::Pending(next))?;
+	Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use sp_core::H256;
+	use sp_runtime::testing::{Block as TestBlock, Digest, ExtrinsicWrapper, Header};
+	use std::collections::HashMap;
+
+	type Block = TestBlock<ExtrinsicWrapper<u64>>;
+
+	/// Headers of a chain of `length` blocks, by hash, and the hash of the last one.
+	fn chain(length: u64) -> (HashMap<H256, Header>, H256) {
+		let mut headers = HashMap::new();
+		let mut parent_hash = H256::zero();
+		for number in 0..length {
+			let header =
+				Header::new(number, H256::zero(), H256::zero(), parent_hash, Digest::default());
+			parent_hash = header.hash();
+			headers.insert(parent_hash, header);
+		}
+		(headers, parent_hash)
+	}
+
+	fn open_backend(dir: &tempfile::TempDir) -> fc_db::Backend<Block> {
+		fc_db::Backend::new(&fc_db::DatabaseSettings {
+			source: fc_db::DatabaseSettingsSrc::RocksDb {
+				path: dir.path().to_path_buf(),
+				cache_size: 0,
+			},
+		})
+		.unwrap()
+	}
+
+	/// Block numbers in which `address` appears, in ascending order.
+	fn indexed(backend: &fc_db::Backend<Block>, address: H160) -> Vec<u32> {
+		backend
+			.mapping()
+			.address_appearances(address, 0, false)
+			.unwrap()
+			.map(|appearance| appearance.unwrap().block_number)
+			.collect()
+	}
+
+	/// Backfill up to `limit` blocks of `headers`, whose state is available from `first_state`.
+	fn backfill(
+		backend: &fc_db::Backend<Block>,
+		(headers, best_hash): &(HashMap<H256, Header>, H256),
+		first_state: u64,
+		limit: usize,
+	) -> bool {
+		backfill_address_index_with(
+			backend,
+			limit,
+			|| *best_hash,
+			|hash| headers.get(&hash).cloned().ok_or("Header not found".to_string()),
+			|header| {
+				if header.number >= first_state {
+					Ok(vec![(H160::repeat_byte(1), 0)])
+				} else {
+					Err("state pruned".to_string())
+				}
+			},
+		)
+		.unwrap()
+	}
+
+	#[test]
+	fn backfill_indexes_down_to_genesis_over_several_runs() {
+		let dir = tempfile::tempdir().unwrap();
+		let backend = open_backend(&dir);
+		let chain = chain(5);
+
+		assert!(backfill(&backend, &chain, 0, 2));
+		assert_eq!(indexed(&backend, H160::repeat_byte(1)), vec![3, 4]);
+		let pending = chain.0[&chain.1].parent_hash;
+		let pending = chain.0[&pending].parent_hash;
+		assert_eq!(
+			backend.meta().address_index_backfill().unwrap(),
+			Some(fc_db::AddressIndexBackfill::Pending(pending))
+		);
+
+		assert!(backfill(&backend, &chain, 0, 2));
+		assert!(!backfill(&backend, &chain, 0, 2));
+		assert_eq!(indexed(&backend, H160::repeat_byte(1)), vec![0, 1, 2, 3, 4]);
+		assert_eq!(
+			backend.meta().address_index_backfill().unwrap(),
+			Some(fc_db::AddressIndexBackfill::Done(0))
+		);
+
+		// Done.
+		assert!(!backfill(&backend, &chain, 0, 2));
+	}
+
+	#[test]
+	fn backfill_stops_at_pruned_state() {
+		let dir = tempfile::tempdir().unwrap();
+		let backend = open_backend(&dir);
+		let chain = chain(5);
+
+		assert!(!backfill(&backend, &chain, 2, 10));
+		assert_eq!(indexed(&backend, H160::repeat_byte(1)), vec![2, 3, 4]);
+		assert_eq!(
+			backend.meta().address_index_backfill().unwrap(),
+			Some(fc_db::AddressIndexBackfill::Done(2))
+		);
+	}
+}
@@ -122,7 +122,23 @@ where
 				self.strategy,
 			) {
 				Ok(have_next) => {
-					self.have_next = have_next;
+					// Blocks synced before the address index are indexed once the tips are.
+					let backfilling = !have_next
+						&& crate::backfill_address_index(
+							self.client.as_ref(),
+							self.substrate_backend.blockchain(),
+							self.frontier_backend.as_ref(),
+							LIMIT,
+						)
+						.unwrap_or_else(|e| {
+							debug!(
+								target: "mapping-sync",
+								"Address index backfill failed with error {:?}, retrying.",
+								e,
+							);
+							false
+						});
+					self.have_next = have_next || backfilling;
 					Poll::Ready(Some(()))
 				}
 				Err(e) => {
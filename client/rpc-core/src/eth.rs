@@ -24,7 +24,7 @@ use jsonrpc_derive::rpc;
 
 use crate::types::{
 	BlockNumber, Bytes, CallRequest, Filter, FilterChanges, Index, Log, Receipt, RichBlock,
-	SyncStatus, Transaction, TransactionRequest, Work,
+	SyncStatus, Transaction, TransactionRequest, TypedData, Work,
 };
 pub use rpc_impl_EthApi::gen_server::EthApi as EthApiServer;
 pub use rpc_impl_EthFilterApi::gen_server::EthFilterApi as EthFilterApiServer;
@@ -119,6 +119,19 @@ pub trait EthApi {
 	#[rpc(name = "eth_sendRawTransaction")]
 	fn send_raw_transaction(&self, _: Bytes) -> BoxFuture<Result<H256>>;
 
+	/// Signs `keccak256("\x19Ethereum Signed Message:\n" + len(data) + data)` with the
+	/// given account.
+	#[rpc(name = "eth_sign")]
+	fn sign(&self, _: H160, _: Bytes) -> Result<Bytes>;
+
+	/// Signs a transaction without submitting it, returning its raw encoding.
+	#[rpc(name = "eth_signTransaction")]
+	fn sign_transaction(&self, _: TransactionRequest) -> Result<Bytes>;
+
+	/// Signs EIP-712 typed data with the given account.
+	#[rpc(name = "eth_signTypedData_v4")]
+	fn sign_typed_data(&self, _: H160, _: TypedData) -> Result<Bytes>;
+
 	/// Call contract, returning the output data.
 	#[rpc(name = "eth_call")]
 	fn call(&self, _: CallRequest, _: Option<BlockNumber>) -> Result<Bytes>;
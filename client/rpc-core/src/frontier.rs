@@ -0,0 +1,43 @@
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+// This file is part of Frontier.
+//
+// Copyright (c) 2015-2020 Parity Technologies (UK) Ltd.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Frontier-specific rpc interface.
+use ethereum_types::H160;
+use jsonrpc_core::Result;
+use jsonrpc_derive::rpc;
+
+use crate::types::{AddressTransactionsCursor, AddressTransactionsPage};
+
+pub use rpc_impl_FrontierApi::gen_server::FrontierApi as FrontierApiServer;
+
+/// Frontier-specific rpc interface.
+#[rpc(server)]
+pub trait FrontierApi {
+	/// Returns the canonical transactions sent by, sent to, creating or emitting logs from an
+	/// address, from the most recent one.
+	///
+	/// The page starts at `cursor` (included) if given, and holds at most `limit` transactions,
+	/// which must be at least 1.
+	#[rpc(name = "frontier_getAddressTransactions")]
+	fn address_transactions(
+		&self,
+		_: H160,
+		_: Option<AddressTransactionsCursor>,
+		_: Option<usize>,
+	) -> Result<AddressTransactionsPage>;
+}
@@ -20,10 +20,16 @@ pub mod types;
 
 mod eth;
 mod eth_pubsub;
+mod frontier;
 mod net;
+mod ots;
+mod personal;
 mod web3;
 
 pub use eth::{EthApi, EthApiServer, EthFilterApi, EthFilterApiServer};
 pub use eth_pubsub::{EthPubSubApi, EthPubSubApiServer};
+pub use frontier::{FrontierApi, FrontierApiServer};
 pub use net::{NetApi, NetApiServer};
+pub use ots::{OtsApi, OtsApiServer};
+pub use personal::{PersonalApi, PersonalApiServer};
 pub use web3::{Web3Api, Web3ApiServer};
@@ -0,0 +1,81 @@
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+// This file is part of Frontier.
+//
+// Copyright (c) 2015-2020 Parity Technologies (UK) Ltd.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Otterscan rpc interface.
+use ethereum_types::{H160, H256, U256};
+use jsonrpc_core::Result;
+use jsonrpc_derive::rpc;
+
+use crate::types::{
+	BlockDetails, BlockNumber, BlockTransactionsPage, Bytes, ContractCreator, InternalOperation,
+	TransactionsPage,
+};
+
+pub use rpc_impl_OtsApi::gen_server::OtsApi as OtsApiServer;
+
+/// Otterscan rpc interface.
+#[rpc(server)]
+pub trait OtsApi {
+	/// Returns the version of the Otterscan API implemented by the node.
+	#[rpc(name = "ots_getApiLevel")]
+	fn get_api_level(&self) -> Result<u64>;
+
+	/// Returns the transfers and contract creations/destructions made by internal calls of a
+	/// transaction.
+	#[rpc(name = "ots_getInternalOperations")]
+	fn get_internal_operations(&self, _: H256) -> Result<Vec<InternalOperation>>;
+
+	/// Returns whether an address holds code at the given block.
+	#[rpc(name = "ots_hasCode")]
+	fn has_code(&self, _: H160, _: Option<BlockNumber>) -> Result<bool>;
+
+	/// Returns the raw revert data of a failed transaction.
+	#[rpc(name = "ots_getTransactionError")]
+	fn get_transaction_error(&self, _: H256) -> Result<Bytes>;
+
+	/// Returns a block without its transactions, with its transaction count and fees.
+	#[rpc(name = "ots_getBlockDetails")]
+	fn get_block_details(&self, _: BlockNumber) -> Result<Option<BlockDetails>>;
+
+	/// Returns a page of the transactions of a block, with their receipts.
+	#[rpc(name = "ots_getBlockTransactions")]
+	fn get_block_transactions(
+		&self,
+		_: BlockNumber,
+		_: usize,
+		_: usize,
+	) -> Result<Option<BlockTransactionsPage>>;
+
+	/// Returns the transactions involving an address in blocks strictly before the given
+	/// block (0 meaning the latest block), from the most recent.
+	#[rpc(name = "ots_searchTransactionsBefore")]
+	fn search_transactions_before(&self, _: H160, _: u64, _: usize) -> Result<TransactionsPage>;
+
+	/// Returns the transactions involving an address in blocks strictly after the given block,
+	/// from the oldest.
+	#[rpc(name = "ots_searchTransactionsAfter")]
+	fn search_transactions_after(&self, _: H160, _: u64, _: usize) -> Result<TransactionsPage>;
+
+	/// Returns the hash of the transaction sent by an address with the given nonce.
+	#[rpc(name = "ots_getTransactionBySenderAndNonce")]
+	fn get_transaction_by_sender_and_nonce(&self, _: H160, _: U256) -> Result<Option<H256>>;
+
+	/// Returns the transaction that created a contract, and its sender.
+	#[rpc(name = "ots_getContractCreator")]
+	fn get_contract_creator(&self, _: H160) -> Result<Option<ContractCreator>>;
+}
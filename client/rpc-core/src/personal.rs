@@ -0,0 +1,52 @@
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+// This file is part of Frontier.
+//
+// Copyright (c) 2015-2020 Parity Technologies (UK) Ltd.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Personal rpc interface.
+use ethereum_types::H160;
+use jsonrpc_core::Result;
+use jsonrpc_derive::rpc;
+
+use crate::types::Bytes;
+
+pub use rpc_impl_PersonalApi::gen_server::PersonalApi as PersonalApiServer;
+
+/// Personal rpc interface.
+#[rpc(server)]
+pub trait PersonalApi {
+	/// Returns the addresses of all keystore accounts.
+	#[rpc(name = "personal_listAccounts")]
+	fn list_accounts(&self) -> Result<Vec<H160>>;
+
+	/// Decrypts the key of an account and keeps it in memory for `duration` seconds
+	/// (default 300, 0 meaning until the node stops).
+	#[rpc(name = "personal_unlockAccount")]
+	fn unlock_account(&self, _: H160, _: String, _: Option<u64>) -> Result<bool>;
+
+	/// Imports an unencrypted hex private key, stores it encrypted with the passphrase
+	/// and returns its address.
+	#[rpc(name = "personal_importRawKey")]
+	fn import_raw_key(&self, _: String, _: String) -> Result<H160>;
+
+	/// Signs `keccak256("\x19Ethereum Signed Message:\n" + len(data) + data)`.
+	#[rpc(name = "personal_sign")]
+	fn sign(&self, _: Bytes, _: H160, _: String) -> Result<Bytes>;
+
+	/// Returns the address that produced a `personal_sign` signature.
+	#[rpc(name = "personal_ecRecover")]
+	fn ec_recover(&self, _: Bytes, _: Bytes) -> Result<H160>;
+}
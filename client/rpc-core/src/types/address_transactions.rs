@@ -0,0 +1,54 @@
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+// This file is part of Frontier.
+//
+// Copyright (c) 2015-2020 Parity Technologies (UK) Ltd.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use ethereum_types::{H256, U256};
+use serde::{Deserialize, Serialize};
+
+/// Position in the transaction history of an address.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AddressTransactionsCursor {
+	/// Block number
+	pub block_number: U256,
+	/// Transaction index in the block
+	pub transaction_index: U256,
+}
+
+/// A transaction involving an address.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AddressTransaction {
+	/// Block hash
+	pub block_hash: H256,
+	/// Block number
+	pub block_number: U256,
+	/// Transaction index in the block
+	pub transaction_index: U256,
+	/// Transaction hash
+	pub transaction_hash: H256,
+}
+
+/// Page of the transaction history of an address, from the most recent transaction.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AddressTransactionsPage {
+	/// Transactions
+	pub transactions: Vec<AddressTransaction>,
+	/// Cursor of the next page, `None` if this is the last one
+	pub next_cursor: Option<AddressTransactionsCursor>,
+}
@@ -19,6 +19,7 @@
 //! RPC types
 
 mod account_info;
+mod address_transactions;
 mod block;
 mod block_number;
 mod bytes;
@@ -26,16 +27,19 @@ mod call_request;
 mod filter;
 mod index;
 mod log;
+mod otterscan;
 mod receipt;
 mod sync;
 mod transaction;
 mod transaction_request;
+mod typed_data;
 mod work;
 
 pub mod pubsub;
 
 pub use self::{
 	account_info::{AccountInfo, EthAccount, ExtAccountInfo, RecoveredAccount, StorageProof},
+	address_transactions::{AddressTransaction, AddressTransactionsCursor, AddressTransactionsPage},
 	block::{Block, BlockTransactions, Header, Rich, RichBlock, RichHeader},
 	block_number::BlockNumber,
 	bytes::Bytes,
@@ -46,6 +50,10 @@ pub use self::{
 	},
 	index::Index,
 	log::Log,
+	otterscan::{
+		BlockDetails, BlockTransactionsPage, ContractCreator, InternalOperation, Issuance,
+		OtsBlock, OtsReceipt, TransactionsPage,
+	},
 	receipt::Receipt,
 	sync::{
 		ChainStatus, EthProtocolInfo, PeerCount, PeerInfo, PeerNetworkInfo, PeerProtocolsInfo,
@@ -53,5 +61,6 @@ pub use self::{
 	},
 	transaction::{LocalTransactionStatus, RichRawTransaction, Transaction},
 	transaction_request::{TransactionMessage, TransactionRequest},
+	typed_data::{TypedData, TypedDataField},
 	work::Work,
 };
@@ -0,0 +1,105 @@
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+// This file is part of Frontier.
+//
+// Copyright (c) 2015-2020 Parity Technologies (UK) Ltd.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Types of the Otterscan `ots_*` namespace.
+
+use crate::types::{Receipt, RichBlock, Transaction};
+use ethereum_types::{H160, H256, U256};
+use serde::Serialize;
+
+/// A block along with its number of transactions.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OtsBlock {
+	#[serde(flatten)]
+	pub block: RichBlock,
+	/// Number of transactions in the block
+	pub transaction_count: usize,
+}
+
+/// Block rewards. Frontier chains do not mint on block production, so these are zero.
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Issuance {
+	pub block_reward: U256,
+	pub uncle_reward: U256,
+	pub issuance: U256,
+}
+
+/// Response of `ots_getBlockDetails`.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockDetails {
+	pub block: OtsBlock,
+	pub issuance: Issuance,
+	/// Sum of the fees paid by the block transactions
+	pub total_fees: U256,
+}
+
+/// Response of `ots_getBlockTransactions`.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockTransactionsPage {
+	pub fullblock: OtsBlock,
+	pub receipts: Vec<Receipt>,
+}
+
+/// A receipt along with the timestamp of its block.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OtsReceipt {
+	#[serde(flatten)]
+	pub receipt: Receipt,
+	pub timestamp: U256,
+}
+
+/// Response of `ots_searchTransactionsBefore` and `ots_searchTransactionsAfter`.
+///
+/// Transactions are always sorted from the most recent to the oldest.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransactionsPage {
+	pub txs: Vec<Transaction>,
+	pub receipts: Vec<OtsReceipt>,
+	/// Whether the page holds the most recent transactions of the address
+	pub first_page: bool,
+	/// Whether the page holds the oldest transactions of the address
+	pub last_page: bool,
+}
+
+/// Response of `ots_getContractCreator`.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContractCreator {
+	/// Hash of the creation transaction
+	pub hash: H256,
+	/// Sender of the creation transaction
+	pub creator: H160,
+}
+
+/// A value transfer or contract creation/destruction happening within a transaction.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InternalOperation {
+	/// 0: transfer, 1: selfdestruct, 2: create, 3: create2
+	#[serde(rename = "type")]
+	pub kind: u8,
+	pub from: H160,
+	pub to: H160,
+	pub value: U256,
+}
@@ -0,0 +1,349 @@
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+// This file is part of Frontier.
+//
+// Copyright (c) 2015-2020 Parity Technologies (UK) Ltd.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! EIP-712 typed structured data.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use ethereum_types::{H256, U256};
+use rustc_hex::FromHex;
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::Value;
+use sha3::{Digest, Keccak256};
+
+/// Member of a struct type.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TypedDataField {
+	/// Member name.
+	pub name: String,
+	/// Member type, e.g. `uint256`, `Person` or `Person[]`.
+	#[serde(rename = "type")]
+	pub type_: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TypedDataObject {
+	types: BTreeMap<String, Vec<TypedDataField>>,
+	primary_type: String,
+	domain: Value,
+	message: Value,
+}
+
+/// Typed data as accepted by `eth_signTypedData_v4`, either as an object or as its JSON string.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TypedData {
+	/// Struct type definitions, including `EIP712Domain`.
+	pub types: BTreeMap<String, Vec<TypedDataField>>,
+	/// Type of `message`.
+	pub primary_type: String,
+	/// Domain separator values.
+	pub domain: Value,
+	/// Data to sign.
+	pub message: Value,
+}
+
+impl<'a> Deserialize<'a> for TypedData {
+	fn deserialize<D>(deserializer: D) -> Result<TypedData, D::Error>
+	where
+		D: Deserializer<'a>,
+	{
+		#[derive(Deserialize)]
+		#[serde(untagged)]
+		enum Input {
+			Object(TypedDataObject),
+			Json(String),
+		}
+
+		let object = match Input::deserialize(deserializer)? {
+			Input::Object(object) => object,
+			Input::Json(json) => serde_json::from_str(&json).map_err(serde::de::Error::custom)?,
+		};
+		Ok(TypedData {
+			types: object.types,
+			primary_type: object.primary_type,
+			domain: object.domain,
+			message: object.message,
+		})
+	}
+}
+
+const DOMAIN_TYPE: &str = "EIP712Domain";
+
+impl TypedData {
+	/// `hashStruct` of the domain, i.e. the domain separator.
+	pub fn domain_separator(&self) -> Result<H256, String> {
+		self.hash_struct(DOMAIN_TYPE, &self.domain)
+	}
+
+	/// Hash to sign: `keccak256("\x19\x01" ‖ domainSeparator ‖ hashStruct(message))`.
+	///
+	/// The message part is omitted when the primary type is `EIP712Domain`.
+	pub fn signing_hash(&self) -> Result<H256, String> {
+		let mut data = vec![0x19, 0x01];
+		data.extend_from_slice(self.domain_separator()?.as_bytes());
+		if self.primary_type != DOMAIN_TYPE {
+			data.extend_from_slice(self.hash_struct(&self.primary_type, &self.message)?.as_bytes());
+		}
+		Ok(keccak(&data))
+	}
+
+	/// `hashStruct(s) = keccak256(typeHash ‖ encodeData(s))`.
+	pub fn hash_struct(&self, type_name: &str, value: &Value) -> Result<H256, String> {
+		Ok(keccak(&self.encode_data(type_name, value)?))
+	}
+
+	/// `encodeType`: the struct signature followed by its referenced struct types in
+	/// alphabetical order.
+	pub fn encode_type(&self, type_name: &str) -> Result<String, String> {
+		let mut dependencies = BTreeSet::new();
+		self.collect_dependencies(type_name, &mut dependencies)?;
+		dependencies.remove(type_name);
+
+		let mut encoded = String::new();
+		for name in std::iter::once(type_name).chain(dependencies.iter().map(|d| d.as_str())) {
+			let fields = self.fields(name)?;
+			let members = fields
+				.iter()
+				.map(|field| format!("{} {}", field.type_, field.name))
+				.collect::<Vec<_>>();
+			encoded.push_str(&format!("{}({})", name, members.join(",")));
+		}
+		Ok(encoded)
+	}
+
+	fn fields(&self, type_name: &str) -> Result<&Vec<TypedDataField>, String> {
+		self.types.get(type_name).ok_or(format!("unknown type {}", type_name))
+	}
+
+	fn collect_dependencies(
+		&self,
+		type_name: &str,
+		found: &mut BTreeSet<String>,
+	) -> Result<(), String> {
+		if found.contains(type_name) {
+			return Ok(());
+		}
+		found.insert(type_name.to_string());
+		for field in self.fields(type_name)? {
+			let base = base_type(&field.type_);
+			if self.types.contains_key(base) {
+				self.collect_dependencies(base, found)?;
+			}
+		}
+		Ok(())
+	}
+
+	fn encode_data(&self, type_name: &str, value: &Value) -> Result<Vec<u8>, String> {
+		let object = value.as_object().ok_or(format!("expected an object for {}", type_name))?;
+		let mut encoded = keccak(self.encode_type(type_name)?.as_bytes()).as_bytes().to_vec();
+		for field in self.fields(type_name)? {
+			let member = object
+				.get(&field.name)
+				.ok_or(format!("missing value for {}.{}", type_name, field.name))?;
+			encoded.extend_from_slice(self.encode_value(&field.type_, member)?.as_bytes());
+		}
+		Ok(encoded)
+	}
+
+	fn encode_value(&self, type_: &str, value: &Value) -> Result<H256, String> {
+		if type_.ends_with(']') {
+			let inner = &type_[..type_.rfind('[').ok_or(format!("invalid type {}", type_))?];
+			let items = value.as_array().ok_or(format!("expected an array for {}", type_))?;
+			let mut encoded = Vec::with_capacity(items.len() * 32);
+			for item in items {
+				encoded.extend_from_slice(self.encode_value(inner, item)?.as_bytes());
+			}
+			return Ok(keccak(&encoded));
+		}
+		if self.types.contains_key(type_) {
+			return self.hash_struct(type_, value);
+		}
+
+		match type_ {
+			"string" => Ok(keccak(value.as_str().ok_or("expected a string")?.as_bytes())),
+			"bytes" => Ok(keccak(&parse_hex(value)?)),
+			"bool" => {
+				let flag = value.as_bool().ok_or("expected a boolean")?;
+				Ok(H256::from_low_u64_be(flag as u64))
+			}
+			"address" => {
+				let bytes = parse_hex(value)?;
+				if bytes.len() != 20 {
+					return Err("expected a 20 bytes address".into());
+				}
+				let mut word = H256::zero();
+				word.as_bytes_mut()[12..].copy_from_slice(&bytes);
+				Ok(word)
+			}
+			_ if type_.starts_with("bytes") => {
+				let size: usize = type_[5..].parse().map_err(|_| format!("invalid type {}", type_))?;
+				let bytes = parse_hex(value)?;
+				if size == 0 || size > 32 || bytes.len() > size {
+					return Err(format!("invalid value for {}", type_));
+				}
+				let mut word = H256::zero();
+				word.as_bytes_mut()[..bytes.len()].copy_from_slice(&bytes);
+				Ok(word)
+			}
+			_ if type_.starts_with("uint") => {
+				let number = parse_number(value)?;
+				if number.0 {
+					return Err(format!("negative value for {}", type_));
+				}
+				Ok(u256_word(number.1))
+			}
+			_ if type_.starts_with("int") => {
+				let (negative, number) = parse_number(value)?;
+				if negative {
+					// Two's complement.
+					Ok(u256_word((!number).overflowing_add(U256::one()).0))
+				} else {
+					Ok(u256_word(number))
+				}
+			}
+			_ => Err(format!("unknown type {}", type_)),
+		}
+	}
+}
+
+fn keccak(data: &[u8]) -> H256 {
+	H256::from_slice(Keccak256::digest(data).as_slice())
+}
+
+fn base_type(type_: &str) -> &str {
+	type_.find('[').map(|i| &type_[..i]).unwrap_or(type_)
+}
+
+fn u256_word(value: U256) -> H256 {
+	let mut word = H256::zero();
+	value.to_big_endian(word.as_bytes_mut());
+	word
+}
+
+fn parse_hex(value: &Value) -> Result<Vec<u8>, String> {
+	let string = value.as_str().ok_or("expected a hex string")?;
+	string.trim_start_matches("0x").from_hex().map_err(|_| format!("invalid hex {}", string))
+}
+
+/// Parse a JSON number, decimal string or `0x` hex string into sign and magnitude.
+fn parse_number(value: &Value) -> Result<(bool, U256), String> {
+	match value {
+		Value::Number(number) => {
+			if let Some(n) = number.as_u64() {
+				Ok((false, U256::from(n)))
+			} else if let Some(n) = number.as_i64() {
+				Ok((n < 0, U256::from(n.unsigned_abs())))
+			} else {
+				Err(format!("invalid integer {}", number))
+			}
+		}
+		Value::String(string) => {
+			let (negative, digits) = match string.strip_prefix('-') {
+				Some(digits) => (true, digits),
+				None => (false, string.as_str()),
+			};
+			let number = match digits.strip_prefix("0x") {
+				Some(hex) => U256::from_str_radix(hex, 16).ok(),
+				None => U256::from_dec_str(digits).ok(),
+			}
+			.ok_or(format!("invalid integer {}", string))?;
+			Ok((negative, number))
+		}
+		_ => Err("expected an integer".into()),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn mail() -> TypedData {
+		serde_json::from_str(
+			r#"{
+				"types": {
+					"EIP712Domain": [
+						{ "name": "name", "type": "string" },
+						{ "name": "version", "type": "string" },
+						{ "name": "chainId", "type": "uint256" },
+						{ "name": "verifyingContract", "type": "address" }
+					],
+					"Person": [
+						{ "name": "name", "type": "string" },
+						{ "name": "wallet", "type": "address" }
+					],
+					"Mail": [
+						{ "name": "from", "type": "Person" },
+						{ "name": "to", "type": "Person" },
+						{ "name": "contents", "type": "string" }
+					]
+				},
+				"primaryType": "Mail",
+				"domain": {
+					"name": "Ether Mail",
+					"version": "1",
+					"chainId": 1,
+					"verifyingContract": "0xCcCCccccCCCCcCCCCCCcCcCccCcCCCcCcccccccC"
+				},
+				"message": {
+					"from": { "name": "Cow", "wallet": "0xCD2a3d9F938E13CD947Ec05AbC7FE734Df8DD826" },
+					"to": { "name": "Bob", "wallet": "0xbBbBBBBbbBBBbbbBbbBbbbbBBbBbbbbBbBbbBBbB" },
+					"contents": "Hello, Bob!"
+				}
+			}"#,
+		)
+		.unwrap()
+	}
+
+	fn h256(hex: &str) -> H256 {
+		H256::from_slice(&hex.from_hex::<Vec<u8>>().unwrap())
+	}
+
+	#[test]
+	fn encode_type_lists_dependencies() {
+		assert_eq!(
+			mail().encode_type("Mail").unwrap(),
+			"Mail(Person from,Person to,string contents)Person(string name,address wallet)"
+		);
+	}
+
+	#[test]
+	fn hashes_eip712_example() {
+		let data = mail();
+		assert_eq!(
+			data.domain_separator().unwrap(),
+			h256("f2cee375fa42b42143804025fc449deafd50cc031ca257e0b194a650a912090f")
+		);
+		assert_eq!(
+			data.hash_struct("Mail", &data.message).unwrap(),
+			h256("c52c0ee5d84264471806290a3f2c4cecfc5490626bf912d01f240d7a274b371e")
+		);
+		assert_eq!(
+			data.signing_hash().unwrap(),
+			h256("be609aee343fb3c4b28e1df9e632fca64fcfaede20f02e86244efddf30957bd2")
+		);
+	}
+
+	#[test]
+	fn deserializes_from_json_string() {
+		let json = serde_json::to_string(&mail()).unwrap();
+		let data: TypedData = serde_json::from_value(Value::String(json)).unwrap();
+		assert_eq!(data, mail());
+	}
+}
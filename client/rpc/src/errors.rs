@@ -0,0 +1,294 @@
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+// This file is part of Frontier.
+//
+// Copyright (c) 2020 Parity Technologies (UK) Ltd.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Typed Ethereum RPC errors, mapped to the codes defined by EIP-1474.
+
+use jsonrpc_core::{Error, ErrorCode};
+use pallet_ethereum::TransactionValidationError;
+use sc_transaction_pool_api::error::{Error as PoolError, IntoPoolError};
+use sp_runtime::transaction_validity::InvalidTransaction;
+use std::fmt;
+
+/// Server error codes reserved by EIP-1474.
+pub mod codes {
+	pub const INVALID_INPUT: i64 = -32000;
+	pub const RESOURCE_NOT_FOUND: i64 = -32001;
+	pub const RESOURCE_UNAVAILABLE: i64 = -32002;
+	pub const TRANSACTION_REJECTED: i64 = -32003;
+	pub const METHOD_NOT_SUPPORTED: i64 = -32004;
+	pub const LIMIT_EXCEEDED: i64 = -32005;
+}
+
+/// Reasons for which a transaction is refused by the pool.
+///
+/// Messages follow the wording used by geth so wallets can match on them.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum TransactionRejection {
+	NonceTooLow,
+	NonceTooHigh,
+	InsufficientFunds,
+	ExceedsBlockGasLimit,
+	ExceedsTransactionGasLimit,
+	IntrinsicGasTooLow,
+	MaxInitcodeSizeExceeded,
+	Underpriced,
+	AlreadyKnown,
+	ReplacementUnderpriced,
+	InvalidChainId,
+	InvalidSender,
+	TemporarilyBanned,
+	Other(String),
+}
+
+impl fmt::Display for TransactionRejection {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Self::NonceTooLow => write!(f, "nonce too low"),
+			Self::NonceTooHigh => write!(f, "nonce too high"),
+			Self::InsufficientFunds => write!(f, "insufficient funds for gas * price + value"),
+			Self::ExceedsBlockGasLimit => write!(f, "exceeds block gas limit"),
+			Self::ExceedsTransactionGasLimit => write!(f, "exceeds transaction gas limit"),
+			Self::IntrinsicGasTooLow => write!(f, "intrinsic gas too low"),
+			Self::MaxInitcodeSizeExceeded => write!(f, "max initcode size exceeded"),
+			Self::Underpriced => write!(f, "transaction underpriced"),
+			Self::AlreadyKnown => write!(f, "already known"),
+			Self::ReplacementUnderpriced => write!(f, "replacement transaction underpriced"),
+			Self::InvalidChainId => write!(f, "invalid chain id for signer"),
+			Self::InvalidSender => write!(f, "invalid sender"),
+			Self::TemporarilyBanned => write!(f, "transaction temporarily banned"),
+			Self::Other(message) => write!(f, "{}", message),
+		}
+	}
+}
+
+impl From<InvalidTransaction> for TransactionRejection {
+	fn from(err: InvalidTransaction) -> Self {
+		match err {
+			InvalidTransaction::Stale => Self::NonceTooLow,
+			InvalidTransaction::Future => Self::NonceTooHigh,
+			InvalidTransaction::Payment => Self::InsufficientFunds,
+			InvalidTransaction::ExhaustsResources => Self::ExceedsBlockGasLimit,
+			InvalidTransaction::BadProof => Self::InvalidSender,
+			InvalidTransaction::Custom(code) => match TransactionValidationError::from_u8(code) {
+				Some(TransactionValidationError::InvalidChainId) => Self::InvalidChainId,
+				Some(TransactionValidationError::InvalidSignature) => Self::InvalidSender,
+				Some(TransactionValidationError::InvalidGasLimit) => Self::ExceedsBlockGasLimit,
+				Some(TransactionValidationError::GasLimitTooLow) => Self::IntrinsicGasTooLow,
+				Some(TransactionValidationError::GasPriceTooLow) => Self::Underpriced,
+				Some(TransactionValidationError::GasLimitTooHigh) => {
+					Self::ExceedsTransactionGasLimit
+				},
+				Some(TransactionValidationError::InitcodeTooLarge) => Self::MaxInitcodeSizeExceeded,
+				Some(TransactionValidationError::UnknownError) | None => {
+					Self::Other(format!("invalid transaction: custom error {}", code))
+				},
+			},
+			other => Self::Other(format!("invalid transaction: {}", <&'static str>::from(other))),
+		}
+	}
+}
+
+/// Error returned by the Ethereum RPC handlers.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum EthRpcError {
+	/// Missing or malformed input, e.g. undecodable transaction bytes (-32000).
+	InvalidInput(String),
+	/// The requested block, transaction or filter does not exist (-32001).
+	ResourceNotFound(String),
+	/// The requested resource exists but cannot be served right now (-32002).
+	ResourceUnavailable(String),
+	/// The transaction was refused by the pool (-32003).
+	TransactionRejected(TransactionRejection),
+	/// The method is not implemented by this node (-32601).
+	MethodNotSupported(String),
+	/// A node-side limit was reached (-32005).
+	LimitExceeded(String),
+	/// Invalid method parameters (-32602).
+	InvalidParams(String),
+	/// Anything else (-32603).
+	Internal(String),
+}
+
+impl EthRpcError {
+	/// Map an error returned by `TransactionPool::submit_one`.
+	pub fn from_pool_error<E: IntoPoolError + fmt::Debug>(err: E) -> Self {
+		let err = match err.into_pool_error() {
+			Ok(err) => err,
+			Err(err) => {
+				return Self::Internal(format!("submit transaction to pool failed: {:?}", err))
+			},
+		};
+		match err {
+			PoolError::InvalidTransaction(err) => Self::TransactionRejected(err.into()),
+			PoolError::UnknownTransaction(err) => Self::TransactionRejected(
+				TransactionRejection::Other(format!("unknown transaction validity: {:?}", err)),
+			),
+			PoolError::TemporarilyBanned => {
+				Self::TransactionRejected(TransactionRejection::TemporarilyBanned)
+			},
+			PoolError::AlreadyImported(_) => {
+				Self::TransactionRejected(TransactionRejection::AlreadyKnown)
+			},
+			PoolError::TooLowPriority { .. } => {
+				Self::TransactionRejected(TransactionRejection::ReplacementUnderpriced)
+			},
+			PoolError::CycleDetected => Self::TransactionRejected(TransactionRejection::Other(
+				"transaction dependency cycle detected".into(),
+			)),
+			PoolError::ImmediatelyDropped => Self::LimitExceeded("txpool is full".into()),
+			PoolError::Dropped => {
+				Self::TransactionRejected(TransactionRejection::Other("transaction dropped".into()))
+			},
+			PoolError::InvalidBlockId(id) => {
+				Self::ResourceNotFound(format!("unknown block {}", id))
+			},
+			PoolError::RejectedFutureTransaction => Self::TransactionRejected(
+				TransactionRejection::Other("future transaction rejected".into()),
+			),
+		}
+	}
+
+	/// The method is not served by this node.
+	pub fn method_not_supported<T: ToString>(method: T) -> Self {
+		Self::MethodNotSupported(format!(
+			"the method {} does not exist/is not available",
+			method.to_string()
+		))
+	}
+}
+
+impl From<EthRpcError> for Error {
+	fn from(err: EthRpcError) -> Self {
+		let (code, message) = match err {
+			EthRpcError::InvalidInput(message) => {
+				(ErrorCode::ServerError(codes::INVALID_INPUT), message)
+			},
+			EthRpcError::ResourceNotFound(message) => {
+				(ErrorCode::ServerError(codes::RESOURCE_NOT_FOUND), message)
+			},
+			EthRpcError::ResourceUnavailable(message) => {
+				(ErrorCode::ServerError(codes::RESOURCE_UNAVAILABLE), message)
+			},
+			EthRpcError::TransactionRejected(reason) => {
+				(ErrorCode::ServerError(codes::TRANSACTION_REJECTED), reason.to_string())
+			},
+			EthRpcError::MethodNotSupported(message) => (ErrorCode::MethodNotFound, message),
+			EthRpcError::LimitExceeded(message) => {
+				(ErrorCode::ServerError(codes::LIMIT_EXCEEDED), message)
+			},
+			EthRpcError::InvalidParams(message) => (ErrorCode::InvalidParams, message),
+			EthRpcError::Internal(message) => (ErrorCode::InternalError, message),
+		};
+		Error { code, message, data: None }
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn code(err: EthRpcError) -> ErrorCode {
+		Error::from(err).code
+	}
+
+	#[test]
+	fn validation_error_codes_round_trip() {
+		for err in [
+			TransactionValidationError::UnknownError,
+			TransactionValidationError::InvalidChainId,
+			TransactionValidationError::InvalidSignature,
+			TransactionValidationError::InvalidGasLimit,
+			TransactionValidationError::GasLimitTooLow,
+			TransactionValidationError::GasPriceTooLow,
+			TransactionValidationError::GasLimitTooHigh,
+			TransactionValidationError::InitcodeTooLarge,
+		] {
+			assert_eq!(TransactionValidationError::from_u8(err as u8), Some(err));
+		}
+		assert_eq!(TransactionValidationError::from_u8(u8::MAX), None);
+	}
+
+	#[test]
+	fn custom_invalid_transactions_are_mapped() {
+		let rejection = |err: TransactionValidationError| {
+			TransactionRejection::from(InvalidTransaction::Custom(err as u8))
+		};
+
+		assert_eq!(
+			rejection(TransactionValidationError::GasPriceTooLow),
+			TransactionRejection::Underpriced
+		);
+		assert_eq!(
+			rejection(TransactionValidationError::GasLimitTooHigh),
+			TransactionRejection::ExceedsTransactionGasLimit
+		);
+		assert_eq!(
+			rejection(TransactionValidationError::InitcodeTooLarge),
+			TransactionRejection::MaxInitcodeSizeExceeded
+		);
+		assert_eq!(
+			TransactionRejection::from(InvalidTransaction::Custom(u8::MAX)).to_string(),
+			"invalid transaction: custom error 255"
+		);
+	}
+
+	#[test]
+	fn pool_errors_are_mapped() {
+		assert_eq!(
+			EthRpcError::from_pool_error(PoolError::InvalidTransaction(InvalidTransaction::Stale)),
+			EthRpcError::TransactionRejected(TransactionRejection::NonceTooLow)
+		);
+		assert_eq!(
+			EthRpcError::from_pool_error(PoolError::AlreadyImported(Box::new(()))),
+			EthRpcError::TransactionRejected(TransactionRejection::AlreadyKnown)
+		);
+		assert_eq!(
+			EthRpcError::from_pool_error(PoolError::TooLowPriority { old: 2, new: 1 }),
+			EthRpcError::TransactionRejected(TransactionRejection::ReplacementUnderpriced)
+		);
+		assert_eq!(
+			EthRpcError::from_pool_error(PoolError::ImmediatelyDropped),
+			EthRpcError::LimitExceeded("txpool is full".into())
+		);
+		assert_eq!(
+			EthRpcError::from_pool_error(PoolError::InvalidBlockId("0x01".into())),
+			EthRpcError::ResourceNotFound("unknown block 0x01".into())
+		);
+	}
+
+	#[test]
+	fn errors_have_eip1474_codes() {
+		assert_eq!(
+			code(EthRpcError::ResourceNotFound("unknown block".into())),
+			ErrorCode::ServerError(codes::RESOURCE_NOT_FOUND)
+		);
+		assert_eq!(
+			code(EthRpcError::TransactionRejected(TransactionRejection::NonceTooLow)),
+			ErrorCode::ServerError(codes::TRANSACTION_REJECTED)
+		);
+		assert_eq!(
+			code(EthRpcError::method_not_supported("eth_newPendingTransactionFilter")),
+			ErrorCode::MethodNotFound
+		);
+		assert_eq!(
+			Error::from(EthRpcError::TransactionRejected(TransactionRejection::Underpriced))
+				.message,
+			"transaction underpriced"
+		);
+	}
+}
@@ -16,8 +16,8 @@
 // You should have received a copy of the GNU General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 use crate::{
-	error_on_execution_failure, frontier_backend_client, internal_err, public_key, EthSigner,
-	StorageOverride,
+	error_on_execution_failure, frontier_backend_client, internal_err, public_key, EthRpcError,
+	EthSigner, StorageOverride, TransactionRejection,
 };
 use ethereum::{BlockV2 as EthereumBlock, TransactionV2 as EthereumTransaction};
 use ethereum_types::{H160, H256, H512, H64, U256, U64};
@@ -27,19 +27,20 @@ use fc_rpc_core::{
 		Block, BlockNumber, BlockTransactions, Bytes, CallRequest, Filter, FilterChanges,
 		FilterPool, FilterPoolItem, FilterType, FilteredParams, Header, Index, Log, PeerCount,
 		Receipt, Rich, RichBlock, SyncInfo, SyncStatus, Transaction, TransactionMessage,
-		TransactionRequest, Work,
+		TransactionRequest, TypedData, Work,
 	},
 	EthApi as EthApiT, EthFilterApi as EthFilterApiT, NetApi as NetApiT, Web3Api as Web3ApiT,
 };
 use fp_rpc::{ConvertTransaction, EthereumRuntimeRPCApi, TransactionStatus};
 use futures::{future::TryFutureExt, StreamExt};
-use jsonrpc_core::{futures::future, BoxFuture, Result};
+use jsonrpc_core::{futures::future, BoxFuture, Error, Result};
 use lru::LruCache;
 use sc_client_api::{
 	backend::{Backend, StateBackend, StorageProvider},
 	client::BlockchainEvents,
 };
 use sc_network::{ExHashT, NetworkService};
+use sc_rpc::DenyUnsafe;
 use sc_transaction_pool::{ChainApi, Pool};
 use sc_transaction_pool_api::{InPoolTransaction, TransactionPool};
 use sha3::{Digest, Keccak256};
@@ -69,6 +70,7 @@ pub struct EthApi<B: BlockT, C, P, CT, BE, H: ExHashT, A: ChainApi> {
 	network: Arc<NetworkService<B, H>>,
 	is_authority: bool,
 	signers: Vec<Box<dyn EthSigner>>,
+	deny_unsafe: DenyUnsafe,
 	overrides: Arc<OverrideHandle<B>>,
 	backend: Arc<fc_db::Backend<B>>,
 	max_past_logs: u32,
@@ -91,6 +93,7 @@ where
 		convert_transaction: CT,
 		network: Arc<NetworkService<B, H>>,
 		signers: Vec<Box<dyn EthSigner>>,
+		deny_unsafe: DenyUnsafe,
 		overrides: Arc<OverrideHandle<B>>,
 		backend: Arc<fc_db::Backend<B>>,
 		is_authority: bool,
@@ -105,6 +108,7 @@ where
 			network,
 			is_authority,
 			signers,
+			deny_unsafe,
 			overrides,
 			backend,
 			max_past_logs,
@@ -114,6 +118,18 @@ where
 	}
 }
 
+/// Raw encoding of a signed transaction, as accepted by `eth_sendRawTransaction`.
+fn transaction_raw_bytes(transaction: &EthereumTransaction) -> Vec<u8> {
+	match transaction {
+		EthereumTransaction::Legacy(t) => rlp::encode(t).to_vec(),
+		// Typed transactions are rlp encoded as a byte string holding `type || payload`.
+		_ => rlp::Rlp::new(&rlp::encode(transaction))
+			.data()
+			.map(|d| d.to_vec())
+			.unwrap_or_default(),
+	}
+}
+
 fn rich_block_build(
 	block: ethereum::Block<EthereumTransaction>,
 	statuses: Vec<Option<TransactionStatus>>,
@@ -330,7 +346,7 @@ where
 		let id = BlockId::Number(current_number);
 		let substrate_hash = client
 			.expect_block_hash_from_id(&id)
-			.map_err(|_| internal_err(format!("Expect block number from id: {}", id)))?;
+			.map_err(|_| EthRpcError::ResourceNotFound(format!("unknown block {}", id)))?;
 
 		let schema = match default_schema {
 			// If there is a single schema, we just assign.
@@ -374,16 +390,18 @@ where
 		}
 		// Check for restrictions
 		if ret.len() as u32 > max_past_logs {
-			return Err(internal_err(format!(
+			return Err(EthRpcError::LimitExceeded(format!(
 				"query returned more than {} results",
 				max_past_logs
-			)));
+			))
+			.into());
 		}
 		if begin_request.elapsed() > max_duration {
-			return Err(internal_err(format!(
+			return Err(EthRpcError::LimitExceeded(format!(
 				"query timeout of {} seconds exceeded",
 				max_duration.as_secs()
-			)));
+			))
+			.into());
 		}
 		if current_number == Zero::zero() {
 			break;
@@ -476,9 +494,10 @@ fn fee_details(
 			if let Some(max_priority) = max_priority {
 				let max_fee = max_fee.unwrap_or_default();
 				if max_priority > max_fee {
-					return Err(internal_err(format!(
-						"Invalid input: `max_priority_fee_per_gas` greater than `max_fee_per_gas`"
-					)));
+					return Err(EthRpcError::InvalidInput(
+						"`max_priority_fee_per_gas` greater than `max_fee_per_gas`".into(),
+					)
+					.into());
 				}
 			}
 			Ok(FeeDetails {
@@ -490,6 +509,110 @@ fn fee_details(
 	}
 }
 
+impl<B, C, P, CT, BE, H: ExHashT, A> EthApi<B, C, P, CT, BE, H, A>
+where
+	C: ProvideRuntimeApi<B> + StorageProvider<B, BE>,
+	C: HeaderBackend<B> + HeaderMetadata<B, Error = BlockChainError> + 'static,
+	C::Api: EthereumRuntimeRPCApi<B>,
+	BE: Backend<B> + 'static,
+	BE::State: StateBackend<BlakeTwo256>,
+	B: BlockT<Hash = H256> + Send + Sync + 'static,
+	C: Send + Sync + 'static,
+	P: TransactionPool<Block = B> + Send + Sync + 'static,
+	A: ChainApi<Block = B> + 'static,
+	CT: ConvertTransaction<<B as BlockT>::Extrinsic> + Send + Sync + 'static,
+{
+	fn signer(&self, address: &H160) -> Result<&dyn EthSigner> {
+		self.signers
+			.iter()
+			.find(|signer| signer.accounts().contains(address))
+			.map(|signer| signer.as_ref())
+			.ok_or(internal_err("no signer available"))
+	}
+
+	/// Fill in the missing fields of a transaction request and sign it with the signer owning
+	/// its `from` account.
+	fn sign_request(&self, request: TransactionRequest) -> Result<EthereumTransaction> {
+		let from = match request.from {
+			Some(from) => from,
+			None => match self.accounts()?.get(0) {
+				Some(account) => account.clone(),
+				None => return Err(internal_err("no signer available")),
+			},
+		};
+
+		let nonce = match request.nonce {
+			Some(nonce) => nonce,
+			None => self.transaction_count(from, None)?,
+		};
+
+		let chain_id = match self.chain_id()? {
+			Some(chain_id) => chain_id.as_u64(),
+			None => return Err(internal_err("chain id not available")),
+		};
+
+		let hash = self.client.info().best_hash;
+
+		let gas_price = request.gas_price;
+		let gas_limit = match request.gas {
+			Some(gas_limit) => gas_limit,
+			None => {
+				let block = self
+					.client
+					.runtime_api()
+					.current_block(&BlockId::Hash(hash));
+				if let Ok(Some(block)) = block {
+					block.header.gas_limit
+				} else {
+					return Err(EthRpcError::ResourceUnavailable(
+						"block unavailable, cannot query gas limit".into(),
+					)
+					.into());
+				}
+			}
+		};
+		let max_fee_per_gas = request.max_fee_per_gas;
+		let message: Option<TransactionMessage> = request.into();
+		let message = match message {
+			Some(TransactionMessage::Legacy(mut m)) => {
+				m.nonce = nonce;
+				m.chain_id = Some(chain_id);
+				m.gas_limit = gas_limit;
+				if gas_price.is_none() {
+					m.gas_price = self.gas_price().unwrap_or(U256::default());
+				}
+				TransactionMessage::Legacy(m)
+			}
+			Some(TransactionMessage::EIP2930(mut m)) => {
+				m.nonce = nonce;
+				m.chain_id = chain_id;
+				m.gas_limit = gas_limit;
+				if gas_price.is_none() {
+					m.gas_price = self.gas_price().unwrap_or(U256::default());
+				}
+				TransactionMessage::EIP2930(m)
+			}
+			Some(TransactionMessage::EIP1559(mut m)) => {
+				m.nonce = nonce;
+				m.chain_id = chain_id;
+				m.gas_limit = gas_limit;
+				if max_fee_per_gas.is_none() {
+					m.max_fee_per_gas = self.gas_price().unwrap_or(U256::default());
+				}
+				TransactionMessage::EIP1559(m)
+			}
+			_ => {
+				return Err(EthRpcError::InvalidParams(
+					"invalid transaction parameters".into(),
+				)
+				.into());
+			}
+		};
+
+		self.signer(&from)?.sign(message, &from)
+	}
+}
+
 impl<B, C, P, CT, BE, H: ExHashT, A> EthApiT for EthApi<B, C, P, CT, BE, H, A>
 where
 	C: ProvideRuntimeApi<B> + StorageProvider<B, BE>,
@@ -639,7 +762,7 @@ where
 		let substrate_hash = self
 			.client
 			.expect_block_hash_from_id(&id)
-			.map_err(|_| internal_err(format!("Expect block number from id: {}", id)))?;
+			.map_err(|_| EthRpcError::ResourceNotFound(format!("unknown block {}", id)))?;
 
 		let schema =
 			frontier_backend_client::onchain_storage_schema::<B, C, BE>(self.client.as_ref(), id);
@@ -692,7 +815,7 @@ where
 		let substrate_hash = self
 			.client
 			.expect_block_hash_from_id(&id)
-			.map_err(|_| internal_err(format!("Expect block number from id: {}", id)))?;
+			.map_err(|_| EthRpcError::ResourceNotFound(format!("unknown block {}", id)))?;
 
 		let schema =
 			frontier_backend_client::onchain_storage_schema::<B, C, BE>(self.client.as_ref(), id);
@@ -853,106 +976,12 @@ where
 	}
 
 	fn send_transaction(&self, request: TransactionRequest) -> BoxFuture<Result<H256>> {
-		let from = match request.from {
-			Some(from) => from,
-			None => {
-				let accounts = match self.accounts() {
-					Ok(accounts) => accounts,
-					Err(e) => return Box::pin(future::err(e)),
-				};
-
-				match accounts.get(0) {
-					Some(account) => account.clone(),
-					None => return Box::pin(future::err(internal_err("no signer available"))),
-				}
-			}
-		};
-
-		let nonce = match request.nonce {
-			Some(nonce) => nonce,
-			None => match self.transaction_count(from, None) {
-				Ok(nonce) => nonce,
-				Err(e) => return Box::pin(future::err(e)),
-			},
-		};
-
-		let chain_id = match self.chain_id() {
-			Ok(Some(chain_id)) => chain_id.as_u64(),
-			Ok(None) => return Box::pin(future::err(internal_err("chain id not available"))),
+		let transaction = match self.sign_request(request) {
+			Ok(transaction) => transaction,
 			Err(e) => return Box::pin(future::err(e)),
 		};
-
-		let hash = self.client.info().best_hash;
-
-		let gas_price = request.gas_price;
-		let gas_limit = match request.gas {
-			Some(gas_limit) => gas_limit,
-			None => {
-				let block = self
-					.client
-					.runtime_api()
-					.current_block(&BlockId::Hash(hash));
-				if let Ok(Some(block)) = block {
-					block.header.gas_limit
-				} else {
-					return Box::pin(future::err(internal_err(format!(
-						"block unavailable, cannot query gas limit"
-					))));
-				}
-			}
-		};
-		let max_fee_per_gas = request.max_fee_per_gas;
-		let message: Option<TransactionMessage> = request.into();
-		let message = match message {
-			Some(TransactionMessage::Legacy(mut m)) => {
-				m.nonce = nonce;
-				m.chain_id = Some(chain_id);
-				m.gas_limit = gas_limit;
-				if gas_price.is_none() {
-					m.gas_price = self.gas_price().unwrap_or(U256::default());
-				}
-				TransactionMessage::Legacy(m)
-			}
-			Some(TransactionMessage::EIP2930(mut m)) => {
-				m.nonce = nonce;
-				m.chain_id = chain_id;
-				m.gas_limit = gas_limit;
-				if gas_price.is_none() {
-					m.gas_price = self.gas_price().unwrap_or(U256::default());
-				}
-				TransactionMessage::EIP2930(m)
-			}
-			Some(TransactionMessage::EIP1559(mut m)) => {
-				m.nonce = nonce;
-				m.chain_id = chain_id;
-				m.gas_limit = gas_limit;
-				if max_fee_per_gas.is_none() {
-					m.max_fee_per_gas = self.gas_price().unwrap_or(U256::default());
-				}
-				TransactionMessage::EIP1559(m)
-			}
-			_ => {
-				return Box::pin(future::err(internal_err("invalid transaction parameters")));
-			}
-		};
-
-		let mut transaction = None;
-
-		for signer in &self.signers {
-			if signer.accounts().contains(&from) {
-				match signer.sign(message, &from) {
-					Ok(t) => transaction = Some(t),
-					Err(e) => return Box::pin(future::err(e)),
-				}
-				break;
-			}
-		}
-
-		let transaction = match transaction {
-			Some(transaction) => transaction,
-			None => return Box::pin(future::err(internal_err("no signer available"))),
-		};
 		let transaction_hash = transaction.hash();
+		let hash = self.client.info().best_hash;
 		Box::pin(
 			self.pool
 				.submit_one(
@@ -962,23 +991,45 @@ where
 						.convert_transaction(transaction.clone()),
 				)
 				.map_ok(move |_| transaction_hash)
-				.map_err(|err| {
-					internal_err(format!("submit transaction to pool failed: {:?}", err))
-				}),
+				.map_err(|err| Error::from(EthRpcError::from_pool_error(err))),
 		)
 	}
 
+	fn sign(&self, address: H160, data: Bytes) -> Result<Bytes> {
+		self.deny_unsafe.check_if_safe()?;
+		let signer = self.signer(&address)?;
+		Ok(Bytes(signer.sign_message(&address, &data.0)?.to_vec()))
+	}
+
+	fn sign_transaction(&self, request: TransactionRequest) -> Result<Bytes> {
+		self.deny_unsafe.check_if_safe()?;
+		let transaction = self.sign_request(request)?;
+		Ok(Bytes(transaction_raw_bytes(&transaction)))
+	}
+
+	fn sign_typed_data(&self, address: H160, typed_data: TypedData) -> Result<Bytes> {
+		self.deny_unsafe.check_if_safe()?;
+		let signer = self.signer(&address)?;
+		Ok(Bytes(signer.sign_typed_data(&address, &typed_data)?.to_vec()))
+	}
+
 	fn send_raw_transaction(&self, bytes: Bytes) -> BoxFuture<Result<H256>> {
 		let slice = &bytes.0[..];
 		if slice.len() == 0 {
-			return Box::pin(future::err(internal_err("transaction data is empty")));
+			return Box::pin(future::err(Error::from(EthRpcError::InvalidInput(
+				"transaction data is empty".into(),
+			))));
 		}
 		let first = slice.get(0).unwrap();
 		let transaction = if first > &0x7f {
 			// Legacy transaction. Decode and wrap in envelope.
 			match rlp::decode::<ethereum::TransactionV0>(slice) {
 				Ok(transaction) => ethereum::TransactionV2::Legacy(transaction),
-				Err(_) => return Box::pin(future::err(internal_err("decode transaction failed"))),
+				Err(_) => {
+					return Box::pin(future::err(Error::from(EthRpcError::InvalidInput(
+						"decode transaction failed".into(),
+					))))
+				}
 			}
 		} else {
 			// Typed Transaction.
@@ -989,7 +1040,11 @@ where
 			let extend = rlp::encode(&slice);
 			match rlp::decode::<ethereum::TransactionV2>(&extend[..]) {
 				Ok(transaction) => transaction,
-				Err(_) => return Box::pin(future::err(internal_err("decode transaction failed"))),
+				Err(_) => {
+					return Box::pin(future::err(Error::from(EthRpcError::InvalidInput(
+						"decode transaction failed".into(),
+					))))
+				}
 			}
 		};
 
@@ -1004,9 +1059,7 @@ where
 						.convert_transaction(transaction.clone()),
 				)
 				.map_ok(move |_| transaction_hash)
-				.map_err(|err| {
-					internal_err(format!("submit transaction to pool failed: {:?}", err))
-				}),
+				.map_err(|err| Error::from(EthRpcError::from_pool_error(err))),
 		)
 	}
 
@@ -1048,9 +1101,10 @@ where
 				if let Some(block) = block {
 					block.header.gas_limit
 				} else {
-					return Err(internal_err(format!(
-						"block unavailable, cannot query gas limit"
-					)));
+					return Err(EthRpcError::ResourceUnavailable(
+						"block unavailable, cannot query gas limit".into(),
+					)
+					.into());
 				}
 			}
 		};
@@ -1207,7 +1261,10 @@ where
 			if let Some(block) = block {
 				Ok(block.header.gas_limit)
 			} else {
-				return Err(internal_err("block unavailable, cannot query gas limit"));
+				return Err(EthRpcError::ResourceUnavailable(
+					"block unavailable, cannot query gas limit".into(),
+				)
+				.into());
 			}
 		};
 
@@ -1233,7 +1290,10 @@ where
 				let mut available = balance;
 				if let Some(value) = request.value {
 					if value > available {
-						return Err(internal_err("insufficient funds for transfer"));
+						return Err(EthRpcError::TransactionRejected(
+							TransactionRejection::InsufficientFunds,
+						)
+						.into());
 					}
 					available -= value;
 				}
@@ -1514,7 +1574,7 @@ where
 		let substrate_hash = self
 			.client
 			.expect_block_hash_from_id(&id)
-			.map_err(|_| internal_err(format!("Expect block number from id: {}", id)))?;
+			.map_err(|_| EthRpcError::ResourceNotFound(format!("unknown block {}", id)))?;
 
 		let schema =
 			frontier_backend_client::onchain_storage_schema::<B, C, BE>(self.client.as_ref(), id);
@@ -1558,7 +1618,7 @@ where
 		let substrate_hash = self
 			.client
 			.expect_block_hash_from_id(&id)
-			.map_err(|_| internal_err(format!("Expect block number from id: {}", id)))?;
+			.map_err(|_| EthRpcError::ResourceNotFound(format!("unknown block {}", id)))?;
 
 		let index = index.value();
 
@@ -1606,7 +1666,7 @@ where
 		let substrate_hash = self
 			.client
 			.expect_block_hash_from_id(&id)
-			.map_err(|_| internal_err(format!("Expect block number from id: {}", id)))?;
+			.map_err(|_| EthRpcError::ResourceNotFound(format!("unknown block {}", id)))?;
 
 		let index = index.value();
 		let schema =
@@ -1659,7 +1719,7 @@ where
 		let substrate_hash = self
 			.client
 			.expect_block_hash_from_id(&id)
-			.map_err(|_| internal_err(format!("Expect block number from id: {}", id)))?;
+			.map_err(|_| EthRpcError::ResourceNotFound(format!("unknown block {}", id)))?;
 
 		let schema =
 			frontier_backend_client::onchain_storage_schema::<B, C, BE>(self.client.as_ref(), id);
@@ -1776,7 +1836,7 @@ where
 			let substrate_hash = self
 				.client
 				.expect_block_hash_from_id(&id)
-				.map_err(|_| internal_err(format!("Expect block number from id: {}", id)))?;
+				.map_err(|_| EthRpcError::ResourceNotFound(format!("unknown block {}", id)))?;
 
 			let schema = frontier_backend_client::onchain_storage_schema::<B, C, BE>(
 				self.client.as_ref(),
@@ -2007,10 +2067,11 @@ where
 		let pool = self.filter_pool.clone();
 		let response = if let Ok(locked) = &mut pool.lock() {
 			if locked.len() >= self.max_stored_filters {
-				return Err(internal_err(format!(
+				return Err(EthRpcError::LimitExceeded(format!(
 					"Filter pool is full (limit {:?}).",
 					self.max_stored_filters
-				)));
+				))
+				.into());
 			}
 			let last_key = match locked.iter().next_back() {
 				Some((k, _)) => *k,
@@ -2053,7 +2114,7 @@ where
 	}
 
 	fn new_pending_transaction_filter(&self) -> Result<U256> {
-		Err(internal_err("Method not available."))
+		Err(EthRpcError::method_not_supported("eth_newPendingTransactionFilter").into())
 	}
 
 	fn filter_changes(&self, index: Index) -> Result<FilterChanges> {
@@ -2075,7 +2136,7 @@ where
 							let id = BlockId::Number(n.unique_saturated_into());
 							let substrate_hash =
 								self.client.expect_block_hash_from_id(&id).map_err(|_| {
-									internal_err(format!("Expect block number from id: {}", id))
+									EthRpcError::ResourceNotFound(format!("unknown block {}", id))
 								})?;
 
 							let schema = frontier_backend_client::onchain_storage_schema::<B, C, BE>(
@@ -2160,11 +2221,18 @@ where
 						);
 						Ok(FilterChanges::Logs(ret))
 					}
-					// Should never reach here.
-					_ => Err(internal_err("Method not available.")),
+					// Pending transaction filters can't be installed.
+					_ => {
+						Err(EthRpcError::method_not_supported("eth_newPendingTransactionFilter")
+							.into())
+					},
 				}
 			} else {
-				Err(internal_err(format!("Filter id {:?} does not exist.", key)))
+				Err(EthRpcError::ResourceNotFound(format!(
+					"Filter id {:?} does not exist.",
+					key
+				))
+				.into())
 			}
 		} else {
 			Err(internal_err("Filter pool is not available."))
@@ -2224,7 +2292,11 @@ where
 					))),
 				}
 			} else {
-				Err(internal_err(format!("Filter id {:?} does not exist.", key)))
+				Err(EthRpcError::ResourceNotFound(format!(
+					"Filter id {:?} does not exist.",
+					key
+				))
+				.into())
 			}
 		} else {
 			Err(internal_err("Filter pool is not available."))
@@ -2240,7 +2312,11 @@ where
 			if let Some(_) = locked.remove(&key) {
 				Ok(true)
 			} else {
-				Err(internal_err(format!("Filter id {:?} does not exist.", key)))
+				Err(EthRpcError::ResourceNotFound(format!(
+					"Filter id {:?} does not exist.",
+					key
+				))
+				.into())
 			}
 		} else {
 			Err(internal_err("Filter pool is not available."))
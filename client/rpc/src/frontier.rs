@@ -0,0 +1,273 @@
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+// This file is part of Frontier.
+//
+// Copyright (c) 2020 Parity Technologies (UK) Ltd.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{marker::PhantomData, sync::Arc};
+
+use ethereum_types::{H160, H256, U256};
+use fc_rpc_core::{
+	types::{AddressTransaction, AddressTransactionsCursor, AddressTransactionsPage},
+	FrontierApi as FrontierApiT,
+};
+use jsonrpc_core::Result;
+use sc_client_api::backend::{Backend, StateBackend, StorageProvider};
+use sp_api::BlockId;
+use sp_blockchain::HeaderBackend;
+use sp_runtime::traits::{BlakeTwo256, Block as BlockT, UniqueSaturatedInto};
+
+pub use fc_rpc_core::FrontierApiServer;
+
+use crate::{check_page_size, frontier_backend_client, internal_err, overrides::OverrideHandle};
+
+/// Number of transactions returned when no limit is given.
+const DEFAULT_PAGE_SIZE: usize = 100;
+
+pub struct FrontierApi<B: BlockT, C, BE> {
+	client: Arc<C>,
+	backend: Arc<fc_db::Backend<B>>,
+	overrides: Arc<OverrideHandle<B>>,
+	_marker: PhantomData<BE>,
+}
+
+impl<B: BlockT, C, BE> FrontierApi<B, C, BE> {
+	pub fn new(
+		client: Arc<C>,
+		backend: Arc<fc_db::Backend<B>>,
+		overrides: Arc<OverrideHandle<B>>,
+	) -> Self {
+		Self { client, backend, overrides, _marker: PhantomData }
+	}
+}
+
+impl<B, C, BE> FrontierApiT for FrontierApi<B, C, BE>
+where
+	B: BlockT<Hash = H256> + Send + Sync + 'static,
+	C: StorageProvider<B, BE> + HeaderBackend<B> + Send + Sync + 'static,
+	BE: Backend<B> + 'static,
+	BE::State: StateBackend<BlakeTwo256>,
+{
+	fn address_transactions(
+		&self,
+		address: H160,
+		cursor: Option<AddressTransactionsCursor>,
+		limit: Option<usize>,
+	) -> Result<AddressTransactionsPage> {
+		let limit = limit.unwrap_or(DEFAULT_PAGE_SIZE);
+		check_page_size(limit)?;
+
+		let saturated = |value: U256| value.min(U256::from(u32::max_value())).low_u32();
+		let (start, start_index) = match cursor {
+			Some(cursor) => (
+				saturated(cursor.block_number),
+				saturated(cursor.transaction_index),
+			),
+			None => (
+				UniqueSaturatedInto::<u32>::unique_saturated_into(self.client.info().best_number),
+				u32::max_value(),
+			),
+		};
+
+		let appearances = self
+			.backend
+			.mapping()
+			.address_appearances(address, start, true)
+			.map_err(|err| internal_err(format!("fetch address index failed: {:?}", err)))?;
+
+		address_transactions_page(
+			appearances,
+			|hash| frontier_backend_client::is_canon::<B, C>(self.client.as_ref(), hash),
+			|appearance| {
+				let id = BlockId::Hash(appearance.block_hash);
+				let schema = frontier_backend_client::onchain_storage_schema::<B, C, BE>(
+					self.client.as_ref(),
+					id,
+				);
+				let handler =
+					self.overrides.schemas.get(&schema).unwrap_or(&self.overrides.fallback);
+				let (block, statuses) =
+					match (handler.current_block(&id), handler.current_transaction_statuses(&id)) {
+						(Some(block), Some(statuses)) => (block, statuses),
+						_ => return None,
+					};
+				statuses
+					.get(appearance.ethereum_index as usize)
+					.map(|status| AddressTransaction {
+						block_hash: block.header.hash(),
+						block_number: block.header.number,
+						transaction_index: U256::from(status.transaction_index),
+						transaction_hash: status.transaction_hash,
+					})
+			},
+			start,
+			start_index,
+			limit,
+		)
+	}
+}
+
+/// Page of up to `limit` transactions of the canonical `appearances` of an address, iterated in
+/// descending order from block `start`, where transactions after `start_index` are skipped.
+///
+/// `transaction` gives the transaction of an appearance, `None` when its block data is missing.
+fn address_transactions_page<B, I, T>(
+	appearances: I,
+	is_canon: impl Fn(H256) -> bool,
+	mut transaction: T,
+	start: u32,
+	start_index: u32,
+	limit: usize,
+) -> Result<AddressTransactionsPage>
+where
+	B: BlockT<Hash = H256>,
+	I: IntoIterator<Item = std::result::Result<fc_db::AddressAppearance<B>, String>>,
+	T: FnMut(&fc_db::AddressAppearance<B>) -> Option<AddressTransaction>,
+{
+	let mut transactions = Vec::new();
+	let mut next_cursor = None;
+	for appearance in appearances {
+		let appearance = appearance
+			.map_err(|err| internal_err(format!("fetch address index failed: {:?}", err)))?;
+		if (appearance.block_number == start && appearance.ethereum_index > start_index)
+			|| !is_canon(appearance.block_hash)
+		{
+			continue;
+		}
+		if transactions.len() == limit {
+			next_cursor = Some(AddressTransactionsCursor {
+				block_number: appearance.block_number.into(),
+				transaction_index: appearance.ethereum_index.into(),
+			});
+			break;
+		}
+		transactions.extend(transaction(&appearance));
+	}
+
+	Ok(AddressTransactionsPage { transactions, next_cursor })
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{error_codes, MAX_PAGE_SIZE};
+	use fc_db::AddressAppearance;
+	use jsonrpc_core::ErrorCode;
+	use sp_runtime::testing::{Block as TestBlock, ExtrinsicWrapper};
+
+	type Block = TestBlock<ExtrinsicWrapper<u64>>;
+
+	fn hash(block_number: u32, fork: u8) -> H256 {
+		let mut hash = H256::from_low_u64_be(block_number.into());
+		hash.0[0] = fork;
+		hash
+	}
+
+	/// Appearances in descending order, as read from the address index.
+	fn appearances(
+		appearances: &[(u32, u32, u8)],
+	) -> Vec<std::result::Result<AddressAppearance<Block>, String>> {
+		appearances
+			.iter()
+			.map(|(block_number, ethereum_index, fork)| {
+				Ok(AddressAppearance {
+					block_number: *block_number,
+					ethereum_index: *ethereum_index,
+					block_hash: hash(*block_number, *fork),
+				})
+			})
+			.collect()
+	}
+
+	fn canonical(block_hash: H256) -> bool {
+		block_hash.0[0] == 0
+	}
+
+	/// Block number and transaction index of the page transactions, and the next cursor.
+	fn page(
+		appearances: Vec<std::result::Result<AddressAppearance<Block>, String>>,
+		start: u32,
+		start_index: u32,
+		limit: usize,
+	) -> (Vec<(u32, u32)>, Option<(u32, u32)>) {
+		let page = address_transactions_page(
+			appearances,
+			canonical,
+			|appearance| {
+				// Block data of block 2 is missing.
+				if appearance.block_number == 2 {
+					return None;
+				}
+				Some(AddressTransaction {
+					block_hash: appearance.block_hash,
+					block_number: appearance.block_number.into(),
+					transaction_index: appearance.ethereum_index.into(),
+					transaction_hash: H256::repeat_byte(appearance.ethereum_index as u8),
+				})
+			},
+			start,
+			start_index,
+			limit,
+		)
+		.unwrap();
+		(
+			page.transactions
+				.iter()
+				.map(|t| (t.block_number.low_u32(), t.transaction_index.low_u32()))
+				.collect(),
+			page.next_cursor
+				.map(|c| (c.block_number.low_u32(), c.transaction_index.low_u32())),
+		)
+	}
+
+	#[test]
+	fn page_sizes_are_checked() {
+		assert_eq!(check_page_size(0).unwrap_err().code, ErrorCode::InvalidParams);
+		assert!(check_page_size(1).is_ok());
+		assert!(check_page_size(MAX_PAGE_SIZE).is_ok());
+		assert_eq!(
+			check_page_size(MAX_PAGE_SIZE + 1).unwrap_err().code,
+			ErrorCode::ServerError(error_codes::LIMIT_EXCEEDED)
+		);
+	}
+
+	#[test]
+	fn pages_continue_from_their_cursor() {
+		let all = appearances(&[(9, 1, 0), (9, 0, 0), (5, 2, 0), (5, 1, 0), (5, 0, 0), (4, 0, 0)]);
+
+		assert_eq!(
+			page(all.clone(), 9, u32::max_value(), 3),
+			(vec![(9, 1), (9, 0), (5, 2)], Some((5, 1)))
+		);
+		assert_eq!(page(all[3..].to_vec(), 5, 1, 3), (vec![(5, 1), (5, 0), (4, 0)], None));
+		// Transactions of the start block after the cursor index are skipped.
+		assert_eq!(page(all[2..].to_vec(), 5, 1, 10), (vec![(5, 1), (5, 0), (4, 0)], None));
+	}
+
+	#[test]
+	fn pages_skip_non_canonical_blocks() {
+		let all = appearances(&[(9, 0, 1), (8, 0, 0), (7, 0, 2), (7, 0, 0)]);
+
+		assert_eq!(page(all.clone(), 9, u32::max_value(), 1), (vec![(8, 0)], Some((7, 0))));
+		assert_eq!(page(all, 9, u32::max_value(), 10), (vec![(8, 0), (7, 0)], None));
+	}
+
+	#[test]
+	fn transactions_without_block_data_are_skipped() {
+		let all = appearances(&[(3, 0, 0), (2, 0, 0), (1, 0, 0)]);
+
+		assert_eq!(page(all, 3, u32::max_value(), 2), (vec![(3, 0), (1, 0)], None));
+	}
+}
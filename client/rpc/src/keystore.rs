@@ -0,0 +1,302 @@
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+// This file is part of Frontier.
+//
+// Copyright (c) 2020 Parity Technologies (UK) Ltd.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Ethereum signer backed by a directory of geth (V3) JSON keystore files.
+
+use std::{
+	collections::BTreeMap,
+	fs,
+	path::{Path, PathBuf},
+	sync::Arc,
+	time::{Duration, Instant},
+};
+
+use ethereum_types::H160;
+use jsonrpc_core::Error;
+use parking_lot::RwLock;
+use rustc_hex::{FromHex, ToHex};
+
+use crate::{
+	eth_message_hash, internal_err, secret_key_address, sign_hash, sign_transaction_message,
+	EthRpcError, EthSigner, EthereumTransaction, TransactionMessage, TypedData,
+};
+
+/// How long an account stays unlocked when `personal_unlockAccount` is not given a duration.
+pub const DEFAULT_UNLOCK_DURATION: Duration = Duration::from_secs(300);
+
+struct KeystoreAccount {
+	path: PathBuf,
+	/// Decrypted key, with the instant it expires at (`None` for never).
+	unlocked: Option<(secp256k1::SecretKey, Option<Instant>)>,
+}
+
+impl KeystoreAccount {
+	fn secret(&self) -> Option<&secp256k1::SecretKey> {
+		match &self.unlocked {
+			Some((secret, None)) => Some(secret),
+			Some((secret, Some(expiry))) if Instant::now() < *expiry => Some(secret),
+			_ => None,
+		}
+	}
+}
+
+/// Signer over the JSON keystore files (scrypt or pbkdf2) of a directory.
+///
+/// Keys are kept encrypted until their account is unlocked, either at startup with a password
+/// file or later through `personal_unlockAccount`. Clones share the same accounts.
+#[derive(Clone)]
+pub struct EthKeystoreSigner {
+	dir: PathBuf,
+	accounts: Arc<RwLock<BTreeMap<H160, KeystoreAccount>>>,
+}
+
+impl EthKeystoreSigner {
+	/// Load all keystore files found in `dir`, creating it if needed.
+	///
+	/// With a password file, the n-th line unlocks the n-th keystore file in name order (the
+	/// last line being reused for the remaining files) until the node stops.
+	pub fn open(dir: &Path, password_file: Option<&Path>) -> Result<Self, String> {
+		fs::create_dir_all(dir).map_err(|err| format!("{:?}", err))?;
+		let mut paths = fs::read_dir(dir)
+			.map_err(|err| format!("{:?}", err))?
+			.filter_map(|entry| entry.ok().map(|entry| entry.path()))
+			.filter(|path| path.is_file())
+			.collect::<Vec<_>>();
+		paths.sort();
+
+		let passwords = match password_file {
+			Some(file) => fs::read_to_string(file)
+				.map_err(|err| format!("{:?}", err))?
+				.lines()
+				.map(|line| line.to_string())
+				.collect(),
+			None => Vec::new(),
+		};
+
+		let mut accounts = BTreeMap::new();
+		for path in paths {
+			let address = match keystore_address(&path) {
+				Some(address) => address,
+				None => {
+					log::warn!(target: "rpc", "Skipping {:?}: not a keystore file", path);
+					continue;
+				},
+			};
+			let unlocked = match passwords.get(accounts.len()).or(passwords.last()) {
+				Some(password) => Some((decrypt(&path, &address, password)?, None)),
+				None => None,
+			};
+			accounts.insert(address, KeystoreAccount { path, unlocked });
+		}
+
+		Ok(Self { dir: dir.to_path_buf(), accounts: Arc::new(RwLock::new(accounts)) })
+	}
+
+	/// Decrypt the key of `address` and keep it for `duration` (`None` for no expiry).
+	pub fn unlock(
+		&self,
+		address: &H160,
+		password: &str,
+		duration: Option<Duration>,
+	) -> Result<(), String> {
+		// Key derivation is slow, do not hold the lock meanwhile.
+		let path = self.path(address)?;
+		let secret = decrypt(&path, address, password)?;
+		if let Some(account) = self.accounts.write().get_mut(address) {
+			account.unlocked = Some((secret, duration.map(|duration| Instant::now() + duration)));
+		}
+		Ok(())
+	}
+
+	/// Store `secret` encrypted with `password` in the keystore directory. The new account is
+	/// locked.
+	pub fn import_raw_key(
+		&self,
+		secret: &secp256k1::SecretKey,
+		password: &str,
+	) -> Result<H160, String> {
+		let address = secret_key_address(secret);
+		if self.accounts.read().contains_key(&address) {
+			return Err("account already exists".into());
+		}
+		let name = format!("{}.json", address.as_bytes().to_hex::<String>());
+		eth_keystore::encrypt_key(
+			&self.dir,
+			&mut rand_core::OsRng,
+			&secret.serialize()[..],
+			password,
+			Some(&name),
+		)
+		.map_err(|err| format!("{:?}", err))?;
+		self.accounts
+			.write()
+			.insert(address, KeystoreAccount { path: self.dir.join(name), unlocked: None });
+		Ok(address)
+	}
+
+	/// Run `f` with the key of `address`. The key is decrypted with `password` when one is
+	/// given, otherwise the account must be unlocked.
+	pub fn with_secret<R>(
+		&self,
+		address: &H160,
+		password: Option<&str>,
+		f: impl FnOnce(&secp256k1::SecretKey) -> R,
+	) -> Result<R, String> {
+		match password {
+			Some(password) => Ok(f(&decrypt(&self.path(address)?, address, password)?)),
+			None => {
+				let accounts = self.accounts.read();
+				let account = accounts.get(address).ok_or("unknown account")?;
+				let secret = account.secret().ok_or("authentication needed: password or unlock")?;
+				Ok(f(secret))
+			},
+		}
+	}
+
+	fn path(&self, address: &H160) -> Result<PathBuf, String> {
+		self.accounts
+			.read()
+			.get(address)
+			.map(|account| account.path.clone())
+			.ok_or("unknown account".into())
+	}
+}
+
+impl EthSigner for EthKeystoreSigner {
+	fn accounts(&self) -> Vec<H160> {
+		self.accounts.read().keys().cloned().collect()
+	}
+
+	fn sign(
+		&self,
+		message: TransactionMessage,
+		address: &H160,
+	) -> Result<EthereumTransaction, Error> {
+		self.with_secret(address, None, |secret| sign_transaction_message(message, secret))
+			.map_err(internal_err)?
+	}
+
+	fn sign_message(&self, address: &H160, data: &[u8]) -> Result<[u8; 65], Error> {
+		let hash = eth_message_hash(data);
+		self.with_secret(address, None, |secret| sign_hash(&hash, secret))
+			.map_err(internal_err)?
+	}
+
+	fn sign_typed_data(&self, address: &H160, typed_data: &TypedData) -> Result<[u8; 65], Error> {
+		let hash = typed_data.signing_hash().map_err(EthRpcError::InvalidParams)?;
+		self.with_secret(address, None, |secret| sign_hash(&hash, secret))
+			.map_err(internal_err)?
+	}
+}
+
+/// Address a keystore file claims to hold, if it looks like one.
+fn keystore_address(path: &Path) -> Option<H160> {
+	let contents = fs::read_to_string(path).ok()?;
+	let json: serde_json::Value = serde_json::from_str(&contents).ok()?;
+	json.get("crypto")?;
+	let address = json.get("address")?.as_str()?;
+	let bytes: Vec<u8> = address.trim_start_matches("0x").from_hex().ok()?;
+	if bytes.len() != 20 {
+		return None;
+	}
+	Some(H160::from_slice(&bytes))
+}
+
+fn decrypt(path: &Path, address: &H160, password: &str) -> Result<secp256k1::SecretKey, String> {
+	let bytes = eth_keystore::decrypt_key(path, password)
+		.map_err(|err| format!("could not decrypt key with given password: {:?}", err))?;
+	let secret = secp256k1::SecretKey::parse_slice(&bytes)
+		.map_err(|_| format!("invalid key in keystore file {:?}", path))?;
+	if &secret_key_address(&secret) != address {
+		return Err(format!("key in keystore file {:?} does not match its address", path));
+	}
+	Ok(secret)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::recover_signer;
+
+	fn secret() -> secp256k1::SecretKey {
+		secp256k1::SecretKey::parse(&[0x42u8; 32]).unwrap()
+	}
+
+	fn keystore_with_key(password: &str) -> (tempfile::TempDir, H160) {
+		let dir = tempfile::tempdir().unwrap();
+		let signer = EthKeystoreSigner::open(dir.path(), None).unwrap();
+		let address = signer.import_raw_key(&secret(), password).unwrap();
+		(dir, address)
+	}
+
+	#[test]
+	fn open_loads_keystore_files_locked() {
+		let (dir, address) = keystore_with_key("password");
+		fs::write(dir.path().join("notes.txt"), "not a keystore").unwrap();
+
+		let signer = EthKeystoreSigner::open(dir.path(), None).unwrap();
+
+		assert_eq!(signer.accounts(), vec![address]);
+		assert!(signer.sign_message(&address, b"hello").is_err());
+	}
+
+	#[test]
+	fn open_unlocks_accounts_with_password_file() {
+		let (dir, address) = keystore_with_key("password");
+		let passwords = tempfile::NamedTempFile::new().unwrap();
+		fs::write(passwords.path(), "password\n").unwrap();
+
+		let signer = EthKeystoreSigner::open(dir.path(), Some(passwords.path())).unwrap();
+
+		assert!(signer.sign_message(&address, b"hello").is_ok());
+	}
+
+	#[test]
+	fn wrong_password_is_rejected() {
+		let (dir, address) = keystore_with_key("password");
+		let passwords = tempfile::NamedTempFile::new().unwrap();
+		fs::write(passwords.path(), "wrong\n").unwrap();
+		let signer = EthKeystoreSigner::open(dir.path(), None).unwrap();
+
+		assert!(signer.unlock(&address, "wrong", None).is_err());
+		assert!(signer.with_secret(&address, Some("wrong"), |_| ()).is_err());
+		assert!(signer.sign_message(&address, b"hello").is_err());
+		assert!(EthKeystoreSigner::open(dir.path(), Some(passwords.path())).is_err());
+	}
+
+	#[test]
+	fn unlocked_account_signs() {
+		let (dir, address) = keystore_with_key("password");
+		let signer = EthKeystoreSigner::open(dir.path(), None).unwrap();
+		signer.unlock(&address, "password", None).unwrap();
+
+		let signature = signer.sign_message(&address, b"hello").unwrap();
+
+		assert_eq!(recover_signer(&eth_message_hash(b"hello"), &signature).unwrap(), address);
+		assert_eq!(address, secret_key_address(&secret()));
+	}
+
+	#[test]
+	fn unlock_expires() {
+		let (dir, address) = keystore_with_key("password");
+		let signer = EthKeystoreSigner::open(dir.path(), None).unwrap();
+		signer.unlock(&address, "password", Some(Duration::from_secs(0))).unwrap();
+
+		assert!(signer.sign_message(&address, b"hello").is_err());
+	}
+}
@@ -16,23 +16,33 @@
 // You should have received a copy of the GNU General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
+mod errors;
 mod eth;
 mod eth_pubsub;
+mod frontier;
+mod keystore;
+mod ots;
 mod overrides;
+mod personal;
 
+pub use errors::{codes as error_codes, EthRpcError, TransactionRejection};
 pub use eth::{
 	EthApi, EthApiServer, EthBlockDataCache, EthFilterApi, EthFilterApiServer, EthTask, NetApi,
 	NetApiServer, Web3Api, Web3ApiServer,
 };
 pub use eth_pubsub::{EthPubSubApi, EthPubSubApiServer, HexEncodedIdProvider};
 pub use ethereum::TransactionV2 as EthereumTransaction;
+pub use frontier::{FrontierApi, FrontierApiServer};
+pub use keystore::{EthKeystoreSigner, DEFAULT_UNLOCK_DURATION};
+pub use ots::{OtsApi, OtsApiServer};
 pub use overrides::{
 	OverrideHandle, RuntimeApiStorageOverride, SchemaV1Override, SchemaV2Override, StorageOverride,
 };
+pub use personal::{PersonalApi, PersonalApiServer};
 
 use ethereum_types::{H160, H256};
 use evm::ExitError;
-pub use fc_rpc_core::types::TransactionMessage;
+pub use fc_rpc_core::types::{TransactionMessage, TypedData};
 use jsonrpc_core::{Error, ErrorCode, Value};
 use pallet_evm::ExitReason;
 use rustc_hex::ToHex;
@@ -203,6 +213,24 @@ pub fn internal_err<T: ToString>(message: T) -> Error {
 	}
 }
 
+/// Maximum number of transactions in a page of the methods served by the address index.
+pub(crate) const MAX_PAGE_SIZE: usize = 1000;
+
+/// Reject a page size of zero or above `MAX_PAGE_SIZE`.
+pub(crate) fn check_page_size(page_size: usize) -> Result<(), Error> {
+	if page_size == 0 {
+		return Err(EthRpcError::InvalidParams("page size must be at least 1".into()).into());
+	}
+	if page_size > MAX_PAGE_SIZE {
+		return Err(EthRpcError::LimitExceeded(format!(
+			"page size {} exceeds the limit of {}",
+			page_size, MAX_PAGE_SIZE
+		))
+		.into());
+	}
+	Ok(())
+}
+
 pub fn error_on_execution_failure(reason: &ExitReason, data: &[u8]) -> Result<(), Error> {
 	match reason {
 		ExitReason::Succeed(_) => Ok(()),
@@ -282,6 +310,11 @@ pub trait EthSigner: Send + Sync {
 		message: TransactionMessage,
 		address: &H160,
 	) -> Result<EthereumTransaction, Error>;
+	/// Sign `data` with the EIP-191 `"\x19Ethereum Signed Message:\n"` prefix, as `eth_sign`
+	/// does. The signature is returned as `r || s || v`.
+	fn sign_message(&self, address: &H160, data: &[u8]) -> Result<[u8; 65], Error>;
+	/// Sign the EIP-712 hash of typed data. The signature is returned as `r || s || v`.
+	fn sign_typed_data(&self, address: &H160, typed_data: &TypedData) -> Result<[u8; 65], Error>;
 }
 
 pub struct EthDevSigner {
@@ -299,20 +332,18 @@ impl EthDevSigner {
 			.expect("Test key is valid; qed")],
 		}
 	}
-}
 
-impl EthSigner for EthDevSigner {
-	fn accounts(&self) -> Vec<H160> {
+	fn secret(&self, address: &H160) -> Result<&secp256k1::SecretKey, Error> {
 		self.keys
 			.iter()
-			.map(|secret| {
-				let public = secp256k1::PublicKey::from_secret_key(secret);
-				let mut res = [0u8; 64];
-				res.copy_from_slice(&public.serialize()[1..65]);
+			.find(|secret| &secret_key_address(secret) == address)
+			.ok_or(internal_err("signer not available"))
+	}
+}
 
-				H160::from(H256::from_slice(Keccak256::digest(&res).as_slice()))
-			})
-			.collect()
+impl EthSigner for EthDevSigner {
+	fn accounts(&self) -> Vec<H160> {
+		self.keys.iter().map(secret_key_address).collect()
 	}
 
 	fn sign(
@@ -320,91 +351,138 @@ impl EthSigner for EthDevSigner {
 		message: TransactionMessage,
 		address: &H160,
 	) -> Result<EthereumTransaction, Error> {
-		let mut transaction = None;
-
-		for secret in &self.keys {
-			let key_address = {
-				let public = secp256k1::PublicKey::from_secret_key(secret);
-				let mut res = [0u8; 64];
-				res.copy_from_slice(&public.serialize()[1..65]);
-				H160::from(H256::from_slice(Keccak256::digest(&res).as_slice()))
-			};
+		sign_transaction_message(message, self.secret(address)?)
+	}
 
-			if &key_address == address {
-				match message {
-					TransactionMessage::Legacy(m) => {
-						let signing_message = secp256k1::Message::parse_slice(&m.hash()[..])
-							.map_err(|_| internal_err("invalid signing message"))?;
-						let (signature, recid) = secp256k1::sign(&signing_message, secret);
-						let v = match m.chain_id {
-							None => 27 + recid.serialize() as u64,
-							Some(chain_id) => 2 * chain_id + 35 + recid.serialize() as u64,
-						};
-						let rs = signature.serialize();
-						let r = H256::from_slice(&rs[0..32]);
-						let s = H256::from_slice(&rs[32..64]);
-						transaction =
-							Some(EthereumTransaction::Legacy(ethereum::LegacyTransaction {
-								nonce: m.nonce,
-								gas_price: m.gas_price,
-								gas_limit: m.gas_limit,
-								action: m.action,
-								value: m.value,
-								input: m.input.clone(),
-								signature: ethereum::TransactionSignature::new(v, r, s)
-									.ok_or(internal_err("signer generated invalid signature"))?,
-							}));
-					}
-					TransactionMessage::EIP2930(m) => {
-						let signing_message = secp256k1::Message::parse_slice(&m.hash()[..])
-							.map_err(|_| internal_err("invalid signing message"))?;
-						let (signature, recid) = secp256k1::sign(&signing_message, secret);
-						let rs = signature.serialize();
-						let r = H256::from_slice(&rs[0..32]);
-						let s = H256::from_slice(&rs[32..64]);
-						transaction =
-							Some(EthereumTransaction::EIP2930(ethereum::EIP2930Transaction {
-								chain_id: m.chain_id,
-								nonce: m.nonce,
-								gas_price: m.gas_price,
-								gas_limit: m.gas_limit,
-								action: m.action,
-								value: m.value,
-								input: m.input.clone(),
-								access_list: m.access_list,
-								odd_y_parity: recid.serialize() != 0,
-								r,
-								s,
-							}));
-					}
-					TransactionMessage::EIP1559(m) => {
-						let signing_message = secp256k1::Message::parse_slice(&m.hash()[..])
-							.map_err(|_| internal_err("invalid signing message"))?;
-						let (signature, recid) = secp256k1::sign(&signing_message, secret);
-						let rs = signature.serialize();
-						let r = H256::from_slice(&rs[0..32]);
-						let s = H256::from_slice(&rs[32..64]);
-						transaction =
-							Some(EthereumTransaction::EIP1559(ethereum::EIP1559Transaction {
-								chain_id: m.chain_id,
-								nonce: m.nonce,
-								max_priority_fee_per_gas: m.max_priority_fee_per_gas,
-								max_fee_per_gas: m.max_fee_per_gas,
-								gas_limit: m.gas_limit,
-								action: m.action,
-								value: m.value,
-								input: m.input.clone(),
-								access_list: m.access_list,
-								odd_y_parity: recid.serialize() != 0,
-								r,
-								s,
-							}));
-					}
-				}
-				break;
-			}
-		}
+	fn sign_message(&self, address: &H160, data: &[u8]) -> Result<[u8; 65], Error> {
+		sign_hash(&eth_message_hash(data), self.secret(address)?)
+	}
+
+	fn sign_typed_data(&self, address: &H160, typed_data: &TypedData) -> Result<[u8; 65], Error> {
+		let hash = typed_data.signing_hash().map_err(EthRpcError::InvalidParams)?;
+		sign_hash(&hash, self.secret(address)?)
+	}
+}
+
+/// Ethereum address of the given public key.
+pub fn public_key_address(public: &secp256k1::PublicKey) -> H160 {
+	let mut res = [0u8; 64];
+	res.copy_from_slice(&public.serialize()[1..65]);
+	H160::from(H256::from_slice(Keccak256::digest(&res).as_slice()))
+}
+
+/// Ethereum address controlled by the given secret key.
+pub fn secret_key_address(secret: &secp256k1::SecretKey) -> H160 {
+	public_key_address(&secp256k1::PublicKey::from_secret_key(secret))
+}
+
+/// Hash signed by `personal_sign`, as defined in EIP-191 (version `0x45`).
+pub fn eth_message_hash(data: &[u8]) -> H256 {
+	let mut message = format!("\x19Ethereum Signed Message:\n{}", data.len()).into_bytes();
+	message.extend_from_slice(data);
+	H256::from_slice(Keccak256::digest(&message).as_slice())
+}
+
+/// Sign a 32 bytes hash, returning the signature as `r || s || v` with `v` in `{27, 28}`.
+pub fn sign_hash(hash: &H256, secret: &secp256k1::SecretKey) -> Result<[u8; 65], Error> {
+	let signing_message = secp256k1::Message::parse_slice(&hash[..])
+		.map_err(|_| internal_err("invalid signing message"))?;
+	let (signature, recid) = secp256k1::sign(&signing_message, secret);
+	let mut res = [0u8; 65];
+	res[0..64].copy_from_slice(&signature.serialize()[..]);
+	res[64] = 27 + recid.serialize();
+	Ok(res)
+}
+
+/// Recover the address that produced a `r || s || v` signature of `hash`.
+///
+/// Both `{0, 1}` and `{27, 28}` are accepted for `v`.
+pub fn recover_signer(hash: &H256, signature: &[u8]) -> Result<H160, Error> {
+	if signature.len() != 65 {
+		return Err(EthRpcError::InvalidParams("signature must be 65 bytes long".into()).into());
+	}
+	let v = if signature[64] >= 27 { signature[64] - 27 } else { signature[64] };
+	let recid = secp256k1::RecoveryId::parse(v)
+		.map_err(|_| EthRpcError::InvalidParams("invalid signature recovery id".into()))?;
+	let rs = secp256k1::Signature::parse_slice(&signature[0..64])
+		.map_err(|_| EthRpcError::InvalidParams("invalid signature".into()))?;
+	let message = secp256k1::Message::parse_slice(&hash[..])
+		.map_err(|_| internal_err("invalid signing message"))?;
+	let public = secp256k1::recover(&message, &rs, &recid)
+		.map_err(|_| EthRpcError::InvalidParams("invalid signature".into()))?;
+	Ok(public_key_address(&public))
+}
 
-		transaction.ok_or(internal_err("signer not available"))
+/// Sign a transaction message with the given secret key.
+pub fn sign_transaction_message(
+	message: TransactionMessage,
+	secret: &secp256k1::SecretKey,
+) -> Result<EthereumTransaction, Error> {
+	match message {
+		TransactionMessage::Legacy(m) => {
+			let signing_message = secp256k1::Message::parse_slice(&m.hash()[..])
+				.map_err(|_| internal_err("invalid signing message"))?;
+			let (signature, recid) = secp256k1::sign(&signing_message, secret);
+			let v = match m.chain_id {
+				None => 27 + recid.serialize() as u64,
+				Some(chain_id) => 2 * chain_id + 35 + recid.serialize() as u64,
+			};
+			let rs = signature.serialize();
+			let r = H256::from_slice(&rs[0..32]);
+			let s = H256::from_slice(&rs[32..64]);
+			Ok(EthereumTransaction::Legacy(ethereum::LegacyTransaction {
+				nonce: m.nonce,
+				gas_price: m.gas_price,
+				gas_limit: m.gas_limit,
+				action: m.action,
+				value: m.value,
+				input: m.input.clone(),
+				signature: ethereum::TransactionSignature::new(v, r, s)
+					.ok_or(internal_err("signer generated invalid signature"))?,
+			}))
+		}
+		TransactionMessage::EIP2930(m) => {
+			let signing_message = secp256k1::Message::parse_slice(&m.hash()[..])
+				.map_err(|_| internal_err("invalid signing message"))?;
+			let (signature, recid) = secp256k1::sign(&signing_message, secret);
+			let rs = signature.serialize();
+			let r = H256::from_slice(&rs[0..32]);
+			let s = H256::from_slice(&rs[32..64]);
+			Ok(EthereumTransaction::EIP2930(ethereum::EIP2930Transaction {
+				chain_id: m.chain_id,
+				nonce: m.nonce,
+				gas_price: m.gas_price,
+				gas_limit: m.gas_limit,
+				action: m.action,
+				value: m.value,
+				input: m.input.clone(),
+				access_list: m.access_list,
+				odd_y_parity: recid.serialize() != 0,
+				r,
+				s,
+			}))
+		}
+		TransactionMessage::EIP1559(m) => {
+			let signing_message = secp256k1::Message::parse_slice(&m.hash()[..])
+				.map_err(|_| internal_err("invalid signing message"))?;
+			let (signature, recid) = secp256k1::sign(&signing_message, secret);
+			let rs = signature.serialize();
+			let r = H256::from_slice(&rs[0..32]);
+			let s = H256::from_slice(&rs[32..64]);
+			Ok(EthereumTransaction::EIP1559(ethereum::EIP1559Transaction {
+				chain_id: m.chain_id,
+				nonce: m.nonce,
+				max_priority_fee_per_gas: m.max_priority_fee_per_gas,
+				max_fee_per_gas: m.max_fee_per_gas,
+				gas_limit: m.gas_limit,
+				action: m.action,
+				value: m.value,
+				input: m.input.clone(),
+				access_list: m.access_list,
+				odd_y_parity: recid.serialize() != 0,
+				r,
+				s,
+			}))
+		}
 	}
 }
@@ -0,0 +1,531 @@
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+// This file is part of Frontier.
+//
+// Copyright (c) 2020 Parity Technologies (UK) Ltd.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{marker::PhantomData, sync::Arc};
+
+use ethereum::BlockV2 as EthereumBlock;
+use ethereum_types::{H160, H256, U256};
+use fc_rpc_core::{
+	types::{
+		BlockDetails, BlockNumber, BlockTransactions, BlockTransactionsPage, Bytes,
+		ContractCreator, InternalOperation, Issuance, OtsBlock, OtsReceipt, RichBlock,
+		TransactionsPage,
+	},
+	EthApi as EthApiT, OtsApi as OtsApiT,
+};
+use fp_rpc::TransactionStatus;
+use jsonrpc_core::Result;
+use sc_client_api::backend::{Backend, StateBackend, StorageProvider};
+use sp_api::BlockId;
+use sp_blockchain::HeaderBackend;
+use sp_runtime::traits::{BlakeTwo256, Block as BlockT, UniqueSaturatedInto};
+
+pub use fc_rpc_core::OtsApiServer;
+
+use crate::{
+	check_page_size, frontier_backend_client, internal_err, overrides::OverrideHandle, EthRpcError,
+	EthereumTransaction,
+};
+
+/// Version of the Otterscan API reported by `ots_getApiLevel`.
+///
+/// Every level of the Otterscan API includes `ots_getInternalOperations` and
+/// `ots_getTransactionError`, which need internal call tracing that this node doesn't have. No
+/// level is advertised, so that Otterscan reports the node as unsupported instead of failing on
+/// these methods, while other clients can still use the methods served.
+const API_LEVEL: u64 = 0;
+
+/// Otterscan API, built on top of the Ethereum API and the address index of the Frontier
+/// database.
+///
+/// Internal calls are not traced, so `ots_getInternalOperations` and `ots_getTransactionError`
+/// are not supported and contracts created by other contracts have no known creator. Otterscan
+/// itself needs them, see `API_LEVEL`.
+pub struct OtsApi<B: BlockT, C, BE, E> {
+	client: Arc<C>,
+	backend: Arc<fc_db::Backend<B>>,
+	overrides: Arc<OverrideHandle<B>>,
+	eth: E,
+	_marker: PhantomData<BE>,
+}
+
+impl<B: BlockT, C, BE, E> OtsApi<B, C, BE, E> {
+	pub fn new(
+		client: Arc<C>,
+		backend: Arc<fc_db::Backend<B>>,
+		overrides: Arc<OverrideHandle<B>>,
+		eth: E,
+	) -> Self {
+		Self { client, backend, overrides, eth, _marker: PhantomData }
+	}
+}
+
+impl<B, C, BE, E> OtsApi<B, C, BE, E>
+where
+	B: BlockT<Hash = H256> + Send + Sync + 'static,
+	C: StorageProvider<B, BE> + HeaderBackend<B> + Send + Sync + 'static,
+	BE: Backend<B> + 'static,
+	BE::State: StateBackend<BlakeTwo256>,
+	E: EthApiT,
+{
+	/// Ethereum block and transaction statuses stored at a Substrate block.
+	fn block_data(
+		&self,
+		substrate_hash: H256,
+	) -> (Option<EthereumBlock>, Option<Vec<TransactionStatus>>) {
+		let id = BlockId::Hash(substrate_hash);
+		let schema =
+			frontier_backend_client::onchain_storage_schema::<B, C, BE>(self.client.as_ref(), id);
+		let handler = self.overrides.schemas.get(&schema).unwrap_or(&self.overrides.fallback);
+		(handler.current_block(&id), handler.current_transaction_statuses(&id))
+	}
+
+	/// Appearances of `address` from block `start`, including the ones of non-canonical blocks.
+	fn appearances(
+		&self,
+		address: H160,
+		start: u32,
+		descending: bool,
+	) -> Result<fc_db::AddressAppearances<B>> {
+		self.backend
+			.mapping()
+			.address_appearances(address, start, descending)
+			.map_err(|err| internal_err(format!("fetch address index failed: {:?}", err)))
+	}
+
+	fn is_canon(&self, block_hash: H256) -> bool {
+		frontier_backend_client::is_canon::<B, C>(self.client.as_ref(), block_hash)
+	}
+
+	/// Collects whole blocks of transactions of `address` until `page_size` is reached.
+	///
+	/// Returns the transactions in iteration order and whether more were left.
+	fn search(
+		&self,
+		address: H160,
+		start: u32,
+		descending: bool,
+		page_size: usize,
+	) -> Result<(Vec<(H256, u32)>, bool)> {
+		collect_page(
+			self.appearances(address, start, descending)?,
+			|hash| self.is_canon(hash),
+			page_size,
+		)
+	}
+
+	fn transactions_page(
+		&self,
+		found: Vec<(H256, u32)>,
+		first_page: bool,
+		last_page: bool,
+	) -> Result<TransactionsPage> {
+		let mut txs = Vec::with_capacity(found.len());
+		let mut receipts = Vec::with_capacity(found.len());
+		for (substrate_hash, index) in found {
+			let (block, statuses) = self.block_data(substrate_hash);
+			let (block, statuses) = match (block, statuses) {
+				(Some(block), Some(statuses)) => (block, statuses),
+				_ => continue,
+			};
+			let hash = match statuses.iter().find(|s| s.transaction_index == index) {
+				Some(status) => status.transaction_hash,
+				None => continue,
+			};
+			if let (Some(transaction), Some(receipt)) =
+				(self.eth.transaction_by_hash(hash)?, self.eth.transaction_receipt(hash)?)
+			{
+				txs.push(transaction);
+				receipts.push(OtsReceipt {
+					receipt,
+					timestamp: U256::from(block.header.timestamp / 1000),
+				});
+			}
+		}
+		Ok(TransactionsPage { txs, receipts, first_page, last_page })
+	}
+
+	fn ots_block(mut block: RichBlock) -> OtsBlock {
+		let transaction_count = match &block.inner.transactions {
+			BlockTransactions::Hashes(hashes) => hashes.len(),
+			BlockTransactions::Full(transactions) => transactions.len(),
+		};
+		block.inner.transactions = BlockTransactions::Hashes(Vec::new());
+		OtsBlock { block, transaction_count }
+	}
+}
+
+/// Calls `f` on the canonical `appearances` until it returns `true`, returning whether it did.
+fn for_each_canonical<B, I, F>(
+	appearances: I,
+	is_canon: impl Fn(H256) -> bool,
+	mut f: F,
+) -> Result<bool>
+where
+	B: BlockT<Hash = H256>,
+	I: IntoIterator<Item = std::result::Result<fc_db::AddressAppearance<B>, String>>,
+	F: FnMut(fc_db::AddressAppearance<B>) -> Result<bool>,
+{
+	for appearance in appearances {
+		let appearance = appearance
+			.map_err(|err| internal_err(format!("fetch address index failed: {:?}", err)))?;
+		if is_canon(appearance.block_hash) && f(appearance)? {
+			return Ok(true);
+		}
+	}
+	Ok(false)
+}
+
+/// Collects whole blocks of canonical `appearances` until `page_size` is reached.
+///
+/// Returns the block hashes and transaction indices in iteration order, and whether more
+/// appearances were left.
+fn collect_page<B, I>(
+	appearances: I,
+	is_canon: impl Fn(H256) -> bool,
+	page_size: usize,
+) -> Result<(Vec<(H256, u32)>, bool)>
+where
+	B: BlockT<Hash = H256>,
+	I: IntoIterator<Item = std::result::Result<fc_db::AddressAppearance<B>, String>>,
+{
+	let mut found: Vec<(H256, u32, u32)> = Vec::new();
+	let has_more = for_each_canonical(appearances, is_canon, |appearance| {
+		// Blocks are never split between pages.
+		if found.len() >= page_size
+			&& found.last().map(|last| last.1) != Some(appearance.block_number)
+		{
+			return Ok(true);
+		}
+		found.push((appearance.block_hash, appearance.block_number, appearance.ethereum_index));
+		Ok(false)
+	})?;
+	Ok((found.into_iter().map(|(hash, _, index)| (hash, index)).collect(), has_more))
+}
+
+/// Hash of the transaction of `sender` with `nonce`, scanning the canonical `appearances` of the
+/// sender in ascending order. `transaction` gives the sender, nonce and hash of the transaction
+/// of an appearance.
+fn find_by_sender_and_nonce<B, I, T>(
+	appearances: I,
+	is_canon: impl Fn(H256) -> bool,
+	mut transaction: T,
+	sender: H160,
+	nonce: U256,
+) -> Result<Option<H256>>
+where
+	B: BlockT<Hash = H256>,
+	I: IntoIterator<Item = std::result::Result<fc_db::AddressAppearance<B>, String>>,
+	T: FnMut(&fc_db::AddressAppearance<B>) -> Option<(H160, U256, H256)>,
+{
+	let mut result = None;
+	for_each_canonical(appearances, is_canon, |appearance| match transaction(&appearance) {
+		Some((from, transaction_nonce, hash)) if from == sender => {
+			if transaction_nonce == nonce {
+				result = Some(hash);
+			}
+			// Nonces of a sender only increase.
+			Ok(transaction_nonce >= nonce)
+		},
+		_ => Ok(false),
+	})?;
+	Ok(result)
+}
+
+fn transaction_nonce(transaction: &EthereumTransaction) -> U256 {
+	match transaction {
+		EthereumTransaction::Legacy(t) => t.nonce,
+		EthereumTransaction::EIP2930(t) => t.nonce,
+		EthereumTransaction::EIP1559(t) => t.nonce,
+	}
+}
+
+impl<B, C, BE, E> OtsApiT for OtsApi<B, C, BE, E>
+where
+	B: BlockT<Hash = H256> + Send + Sync + 'static,
+	C: StorageProvider<B, BE> + HeaderBackend<B> + Send + Sync + 'static,
+	BE: Backend<B> + 'static,
+	BE::State: StateBackend<BlakeTwo256>,
+	E: EthApiT + Send + Sync + 'static,
+{
+	fn get_api_level(&self) -> Result<u64> {
+		Ok(API_LEVEL)
+	}
+
+	fn get_internal_operations(&self, _: H256) -> Result<Vec<InternalOperation>> {
+		Err(EthRpcError::method_not_supported("ots_getInternalOperations").into())
+	}
+
+	fn has_code(&self, address: H160, number: Option<BlockNumber>) -> Result<bool> {
+		Ok(!self.eth.code_at(address, number)?.0.is_empty())
+	}
+
+	fn get_transaction_error(&self, _: H256) -> Result<Bytes> {
+		Err(EthRpcError::method_not_supported("ots_getTransactionError").into())
+	}
+
+	fn get_block_details(&self, number: BlockNumber) -> Result<Option<BlockDetails>> {
+		let block = match self.eth.block_by_number(number, false)? {
+			Some(block) => block,
+			None => return Ok(None),
+		};
+		let mut total_fees = U256::zero();
+		if let BlockTransactions::Hashes(hashes) = &block.inner.transactions {
+			for hash in hashes {
+				if let Some(receipt) = self.eth.transaction_receipt(*hash)? {
+					total_fees = total_fees.saturating_add(
+						receipt
+							.gas_used
+							.unwrap_or_default()
+							.saturating_mul(receipt.effective_gas_price),
+					);
+				}
+			}
+		}
+		Ok(Some(BlockDetails {
+			block: Self::ots_block(block),
+			issuance: Issuance::default(),
+			total_fees,
+		}))
+	}
+
+	fn get_block_transactions(
+		&self,
+		number: BlockNumber,
+		page_number: usize,
+		page_size: usize,
+	) -> Result<Option<BlockTransactionsPage>> {
+		check_page_size(page_size)?;
+		let mut block = match self.eth.block_by_number(number, true)? {
+			Some(block) => block,
+			None => return Ok(None),
+		};
+		let transactions = match std::mem::replace(
+			&mut block.inner.transactions,
+			BlockTransactions::Hashes(Vec::new()),
+		) {
+			BlockTransactions::Full(transactions) => transactions,
+			BlockTransactions::Hashes(_) => Vec::new(),
+		};
+		let transaction_count = transactions.len();
+		let page: Vec<_> = transactions
+			.into_iter()
+			.skip(page_number.saturating_mul(page_size))
+			.take(page_size)
+			.collect();
+
+		let mut receipts = Vec::with_capacity(page.len());
+		for transaction in &page {
+			if let Some(mut receipt) = self.eth.transaction_receipt(transaction.hash)? {
+				// Logs are fetched separately by the explorer.
+				receipt.logs = Vec::new();
+				receipts.push(receipt);
+			}
+		}
+		block.inner.transactions = BlockTransactions::Full(page);
+
+		Ok(Some(BlockTransactionsPage {
+			fullblock: OtsBlock { block, transaction_count },
+			receipts,
+		}))
+	}
+
+	fn search_transactions_before(
+		&self,
+		address: H160,
+		block_number: u64,
+		page_size: usize,
+	) -> Result<TransactionsPage> {
+		check_page_size(page_size)?;
+		let start = if block_number == 0 {
+			UniqueSaturatedInto::<u32>::unique_saturated_into(self.client.info().best_number)
+		} else {
+			(block_number - 1).unique_saturated_into()
+		};
+		let (found, has_more) = self.search(address, start, true, page_size)?;
+		self.transactions_page(found, block_number == 0, !has_more)
+	}
+
+	fn search_transactions_after(
+		&self,
+		address: H160,
+		block_number: u64,
+		page_size: usize,
+	) -> Result<TransactionsPage> {
+		check_page_size(page_size)?;
+		let start: u32 = block_number.saturating_add(1).unique_saturated_into();
+		let (mut found, has_more) = self.search(address, start, false, page_size)?;
+		found.reverse();
+		self.transactions_page(found, !has_more, block_number == 0)
+	}
+
+	fn get_transaction_by_sender_and_nonce(
+		&self,
+		address: H160,
+		nonce: U256,
+	) -> Result<Option<H256>> {
+		find_by_sender_and_nonce(
+			self.appearances(address, 0, false)?,
+			|hash| self.is_canon(hash),
+			|appearance| {
+				let (block, statuses) = match self.block_data(appearance.block_hash) {
+					(Some(block), Some(statuses)) => (block, statuses),
+					_ => return None,
+				};
+				let index = appearance.ethereum_index as usize;
+				let (transaction, status) = (block.transactions.get(index)?, statuses.get(index)?);
+				Some((status.from, transaction_nonce(transaction), status.transaction_hash))
+			},
+			address,
+			nonce,
+		)
+	}
+
+	fn get_contract_creator(&self, address: H160) -> Result<Option<ContractCreator>> {
+		let mut result = None;
+		for_each_canonical(
+			self.appearances(address, 0, false)?,
+			|hash| self.is_canon(hash),
+			|appearance| {
+				let statuses = match self.block_data(appearance.block_hash).1 {
+					Some(statuses) => statuses,
+					None => return Ok(false),
+				};
+				match statuses.get(appearance.ethereum_index as usize) {
+					Some(status) if status.contract_address == Some(address) => {
+						result = Some(ContractCreator {
+							hash: status.transaction_hash,
+							creator: status.from,
+						});
+						Ok(true)
+					},
+					_ => Ok(false),
+				}
+			},
+		)?;
+		Ok(result)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use fc_db::AddressAppearance;
+	use sp_runtime::testing::{Block as TestBlock, ExtrinsicWrapper};
+
+	type Block = TestBlock<ExtrinsicWrapper<u64>>;
+
+	fn hash(block_number: u32, fork: u8) -> H256 {
+		let mut hash = H256::from_low_u64_be(block_number.into());
+		hash.0[0] = fork;
+		hash
+	}
+
+	fn appearance(
+		block_number: u32,
+		ethereum_index: u32,
+		fork: u8,
+	) -> std::result::Result<AddressAppearance<Block>, String> {
+		Ok(AddressAppearance { block_number, ethereum_index, block_hash: hash(block_number, fork) })
+	}
+
+	fn canonical(block_hash: H256) -> bool {
+		block_hash.0[0] == 0
+	}
+
+	#[test]
+	fn pages_keep_whole_blocks() {
+		let appearances = vec![appearance(9, 1, 0), appearance(9, 0, 0), appearance(7, 2, 0)];
+
+		assert_eq!(
+			collect_page(appearances.clone(), canonical, 1).unwrap(),
+			(vec![(hash(9, 0), 1), (hash(9, 0), 0)], true)
+		);
+		assert_eq!(
+			collect_page(appearances, canonical, 3).unwrap(),
+			(vec![(hash(9, 0), 1), (hash(9, 0), 0), (hash(7, 0), 2)], false)
+		);
+	}
+
+	#[test]
+	fn pages_skip_non_canonical_blocks() {
+		let appearances = vec![appearance(9, 0, 1), appearance(8, 0, 0), appearance(8, 1, 1)];
+
+		assert_eq!(
+			collect_page(appearances, canonical, 10).unwrap(),
+			(vec![(hash(8, 0), 0)], false)
+		);
+	}
+
+	#[test]
+	fn index_errors_are_returned() {
+		let appearances = vec![appearance(9, 0, 0), Err("corrupted".to_string())];
+
+		assert!(collect_page(appearances, canonical, 10).is_err());
+	}
+
+	#[test]
+	fn transaction_by_sender_and_nonce_is_found_from_the_first_block() {
+		let sender = H160::repeat_byte(1);
+		let other = H160::repeat_byte(2);
+		let appearances = vec![
+			appearance(1, 0, 0),
+			appearance(2, 0, 0),
+			appearance(2, 1, 0),
+			appearance(3, 0, 1),
+			appearance(3, 0, 0),
+			appearance(4, 0, 0),
+		];
+		// Transactions of the appearances: the sender sends nonces 0, 1 and 2, receives one from
+		// `other`, and a non-canonical block has another transaction with nonce 2.
+		let transaction = |appearance: &AddressAppearance<Block>| {
+			let from = if appearance.ethereum_index == 1 { other } else { sender };
+			let nonce = match (appearance.block_number, appearance.block_hash.0[0]) {
+				(1, _) => 0,
+				(2, _) if from == other => 5,
+				(2, _) => 1,
+				_ => 2,
+			};
+			Some((from, U256::from(nonce), H256::repeat_byte(appearance.block_hash.0[0] + 1)))
+		};
+		let mut scanned = Vec::new();
+		let find = |nonce: u64, scanned: &mut Vec<u32>| {
+			find_by_sender_and_nonce(
+				appearances.clone(),
+				canonical,
+				|appearance: &AddressAppearance<Block>| {
+					scanned.push(appearance.block_number);
+					transaction(appearance)
+				},
+				sender,
+				U256::from(nonce),
+			)
+			.unwrap()
+		};
+
+		assert_eq!(find(0, &mut scanned), Some(H256::repeat_byte(1)));
+		assert_eq!(scanned, vec![1]);
+		scanned.clear();
+		assert_eq!(find(2, &mut scanned), Some(H256::repeat_byte(1)));
+		// Stops at the first transaction of the sender with the nonce.
+		assert_eq!(scanned, vec![1, 2, 2, 3]);
+		scanned.clear();
+		assert_eq!(find(5, &mut scanned), None);
+		assert_eq!(scanned, vec![1, 2, 2, 3, 4]);
+	}
+}
@@ -42,7 +42,7 @@ pub struct OverrideHandle<Block: BlockT> {
 /// State Backend with some assumptions about pallet-ethereum's storage schema. Using such an
 /// optimized implementation avoids spawning a runtime and the overhead associated with it.
 pub trait StorageOverride<Block: BlockT> {
-	/// For a given account address, returns pallet_evm::AccountCodes.
+	/// For a given account address, returns its code from pallet_evm::Codes.
 	fn account_code_at(&self, block: &BlockId<Block>, address: H160) -> Option<Vec<u8>>;
 	/// For a given account address and index, returns pallet_evm::AccountStorages.
 	fn storage_at(&self, block: &BlockId<Block>, address: H160, index: U256) -> Option<H256>;
@@ -71,6 +71,28 @@ fn blake2_128_extend(bytes: &[u8]) -> Vec<u8> {
 	ext
 }
 
+/// Key of the code metadata of `address` in pallet_evm::AccountCodesMetadata.
+fn account_code_metadata_key(address: H160) -> Vec<u8> {
+	let mut key = storage_prefix_build(b"EVM", b"AccountCodesMetadata");
+	key.extend(blake2_128_extend(address.as_bytes()));
+	key
+}
+
+/// Key of the code of `hash` in pallet_evm::Codes.
+fn code_key(hash: H256) -> Vec<u8> {
+	let mut key = storage_prefix_build(b"EVM", b"Codes");
+	key.extend_from_slice(hash.as_bytes());
+	key
+}
+
+/// Key of the code of `address` in the pallet_evm::AccountCodes of runtimes which didn't move it
+/// to pallet_evm::Codes yet.
+fn legacy_account_code_key(address: H160) -> Vec<u8> {
+	let mut key = storage_prefix_build(b"EVM", b"AccountCodes");
+	key.extend(blake2_128_extend(address.as_bytes()));
+	key
+}
+
 /// A wrapper type for the Runtime API. This type implements `StorageOverride`, so it can be used
 /// when calling the runtime API is desired but a `dyn StorageOverride` is required.
 pub struct RuntimeApiStorageOverride<B: BlockT, C> {
@@ -100,7 +122,7 @@ where
 	Block: BlockT<Hash = H256> + Send + Sync + 'static,
 	C: Send + Sync + 'static,
 {
-	/// For a given account address, returns pallet_evm::AccountCodes.
+	/// For a given account address, returns its code from pallet_evm::Codes.
 	fn account_code_at(&self, block: &BlockId<Block>, address: H160) -> Option<Vec<u8>> {
 		self.client
 			.runtime_api()
@@ -17,6 +17,7 @@
 use codec::Decode;
 use ethereum_types::{H160, H256, U256};
 use fp_rpc::TransactionStatus;
+use pallet_evm::CodeMetadata;
 use sc_client_api::backend::{AuxStore, Backend, StateBackend, StorageProvider};
 use sp_api::BlockId;
 use sp_blockchain::{Error as BlockChainError, HeaderBackend, HeaderMetadata};
@@ -24,7 +25,10 @@ use sp_runtime::traits::{BlakeTwo256, Block as BlockT};
 use sp_storage::StorageKey;
 use std::{marker::PhantomData, sync::Arc};
 
-use super::{blake2_128_extend, storage_prefix_build, StorageOverride};
+use super::{
+	account_code_metadata_key, blake2_128_extend, code_key, legacy_account_code_key,
+	storage_prefix_build, StorageOverride,
+};
 
 /// An override for runtimes that use Schema V1
 pub struct SchemaV1Override<B: BlockT, C, BE> {
@@ -79,15 +83,29 @@ where
 	Block: BlockT<Hash = H256> + Send + Sync + 'static,
 	C: Send + Sync + 'static,
 {
-	/// For a given account address, returns pallet_evm::AccountCodes.
+	/// For a given account address, returns its code from pallet_evm::Codes.
 	fn account_code_at(&self, block: &BlockId<Block>, address: H160) -> Option<Vec<u8>> {
-		let mut key: Vec<u8> = storage_prefix_build(b"EVM", b"AccountCodes");
-		key.extend(blake2_128_extend(address.as_bytes()));
-		self.query_storage::<Vec<u8>>(block, &StorageKey(key))
+		let metadata_key = StorageKey(account_code_metadata_key(address));
+		match self.query_storage::<CodeMetadata>(block, &metadata_key) {
+			Some(metadata) => {
+				self.query_storage::<Vec<u8>>(block, &StorageKey(code_key(metadata.hash)))
+			}
+			// Blocks before the migration of pallet_evm to `Codes`.
+			None => {
+				self.query_storage::<Vec<u8>>(block, &StorageKey(legacy_account_code_key(address)))
+			}
+		}
 	}
 
-	/// For a given account address and index, returns pallet_evm::AccountStorages.
+	/// For a given account address and index, returns pallet_evm::AccountStorages, empty while the
+	/// account is in pallet_evm::DeletionQueue.
 	fn storage_at(&self, block: &BlockId<Block>, address: H160, index: U256) -> Option<H256> {
+		let mut deletion_key: Vec<u8> = storage_prefix_build(b"EVM", b"DeletionQueue");
+		deletion_key.extend(blake2_128_extend(address.as_bytes()));
+		if self.query_storage::<()>(block, &StorageKey(deletion_key)).is_some() {
+			return Some(H256::default());
+		}
+
 		let tmp: &mut [u8; 32] = &mut [0; 32];
 		index.to_big_endian(tmp);
 
@@ -0,0 +1,92 @@
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+// This file is part of Frontier.
+//
+// Copyright (c) 2020 Parity Technologies (UK) Ltd.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use std::time::Duration;
+
+use ethereum_types::H160;
+use fc_rpc_core::{types::Bytes, PersonalApi as PersonalApiT};
+use jsonrpc_core::Result;
+use rustc_hex::FromHex;
+use sc_rpc::DenyUnsafe;
+
+pub use fc_rpc_core::PersonalApiServer;
+
+use crate::{
+	eth_message_hash, internal_err, keystore::DEFAULT_UNLOCK_DURATION, recover_signer, sign_hash,
+	EthKeystoreSigner, EthRpcError, EthSigner,
+};
+
+pub struct PersonalApi {
+	keystore: EthKeystoreSigner,
+	deny_unsafe: DenyUnsafe,
+}
+
+impl PersonalApi {
+	pub fn new(keystore: EthKeystoreSigner, deny_unsafe: DenyUnsafe) -> Self {
+		Self { keystore, deny_unsafe }
+	}
+}
+
+impl PersonalApiT for PersonalApi {
+	fn list_accounts(&self) -> Result<Vec<H160>> {
+		self.deny_unsafe.check_if_safe()?;
+		Ok(self.keystore.accounts())
+	}
+
+	fn unlock_account(
+		&self,
+		address: H160,
+		password: String,
+		duration: Option<u64>,
+	) -> Result<bool> {
+		self.deny_unsafe.check_if_safe()?;
+		let duration = match duration {
+			Some(0) => None,
+			Some(seconds) => Some(Duration::from_secs(seconds)),
+			None => Some(DEFAULT_UNLOCK_DURATION),
+		};
+		self.keystore.unlock(&address, &password, duration).map_err(internal_err)?;
+		Ok(true)
+	}
+
+	fn import_raw_key(&self, key: String, password: String) -> Result<H160> {
+		self.deny_unsafe.check_if_safe()?;
+		let bytes: Vec<u8> = key
+			.trim_start_matches("0x")
+			.from_hex()
+			.map_err(|_| EthRpcError::InvalidParams("invalid hex private key".into()))?;
+		let secret = secp256k1::SecretKey::parse_slice(&bytes)
+			.map_err(|_| EthRpcError::InvalidParams("invalid private key".into()))?;
+		self.keystore.import_raw_key(&secret, &password).map_err(internal_err)
+	}
+
+	fn sign(&self, data: Bytes, address: H160, password: String) -> Result<Bytes> {
+		self.deny_unsafe.check_if_safe()?;
+		let hash = eth_message_hash(&data.0);
+		let signature = self
+			.keystore
+			.with_secret(&address, Some(&password), |secret| sign_hash(&hash, secret))
+			.map_err(internal_err)??;
+		Ok(Bytes(signature.to_vec()))
+	}
+
+	fn ec_recover(&self, data: Bytes, signature: Bytes) -> Result<H160> {
+		self.deny_unsafe.check_if_safe()?;
+		recover_signer(&eth_message_hash(&data.0), &signature.0)
+	}
+}
@@ -1,9 +1,11 @@
 use cumulus_primitives_core::ParaId;
-use parachain_template_runtime::{AccountId, AuraId, EVMConfig, EthereumConfig, Signature, EXISTENTIAL_DEPOSIT, SudoConfig};
+use parachain_template_runtime::{AccountId, AuraId, EVMConfig, EthereumConfig, PrecompileRegistryConfig, Signature, EXISTENTIAL_DEPOSIT, SudoConfig};
 use sc_chain_spec::{ChainSpecExtension, ChainSpecGroup};
 use sc_service::ChainType;
 use serde::{Deserialize, Serialize};
-use sp_core::{sr25519, Pair, Public, H160, U256};
+#[cfg(feature = "account-id-20")]
+use sp_core::crypto::DEV_PHRASE;
+use sp_core::{Pair, Public, H160, U256};
 use sp_runtime::traits::{IdentifyAccount, Verify};
 use std::{collections::BTreeMap, str::FromStr};
 
@@ -52,6 +54,45 @@ where
 	AccountPublic::from(get_public_from_seed::<TPublic>(seed)).into_account()
 }
 
+/// Names of the development accounts.
+const DEV_ACCOUNTS: [&str; 6] = ["Alice", "Bob", "Charlie", "Dave", "Eve", "Ferdie"];
+
+/// Account ID of the development account of the given name, derived from `//<name>`.
+#[cfg(not(feature = "account-id-20"))]
+pub fn get_dev_account_id(name: &str) -> AccountId {
+	get_account_id_from_seed::<sp_core::sr25519::Public>(name)
+}
+
+/// Account ID of the development account of the given name: the Ethereum account of the
+/// development phrase at `m/44'/60'/0'/0/<index>`, so that "Alice" is the usual "Alith" account
+/// (0xf24FF3a9CF04c71Dbc94D0b566f7A27B94566cac), "Bob" is "Baltathar", and so on.
+#[cfg(feature = "account-id-20")]
+pub fn get_dev_account_id(name: &str) -> AccountId {
+	let index = DEV_ACCOUNTS
+		.iter()
+		.position(|dev| *dev == name)
+		.expect("unknown development account");
+	let pair = fp_account::derivation::derive_ethereum_pair(DEV_PHRASE, index as u32)
+		.expect("static values are valid; qed");
+	AccountPublic::from(pair.public()).into_account()
+}
+
+/// Development accounts and their stash accounts.
+#[cfg(not(feature = "account-id-20"))]
+fn dev_endowed_accounts() -> Vec<AccountId> {
+	DEV_ACCOUNTS
+		.iter()
+		.map(|name| get_dev_account_id(name))
+		.chain(DEV_ACCOUNTS.iter().map(|name| get_dev_account_id(&format!("{}//stash", name))))
+		.collect()
+}
+
+/// Development accounts.
+#[cfg(feature = "account-id-20")]
+fn dev_endowed_accounts() -> Vec<AccountId> {
+	DEV_ACCOUNTS.iter().map(|name| get_dev_account_id(name)).collect()
+}
+
 /// Generate the session keys from individual elements.
 ///
 /// The input must be a tuple of individual keys (a single arg for now since we have just one key).
@@ -76,31 +117,12 @@ pub fn development_config() -> ChainSpec {
 			testnet_genesis(
 				// initial collators.
 				vec![
-					(
-						get_account_id_from_seed::<sr25519::Public>("Alice"),
-						get_collator_keys_from_seed("Alice"),
-					),
-					(
-						get_account_id_from_seed::<sr25519::Public>("Bob"),
-						get_collator_keys_from_seed("Bob"),
-					),
+					(get_dev_account_id("Alice"), get_collator_keys_from_seed("Alice")),
+					(get_dev_account_id("Bob"), get_collator_keys_from_seed("Bob")),
 				],
 				// sudo account
-				get_account_id_from_seed::<sr25519::Public>("Alice"),
-				vec![
-					get_account_id_from_seed::<sr25519::Public>("Alice"),
-					get_account_id_from_seed::<sr25519::Public>("Bob"),
-					get_account_id_from_seed::<sr25519::Public>("Charlie"),
-					get_account_id_from_seed::<sr25519::Public>("Dave"),
-					get_account_id_from_seed::<sr25519::Public>("Eve"),
-					get_account_id_from_seed::<sr25519::Public>("Ferdie"),
-					get_account_id_from_seed::<sr25519::Public>("Alice//stash"),
-					get_account_id_from_seed::<sr25519::Public>("Bob//stash"),
-					get_account_id_from_seed::<sr25519::Public>("Charlie//stash"),
-					get_account_id_from_seed::<sr25519::Public>("Dave//stash"),
-					get_account_id_from_seed::<sr25519::Public>("Eve//stash"),
-					get_account_id_from_seed::<sr25519::Public>("Ferdie//stash"),
-				],
+				get_dev_account_id("Alice"),
+				dev_endowed_accounts(),
 				1000.into(),
 			)
 		},
@@ -132,31 +154,12 @@ pub fn local_testnet_config() -> ChainSpec {
 			testnet_genesis(
 				// initial collators.
 				vec![
-					(
-						get_account_id_from_seed::<sr25519::Public>("Alice"),
-						get_collator_keys_from_seed("Alice"),
-					),
-					(
-						get_account_id_from_seed::<sr25519::Public>("Bob"),
-						get_collator_keys_from_seed("Bob"),
-					),
+					(get_dev_account_id("Alice"), get_collator_keys_from_seed("Alice")),
+					(get_dev_account_id("Bob"), get_collator_keys_from_seed("Bob")),
 				],
 				// sudo account
-				get_account_id_from_seed::<sr25519::Public>("Alice"),
-				vec![
-					get_account_id_from_seed::<sr25519::Public>("Alice"),
-					get_account_id_from_seed::<sr25519::Public>("Bob"),
-					get_account_id_from_seed::<sr25519::Public>("Charlie"),
-					get_account_id_from_seed::<sr25519::Public>("Dave"),
-					get_account_id_from_seed::<sr25519::Public>("Eve"),
-					get_account_id_from_seed::<sr25519::Public>("Ferdie"),
-					get_account_id_from_seed::<sr25519::Public>("Alice//stash"),
-					get_account_id_from_seed::<sr25519::Public>("Bob//stash"),
-					get_account_id_from_seed::<sr25519::Public>("Charlie//stash"),
-					get_account_id_from_seed::<sr25519::Public>("Dave//stash"),
-					get_account_id_from_seed::<sr25519::Public>("Eve//stash"),
-					get_account_id_from_seed::<sr25519::Public>("Ferdie//stash"),
-				],
+				get_dev_account_id("Alice"),
+				dev_endowed_accounts(),
 				1000.into(),
 			)
 		},
@@ -254,5 +257,8 @@ fn testnet_genesis(
 		ethereum: EthereumConfig {},
 		dynamic_fee: Default::default(),
 		base_fee: Default::default(),
+		precompile_registry: PrecompileRegistryConfig {
+			precompiles: parachain_template_runtime::genesis_precompiles(),
+		},
 	}
 }
@@ -84,11 +84,32 @@ pub struct Cli {
 	#[structopt(flatten)]
 	pub run: cumulus_client_cli::RunCmd,
 
+	#[structopt(flatten)]
+	pub eth: EthConfiguration,
+
 	/// Relay chain arguments
 	#[structopt(raw = true)]
 	pub relay_chain_args: Vec<String>,
 }
 
+/// Ethereum RPC options.
+#[derive(Debug, Clone, StructOpt)]
+pub struct EthConfiguration {
+	/// Enable the development signer for `eth_sendTransaction`.
+	#[structopt(long)]
+	pub enable_dev_signer: bool,
+
+	/// Directory of Ethereum JSON keystore files used to sign transactions and exposed
+	/// through the `personal_*` RPC namespace.
+	#[structopt(long, parse(from_os_str))]
+	pub eth_keystore_path: Option<PathBuf>,
+
+	/// File holding the keystore passwords, one per line in keystore file name order.
+	/// Accounts it unlocks stay unlocked until the node stops.
+	#[structopt(long, parse(from_os_str), requires = "eth-keystore-path")]
+	pub eth_keystore_password_file: Option<PathBuf>,
+}
+
 #[derive(Debug)]
 pub struct RelayChainCli {
 	/// The actual relay chain cli object.
@@ -42,6 +42,8 @@ pub struct FullDeps<C, P, A: ChainApi> {
 	pub is_authority: bool,
 	/// Whether to enable dev signer
 	pub enable_dev_signer: bool,
+	/// Ethereum JSON keystore, if configured
+	pub eth_keystore: Option<fc_rpc::EthKeystoreSigner>,
 	/// Network service
 	pub network: Arc<NetworkService<Block, Hash>>,
 	/// EthFilterApi pool.
@@ -78,7 +80,8 @@ where
 {
 	use fc_rpc::{
 		EthApi, EthApiServer, EthDevSigner, EthFilterApi, EthFilterApiServer, EthPubSubApi,
-		EthPubSubApiServer, EthSigner, HexEncodedIdProvider, NetApi, NetApiServer, Web3Api,
+		EthPubSubApiServer, EthSigner, FrontierApi, FrontierApiServer, HexEncodedIdProvider,
+		NetApi, NetApiServer, OtsApi, OtsApiServer, PersonalApi, PersonalApiServer, Web3Api,
 		Web3ApiServer,
 	};
 	use pallet_transaction_payment_rpc::{TransactionPayment, TransactionPaymentApi};
@@ -96,6 +99,7 @@ where
 		backend,
 		max_past_logs,
 		enable_dev_signer,
+		eth_keystore,
 	} = deps;
 
 	io.extend_with(SystemApi::to_delegate(FullSystem::new(client.clone(), pool.clone(), deny_unsafe)));
@@ -105,6 +109,14 @@ where
 	if enable_dev_signer {
 		signers.push(Box::new(EthDevSigner::new()) as Box<dyn EthSigner>);
 	}
+	if let Some(keystore) = eth_keystore {
+		// Unlocked keystore accounts sign for anyone calling `eth_sendTransaction`, only expose
+		// them where unsafe RPCs are allowed.
+		if deny_unsafe.check_if_safe().is_ok() {
+			signers.push(Box::new(keystore.clone()) as Box<dyn EthSigner>);
+		}
+		io.extend_with(PersonalApiServer::to_delegate(PersonalApi::new(keystore, deny_unsafe)));
+	}
 	let mut overrides_map = BTreeMap::new();
 	overrides_map.insert(
 		EthereumStorageSchema::V1,
@@ -127,10 +139,11 @@ where
 	io.extend_with(EthApiServer::to_delegate(EthApi::new(
 		client.clone(),
 		pool.clone(),
-		graph,
+		graph.clone(),
 		parachain_template_runtime::TransactionConverter,
 		network.clone(),
 		signers,
+		deny_unsafe,
 		overrides.clone(),
 		backend.clone(),
 		is_authority,
@@ -138,6 +151,33 @@ where
 		block_data_cache.clone(),
 	)));
 
+	io.extend_with(OtsApiServer::to_delegate(OtsApi::new(
+		client.clone(),
+		backend.clone(),
+		overrides.clone(),
+		// Read-only Ethereum API the Otterscan methods are built upon.
+		EthApi::new(
+			client.clone(),
+			pool.clone(),
+			graph,
+			parachain_template_runtime::TransactionConverter,
+			network.clone(),
+			Vec::new(),
+			deny_unsafe,
+			overrides.clone(),
+			backend.clone(),
+			is_authority,
+			max_past_logs,
+			block_data_cache.clone(),
+		),
+	)));
+
+	io.extend_with(FrontierApiServer::to_delegate(FrontierApi::new(
+		client.clone(),
+		backend.clone(),
+		overrides.clone(),
+	)));
+
 	if let Some(filter_pool) = filter_pool {
 		io.extend_with(EthFilterApiServer::to_delegate(EthFilterApi::new(
 			client.clone(),
@@ -201,6 +201,7 @@ async fn start_node_impl<RuntimeApi, Executor, RB, BIQ, BIC>(
 	parachain_config: Configuration,
 	polkadot_config: Configuration,
 	id: ParaId,
+	eth_config: crate::cli::EthConfiguration,
 	_rpc_ext_builder: RB,
 	build_import_queue: BIQ,
 	build_consensus: BIC,
@@ -306,8 +307,14 @@ where
 	let filter_pool: Option<FilterPool> = Some(Arc::new(Mutex::new(BTreeMap::new())));
 	let frontier_backend = open_frontier_backend(&parachain_config)?;
 	let is_authority = parachain_config.role.is_authority();
-	// FixMe: get value from command line
-	let enable_dev_signer = false;
+	let enable_dev_signer = eth_config.enable_dev_signer;
+	let eth_keystore = match &eth_config.eth_keystore_path {
+		Some(path) => Some(
+			fc_rpc::EthKeystoreSigner::open(path, eth_config.eth_keystore_password_file.as_deref())
+				.map_err(|err| format!("Failed to open Ethereum keystore: {}", err))?,
+		),
+		None => None,
+	};
 	let subscription_task_executor =
 		sc_rpc::SubscriptionTaskExecutor::new(task_manager.spawn_handle());
 
@@ -317,6 +324,7 @@ where
 		let network = network.clone();
 		let filter_pool = filter_pool.clone();
 		let frontier_backend = frontier_backend.clone();
+		let eth_keystore = eth_keystore.clone();
 		// FixMe: get value from command line
 		let max_past_logs: u32 = 0;
 
@@ -328,6 +336,7 @@ where
 				deny_unsafe,
 				is_authority,
 				enable_dev_signer,
+				eth_keystore: eth_keystore.clone(),
 				network: network.clone(),
 				filter_pool: filter_pool.clone(),
 				backend: frontier_backend.clone(),
@@ -483,6 +492,7 @@ pub async fn start_parachain_node(
 	parachain_config: Configuration,
 	polkadot_config: Configuration,
 	id: ParaId,
+	eth_config: crate::cli::EthConfiguration,
 ) -> sc_service::error::Result<(
 	TaskManager,
 	Arc<TFullClient<Block, RuntimeApi, NativeElseWasmExecutor<TemplateRuntimeExecutor>>>,
@@ -491,6 +501,7 @@ pub async fn start_parachain_node(
 		parachain_config,
 		polkadot_config,
 		id,
+		eth_config,
 		|_| Ok(Default::default()),
 		parachain_build_import_queue,
 		|client,
@@ -0,0 +1,307 @@
+// SPDX-License-Identifier: Apache-2.0
+// This file is part of Frontier.
+//
+// Copyright (c) 2021 Parity Technologies (UK) Ltd.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! # Account link
+//!
+//! Links EVM addresses to the Substrate accounts controlling them, so that a user has a single
+//! account, and a single balance, for both. With [`LinkedAddressMapping`] as the EVM address
+//! mapping, a linked address maps to its account, and other addresses to `FallbackMapping`,
+//! usually `HashedAddressMapping`.
+//!
+//! A link needs the consent of both sides, given by the origin of the call for one side and by
+//! a signature of [`Pallet::link_message`] for the other:
+//! - [`Pallet::link_address`] is called by the account, with the signature of the message by the
+//!   key of the address, as produced by `personal_sign`;
+//! - [`Pallet::link_account`] is called by the fallback account of the address, for example
+//!   through the dispatch precompile, with the signature of the message by the account.
+//!
+//! Addresses with code can't be linked, so that the account of a contract stays its fallback
+//! account, which it alone controls.
+//!
+//! Linking moves the funds of the fallback account to the account. As Ethereum transactions of
+//! the address then use the nonce of the account, and after unlinking the nonce of the fallback
+//! account again, linking and unlinking raise the nonce taking over to the nonce being replaced,
+//! so that transactions already executed can't be replayed. Link messages contain a nonce of the
+//! address bumped by each link and unlink, so that their signatures can't be replayed either.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(test)]
+mod tests;
+
+pub use pallet::*;
+
+use codec::Encode;
+use frame_system::RawOrigin;
+use pallet_evm::{AddressMapping, EnsureAddressOrigin};
+use sp_core::{H160, H256};
+use sp_io::hashing::keccak_256;
+use sp_std::{marker::PhantomData, prelude::*};
+
+/// Prefix of the link messages.
+pub const LINK_MESSAGE_PREFIX: &[u8] = b"evm-account-link:";
+
+/// Proof size of reading the link of an address, besides trie nodes: its key and value in
+/// `Accounts`, for 32-byte account ids.
+pub const LINKED_ACCOUNT_PROOF_SIZE: u64 = 100;
+
+#[frame_support::pallet]
+pub mod pallet {
+	use super::*;
+	use frame_support::{
+		dispatch::DispatchResult,
+		pallet_prelude::*,
+		traits::{Currency, ExistenceRequirement},
+	};
+	use frame_system::pallet_prelude::*;
+	use sp_runtime::traits::{IdentifyAccount, Verify, Zero};
+
+	#[pallet::config]
+	pub trait Config: frame_system::Config + pallet_evm::Config {
+		type Event: From<Event<Self>> + IsType<<Self as frame_system::Config>::Event>;
+		/// Signature of a Substrate account.
+		type Signature: Parameter + Verify<Signer = Self::Signer>;
+		/// Signer of a `Signature`.
+		type Signer: IdentifyAccount<AccountId = Self::AccountId>;
+		/// Mapping of the addresses without link.
+		type FallbackMapping: AddressMapping<Self::AccountId>;
+	}
+
+	#[pallet::pallet]
+	#[pallet::generate_store(pub(super) trait Store)]
+	pub struct Pallet<T>(_);
+
+	/// Address linked to an account.
+	#[pallet::storage]
+	#[pallet::getter(fn address_of)]
+	pub type Addresses<T: Config> =
+		StorageMap<_, Blake2_128Concat, T::AccountId, H160, OptionQuery>;
+
+	/// Account linked to an address.
+	#[pallet::storage]
+	#[pallet::getter(fn account_of)]
+	pub type Accounts<T: Config> = StorageMap<_, Blake2_128Concat, H160, T::AccountId, OptionQuery>;
+
+	/// Number of links and unlinks of an address, part of its link messages.
+	#[pallet::storage]
+	#[pallet::getter(fn link_nonce)]
+	pub type LinkNonces<T: Config> = StorageMap<_, Blake2_128Concat, H160, u32, ValueQuery>;
+
+	#[pallet::event]
+	#[pallet::generate_deposit(pub(super) fn deposit_event)]
+	pub enum Event<T: Config> {
+		/// An address was linked to an account. \[account, address\]
+		Linked(T::AccountId, H160),
+		/// An address was unlinked from an account. \[account, address\]
+		Unlinked(T::AccountId, H160),
+	}
+
+	#[pallet::error]
+	pub enum Error<T> {
+		/// The account is already linked to an address.
+		AccountAlreadyLinked,
+		/// The address is already linked to an account.
+		AddressAlreadyLinked,
+		/// The account isn't linked to an address.
+		NotLinked,
+		/// The signature isn't a signature of the link message by the other side.
+		BadSignature,
+		/// The origin isn't the fallback account of the address.
+		NotAddressOrigin,
+		/// The address has code.
+		AddressHasCode,
+	}
+
+	#[pallet::call]
+	impl<T: Config> Pallet<T> {
+		/// Link an address to the calling account, given the signature of the link message by the
+		/// key of the address.
+		#[pallet::weight(100_000_000 + T::DbWeight::get().reads_writes(11, 7))]
+		pub fn link_address(
+			origin: OriginFor<T>,
+			address: H160,
+			signature: [u8; 65],
+		) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			let message = Self::link_message(&who, &address);
+			ensure!(
+				recover_signer(&signature, &message) == Some(address),
+				Error::<T>::BadSignature
+			);
+			Self::do_link(who, address)
+		}
+
+		/// Link the address of the calling fallback account to an account, given the signature
+		/// of the link message by the account.
+		#[pallet::weight(100_000_000 + T::DbWeight::get().reads_writes(11, 7))]
+		pub fn link_account(
+			origin: OriginFor<T>,
+			address: H160,
+			account: T::AccountId,
+			signature: T::Signature,
+		) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			ensure!(
+				who == T::FallbackMapping::into_account_id(address),
+				Error::<T>::NotAddressOrigin
+			);
+			let message = Self::link_message(&account, &address);
+			ensure!(signature.verify(&message[..], &account), Error::<T>::BadSignature);
+			Self::do_link(account, address)
+		}
+
+		/// Unlink the address of the calling account. Its funds stay with the account.
+		#[pallet::weight(10_000 + T::DbWeight::get().reads_writes(5, 5))]
+		pub fn unlink(origin: OriginFor<T>) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			let address = Addresses::<T>::take(&who).ok_or(Error::<T>::NotLinked)?;
+			Accounts::<T>::remove(address);
+			LinkNonces::<T>::mutate(address, |nonce| *nonce = nonce.wrapping_add(1));
+
+			Self::raise_nonce(
+				&T::FallbackMapping::into_account_id(address),
+				frame_system::Pallet::<T>::account_nonce(&who),
+			);
+
+			Self::deposit_event(Event::Unlinked(who, address));
+			Ok(())
+		}
+	}
+
+	impl<T: Config> Pallet<T> {
+		/// Message to sign to link `address` to `account`, valid until the next link or unlink
+		/// of the address.
+		pub fn link_message(account: &T::AccountId, address: &H160) -> Vec<u8> {
+			let mut message = LINK_MESSAGE_PREFIX.to_vec();
+			(T::ChainId::get(), account, address, Self::link_nonce(address))
+				.using_encoded(|encoded| message.extend_from_slice(encoded));
+			message
+		}
+
+		fn do_link(account: T::AccountId, address: H160) -> DispatchResult {
+			ensure!(!Addresses::<T>::contains_key(&account), Error::<T>::AccountAlreadyLinked);
+			ensure!(!Accounts::<T>::contains_key(address), Error::<T>::AddressAlreadyLinked);
+			ensure!(!pallet_evm::Pallet::<T>::has_code(&address), Error::<T>::AddressHasCode);
+
+			let fallback = T::FallbackMapping::into_account_id(address);
+			Self::raise_nonce(&account, frame_system::Pallet::<T>::account_nonce(&fallback));
+			let balance = T::Currency::free_balance(&fallback);
+			if !balance.is_zero() {
+				T::Currency::transfer(
+					&fallback,
+					&account,
+					balance,
+					ExistenceRequirement::AllowDeath,
+				)?;
+			}
+
+			Addresses::<T>::insert(&account, address);
+			Accounts::<T>::insert(address, &account);
+			LinkNonces::<T>::mutate(address, |nonce| *nonce = nonce.wrapping_add(1));
+
+			Self::deposit_event(Event::Linked(account, address));
+			Ok(())
+		}
+
+		/// Raise the nonce of an account to `nonce`, if lower, with a nonce reference to keep it.
+		fn raise_nonce(who: &T::AccountId, nonce: T::Index) {
+			if frame_system::Pallet::<T>::account_nonce(who) < nonce {
+				pallet_evm::Pallet::<T>::hold_nonce_reference(who);
+				frame_system::Account::<T>::mutate(who, |info| info.nonce = nonce);
+			}
+		}
+	}
+}
+
+/// Address of the key that signed `message` with `personal_sign`.
+pub fn recover_signer(signature: &[u8; 65], message: &[u8]) -> Option<H160> {
+	let hash = keccak_256(&ethereum_signable_message(message));
+	sp_io::crypto::secp256k1_ecdsa_recover(signature, &hash)
+		.ok()
+		.map(|public| H160::from(H256::from(keccak_256(&public))))
+}
+
+/// Message actually signed by `personal_sign`.
+fn ethereum_signable_message(message: &[u8]) -> Vec<u8> {
+	let mut length = message.len();
+	let mut digits = Vec::new();
+	loop {
+		digits.push(b'0' + (length % 10) as u8);
+		length /= 10;
+		if length == 0 {
+			break;
+		}
+	}
+
+	let mut signable = b"\x19Ethereum Signed Message:\n".to_vec();
+	signable.extend(digits.iter().rev());
+	signable.extend_from_slice(message);
+	signable
+}
+
+/// Address mapping of linked addresses to their account, and of other addresses with
+/// `FallbackMapping`. Its storage read counts in the proof size of EVM executions.
+pub struct LinkedAddressMapping<T>(PhantomData<T>);
+
+impl<T: Config> AddressMapping<T::AccountId> for LinkedAddressMapping<T> {
+	const PROOF_SIZE: u64 = LINKED_ACCOUNT_PROOF_SIZE +
+		<T::FallbackMapping as AddressMapping<T::AccountId>>::PROOF_SIZE;
+
+	fn into_account_id(address: H160) -> T::AccountId {
+		Accounts::<T>::get(address).unwrap_or_else(|| T::FallbackMapping::into_account_id(address))
+	}
+
+	fn is_address_account(address: &H160) -> bool {
+		!Accounts::<T>::contains_key(address) &&
+			<T::FallbackMapping as AddressMapping<T::AccountId>>::is_address_account(address)
+	}
+}
+
+/// Ensure that the origin is the account linked to the address or, for addresses without link,
+/// that `Fallback` accepts it.
+pub struct EnsureAddressLinkedOr<T, Fallback>(PhantomData<(T, Fallback)>);
+
+impl<T, Fallback, OuterOrigin> EnsureAddressOrigin<OuterOrigin>
+	for EnsureAddressLinkedOr<T, Fallback>
+where
+	T: Config,
+	Fallback: EnsureAddressOrigin<OuterOrigin, Success = T::AccountId>,
+	OuterOrigin: Into<Result<RawOrigin<T::AccountId>, OuterOrigin>> + From<RawOrigin<T::AccountId>>,
+{
+	type Success = T::AccountId;
+
+	fn try_address_origin(
+		address: &H160,
+		origin: OuterOrigin,
+	) -> Result<T::AccountId, OuterOrigin> {
+		match Accounts::<T>::get(address) {
+			Some(account) => origin.into().and_then(|o| match o {
+				RawOrigin::Signed(who) if who == account => Ok(who),
+				r => Err(OuterOrigin::from(r)),
+			}),
+			None => Fallback::try_address_origin(address, origin),
+		}
+	}
+
+	#[cfg(feature = "runtime-benchmarks")]
+	fn successful_origin(address: &H160) -> OuterOrigin {
+		match Accounts::<T>::get(address) {
+			Some(account) => OuterOrigin::from(RawOrigin::Signed(account)),
+			None => Fallback::successful_origin(address),
+		}
+	}
+}
@@ -0,0 +1,527 @@
+// SPDX-License-Identifier: Apache-2.0
+// This file is part of Frontier.
+//
+// Copyright (c) 2021 Parity Technologies (UK) Ltd.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::*;
+use crate as pallet_account_link;
+
+use fp_account::EthereumSigner;
+use frame_support::{assert_noop, assert_ok, parameter_types, traits::Currency};
+use pallet_evm::{EnsureAddressTruncated, FeeCalculator, HashedAddressMapping, Runner};
+use sp_core::{ecdsa, sr25519, Pair, U256};
+use sp_runtime::{
+	generic,
+	traits::{BlakeTwo256, IdentifyAccount, IdentityLookup},
+	AccountId32, MultiSignature, MultiSigner,
+};
+
+type UncheckedExtrinsic = frame_system::mocking::MockUncheckedExtrinsic<Test>;
+type Block = frame_system::mocking::MockBlock<Test>;
+
+frame_support::construct_runtime! {
+	pub enum Test where
+		Block = Block,
+		NodeBlock = Block,
+		UncheckedExtrinsic = UncheckedExtrinsic,
+	{
+		System: frame_system::{Pallet, Call, Config, Storage, Event<T>},
+		Balances: pallet_balances::{Pallet, Call, Storage, Config<T>, Event<T>},
+		Timestamp: pallet_timestamp::{Pallet, Call, Storage},
+		EVM: pallet_evm::{Pallet, Call, Storage, Config, Event<T>},
+		AccountLink: pallet_account_link::{Pallet, Call, Storage, Event<T>},
+	}
+}
+
+parameter_types! {
+	pub const BlockHashCount: u64 = 250;
+}
+impl frame_system::Config for Test {
+	type BaseCallFilter = frame_support::traits::Everything;
+	type BlockWeights = ();
+	type BlockLength = ();
+	type DbWeight = ();
+	type Origin = Origin;
+	type Index = u64;
+	type BlockNumber = u64;
+	type Hash = H256;
+	type Call = Call;
+	type Hashing = BlakeTwo256;
+	type AccountId = AccountId32;
+	type Lookup = IdentityLookup<Self::AccountId>;
+	type Header = generic::Header<u64, BlakeTwo256>;
+	type Event = Event;
+	type BlockHashCount = BlockHashCount;
+	type Version = ();
+	type PalletInfo = PalletInfo;
+	type AccountData = pallet_balances::AccountData<u64>;
+	type OnNewAccount = ();
+	type OnKilledAccount = ();
+	type SystemWeightInfo = ();
+	type SS58Prefix = ();
+	type OnSetCode = ();
+}
+
+parameter_types! {
+	pub const ExistentialDeposit: u64 = 1;
+}
+impl pallet_balances::Config for Test {
+	type MaxLocks = ();
+	type Balance = u64;
+	type DustRemoval = ();
+	type Event = Event;
+	type ExistentialDeposit = ExistentialDeposit;
+	type AccountStore = System;
+	type WeightInfo = ();
+	type MaxReserves = ();
+	type ReserveIdentifier = ();
+}
+
+parameter_types! {
+	pub const MinimumPeriod: u64 = 1000;
+}
+impl pallet_timestamp::Config for Test {
+	type Moment = u64;
+	type OnTimestampSet = ();
+	type MinimumPeriod = MinimumPeriod;
+	type WeightInfo = ();
+}
+
+pub struct FixedGasPrice;
+impl FeeCalculator for FixedGasPrice {
+	fn min_gas_price() -> U256 {
+		1.into()
+	}
+}
+
+parameter_types! {
+	pub const ChainId: u64 = 42;
+	pub BlockGasLimit: U256 = U256::from(u32::MAX);
+}
+impl pallet_evm::Config for Test {
+	type FeeCalculator = FixedGasPrice;
+	type GasWeightMapping = ();
+	type CallOrigin = EnsureAddressLinkedOr<Self, EnsureAddressTruncated>;
+	type WithdrawOrigin = EnsureAddressLinkedOr<Self, EnsureAddressTruncated>;
+	type AddressMapping = LinkedAddressMapping<Self>;
+	type Currency = Balances;
+	type Runner = pallet_evm::runner::stack::Runner<Self>;
+	type Event = Event;
+	type PrecompilesType = ();
+	type PrecompilesValue = ();
+	type ChainId = ChainId;
+	type BlockGasLimit = BlockGasLimit;
+	type GasLimitPovSizeRatio = ();
+	type MaxBlockProofSize = ();
+	type MaxCodeSize = ();
+	type MaxTransactionGas = ();
+	type OnChargeTransaction = ();
+	type BlockHashMapping = pallet_evm::SubstrateBlockHashMapping<Self>;
+	type FindAuthor = ();
+	type WeightInfo = ();
+}
+
+impl Config for Test {
+	type Event = Event;
+	type Signature = MultiSignature;
+	type Signer = MultiSigner;
+	type FallbackMapping = HashedAddressMapping<BlakeTwo256>;
+}
+
+fn new_test_ext() -> sp_io::TestExternalities {
+	let t = frame_system::GenesisConfig::default().build_storage::<Test>().unwrap();
+	let mut ext = sp_io::TestExternalities::new(t);
+	ext.execute_with(|| System::set_block_number(1));
+	ext
+}
+
+fn substrate_pair() -> sr25519::Pair {
+	sr25519::Pair::from_seed(&[1u8; 32])
+}
+
+fn account_of(pair: &sr25519::Pair) -> AccountId32 {
+	MultiSigner::from(pair.public()).into_account()
+}
+
+fn ethereum_pair() -> ecdsa::Pair {
+	ecdsa::Pair::from_seed(&[2u8; 32])
+}
+
+fn address_of(pair: &ecdsa::Pair) -> H160 {
+	EthereumSigner::from(pair.public()).into_account()
+}
+
+fn fallback_of(address: H160) -> AccountId32 {
+	HashedAddressMapping::<BlakeTwo256>::into_account_id(address)
+}
+
+/// Signature of the link message with `personal_sign`.
+fn ethereum_sign(pair: &ecdsa::Pair, message: &[u8]) -> [u8; 65] {
+	let signature = pair.sign_prehashed(&keccak_256(&ethereum_signable_message(message)));
+	let mut raw = [0u8; 65];
+	raw.copy_from_slice(AsRef::<[u8]>::as_ref(&signature));
+	raw
+}
+
+fn link(account: &AccountId32, pair: &ecdsa::Pair) {
+	let address = address_of(pair);
+	let signature = ethereum_sign(pair, &AccountLink::link_message(account, &address));
+	assert_ok!(AccountLink::link_address(Origin::signed(account.clone()), address, signature));
+}
+
+#[test]
+fn signable_message_has_length_prefix() {
+	assert_eq!(ethereum_signable_message(b""), b"\x19Ethereum Signed Message:\n0".to_vec());
+	assert_eq!(
+		ethereum_signable_message(&[0u8; 120])[..29],
+		b"\x19Ethereum Signed Message:\n120"[..]
+	);
+}
+
+#[test]
+fn link_address_works() {
+	new_test_ext().execute_with(|| {
+		let account = account_of(&substrate_pair());
+		let address = address_of(&ethereum_pair());
+		assert_eq!(LinkedAddressMapping::<Test>::into_account_id(address), fallback_of(address));
+
+		link(&account, &ethereum_pair());
+
+		assert_eq!(AccountLink::address_of(&account), Some(address));
+		assert_eq!(AccountLink::account_of(address), Some(account.clone()));
+		assert_eq!(AccountLink::link_nonce(address), 1);
+		assert_eq!(LinkedAddressMapping::<Test>::into_account_id(address), account);
+		System::assert_last_event(Event::AccountLink(crate::Event::Linked(account, address)));
+	});
+}
+
+#[test]
+fn link_address_requires_signature_of_address() {
+	new_test_ext().execute_with(|| {
+		let account = account_of(&substrate_pair());
+		let address = address_of(&ethereum_pair());
+		let other = ecdsa::Pair::from_seed(&[3u8; 32]);
+
+		// Signed by another key.
+		let signature = ethereum_sign(&other, &AccountLink::link_message(&account, &address));
+		assert_noop!(
+			AccountLink::link_address(Origin::signed(account.clone()), address, signature),
+			Error::<Test>::BadSignature
+		);
+
+		// Signed for another account.
+		let signature = ethereum_sign(
+			&ethereum_pair(),
+			&AccountLink::link_message(&AccountId32::new([9u8; 32]), &address),
+		);
+		assert_noop!(
+			AccountLink::link_address(Origin::signed(account), address, signature),
+			Error::<Test>::BadSignature
+		);
+	});
+}
+
+#[test]
+fn link_account_works() {
+	new_test_ext().execute_with(|| {
+		let pair = substrate_pair();
+		let account = account_of(&pair);
+		let address = address_of(&ethereum_pair());
+		let signature =
+			MultiSignature::from(pair.sign(&AccountLink::link_message(&account, &address)));
+
+		assert_ok!(AccountLink::link_account(
+			Origin::signed(fallback_of(address)),
+			address,
+			account.clone(),
+			signature,
+		));
+
+		assert_eq!(AccountLink::account_of(address), Some(account.clone()));
+		System::assert_last_event(Event::AccountLink(crate::Event::Linked(account, address)));
+	});
+}
+
+#[test]
+fn link_account_requires_address_origin_and_signature_of_account() {
+	new_test_ext().execute_with(|| {
+		let pair = substrate_pair();
+		let account = account_of(&pair);
+		let address = address_of(&ethereum_pair());
+		let message = AccountLink::link_message(&account, &address);
+
+		assert_noop!(
+			AccountLink::link_account(
+				Origin::signed(AccountId32::new([9u8; 32])),
+				address,
+				account.clone(),
+				MultiSignature::from(pair.sign(&message)),
+			),
+			Error::<Test>::NotAddressOrigin
+		);
+		assert_noop!(
+			AccountLink::link_account(
+				Origin::signed(fallback_of(address)),
+				address,
+				account,
+				MultiSignature::from(sr25519::Pair::from_seed(&[3u8; 32]).sign(&message)),
+			),
+			Error::<Test>::BadSignature
+		);
+	});
+}
+
+#[test]
+fn accounts_and_addresses_link_once() {
+	new_test_ext().execute_with(|| {
+		let account = account_of(&substrate_pair());
+		let other_account = AccountId32::new([9u8; 32]);
+		let other_pair = ecdsa::Pair::from_seed(&[3u8; 32]);
+		link(&account, &ethereum_pair());
+
+		let address = address_of(&ethereum_pair());
+		let signature =
+			ethereum_sign(&ethereum_pair(), &AccountLink::link_message(&other_account, &address));
+		assert_noop!(
+			AccountLink::link_address(Origin::signed(other_account), address, signature),
+			Error::<Test>::AddressAlreadyLinked
+		);
+
+		let other_address = address_of(&other_pair);
+		let signature =
+			ethereum_sign(&other_pair, &AccountLink::link_message(&account, &other_address));
+		assert_noop!(
+			AccountLink::link_address(Origin::signed(account), other_address, signature),
+			Error::<Test>::AccountAlreadyLinked
+		);
+	});
+}
+
+#[test]
+fn addresses_with_code_cannot_be_linked() {
+	new_test_ext().execute_with(|| {
+		let pair = substrate_pair();
+		let account = account_of(&pair);
+		let address = address_of(&ethereum_pair());
+		pallet_evm::Pallet::<Test>::create_account(address, vec![0x00]);
+
+		let signature =
+			MultiSignature::from(pair.sign(&AccountLink::link_message(&account, &address)));
+		assert_noop!(
+			AccountLink::link_account(
+				Origin::signed(fallback_of(address)),
+				address,
+				account.clone(),
+				signature,
+			),
+			Error::<Test>::AddressHasCode
+		);
+		let signature =
+			ethereum_sign(&ethereum_pair(), &AccountLink::link_message(&account, &address));
+		assert_noop!(
+			AccountLink::link_address(Origin::signed(account), address, signature),
+			Error::<Test>::AddressHasCode
+		);
+	});
+}
+
+#[test]
+fn link_moves_funds_and_raises_nonce() {
+	new_test_ext().execute_with(|| {
+		let account = account_of(&substrate_pair());
+		let address = address_of(&ethereum_pair());
+		let fallback = fallback_of(address);
+		let _ = Balances::deposit_creating(&account, 100);
+		let _ = Balances::deposit_creating(&fallback, 50);
+		frame_system::Account::<Test>::mutate(&fallback, |info| info.nonce = 7);
+		frame_system::Account::<Test>::mutate(&account, |info| info.nonce = 3);
+
+		link(&account, &ethereum_pair());
+
+		assert_eq!(Balances::free_balance(&account), 150);
+		assert_eq!(Balances::free_balance(&fallback), 0);
+		assert_eq!(System::account_nonce(&account), 7);
+		assert!(EVM::has_nonce_reference(&account));
+		assert_eq!(EVM::account_basic(&address).balance, U256::from(150));
+		assert_eq!(EVM::account_basic(&address).nonce, U256::from(7));
+	});
+}
+
+#[test]
+fn link_keeps_higher_nonce() {
+	new_test_ext().execute_with(|| {
+		let account = account_of(&substrate_pair());
+		let address = address_of(&ethereum_pair());
+		frame_system::Account::<Test>::mutate(&fallback_of(address), |info| info.nonce = 2);
+		frame_system::Account::<Test>::mutate(&account, |info| info.nonce = 5);
+
+		link(&account, &ethereum_pair());
+
+		assert_eq!(System::account_nonce(&account), 5);
+	});
+}
+
+#[test]
+fn self_destructing_linked_address_keeps_account_nonce() {
+	new_test_ext().execute_with(|| {
+		let account = account_of(&substrate_pair());
+		let address = address_of(&ethereum_pair());
+		frame_system::Account::<Test>::mutate(&fallback_of(address), |info| info.nonce = 5);
+		link(&account, &ethereum_pair());
+		// CALLER SELFDESTRUCT
+		pallet_evm::Pallet::<Test>::create_account(address, vec![0x33, 0xff]);
+
+		assert_ok!(<Test as pallet_evm::Config>::Runner::call(
+			H160::zero(),
+			address,
+			Vec::new(),
+			U256::zero(),
+			1_000_000,
+			None,
+			None,
+			None,
+			Vec::new(),
+			<Test as pallet_evm::Config>::config(),
+		));
+
+		assert!(!EVM::has_code(&address));
+		// Extrinsics of the account can't be replayed.
+		assert_eq!(System::account_nonce(&account), 5);
+		assert!(EVM::has_nonce_reference(&account));
+	});
+}
+
+#[test]
+fn unlink_works() {
+	new_test_ext().execute_with(|| {
+		let account = account_of(&substrate_pair());
+		let address = address_of(&ethereum_pair());
+		let _ = Balances::deposit_creating(&account, 100);
+		link(&account, &ethereum_pair());
+		frame_system::Account::<Test>::mutate(&account, |info| info.nonce = 4);
+
+		assert_ok!(AccountLink::unlink(Origin::signed(account.clone())));
+
+		assert_eq!(AccountLink::address_of(&account), None);
+		assert_eq!(AccountLink::account_of(address), None);
+		assert_eq!(AccountLink::link_nonce(address), 2);
+		assert_eq!(LinkedAddressMapping::<Test>::into_account_id(address), fallback_of(address));
+		// Transactions executed while linked can't be replayed from the fallback account.
+		assert_eq!(System::account_nonce(&fallback_of(address)), 4);
+		// Funds stay with the account.
+		assert_eq!(Balances::free_balance(&account), 100);
+		System::assert_last_event(Event::AccountLink(crate::Event::Unlinked(account, address)));
+	});
+}
+
+#[test]
+fn unlink_requires_link() {
+	new_test_ext().execute_with(|| {
+		assert_noop!(
+			AccountLink::unlink(Origin::signed(account_of(&substrate_pair()))),
+			Error::<Test>::NotLinked
+		);
+	});
+}
+
+#[test]
+fn link_signatures_cannot_be_replayed() {
+	new_test_ext().execute_with(|| {
+		let account = account_of(&substrate_pair());
+		let address = address_of(&ethereum_pair());
+		let signature =
+			ethereum_sign(&ethereum_pair(), &AccountLink::link_message(&account, &address));
+		assert_ok!(AccountLink::link_address(Origin::signed(account.clone()), address, signature));
+		assert_ok!(AccountLink::unlink(Origin::signed(account.clone())));
+
+		assert_noop!(
+			AccountLink::link_address(Origin::signed(account.clone()), address, signature),
+			Error::<Test>::BadSignature
+		);
+
+		// A new signature relinks.
+		link(&account, &ethereum_pair());
+		assert_eq!(AccountLink::account_of(address), Some(account));
+	});
+}
+
+#[test]
+fn ensure_address_linked_or_works() {
+	new_test_ext().execute_with(|| {
+		type Ensure = EnsureAddressLinkedOr<Test, EnsureAddressTruncated>;
+		let account = account_of(&substrate_pair());
+		let address = address_of(&ethereum_pair());
+		let mut truncated = [0u8; 32];
+		truncated[..20].copy_from_slice(address.as_bytes());
+		let truncated = AccountId32::new(truncated);
+
+		// Without link, the fallback origin applies.
+		assert_eq!(
+			Ensure::ensure_address_origin(&address, Origin::signed(truncated.clone())),
+			Ok(truncated.clone())
+		);
+		assert!(Ensure::ensure_address_origin(&address, Origin::signed(account.clone())).is_err());
+
+		link(&account, &ethereum_pair());
+
+		assert_eq!(
+			Ensure::ensure_address_origin(&address, Origin::signed(account.clone())),
+			Ok(account)
+		);
+		assert!(Ensure::ensure_address_origin(&address, Origin::signed(truncated)).is_err());
+	});
+}
+
+/// Proof size of a call to a contract at `contract` running `code`.
+fn call_proof_size(contract: H160, code: Vec<u8>) -> u64 {
+	pallet_evm::Pallet::<Test>::create_account(contract, code);
+	let before = pallet_evm::Pallet::<Test>::block_proof_size();
+	<Test as pallet_evm::Config>::Runner::call(
+		H160::zero(),
+		contract,
+		Vec::new(),
+		U256::zero(),
+		1_000_000,
+		None,
+		None,
+		None,
+		Vec::new(),
+		<Test as pallet_evm::Config>::config(),
+	)
+	.unwrap();
+	pallet_evm::Pallet::<Test>::block_proof_size() - before
+}
+
+#[test]
+fn linked_address_mapping_counts_in_proof_size() {
+	use pallet_evm::runner::stack::{ACCOUNT_BASIC_PROOF_SIZE, TRIE_NODES_PROOF_SIZE};
+
+	new_test_ext().execute_with(|| {
+		let address = address_of(&ethereum_pair());
+		// STOP
+		let stop = call_proof_size(H160::repeat_byte(0xc1), vec![0x00]);
+		// PUSH20 address BALANCE STOP
+		let mut code = vec![0x73];
+		code.extend_from_slice(address.as_bytes());
+		code.extend_from_slice(&[0x31, 0x00]);
+		let balance = call_proof_size(H160::repeat_byte(0xc2), code);
+
+		// The longer code, and the account and link of `address`.
+		assert_eq!(
+			balance - stop,
+			22 + ACCOUNT_BASIC_PROOF_SIZE + LINKED_ACCOUNT_PROOF_SIZE + 2 * TRIE_NODES_PROOF_SIZE
+		);
+	});
+}
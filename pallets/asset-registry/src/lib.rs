@@ -0,0 +1,299 @@
+// SPDX-License-Identifier: Apache-2.0
+// This file is part of Frontier.
+//
+// Copyright (c) 2021 Parity Technologies (UK) Ltd.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! # Asset registry
+//!
+//! Maps the assets of the runtime to the XCM locations identifying them, and exposes them to the
+//! EVM. Foreign assets are registered with their location on their reserve chain, for example
+//! `(1, X3(Parachain(1000), PalletInstance(50), GeneralIndex(1984)))`, and local assets with
+//! their location on this chain, `(0, X2(PalletInstance(<assets>), GeneralIndex(<id>)))`.
+//!
+//! The registry provides the XCM executor with:
+//! - the conversion of locations to asset ids, for the asset transactor;
+//! - [`ReserveOfRegisteredAsset`], accepting reserve deposits of an asset from its reserve chain;
+//! - [`UnitsPerSecondTrader`], buying execution with an asset at a rate set by `RegisterOrigin`.
+//!
+//! Registering an asset stores the dummy code of `pallet_precompile_registry` at the address of
+//! its ERC-20 precompile, given by `AssetAddress`, so that contracts can call it.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(test)]
+mod tests;
+
+pub use pallet::*;
+
+use frame_support::weights::{constants::WEIGHT_PER_SECOND, Weight};
+use sp_std::{borrow::Borrow, marker::PhantomData};
+use xcm::latest::{
+	AssetId::Concrete,
+	Error as XcmError,
+	Junction::Parachain,
+	Junctions::{Here, X1},
+	MultiAsset, MultiLocation,
+};
+use xcm_builder::TakeRevenue;
+use xcm_executor::{
+	traits::{Convert, FilterAssetLocation, WeightTrader},
+	Assets,
+};
+
+#[frame_support::pallet]
+pub mod pallet {
+	use frame_support::{dispatch::DispatchResult, pallet_prelude::*, traits::tokens::fungibles};
+	use frame_system::pallet_prelude::*;
+	use pallet_precompile_registry::DUMMY_CODE;
+	use sp_core::H160;
+	use sp_runtime::traits::{Convert, Zero};
+	use sp_std::{boxed::Box, convert::TryFrom};
+	use xcm::{latest::MultiLocation, VersionedMultiLocation};
+
+	#[pallet::config]
+	pub trait Config: frame_system::Config + pallet_evm::Config {
+		type Event: From<Event<Self>> + IsType<<Self as frame_system::Config>::Event>;
+		/// Identifier of the assets.
+		type AssetId: Parameter + Member + Copy;
+		/// The assets that can be registered.
+		type Assets: fungibles::Inspect<Self::AccountId, AssetId = Self::AssetId>;
+		/// Address of the ERC-20 precompile of an asset.
+		type AssetAddress: Convert<Self::AssetId, H160>;
+		/// Origin allowed to register assets and set their fee rates.
+		type RegisterOrigin: EnsureOrigin<Self::Origin>;
+	}
+
+	#[pallet::pallet]
+	#[pallet::generate_store(pub(super) trait Store)]
+	pub struct Pallet<T>(_);
+
+	/// Location of a registered asset.
+	#[pallet::storage]
+	#[pallet::getter(fn asset_location)]
+	pub type AssetLocations<T: Config> =
+		StorageMap<_, Blake2_128Concat, T::AssetId, MultiLocation, OptionQuery>;
+
+	/// Registered asset at a location.
+	#[pallet::storage]
+	#[pallet::getter(fn asset_at)]
+	pub type AssetIds<T: Config> =
+		StorageMap<_, Blake2_128Concat, MultiLocation, T::AssetId, OptionQuery>;
+
+	/// Amount of a registered asset paying for one second of XCM execution. Assets without a rate
+	/// can't pay for execution.
+	#[pallet::storage]
+	#[pallet::getter(fn units_per_second)]
+	pub type UnitsPerSecond<T: Config> =
+		StorageMap<_, Blake2_128Concat, T::AssetId, u128, OptionQuery>;
+
+	#[pallet::event]
+	#[pallet::generate_deposit(pub(super) fn deposit_event)]
+	pub enum Event<T: Config> {
+		/// An asset was registered at a location. \[asset_id, location\]
+		AssetRegistered(T::AssetId, MultiLocation),
+		/// An asset was removed from the registry. \[asset_id, location\]
+		AssetDeregistered(T::AssetId, MultiLocation),
+		/// The fee rate of an asset was set. \[asset_id, units_per_second\]
+		UnitsPerSecondSet(T::AssetId, u128),
+	}
+
+	#[pallet::error]
+	pub enum Error<T> {
+		/// The asset does not exist.
+		AssetNotFound,
+		/// The asset is already registered.
+		AlreadyRegistered,
+		/// The asset is not registered.
+		NotRegistered,
+		/// Another asset is registered at the location.
+		LocationInUse,
+		/// The location can't be converted to the current XCM version.
+		BadLocation,
+		/// The address of the asset precompile holds contract code.
+		AddressHasCode,
+	}
+
+	#[pallet::call]
+	impl<T: Config> Pallet<T> {
+		/// Register an existing asset at a location and enable its ERC-20 precompile.
+		#[pallet::weight(10_000 + T::DbWeight::get().reads_writes(4, 3))]
+		pub fn register_asset(
+			origin: OriginFor<T>,
+			asset_id: T::AssetId,
+			location: Box<VersionedMultiLocation>,
+		) -> DispatchResult {
+			T::RegisterOrigin::ensure_origin(origin)?;
+			let location =
+				MultiLocation::try_from(*location).map_err(|()| Error::<T>::BadLocation)?;
+			ensure!(
+				!<T::Assets as fungibles::Inspect<_>>::minimum_balance(asset_id).is_zero(),
+				Error::<T>::AssetNotFound
+			);
+			ensure!(!<AssetLocations<T>>::contains_key(&asset_id), Error::<T>::AlreadyRegistered);
+			ensure!(!<AssetIds<T>>::contains_key(&location), Error::<T>::LocationInUse);
+			let address = T::AssetAddress::convert(asset_id);
+			ensure!(!pallet_evm::Pallet::<T>::has_code(&address), Error::<T>::AddressHasCode);
+
+			<AssetLocations<T>>::insert(&asset_id, &location);
+			<AssetIds<T>>::insert(&location, asset_id);
+			pallet_evm::Pallet::<T>::create_account(address, DUMMY_CODE.to_vec());
+			Self::deposit_event(Event::AssetRegistered(asset_id, location));
+			Ok(())
+		}
+
+		/// Remove an asset from the registry and disable its ERC-20 precompile. The asset itself
+		/// is kept.
+		#[pallet::weight(10_000 + T::DbWeight::get().reads_writes(1, 4))]
+		pub fn deregister_asset(origin: OriginFor<T>, asset_id: T::AssetId) -> DispatchResult {
+			T::RegisterOrigin::ensure_origin(origin)?;
+			let location = <AssetLocations<T>>::take(&asset_id).ok_or(Error::<T>::NotRegistered)?;
+
+			<AssetIds<T>>::remove(&location);
+			<UnitsPerSecond<T>>::remove(&asset_id);
+			pallet_evm::Pallet::<T>::remove_account(&T::AssetAddress::convert(asset_id));
+			Self::deposit_event(Event::AssetDeregistered(asset_id, location));
+			Ok(())
+		}
+
+		/// Set the amount of a registered asset paying for one second of XCM execution. Zero
+		/// prevents the asset from paying fees.
+		#[pallet::weight(10_000 + T::DbWeight::get().reads_writes(1, 1))]
+		pub fn set_units_per_second(
+			origin: OriginFor<T>,
+			asset_id: T::AssetId,
+			units_per_second: u128,
+		) -> DispatchResult {
+			T::RegisterOrigin::ensure_origin(origin)?;
+			ensure!(<AssetLocations<T>>::contains_key(&asset_id), Error::<T>::NotRegistered);
+
+			if units_per_second.is_zero() {
+				<UnitsPerSecond<T>>::remove(&asset_id);
+			} else {
+				<UnitsPerSecond<T>>::insert(&asset_id, units_per_second);
+			}
+			Self::deposit_event(Event::UnitsPerSecondSet(asset_id, units_per_second));
+			Ok(())
+		}
+	}
+}
+
+/// Converts the locations of registered assets to their id, for `ConvertedConcreteAssetId`.
+impl<T: Config> Convert<MultiLocation, T::AssetId> for Pallet<T> {
+	fn convert_ref(location: impl Borrow<MultiLocation>) -> Result<T::AssetId, ()> {
+		<AssetIds<T>>::get(location.borrow()).ok_or(())
+	}
+
+	fn reverse_ref(asset_id: impl Borrow<T::AssetId>) -> Result<MultiLocation, ()> {
+		<AssetLocations<T>>::get(asset_id.borrow()).ok_or(())
+	}
+}
+
+/// Chain holding the reserve of the asset at `location`: the parachain it starts with, if any,
+/// else the chain at its `parents`.
+fn reserve_chain(location: &MultiLocation) -> MultiLocation {
+	match location.first_interior() {
+		Some(Parachain(id)) => MultiLocation::new(location.parents, X1(Parachain(*id))),
+		_ => MultiLocation::new(location.parents, Here),
+	}
+}
+
+/// Accepts reserve deposits of registered assets coming from their reserve chain.
+pub struct ReserveOfRegisteredAsset<T>(PhantomData<T>);
+
+impl<T: Config> FilterAssetLocation for ReserveOfRegisteredAsset<T> {
+	fn filter_asset_location(asset: &MultiAsset, origin: &MultiLocation) -> bool {
+		match &asset.id {
+			Concrete(location) => {
+				<AssetIds<T>>::contains_key(location) && &reserve_chain(location) == origin
+			},
+			_ => false,
+		}
+	}
+}
+
+/// Buys XCM execution with the first registered asset of the payment having a fee rate, at its
+/// `UnitsPerSecond`. The fees are given to `Revenue` when the trader is dropped.
+pub struct UnitsPerSecondTrader<T: Config, Revenue: TakeRevenue> {
+	weight: Weight,
+	/// Asset paying the fees, with its rate and the amount paid.
+	paid: Option<(MultiLocation, u128, u128)>,
+	_marker: PhantomData<(T, Revenue)>,
+}
+
+impl<T: Config, Revenue: TakeRevenue> UnitsPerSecondTrader<T, Revenue> {
+	fn rate(location: &MultiLocation) -> Option<u128> {
+		<AssetIds<T>>::get(location).and_then(<UnitsPerSecond<T>>::get)
+	}
+
+	fn fee(units_per_second: u128, weight: Weight) -> u128 {
+		units_per_second.saturating_mul(weight as u128) / (WEIGHT_PER_SECOND as u128)
+	}
+}
+
+impl<T: Config, Revenue: TakeRevenue> WeightTrader for UnitsPerSecondTrader<T, Revenue> {
+	fn new() -> Self {
+		Self { weight: 0, paid: None, _marker: PhantomData }
+	}
+
+	fn buy_weight(&mut self, weight: Weight, payment: Assets) -> Result<Assets, XcmError> {
+		// Once an asset paid, later purchases must use the same one.
+		let (location, units_per_second) = match &self.paid {
+			Some((location, units_per_second, _)) => (location.clone(), *units_per_second),
+			None => payment
+				.fungible_assets_iter()
+				.find_map(|asset| match asset.id {
+					Concrete(location) => {
+						Self::rate(&location).map(|units_per_second| (location, units_per_second))
+					},
+					_ => None,
+				})
+				.ok_or(XcmError::TooExpensive)?,
+		};
+
+		let amount = Self::fee(units_per_second, weight);
+		let unused = payment
+			.checked_sub((Concrete(location.clone()), amount).into())
+			.map_err(|_| XcmError::TooExpensive)?;
+
+		let paid = self.paid.as_ref().map_or(0, |(_, _, paid)| *paid).saturating_add(amount);
+		self.weight = self.weight.saturating_add(weight);
+		self.paid = Some((location, units_per_second, paid));
+		Ok(unused)
+	}
+
+	fn refund_weight(&mut self, weight: Weight) -> Option<MultiAsset> {
+		let (location, units_per_second, paid) = self.paid.as_mut()?;
+		let weight = weight.min(self.weight);
+		let amount = Self::fee(*units_per_second, weight).min(*paid);
+		self.weight -= weight;
+		*paid -= amount;
+
+		if amount > 0 {
+			Some((Concrete(location.clone()), amount).into())
+		} else {
+			None
+		}
+	}
+}
+
+impl<T: Config, Revenue: TakeRevenue> Drop for UnitsPerSecondTrader<T, Revenue> {
+	fn drop(&mut self) {
+		if let Some((location, _, paid)) = self.paid.take() {
+			if paid > 0 {
+				Revenue::take_revenue((Concrete(location), paid).into());
+			}
+		}
+	}
+}
@@ -0,0 +1,396 @@
+// SPDX-License-Identifier: Apache-2.0
+// This file is part of Frontier.
+//
+// Copyright (c) 2021 Parity Technologies (UK) Ltd.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::*;
+use crate as pallet_asset_registry;
+
+use frame_support::{assert_noop, assert_ok, parameter_types};
+use frame_system::EnsureRoot;
+use pallet_evm::{EnsureAddressNever, EnsureAddressRoot, IdentityAddressMapping};
+use pallet_precompile_registry::DUMMY_CODE;
+use sp_core::{H160, H256};
+use sp_runtime::{
+	generic,
+	traits::{BlakeTwo256, IdentityLookup},
+	DispatchError,
+};
+use sp_std::cell::RefCell;
+use xcm::{
+	latest::{
+		Junction::{GeneralIndex, PalletInstance},
+		Junctions::X3,
+	},
+	VersionedMultiLocation,
+};
+
+type UncheckedExtrinsic = frame_system::mocking::MockUncheckedExtrinsic<Test>;
+type Block = frame_system::mocking::MockBlock<Test>;
+
+frame_support::construct_runtime! {
+	pub enum Test where
+		Block = Block,
+		NodeBlock = Block,
+		UncheckedExtrinsic = UncheckedExtrinsic,
+	{
+		System: frame_system::{Pallet, Call, Config, Storage, Event<T>},
+		Balances: pallet_balances::{Pallet, Call, Storage, Config<T>, Event<T>},
+		Timestamp: pallet_timestamp::{Pallet, Call, Storage},
+		EVM: pallet_evm::{Pallet, Call, Storage, Config, Event<T>},
+		Assets: pallet_assets::{Pallet, Call, Storage, Event<T>},
+		AssetRegistry: pallet_asset_registry::{Pallet, Call, Storage, Event<T>},
+	}
+}
+
+parameter_types! {
+	pub const BlockHashCount: u64 = 250;
+}
+impl frame_system::Config for Test {
+	type BaseCallFilter = frame_support::traits::Everything;
+	type BlockWeights = ();
+	type BlockLength = ();
+	type DbWeight = ();
+	type Origin = Origin;
+	type Index = u64;
+	type BlockNumber = u64;
+	type Hash = H256;
+	type Call = Call;
+	type Hashing = BlakeTwo256;
+	type AccountId = H160;
+	type Lookup = IdentityLookup<Self::AccountId>;
+	type Header = generic::Header<u64, BlakeTwo256>;
+	type Event = Event;
+	type BlockHashCount = BlockHashCount;
+	type Version = ();
+	type PalletInfo = PalletInfo;
+	type AccountData = pallet_balances::AccountData<u64>;
+	type OnNewAccount = ();
+	type OnKilledAccount = ();
+	type SystemWeightInfo = ();
+	type SS58Prefix = ();
+	type OnSetCode = ();
+}
+
+parameter_types! {
+	pub const ExistentialDeposit: u64 = 0;
+}
+impl pallet_balances::Config for Test {
+	type MaxLocks = ();
+	type Balance = u64;
+	type DustRemoval = ();
+	type Event = Event;
+	type ExistentialDeposit = ExistentialDeposit;
+	type AccountStore = System;
+	type WeightInfo = ();
+	type MaxReserves = ();
+	type ReserveIdentifier = ();
+}
+
+parameter_types! {
+	pub const MinimumPeriod: u64 = 1000;
+}
+impl pallet_timestamp::Config for Test {
+	type Moment = u64;
+	type OnTimestampSet = ();
+	type MinimumPeriod = MinimumPeriod;
+	type WeightInfo = ();
+}
+
+impl pallet_evm::Config for Test {
+	type FeeCalculator = ();
+	type GasWeightMapping = ();
+	type CallOrigin = EnsureAddressRoot<Self::AccountId>;
+	type WithdrawOrigin = EnsureAddressNever<Self::AccountId>;
+	type AddressMapping = IdentityAddressMapping;
+	type Currency = Balances;
+	type Runner = pallet_evm::runner::stack::Runner<Self>;
+	type Event = Event;
+	type PrecompilesType = ();
+	type PrecompilesValue = ();
+	type ChainId = ();
+	type BlockGasLimit = ();
+	type GasLimitPovSizeRatio = ();
+	type MaxBlockProofSize = ();
+	type MaxCodeSize = ();
+	type MaxTransactionGas = ();
+	type OnChargeTransaction = ();
+	type BlockHashMapping = pallet_evm::SubstrateBlockHashMapping<Self>;
+	type FindAuthor = ();
+	type WeightInfo = ();
+}
+
+parameter_types! {
+	pub const AssetDeposit: u64 = 0;
+	pub const ApprovalDeposit: u64 = 0;
+	pub const StringLimit: u32 = 32;
+	pub const MetadataDepositBase: u64 = 0;
+	pub const MetadataDepositPerByte: u64 = 0;
+}
+impl pallet_assets::Config for Test {
+	type Event = Event;
+	type Balance = u64;
+	type AssetId = u32;
+	type Currency = Balances;
+	type ForceOrigin = EnsureRoot<H160>;
+	type AssetDeposit = AssetDeposit;
+	type MetadataDepositBase = MetadataDepositBase;
+	type MetadataDepositPerByte = MetadataDepositPerByte;
+	type ApprovalDeposit = ApprovalDeposit;
+	type StringLimit = StringLimit;
+	type Freezer = ();
+	type Extra = ();
+	type WeightInfo = ();
+}
+
+pub struct AssetAddress;
+impl sp_runtime::traits::Convert<u32, H160> for AssetAddress {
+	fn convert(asset_id: u32) -> H160 {
+		H160::from_low_u64_be(0xff00_0000 + asset_id as u64)
+	}
+}
+
+impl Config for Test {
+	type Event = Event;
+	type AssetId = u32;
+	type Assets = Assets;
+	type AssetAddress = AssetAddress;
+	type RegisterOrigin = EnsureRoot<H160>;
+}
+
+thread_local! {
+	static REVENUE: RefCell<Vec<MultiAsset>> = RefCell::new(Vec::new());
+}
+
+pub struct Revenue;
+impl TakeRevenue for Revenue {
+	fn take_revenue(revenue: MultiAsset) {
+		REVENUE.with(|r| r.borrow_mut().push(revenue));
+	}
+}
+
+type Trader = UnitsPerSecondTrader<Test, Revenue>;
+
+fn usdt() -> MultiLocation {
+	MultiLocation::new(1, X3(Parachain(1000), PalletInstance(50), GeneralIndex(1984)))
+}
+
+fn statemine() -> MultiLocation {
+	MultiLocation::new(1, X1(Parachain(1000)))
+}
+
+fn versioned(location: MultiLocation) -> Box<VersionedMultiLocation> {
+	Box::new(location.into())
+}
+
+fn asset_address(asset_id: u32) -> H160 {
+	<AssetAddress as sp_runtime::traits::Convert<_, _>>::convert(asset_id)
+}
+
+fn new_test_ext() -> sp_io::TestExternalities {
+	let t = frame_system::GenesisConfig::default().build_storage::<Test>().unwrap();
+	let mut ext = sp_io::TestExternalities::new(t);
+	ext.execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(Assets::force_create(Origin::root(), 1, H160::default(), true, 1));
+		assert_ok!(Assets::force_create(Origin::root(), 2, H160::default(), true, 1));
+	});
+	ext
+}
+
+fn register_usdt() {
+	assert_ok!(AssetRegistry::register_asset(Origin::root(), 1, versioned(usdt())));
+}
+
+#[test]
+fn register_asset_maps_location_and_enables_precompile() {
+	new_test_ext().execute_with(|| {
+		register_usdt();
+
+		assert_eq!(AssetRegistry::asset_location(1), Some(usdt()));
+		assert_eq!(AssetRegistry::asset_at(usdt()), Some(1));
+		assert_eq!(EVM::account_codes(asset_address(1)), DUMMY_CODE.to_vec());
+		System::assert_last_event(Event::AssetRegistry(crate::Event::AssetRegistered(1, usdt())));
+
+		assert_eq!(<AssetRegistry as Convert<MultiLocation, u32>>::convert_ref(usdt()), Ok(1));
+		assert_eq!(<AssetRegistry as Convert<MultiLocation, u32>>::reverse_ref(1), Ok(usdt()));
+		assert_eq!(
+			<AssetRegistry as Convert<MultiLocation, u32>>::convert_ref(statemine()),
+			Err(())
+		);
+		assert_eq!(<AssetRegistry as Convert<MultiLocation, u32>>::reverse_ref(2), Err(()));
+	});
+}
+
+#[test]
+fn register_asset_requires_register_origin() {
+	new_test_ext().execute_with(|| {
+		assert_noop!(
+			AssetRegistry::register_asset(Origin::signed(H160::default()), 1, versioned(usdt())),
+			DispatchError::BadOrigin
+		);
+	});
+}
+
+#[test]
+fn register_asset_checks_asset_location_and_address() {
+	new_test_ext().execute_with(|| {
+		assert_noop!(
+			AssetRegistry::register_asset(Origin::root(), 3, versioned(usdt())),
+			Error::<Test>::AssetNotFound
+		);
+
+		register_usdt();
+		assert_noop!(
+			AssetRegistry::register_asset(Origin::root(), 1, versioned(statemine())),
+			Error::<Test>::AlreadyRegistered
+		);
+		assert_noop!(
+			AssetRegistry::register_asset(Origin::root(), 2, versioned(usdt())),
+			Error::<Test>::LocationInUse
+		);
+
+		pallet_evm::Pallet::<Test>::create_account(asset_address(2), vec![0x00]);
+		assert_noop!(
+			AssetRegistry::register_asset(Origin::root(), 2, versioned(statemine())),
+			Error::<Test>::AddressHasCode
+		);
+	});
+}
+
+#[test]
+fn deregister_asset_removes_mappings_and_code() {
+	new_test_ext().execute_with(|| {
+		register_usdt();
+		assert_ok!(AssetRegistry::set_units_per_second(Origin::root(), 1, 1_000));
+
+		assert_ok!(AssetRegistry::deregister_asset(Origin::root(), 1));
+		assert_eq!(AssetRegistry::asset_location(1), None);
+		assert_eq!(AssetRegistry::asset_at(usdt()), None);
+		assert_eq!(AssetRegistry::units_per_second(1), None);
+		assert!(EVM::account_codes(asset_address(1)).is_empty());
+		System::assert_last_event(Event::AssetRegistry(crate::Event::AssetDeregistered(1, usdt())));
+
+		assert_noop!(
+			AssetRegistry::deregister_asset(Origin::root(), 1),
+			Error::<Test>::NotRegistered
+		);
+	});
+}
+
+#[test]
+fn set_units_per_second_works() {
+	new_test_ext().execute_with(|| {
+		assert_noop!(
+			AssetRegistry::set_units_per_second(Origin::root(), 1, 1_000),
+			Error::<Test>::NotRegistered
+		);
+
+		register_usdt();
+		assert_ok!(AssetRegistry::set_units_per_second(Origin::root(), 1, 1_000));
+		assert_eq!(AssetRegistry::units_per_second(1), Some(1_000));
+		System::assert_last_event(Event::AssetRegistry(crate::Event::UnitsPerSecondSet(1, 1_000)));
+
+		assert_ok!(AssetRegistry::set_units_per_second(Origin::root(), 1, 0));
+		assert_eq!(AssetRegistry::units_per_second(1), None);
+	});
+}
+
+#[test]
+fn registered_assets_are_reserve_of_their_chain() {
+	new_test_ext().execute_with(|| {
+		register_usdt();
+		assert_ok!(AssetRegistry::register_asset(
+			Origin::root(),
+			2,
+			versioned(MultiLocation::parent())
+		));
+
+		let usdt_asset: MultiAsset = (Concrete(usdt()), 10).into();
+		let relay_asset: MultiAsset = (Concrete(MultiLocation::parent()), 10).into();
+		let unknown_asset: MultiAsset = (Concrete(statemine()), 10).into();
+
+		assert!(ReserveOfRegisteredAsset::<Test>::filter_asset_location(&usdt_asset, &statemine()));
+		assert!(!ReserveOfRegisteredAsset::<Test>::filter_asset_location(
+			&usdt_asset,
+			&MultiLocation::new(1, X1(Parachain(2000)))
+		));
+		assert!(!ReserveOfRegisteredAsset::<Test>::filter_asset_location(
+			&usdt_asset,
+			&MultiLocation::parent()
+		));
+		assert!(ReserveOfRegisteredAsset::<Test>::filter_asset_location(
+			&relay_asset,
+			&MultiLocation::parent()
+		));
+		assert!(!ReserveOfRegisteredAsset::<Test>::filter_asset_location(
+			&unknown_asset,
+			&statemine()
+		));
+	});
+}
+
+#[test]
+fn trader_buys_weight_with_registered_asset() {
+	new_test_ext().execute_with(|| {
+		register_usdt();
+		assert_ok!(AssetRegistry::set_units_per_second(Origin::root(), 1, 1_000));
+		REVENUE.with(|r| r.borrow_mut().clear());
+
+		{
+			let mut trader = Trader::new();
+			let payment: MultiAsset = (Concrete(usdt()), 800).into();
+			let unused = trader.buy_weight(WEIGHT_PER_SECOND / 2, payment.into());
+			assert_eq!(unused, Ok(MultiAsset::from((Concrete(usdt()), 300)).into()));
+
+			let payment: MultiAsset = (Concrete(usdt()), 300).into();
+			let unused = trader.buy_weight(WEIGHT_PER_SECOND / 10, payment.into());
+			assert_eq!(unused, Ok(MultiAsset::from((Concrete(usdt()), 200)).into()));
+
+			assert_eq!(
+				trader.refund_weight(WEIGHT_PER_SECOND / 5),
+				Some((Concrete(usdt()), 200).into())
+			);
+		}
+
+		REVENUE.with(|r| assert_eq!(*r.borrow(), vec![(Concrete(usdt()), 400).into()]));
+	});
+}
+
+#[test]
+fn trader_rejects_assets_without_rate_and_short_payments() {
+	new_test_ext().execute_with(|| {
+		register_usdt();
+		REVENUE.with(|r| r.borrow_mut().clear());
+
+		{
+			let mut trader = Trader::new();
+			let payment: MultiAsset = (Concrete(usdt()), 1_000).into();
+			assert_eq!(
+				trader.buy_weight(WEIGHT_PER_SECOND, payment.clone().into()),
+				Err(XcmError::TooExpensive)
+			);
+
+			assert_ok!(AssetRegistry::set_units_per_second(Origin::root(), 1, 2_000));
+			assert_eq!(
+				trader.buy_weight(WEIGHT_PER_SECOND, payment.into()),
+				Err(XcmError::TooExpensive)
+			);
+			assert_eq!(trader.refund_weight(WEIGHT_PER_SECOND), None);
+		}
+
+		REVENUE.with(|r| assert!(r.borrow().is_empty()));
+	});
+}
@@ -0,0 +1,150 @@
+// SPDX-License-Identifier: Apache-2.0
+// This file is part of Frontier.
+//
+// Copyright (c) 2021 Parity Technologies (UK) Ltd.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! # Ethereum XCM pallet
+//!
+//! Lets other chains run Ethereum transactions on this chain with XCM `Transact`.
+//!
+//! The `transact` call of the pallet accepts the origins of `Config::XcmOrigin`, usually the XCM
+//! origin of `pallet_xcm` that `Transact` with `OriginKind::Xcm` dispatches from. The Ethereum
+//! address of the sender is derived from its `MultiLocation` with [`location_to_address`]. The
+//! call turns an unsigned [`EthereumXcmTransaction`] into an EIP-1559 transaction of that address,
+//! using its EVM nonce, and runs it through `pallet_ethereum`. It is validated, charged and
+//! included in the Ethereum block and receipts like a signed transaction.
+//!
+//! The signature of the transaction is a placeholder: `r` holds the source address, so that
+//! transactions of distinct origins have distinct hashes.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(test)]
+mod tests;
+
+pub use pallet::*;
+
+use codec::{Decode, Encode};
+use ethereum::{AccessListItem, EIP1559Transaction, TransactionAction, TransactionV2};
+use frame_support::traits::Get;
+use scale_info::TypeInfo;
+use sp_core::{H160, H256, U256};
+use sp_runtime::RuntimeDebug;
+use sp_std::vec::Vec;
+use xcm::latest::MultiLocation;
+
+/// Ethereum address of the sender of an XCM message.
+pub fn location_to_address(location: &MultiLocation) -> H160 {
+	let hash = sp_io::hashing::blake2_256(&(b"ethereum-xcm", location).encode());
+	H160::from_slice(&hash[0..20])
+}
+
+/// Ethereum transaction sent with XCM: an EIP-1559 transaction without nonce, chain id and
+/// signature.
+#[derive(Clone, PartialEq, Eq, Encode, Decode, RuntimeDebug, TypeInfo)]
+pub struct EthereumXcmTransaction {
+	pub gas_limit: U256,
+	pub max_fee_per_gas: U256,
+	pub max_priority_fee_per_gas: U256,
+	pub action: TransactionAction,
+	pub value: U256,
+	pub input: Vec<u8>,
+	pub access_list: Vec<AccessListItem>,
+}
+
+impl EthereumXcmTransaction {
+	/// EIP-1559 transaction of `source`, with a placeholder signature.
+	pub fn into_transaction(self, source: H160, nonce: U256, chain_id: u64) -> TransactionV2 {
+		TransactionV2::EIP1559(EIP1559Transaction {
+			chain_id,
+			nonce,
+			max_priority_fee_per_gas: self.max_priority_fee_per_gas,
+			max_fee_per_gas: self.max_fee_per_gas,
+			gas_limit: self.gas_limit,
+			action: self.action,
+			value: self.value,
+			input: self.input,
+			access_list: self.access_list,
+			odd_y_parity: false,
+			r: H256::from(source),
+			s: H256::from_low_u64_be(1),
+		})
+	}
+}
+
+#[frame_support::pallet]
+pub mod pallet {
+	use super::*;
+	use frame_support::pallet_prelude::*;
+	use frame_system::pallet_prelude::*;
+	use pallet_ethereum::WeightInfo;
+	use pallet_evm::GasWeightMapping;
+	use sp_runtime::traits::UniqueSaturatedInto;
+
+	#[pallet::config]
+	pub trait Config: frame_system::Config + pallet_ethereum::Config {
+		/// Origin of the senders of XCM messages, allowed to transact from the address derived
+		/// from their location.
+		type XcmOrigin: EnsureOrigin<Self::Origin, Success = MultiLocation>;
+	}
+
+	#[pallet::pallet]
+	#[pallet::generate_store(pub(super) trait Store)]
+	pub struct Pallet<T>(PhantomData<T>);
+
+	#[pallet::error]
+	pub enum Error<T> {
+		/// The transaction failed the checks of a signed Ethereum transaction, such as the fee
+		/// or the gas limit.
+		InvalidTransaction,
+		/// Pre-log is present, therefore transact is not allowed.
+		PreLogExists,
+	}
+
+	#[pallet::call]
+	impl<T: Config> Pallet<T> {
+		/// Transact an Ethereum transaction sent with XCM.
+		#[pallet::weight(<T as pallet_evm::Config>::GasWeightMapping::gas_to_weight(
+			xcm_transaction.gas_limit.unique_saturated_into()
+		).saturating_add(<T as pallet_ethereum::Config>::WeightInfo::transact()))]
+		pub fn transact(
+			origin: OriginFor<T>,
+			xcm_transaction: EthereumXcmTransaction,
+		) -> DispatchResultWithPostInfo {
+			let source = location_to_address(&T::XcmOrigin::ensure_origin(origin)?);
+			ensure!(
+				!pallet_evm::is_dispatching_from_evm(),
+				pallet_evm::Error::<T>::DispatchedFromEvm
+			);
+			ensure!(
+				pallet_evm::Pallet::<T>::proof_size_fits_in_block(xcm_transaction.gas_limit),
+				pallet_evm::Error::<T>::BlockProofSizeExhausted
+			);
+			ensure!(
+				fp_consensus::find_pre_log(&frame_system::Pallet::<T>::digest()).is_err(),
+				Error::<T>::PreLogExists,
+			);
+
+			let nonce = pallet_evm::Pallet::<T>::account_basic(&source).nonce;
+			let chain_id = <T as pallet_evm::Config>::ChainId::get();
+			let transaction = xcm_transaction.into_transaction(source, nonce, chain_id);
+
+			pallet_ethereum::Pallet::<T>::validate_transaction_in_block(source, &transaction)
+				.map_err(|_| Error::<T>::InvalidTransaction)?;
+
+			Ok(pallet_ethereum::Pallet::<T>::apply_validated_transaction(source, transaction))
+		}
+	}
+}
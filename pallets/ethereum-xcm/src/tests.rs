@@ -0,0 +1,341 @@
+// SPDX-License-Identifier: Apache-2.0
+// This file is part of Frontier.
+//
+// Copyright (c) 2021 Parity Technologies (UK) Ltd.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::*;
+use crate as pallet_ethereum_xcm;
+
+use frame_support::{
+	assert_noop, assert_ok, parameter_types,
+	traits::{EnsureOrigin, GenesisBuild, OnFinalize},
+};
+use pallet_evm::{AddressMapping, EnsureAddressNever, EnsureAddressRoot, FeeCalculator};
+use sp_runtime::{
+	generic,
+	traits::{BlakeTwo256, IdentityLookup},
+	AccountId32, DispatchError,
+};
+use xcm::latest::Junction;
+
+type UncheckedExtrinsic = frame_system::mocking::MockUncheckedExtrinsic<Test>;
+type Block = frame_system::mocking::MockBlock<Test>;
+
+frame_support::construct_runtime! {
+	pub enum Test where
+		Block = Block,
+		NodeBlock = Block,
+		UncheckedExtrinsic = UncheckedExtrinsic,
+	{
+		System: frame_system::{Pallet, Call, Config, Storage, Event<T>},
+		Balances: pallet_balances::{Pallet, Call, Storage, Config<T>, Event<T>},
+		Timestamp: pallet_timestamp::{Pallet, Call, Storage},
+		EVM: pallet_evm::{Pallet, Call, Storage, Config, Event<T>},
+		Ethereum: pallet_ethereum::{Pallet, Call, Storage, Event, Origin},
+		EthereumXcm: pallet_ethereum_xcm::{Pallet, Call},
+	}
+}
+
+parameter_types! {
+	pub const BlockHashCount: u64 = 250;
+}
+impl frame_system::Config for Test {
+	type BaseCallFilter = frame_support::traits::Everything;
+	type BlockWeights = ();
+	type BlockLength = ();
+	type DbWeight = ();
+	type Origin = Origin;
+	type Index = u64;
+	type BlockNumber = u64;
+	type Hash = H256;
+	type Call = Call;
+	type Hashing = BlakeTwo256;
+	type AccountId = AccountId32;
+	type Lookup = IdentityLookup<Self::AccountId>;
+	type Header = generic::Header<u64, BlakeTwo256>;
+	type Event = Event;
+	type BlockHashCount = BlockHashCount;
+	type Version = ();
+	type PalletInfo = PalletInfo;
+	type AccountData = pallet_balances::AccountData<u64>;
+	type OnNewAccount = ();
+	type OnKilledAccount = ();
+	type SystemWeightInfo = ();
+	type SS58Prefix = ();
+	type OnSetCode = ();
+}
+
+parameter_types! {
+	pub const ExistentialDeposit: u64 = 1;
+}
+impl pallet_balances::Config for Test {
+	type MaxLocks = ();
+	type Balance = u64;
+	type DustRemoval = ();
+	type Event = Event;
+	type ExistentialDeposit = ExistentialDeposit;
+	type AccountStore = System;
+	type WeightInfo = ();
+	type MaxReserves = ();
+	type ReserveIdentifier = ();
+}
+
+parameter_types! {
+	pub const MinimumPeriod: u64 = 1000;
+}
+impl pallet_timestamp::Config for Test {
+	type Moment = u64;
+	type OnTimestampSet = ();
+	type MinimumPeriod = MinimumPeriod;
+	type WeightInfo = ();
+}
+
+pub struct FixedGasPrice;
+impl FeeCalculator for FixedGasPrice {
+	fn min_gas_price() -> U256 {
+		1.into()
+	}
+}
+
+parameter_types! {
+	pub const ChainId: u64 = 42;
+	pub BlockGasLimit: U256 = U256::from(u32::MAX);
+	pub static GasLimitPovSizeRatio: u64 = 0;
+	pub static MaxBlockProofSize: u64 = 0;
+}
+impl pallet_evm::Config for Test {
+	type FeeCalculator = FixedGasPrice;
+	type GasWeightMapping = ();
+	type CallOrigin = EnsureAddressRoot<Self::AccountId>;
+	type WithdrawOrigin = EnsureAddressNever<Self::AccountId>;
+	type AddressMapping = pallet_evm::HashedAddressMapping<BlakeTwo256>;
+	type Currency = Balances;
+	type Runner = pallet_evm::runner::stack::Runner<Self>;
+	type Event = Event;
+	type PrecompilesType = ();
+	type PrecompilesValue = ();
+	type ChainId = ChainId;
+	type BlockGasLimit = BlockGasLimit;
+	type GasLimitPovSizeRatio = GasLimitPovSizeRatio;
+	type MaxBlockProofSize = MaxBlockProofSize;
+	type MaxCodeSize = ();
+	type MaxTransactionGas = ();
+	type OnChargeTransaction = ();
+	type BlockHashMapping = pallet_ethereum::EthereumBlockHashMapping<Self>;
+	type FindAuthor = ();
+	type WeightInfo = ();
+}
+
+impl pallet_ethereum::Config for Test {
+	type Event = Event;
+	type StateRoot = pallet_ethereum::IntermediateStateRoot;
+	type WeightInfo = ();
+}
+
+/// Accepts the accounts of sibling parachain 2001 as the senders of XCM messages.
+pub struct EnsureSiblingAccount;
+impl EnsureOrigin<Origin> for EnsureSiblingAccount {
+	type Success = MultiLocation;
+
+	fn try_origin(o: Origin) -> Result<MultiLocation, Origin> {
+		match o.clone().into() {
+			Ok(frame_system::RawOrigin::Signed(account)) => {
+				Ok(sibling_account_location(account.into()))
+			},
+			_ => Err(o),
+		}
+	}
+
+	#[cfg(feature = "runtime-benchmarks")]
+	fn successful_origin() -> Origin {
+		xcm_origin(1)
+	}
+}
+
+impl Config for Test {
+	type XcmOrigin = EnsureSiblingAccount;
+}
+
+/// Location of an account on a sibling parachain.
+fn sibling_account(id: u8) -> MultiLocation {
+	sibling_account_location([id; 32])
+}
+
+fn sibling_account_location(id: [u8; 32]) -> MultiLocation {
+	MultiLocation::new(
+		1,
+		xcm::latest::Junctions::X2(
+			Junction::Parachain(2001),
+			Junction::AccountId32 { network: xcm::latest::NetworkId::Any, id },
+		),
+	)
+}
+
+fn account_id(address: H160) -> AccountId32 {
+	<Test as pallet_evm::Config>::AddressMapping::into_account_id(address)
+}
+
+/// Stores its caller at slot 0: `CALLER PUSH1 0 SSTORE STOP`.
+fn store_caller() -> H160 {
+	H160::repeat_byte(0x01)
+}
+
+fn new_test_ext() -> sp_io::TestExternalities {
+	let mut t = frame_system::GenesisConfig::default().build_storage::<Test>().unwrap();
+	let balances = vec![
+		(account_id(location_to_address(&sibling_account(1))), 10_000_000),
+		(account_id(location_to_address(&sibling_account(2))), 10_000_000),
+	];
+	pallet_balances::GenesisConfig::<Test> { balances }
+		.assimilate_storage(&mut t)
+		.unwrap();
+	let mut ext: sp_io::TestExternalities = t.into();
+	ext.execute_with(|| {
+		pallet_evm::Pallet::<Test>::create_account(
+			store_caller(),
+			vec![0x33, 0x60, 0x00, 0x55, 0x00],
+		);
+	});
+	ext
+}
+
+/// Origin of the XCM messages of `sibling_account(id)`.
+fn xcm_origin(id: u8) -> Origin {
+	Origin::signed(AccountId32::new([id; 32]))
+}
+
+fn call_store_caller() -> EthereumXcmTransaction {
+	EthereumXcmTransaction {
+		gas_limit: 100_000.into(),
+		max_fee_per_gas: 1.into(),
+		max_priority_fee_per_gas: 0.into(),
+		action: TransactionAction::Call(store_caller()),
+		value: 0.into(),
+		input: vec![],
+		access_list: vec![],
+	}
+}
+
+fn finalize_block() -> ethereum::BlockV2 {
+	Ethereum::on_finalize(1);
+	Ethereum::current_block().unwrap()
+}
+
+#[test]
+fn distinct_locations_have_distinct_addresses() {
+	assert_ne!(location_to_address(&sibling_account(1)), location_to_address(&sibling_account(2)));
+	assert_ne!(
+		location_to_address(&sibling_account(1)),
+		location_to_address(&MultiLocation::parent())
+	);
+}
+
+#[test]
+fn transact_runs_as_derived_address() {
+	new_test_ext().execute_with(|| {
+		let source = location_to_address(&sibling_account(1));
+
+		assert_ok!(EthereumXcm::transact(xcm_origin(1), call_store_caller()));
+
+		assert_eq!(
+			pallet_evm::Pallet::<Test>::account_storages(store_caller(), H256::zero()),
+			H256::from(source)
+		);
+		assert_eq!(pallet_evm::Pallet::<Test>::account_basic(&source).nonce, U256::one());
+	});
+}
+
+#[test]
+fn transact_is_included_in_ethereum_block() {
+	new_test_ext().execute_with(|| {
+		let source = location_to_address(&sibling_account(1));
+
+		assert_ok!(EthereumXcm::transact(xcm_origin(1), call_store_caller()));
+		assert_ok!(EthereumXcm::transact(xcm_origin(1), call_store_caller()));
+		let block = finalize_block();
+
+		let nonces: Vec<_> = block
+			.transactions
+			.iter()
+			.map(|transaction| match transaction {
+				TransactionV2::EIP1559(t) => t.nonce,
+				_ => panic!("expected an EIP-1559 transaction"),
+			})
+			.collect();
+		assert_eq!(nonces, vec![U256::zero(), U256::one()]);
+		assert_eq!(Ethereum::current_receipts().unwrap().len(), 2);
+		let statuses = Ethereum::current_transaction_statuses().unwrap();
+		assert_eq!(statuses[0].from, source);
+		assert_eq!(statuses[0].to, Some(store_caller()));
+		assert_eq!(statuses[0].transaction_hash, block.transactions[0].hash());
+	});
+}
+
+#[test]
+fn transactions_of_distinct_origins_have_distinct_hashes() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(EthereumXcm::transact(xcm_origin(1), call_store_caller()));
+		assert_ok!(EthereumXcm::transact(xcm_origin(2), call_store_caller()));
+		let block = finalize_block();
+
+		assert_ne!(block.transactions[0].hash(), block.transactions[1].hash());
+	});
+}
+
+#[test]
+fn transact_checks_fee_like_signed_transactions() {
+	new_test_ext().execute_with(|| {
+		let transaction =
+			EthereumXcmTransaction { max_fee_per_gas: 0.into(), ..call_store_caller() };
+
+		assert_noop!(
+			EthereumXcm::transact(xcm_origin(1), transaction),
+			Error::<Test>::InvalidTransaction
+		);
+
+		let transaction =
+			EthereumXcmTransaction { max_fee_per_gas: 1_000.into(), ..call_store_caller() };
+
+		assert_noop!(
+			EthereumXcm::transact(xcm_origin(1), transaction),
+			Error::<Test>::InvalidTransaction
+		);
+	});
+}
+
+#[test]
+fn transact_requires_xcm_origin() {
+	new_test_ext().execute_with(|| {
+		assert_noop!(
+			EthereumXcm::transact(Origin::root(), call_store_caller()),
+			DispatchError::BadOrigin
+		);
+	});
+}
+
+#[test]
+fn transact_fits_in_block_proof_size() {
+	new_test_ext().execute_with(|| {
+		GasLimitPovSizeRatio::set(1_000);
+		MaxBlockProofSize::set(1_000);
+		// The gas limit of 100_000 may reach a proof size of 100.
+		pallet_evm::BlockProofSize::<Test>::put(901);
+
+		assert_noop!(
+			EthereumXcm::transact(xcm_origin(1), call_store_caller()),
+			pallet_evm::Error::<Test>::BlockProofSizeExhausted
+		);
+	});
+}
@@ -0,0 +1,88 @@
+// SPDX-License-Identifier: Apache-2.0
+// This file is part of Frontier.
+//
+// Copyright (c) 2021 Parity Technologies (UK) Ltd.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#![cfg(feature = "runtime-benchmarks")]
+
+//! Benchmarking
+use crate::{Call, Config, Pallet, Pending, RawOrigin, Transaction};
+use ethereum::{LegacyTransaction, TransactionAction, TransactionSignature};
+use ethereum_types::{H160, H256, U256};
+use frame_benchmarking::benchmarks;
+use frame_support::traits::Currency;
+use frame_system::pallet_prelude::OriginFor;
+use pallet_evm::{AddressMapping, BalanceOf, FeeCalculator};
+use sp_runtime::traits::UniqueSaturatedFrom;
+use sp_std::prelude::*;
+
+benchmarks! {
+	where_clause {
+		where
+			T::Origin: From<RawOrigin>,
+			OriginFor<T>: Into<Result<RawOrigin, OriginFor<T>>>,
+	}
+
+	// A call to an account without code. Its weight is the overhead of a transaction, on top of
+	// the weight of the gas it executes.
+	transact {
+		let source = H160::repeat_byte(0x11);
+		<T as pallet_evm::Config>::Currency::make_free_balance_be(
+			&<T as pallet_evm::Config>::AddressMapping::into_account_id(source),
+			BalanceOf::<T>::unique_saturated_from(1_000_000_000_000_000_000_000u128),
+		);
+
+		// `transact` doesn't check signatures, which were checked by the origin before.
+		let transaction = Transaction::Legacy(LegacyTransaction {
+			nonce: U256::zero(),
+			gas_price: <T as pallet_evm::Config>::FeeCalculator::min_gas_price(),
+			gas_limit: U256::from(21_000),
+			action: TransactionAction::Call(H160::repeat_byte(0x22)),
+			value: U256::zero(),
+			input: Vec::new(),
+			signature: TransactionSignature::new(
+				27,
+				H256::from_low_u64_be(1),
+				H256::from_low_u64_be(1),
+			)
+			.expect("valid signature values; qed"),
+		});
+	}: _(RawOrigin::EthereumTransaction(source), transaction)
+	verify {
+		assert_eq!(Pending::<T>::get().len(), 1);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::mock::Test;
+	use frame_support::assert_ok;
+	use sp_io::TestExternalities;
+
+	pub fn new_test_ext() -> TestExternalities {
+		let t = frame_system::GenesisConfig::default()
+			.build_storage::<Test>()
+			.unwrap();
+		TestExternalities::new(t)
+	}
+
+	#[test]
+	fn test_transact() {
+		new_test_ext().execute_with(|| {
+			assert_ok!(test_benchmark_transact::<Test>());
+		});
+	}
+}
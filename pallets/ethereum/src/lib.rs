@@ -34,7 +34,7 @@ use frame_support::{
 	traits::{EnsureOrigin, Get},
 	weights::{Pays, PostDispatchInfo, Weight},
 };
-use frame_system::{pallet_prelude::OriginFor, WeightInfo};
+use frame_system::pallet_prelude::OriginFor;
 use pallet_evm::{BlockHashMapping, FeeCalculator, GasWeightMapping, Runner};
 use sha3::{Digest, Keccak256};
 use sp_runtime::{
@@ -53,10 +53,15 @@ pub use ethereum::{
 };
 pub use fp_rpc::TransactionStatus;
 
+pub use crate::weights::WeightInfo;
+
+#[cfg(any(test, feature = "runtime-benchmarks"))]
+pub mod benchmarks;
 #[cfg(all(feature = "std", test))]
 mod mock;
 #[cfg(all(feature = "std", test))]
 mod tests;
+pub mod weights;
 
 #[derive(PartialEq, Eq, Clone, Encode, Decode, RuntimeDebug, scale_info::TypeInfo)]
 pub enum RawOrigin {
@@ -176,6 +181,8 @@ pub mod pallet {
 		type Event: From<Event> + IsType<<Self as frame_system::Config>::Event>;
 		/// How Ethereum state root is calculated.
 		type StateRoot: Get<H256>;
+		/// Weight of a transaction beside the weight of its gas.
+		type WeightInfo: WeightInfo;
 	}
 
 	#[pallet::pallet]
@@ -209,7 +216,7 @@ pub mod pallet {
 
 		fn on_initialize(_: T::BlockNumber) -> Weight {
 			Pending::<T>::kill();
-			let mut weight = T::SystemWeightInfo::kill_storage(1);
+			let mut weight = <T::SystemWeightInfo as frame_system::WeightInfo>::kill_storage(1);
 
 			// If the digest contain an existing ethereum block(encoded as PreLog), If contains,
 			// execute the imported block firstly and disable transact dispatch function.
@@ -254,12 +261,16 @@ pub mod pallet {
 		/// Transact an Ethereum transaction.
 		#[pallet::weight(<T as pallet_evm::Config>::GasWeightMapping::gas_to_weight(
 			Pallet::<T>::transaction_data(transaction).gas_limit.unique_saturated_into()
-		))]
+		).saturating_add(<T as Config>::WeightInfo::transact()))]
 		pub fn transact(
 			origin: OriginFor<T>,
 			transaction: Transaction,
 		) -> DispatchResultWithPostInfo {
 			let source = ensure_ethereum_transaction(origin)?;
+			ensure!(
+				!pallet_evm::is_dispatching_from_evm(),
+				pallet_evm::Error::<T>::DispatchedFromEvm
+			);
 			// Disable transact functionality if PreLog exist.
 			assert!(
 				fp_consensus::find_pre_log(&frame_system::Pallet::<T>::digest()).is_err(),
@@ -478,10 +489,8 @@ impl<T: Config> Pallet<T> {
 
 		// We must ensure a transaction can pay the cost of its data bytes.
 		// If it can't it should not be included in a block.
-		let mut gasometer = evm::gasometer::Gasometer::new(
-			gas_limit.low_u64(),
-			<T as pallet_evm::Config>::config(),
-		);
+		let config = pallet_evm::Pallet::<T>::evm_config();
+		let mut gasometer = evm::gasometer::Gasometer::new(gas_limit.low_u64(), &config);
 		let transaction_cost = match transaction_data.action {
 			TransactionAction::Call(_) => evm::gasometer::call_transaction_cost(
 				&transaction_data.input,
@@ -494,7 +503,7 @@ impl<T: Config> Pallet<T> {
 		};
 		if gasometer.record_transaction(transaction_cost).is_err() {
 			return Err(InvalidTransaction::Custom(
-				TransactionValidationError::InvalidGasLimit as u8,
+				TransactionValidationError::GasLimitTooLow as u8,
 			)
 			.into());
 		}
@@ -508,13 +517,31 @@ impl<T: Config> Pallet<T> {
 			}
 		}
 
-		if gas_limit >= T::BlockGasLimit::get() {
+		if gas_limit > T::BlockGasLimit::get() {
 			return Err(InvalidTransaction::Custom(
 				TransactionValidationError::InvalidGasLimit as u8,
 			)
 			.into());
 		}
 
+		if !pallet_evm::Pallet::<T>::gas_limit_fits_in_transaction(gas_limit) {
+			return Err(InvalidTransaction::Custom(
+				TransactionValidationError::GasLimitTooHigh as u8,
+			)
+			.into());
+		}
+
+		if let TransactionAction::Create = transaction_data.action {
+			if let Some(max_initcode_size) = pallet_evm::Pallet::<T>::max_initcode_size(&config) {
+				if transaction_data.input.len() > max_initcode_size {
+					return Err(InvalidTransaction::Custom(
+						TransactionValidationError::InitcodeTooLarge as u8,
+					)
+					.into());
+				}
+			}
+		}
+
 		let base_fee = T::FeeCalculator::min_gas_price();
 		let mut priority = 0;
 
@@ -532,7 +559,10 @@ impl<T: Config> Pallet<T> {
 		};
 
 		if gas_price < base_fee {
-			return Err(InvalidTransaction::Payment.into());
+			return Err(InvalidTransaction::Custom(
+				TransactionValidationError::GasPriceTooLow as u8,
+			)
+			.into());
 		}
 
 		let mut fee = gas_price.saturating_mul(gas_limit);
@@ -586,7 +616,10 @@ impl<T: Config> Pallet<T> {
 		builder.build()
 	}
 
-	fn apply_validated_transaction(source: H160, transaction: Transaction) -> PostDispatchInfo {
+	/// Execute a transaction of `source` and add it to the pending Ethereum block.
+	///
+	/// The transaction must have been checked with `validate_transaction_in_block` first.
+	pub fn apply_validated_transaction(source: H160, transaction: Transaction) -> PostDispatchInfo {
 		let transaction_hash = transaction.hash();
 		let transaction_index = Pending::<T>::get().len() as u32;
 
@@ -654,9 +687,10 @@ impl<T: Config> Pallet<T> {
 		));
 
 		PostDispatchInfo {
-			actual_weight: Some(T::GasWeightMapping::gas_to_weight(
-				used_gas.unique_saturated_into(),
-			)),
+			actual_weight: Some(
+				T::GasWeightMapping::gas_to_weight(used_gas.unique_saturated_into())
+					.saturating_add(<T as Config>::WeightInfo::transact()),
+			),
 			pays_fee: Pays::No,
 		}
 	}
@@ -758,6 +792,7 @@ impl<T: Config> Pallet<T> {
 				}
 			}
 		};
+		let config = config.unwrap_or_else(pallet_evm::Pallet::<T>::evm_config);
 
 		match action {
 			ethereum::TransactionAction::Call(target) => {
@@ -771,7 +806,7 @@ impl<T: Config> Pallet<T> {
 					max_priority_fee_per_gas,
 					nonce,
 					access_list,
-					config.as_ref().unwrap_or(T::config()),
+					&config,
 				)
 				.map_err(Into::into)?;
 
@@ -787,7 +822,7 @@ impl<T: Config> Pallet<T> {
 					max_priority_fee_per_gas,
 					nonce,
 					access_list,
-					config.as_ref().unwrap_or(T::config()),
+					&config,
 				)
 				.map_err(Into::into)?;
 
@@ -808,6 +843,10 @@ impl<T: Config> Pallet<T> {
 		let transaction_nonce = transaction_data.nonce;
 		let (account_nonce, _) = Self::validate_transaction_common(origin, &transaction_data)?;
 
+		if !pallet_evm::Pallet::<T>::proof_size_fits_in_block(transaction_data.gas_limit) {
+			return Err(TransactionValidityError::Invalid(InvalidTransaction::ExhaustsResources));
+		}
+
 		// In the context of the block, a transaction with a nonce that is
 		// too high should be considered invalid and make the whole block invalid.
 		if transaction_nonce > account_nonce {
@@ -858,11 +897,38 @@ impl<T: Config> BlockHashMapping for EthereumBlockHashMapping<T> {
 	}
 }
 
+/// Codes carried by `InvalidTransaction::Custom` when an Ethereum transaction fails validation.
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
 #[repr(u8)]
-enum TransactionValidationError {
-	#[allow(dead_code)]
+pub enum TransactionValidationError {
 	UnknownError,
 	InvalidChainId,
 	InvalidSignature,
+	/// The gas limit is above the block gas limit.
 	InvalidGasLimit,
+	/// The gas limit does not cover the intrinsic cost of the transaction.
+	GasLimitTooLow,
+	/// The gas price (or max fee per gas) is below the current base fee.
+	GasPriceTooLow,
+	/// The gas limit is above the maximum gas limit of a transaction.
+	GasLimitTooHigh,
+	/// The init code of a contract creation is above the maximum initcode size.
+	InitcodeTooLarge,
+}
+
+impl TransactionValidationError {
+	/// Decode a code previously produced with `as u8`.
+	pub fn from_u8(code: u8) -> Option<Self> {
+		match code {
+			0 => Some(Self::UnknownError),
+			1 => Some(Self::InvalidChainId),
+			2 => Some(Self::InvalidSignature),
+			3 => Some(Self::InvalidGasLimit),
+			4 => Some(Self::GasLimitTooLow),
+			5 => Some(Self::GasPriceTooLow),
+			6 => Some(Self::GasLimitTooHigh),
+			7 => Some(Self::InitcodeTooLarge),
+			_ => None,
+		}
+	}
 }
@@ -135,6 +135,10 @@ parameter_types! {
 	pub const ChainId: u64 = 42;
 	pub const EVMModuleId: PalletId = PalletId(*b"py/evmpa");
 	pub const BlockGasLimit: U256 = U256::MAX;
+	pub static GasLimitPovSizeRatio: u64 = 0;
+	pub static MaxBlockProofSize: u64 = 0;
+	pub static MaxCodeSize: u32 = 0;
+	pub static MaxTransactionGas: u64 = 0;
 }
 
 pub struct HashedAddressMapping;
@@ -160,14 +164,20 @@ impl pallet_evm::Config for Test {
 	type Runner = pallet_evm::runner::stack::Runner<Self>;
 	type ChainId = ChainId;
 	type BlockGasLimit = BlockGasLimit;
+	type GasLimitPovSizeRatio = GasLimitPovSizeRatio;
+	type MaxBlockProofSize = MaxBlockProofSize;
+	type MaxCodeSize = MaxCodeSize;
+	type MaxTransactionGas = MaxTransactionGas;
 	type OnChargeTransaction = ();
 	type FindAuthor = FindAuthorTruncated;
+	type WeightInfo = ();
 	type BlockHashMapping = crate::EthereumBlockHashMapping<Self>;
 }
 
 impl crate::Config for Test {
 	type Event = Event;
 	type StateRoot = IntermediateStateRoot;
+	type WeightInfo = ();
 }
 
 impl fp_self_contained::SelfContainedCall for Call {
@@ -68,6 +68,95 @@ fn transaction_without_enough_gas_should_not_work() {
 	});
 }
 
+#[test]
+fn transaction_with_gas_price_below_base_fee_should_not_work() {
+	let (pairs, mut ext) = new_test_ext(1);
+	let alice = &pairs[0];
+
+	ext.execute_with(|| {
+		let mut transaction = legacy_erc20_creation_unsigned_transaction();
+		transaction.gas_price = U256::zero();
+
+		let call = crate::Call::<Test>::transact {
+			transaction: transaction.sign(&alice.private_key),
+		};
+		let source = call.check_self_contained().unwrap().unwrap();
+
+		assert_err!(
+			call.validate_self_contained(&source).unwrap(),
+			InvalidTransaction::Custom(crate::TransactionValidationError::GasPriceTooLow as u8)
+		);
+	});
+}
+
+#[test]
+fn transaction_with_gas_limit_below_intrinsic_cost_should_not_work() {
+	let (pairs, mut ext) = new_test_ext(1);
+	let alice = &pairs[0];
+
+	ext.execute_with(|| {
+		let mut transaction = legacy_erc20_creation_unsigned_transaction();
+		transaction.gas_limit = U256::from(21_000);
+
+		let call = crate::Call::<Test>::transact {
+			transaction: transaction.sign(&alice.private_key),
+		};
+		let source = call.check_self_contained().unwrap().unwrap();
+
+		assert_err!(
+			call.validate_self_contained(&source).unwrap(),
+			InvalidTransaction::Custom(crate::TransactionValidationError::GasLimitTooLow as u8)
+		);
+	});
+}
+
+#[test]
+fn transaction_with_gas_limit_above_transaction_gas_limit_should_not_work() {
+	let (pairs, mut ext) = new_test_ext(1);
+	let alice = &pairs[0];
+
+	ext.execute_with(|| {
+		MaxTransactionGas::set(0x100000 - 1);
+
+		let call = crate::Call::<Test>::transact {
+			transaction: legacy_erc20_creation_transaction(alice),
+		};
+		let source = call.check_self_contained().unwrap().unwrap();
+
+		assert_err!(
+			call.validate_self_contained(&source).unwrap(),
+			InvalidTransaction::Custom(crate::TransactionValidationError::GasLimitTooHigh as u8)
+		);
+	});
+}
+
+#[test]
+fn contract_creation_with_initcode_above_limit_should_not_work() {
+	let (pairs, mut ext) = new_test_ext(1);
+	let alice = &pairs[0];
+
+	ext.execute_with(|| {
+		// The initcode of the ERC-20 contract is above twice 256 bytes.
+		MaxCodeSize::set(256);
+		let fork = pallet_evm::ForkConfig {
+			hardfork: pallet_evm::Hardfork::London,
+			contract_size_limit: None,
+			limit_initcode_size: true,
+		};
+		pallet_evm::ForkSchedule::<Test>::put(vec![(0, fork)]);
+
+		let call = crate::Call::<Test>::transact {
+			transaction: legacy_erc20_creation_transaction(alice),
+		};
+		let source = call.check_self_contained().unwrap().unwrap();
+
+		assert_err!(
+			call.validate_self_contained(&source).unwrap(),
+			InvalidTransaction::Custom(crate::TransactionValidationError::InitcodeTooLarge as u8)
+		);
+	});
+}
+
 #[test]
 fn transaction_with_to_low_nonce_should_not_work() {
 	let (pairs, mut ext) = new_test_ext(1);
@@ -113,6 +202,32 @@ fn transaction_with_to_low_nonce_should_not_work() {
 	});
 }
 
+#[test]
+fn transaction_should_not_be_replayable_after_reaping_the_sender() {
+	use frame_support::traits::Currency;
+
+	let (pairs, mut ext) = new_test_ext(1);
+	let alice = &pairs[0];
+
+	ext.execute_with(|| {
+		let t = legacy_erc20_creation_transaction(alice);
+		assert_ok!(Ethereum::execute(alice.address, &t, None,));
+
+		// Reap the balance of the sender, then fund it again.
+		Balances::make_free_balance_be(&alice.account_id, 0);
+		assert_eq!(Balances::total_balance(&alice.account_id), 0);
+		assert_eq!(EVM::account_basic(&alice.address).nonce, U256::from(1));
+		Balances::make_free_balance_be(&alice.account_id, 10_000_000);
+
+		let call = crate::Call::<Test>::transact { transaction: t };
+		let source = call.check_self_contained().unwrap().unwrap();
+		assert_err!(
+			call.validate_self_contained(&source).unwrap(),
+			InvalidTransaction::Stale
+		);
+	});
+}
+
 #[test]
 fn transaction_with_to_hight_nonce_should_fail_in_block() {
 	let (pairs, mut ext) = new_test_ext(1);
@@ -166,6 +281,27 @@ fn transaction_with_invalid_chain_id_should_fail_in_block() {
 	});
 }
 
+#[test]
+fn transaction_exceeding_block_proof_size_should_fail_in_block() {
+	let (pairs, mut ext) = new_test_ext(1);
+	let alice = &pairs[0];
+
+	ext.execute_with(|| {
+		// The gas limit of 0x100000 pays for up to 0x10000 bytes of proof.
+		GasLimitPovSizeRatio::set(0x10);
+		MaxBlockProofSize::set(0x10000);
+		let transaction = legacy_erc20_creation_transaction(alice);
+
+		assert_ok!(Ethereum::validate_transaction_in_block(alice.address, &transaction));
+
+		pallet_evm::BlockProofSize::<Test>::put(1);
+		assert_err!(
+			Ethereum::validate_transaction_in_block(alice.address, &transaction),
+			TransactionValidityError::Invalid(InvalidTransaction::ExhaustsResources)
+		);
+	});
+}
+
 #[test]
 fn contract_constructor_should_get_executed() {
 	let (pairs, mut ext) = new_test_ext(1);
@@ -0,0 +1,242 @@
+// SPDX-License-Identifier: Apache-2.0
+// This file is part of Frontier.
+//
+// Copyright (c) 2020 Parity Technologies (UK) Ltd.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! ERC-20 interfaces to the assets of `pallet_assets`, one precompile per asset.
+//!
+//! The precompile of an asset is at the address made of [`ASSET_PRECOMPILE_ADDRESS_PREFIX`]
+//! followed by the asset id as a big-endian `u128`, and exists as long as the asset is registered
+//! in `pallet_asset_registry`. The name, symbol and decimals are the metadata of the asset.
+//! Allowances are kept in the storage of the precompile set, as for the ERC-20 of the native
+//! token, whose ERC-20 functions are shared through [`Erc20Backend`].
+//!
+//! The precompiles act on behalf of `context.caller`, so they refuse to be called with
+//! `DELEGATECALL` or `CALLCODE`.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(test)]
+mod tests;
+
+use fp_evm::{Context, PrecompileResult, PrecompileSet};
+use frame_support::{
+	storage::types::{Key, StorageNMap, ValueQuery},
+	traits::{
+		tokens::fungibles::{Inspect, InspectMetadata, Transfer},
+		StorageInstance,
+	},
+	weights::Weight,
+	Blake2_128Concat,
+};
+use pallet_assets::WeightInfo;
+use pallet_evm_precompile_balances_erc20::{Erc20, Erc20Backend};
+use precompile_utils::revert;
+use sp_core::{H160, U256};
+use sp_runtime::{
+	traits::{Convert, Zero},
+	DispatchError,
+};
+use sp_std::{convert::TryFrom, marker::PhantomData, vec::Vec};
+
+/// First bytes of the address of the asset precompiles.
+pub const ASSET_PRECOMPILE_ADDRESS_PREFIX: [u8; 4] = [0xff; 4];
+
+type Assets<Runtime> = pallet_assets::Pallet<Runtime>;
+type AccountIdOf<Runtime> = <Runtime as frame_system::Config>::AccountId;
+type AssetIdOf<Runtime> = <Runtime as pallet_assets::Config>::AssetId;
+type BalanceOf<Runtime> = <Runtime as pallet_assets::Config>::Balance;
+
+/// Address of the precompile of an asset.
+pub fn asset_id_to_address(asset_id: u128) -> H160 {
+	let mut address = [0u8; 20];
+	address[..4].copy_from_slice(&ASSET_PRECOMPILE_ADDRESS_PREFIX);
+	address[4..].copy_from_slice(&asset_id.to_be_bytes());
+	H160(address)
+}
+
+/// Asset id of the precompile at `address`, if the address has the asset precompile prefix.
+pub fn address_to_asset_id(address: H160) -> Option<u128> {
+	if address[..4] != ASSET_PRECOMPILE_ADDRESS_PREFIX {
+		return None;
+	}
+	let mut asset_id = [0u8; 16];
+	asset_id.copy_from_slice(&address[4..]);
+	Some(u128::from_be_bytes(asset_id))
+}
+
+/// Converts asset ids to the address of their precompile.
+pub struct AssetIdToAddress<AssetId>(PhantomData<AssetId>);
+
+impl<AssetId: Into<u128>> Convert<AssetId, H160> for AssetIdToAddress<AssetId> {
+	fn convert(asset_id: AssetId) -> H160 {
+		asset_id_to_address(asset_id.into())
+	}
+}
+
+/// Storage prefix of the allowances.
+pub struct ApprovesPrefix;
+
+impl StorageInstance for ApprovesPrefix {
+	const STORAGE_PREFIX: &'static str = "Approves";
+
+	fn pallet_prefix() -> &'static str {
+		"Erc20AssetsPrecompileSet"
+	}
+}
+
+/// Allowances, by asset, owner then spender.
+pub type ApprovesStorage<Runtime> = StorageNMap<
+	ApprovesPrefix,
+	(
+		Key<Blake2_128Concat, AssetIdOf<Runtime>>,
+		Key<Blake2_128Concat, AccountIdOf<Runtime>>,
+		Key<Blake2_128Concat, AccountIdOf<Runtime>>,
+	),
+	BalanceOf<Runtime>,
+	ValueQuery,
+>;
+
+/// Metadata, balances and allowances of an asset of `Runtime`.
+pub struct AssetBalances<Runtime: pallet_assets::Config>(AssetIdOf<Runtime>);
+
+impl<Runtime> Erc20Backend<Runtime> for AssetBalances<Runtime>
+where
+	Runtime: pallet_assets::Config,
+	BalanceOf<Runtime>: TryFrom<U256> + Into<U256>,
+{
+	type Balance = BalanceOf<Runtime>;
+
+	const METADATA_READS: u64 = 1;
+
+	fn name(&self) -> Vec<u8> {
+		Assets::<Runtime>::name(&self.0)
+	}
+
+	fn symbol(&self) -> Vec<u8> {
+		Assets::<Runtime>::symbol(&self.0)
+	}
+
+	fn decimals(&self) -> u8 {
+		Assets::<Runtime>::decimals(&self.0)
+	}
+
+	fn total_supply(&self) -> Self::Balance {
+		Assets::<Runtime>::total_issuance(self.0)
+	}
+
+	fn balance_of(&self, owner: &AccountIdOf<Runtime>) -> Self::Balance {
+		Assets::<Runtime>::balance(self.0, owner)
+	}
+
+	fn allowance(
+		&self,
+		owner: &AccountIdOf<Runtime>,
+		spender: &AccountIdOf<Runtime>,
+	) -> Self::Balance {
+		ApprovesStorage::<Runtime>::get((self.0, owner, spender))
+	}
+
+	fn set_allowance(
+		&self,
+		owner: &AccountIdOf<Runtime>,
+		spender: &AccountIdOf<Runtime>,
+		amount: Self::Balance,
+	) {
+		ApprovesStorage::<Runtime>::insert((self.0, owner, spender), amount);
+	}
+
+	fn transfer(
+		&self,
+		from: &AccountIdOf<Runtime>,
+		to: &AccountIdOf<Runtime>,
+		amount: Self::Balance,
+	) -> Result<(), DispatchError> {
+		<Assets<Runtime> as Transfer<_>>::transfer(self.0, from, to, amount, false).map(|_| ())
+	}
+
+	fn transfer_weight(&self) -> Weight {
+		<Runtime as pallet_assets::Config>::WeightInfo::transfer()
+	}
+}
+
+/// ERC-20 precompiles of the registered assets of `Runtime`.
+pub struct Erc20AssetsPrecompileSet<Runtime>(PhantomData<Runtime>);
+
+impl<Runtime> Erc20AssetsPrecompileSet<Runtime> {
+	pub fn new() -> Self {
+		Self(PhantomData)
+	}
+}
+
+impl<Runtime> Default for Erc20AssetsPrecompileSet<Runtime> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl<Runtime> PrecompileSet for Erc20AssetsPrecompileSet<Runtime>
+where
+	Runtime: pallet_assets::Config
+		+ pallet_asset_registry::Config<AssetId = AssetIdOf<Runtime>>
+		+ pallet_evm::Config,
+	AssetIdOf<Runtime>: TryFrom<u128>,
+	BalanceOf<Runtime>: TryFrom<U256> + Into<U256>,
+{
+	fn execute(
+		&self,
+		address: H160,
+		input: &[u8],
+		target_gas: Option<u64>,
+		context: &Context,
+		is_static: bool,
+	) -> Option<PrecompileResult> {
+		let asset_id = Self::asset_at(address)?;
+		if context.address != address {
+			return Some(Err(revert("cannot be called with DELEGATECALL or CALLCODE")));
+		}
+		Some(Erc20::<Runtime, AssetBalances<Runtime>>::execute(
+			&AssetBalances(asset_id),
+			input,
+			target_gas,
+			context,
+			is_static,
+		))
+	}
+
+	fn is_precompile(&self, address: H160) -> bool {
+		Self::asset_at(address).is_some()
+	}
+}
+
+impl<Runtime> Erc20AssetsPrecompileSet<Runtime>
+where
+	Runtime: pallet_assets::Config
+		+ pallet_asset_registry::Config<AssetId = AssetIdOf<Runtime>>
+		+ pallet_evm::Config,
+	AssetIdOf<Runtime>: TryFrom<u128>,
+{
+	/// Registered asset whose precompile is at `address`. The asset must also still exist: assets
+	/// always have a non-zero minimum balance.
+	fn asset_at(address: H160) -> Option<AssetIdOf<Runtime>> {
+		let asset_id = AssetIdOf::<Runtime>::try_from(address_to_asset_id(address)?).ok()?;
+		if !pallet_asset_registry::AssetLocations::<Runtime>::contains_key(&asset_id) ||
+			Assets::<Runtime>::minimum_balance(asset_id).is_zero()
+		{
+			return None;
+		}
+		Some(asset_id)
+	}
+}
@@ -0,0 +1,468 @@
+// SPDX-License-Identifier: Apache-2.0
+// This file is part of Frontier.
+//
+// Copyright (c) 2020 Parity Technologies (UK) Ltd.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::*;
+
+use frame_support::{assert_ok, parameter_types, weights::RuntimeDbWeight};
+use frame_system::EnsureRoot;
+use pallet_evm::{EnsureAddressNever, EnsureAddressRoot, IdentityAddressMapping};
+use pallet_evm_precompile_balances_erc20::{testing::*, APPROVAL_EVENT};
+use precompile_utils::{keccak256, testing::*, Address, Bytes, EvmDataWriter, LogsBuilder};
+use sp_core::H256;
+use sp_runtime::{
+	generic,
+	traits::{BlakeTwo256, IdentityLookup},
+};
+use xcm::latest::MultiLocation;
+
+type UncheckedExtrinsic = frame_system::mocking::MockUncheckedExtrinsic<Test>;
+type Block = frame_system::mocking::MockBlock<Test>;
+
+frame_support::construct_runtime! {
+	pub enum Test where
+		Block = Block,
+		NodeBlock = Block,
+		UncheckedExtrinsic = UncheckedExtrinsic,
+	{
+		System: frame_system::{Pallet, Call, Config, Storage, Event<T>},
+		Balances: pallet_balances::{Pallet, Call, Storage, Config<T>, Event<T>},
+		Timestamp: pallet_timestamp::{Pallet, Call, Storage},
+		EVM: pallet_evm::{Pallet, Call, Storage, Config, Event<T>},
+		Assets: pallet_assets::{Pallet, Call, Storage, Event<T>},
+		AssetRegistry: pallet_asset_registry::{Pallet, Call, Storage, Event<T>},
+	}
+}
+
+parameter_types! {
+	pub const BlockHashCount: u64 = 250;
+	pub const DbWeight: RuntimeDbWeight = RuntimeDbWeight { read: 100, write: 1_000 };
+}
+impl frame_system::Config for Test {
+	type BaseCallFilter = frame_support::traits::Everything;
+	type BlockWeights = ();
+	type BlockLength = ();
+	type DbWeight = DbWeight;
+	type Origin = Origin;
+	type Index = u64;
+	type BlockNumber = u64;
+	type Hash = H256;
+	type Call = Call;
+	type Hashing = BlakeTwo256;
+	type AccountId = H160;
+	type Lookup = IdentityLookup<Self::AccountId>;
+	type Header = generic::Header<u64, BlakeTwo256>;
+	type Event = Event;
+	type BlockHashCount = BlockHashCount;
+	type Version = ();
+	type PalletInfo = PalletInfo;
+	type AccountData = pallet_balances::AccountData<u64>;
+	type OnNewAccount = ();
+	type OnKilledAccount = ();
+	type SystemWeightInfo = ();
+	type SS58Prefix = ();
+	type OnSetCode = ();
+}
+
+parameter_types! {
+	pub const ExistentialDeposit: u64 = 0;
+}
+impl pallet_balances::Config for Test {
+	type MaxLocks = ();
+	type Balance = u64;
+	type DustRemoval = ();
+	type Event = Event;
+	type ExistentialDeposit = ExistentialDeposit;
+	type AccountStore = System;
+	type WeightInfo = ();
+	type MaxReserves = ();
+	type ReserveIdentifier = ();
+}
+
+parameter_types! {
+	pub const MinimumPeriod: u64 = 1000;
+}
+impl pallet_timestamp::Config for Test {
+	type Moment = u64;
+	type OnTimestampSet = ();
+	type MinimumPeriod = MinimumPeriod;
+	type WeightInfo = ();
+}
+
+impl pallet_evm::Config for Test {
+	type FeeCalculator = ();
+	type GasWeightMapping = ();
+	type CallOrigin = EnsureAddressRoot<Self::AccountId>;
+	type WithdrawOrigin = EnsureAddressNever<Self::AccountId>;
+	type AddressMapping = IdentityAddressMapping;
+	type Currency = Balances;
+	type Runner = pallet_evm::runner::stack::Runner<Self>;
+	type Event = Event;
+	type PrecompilesType = ();
+	type PrecompilesValue = ();
+	type ChainId = ();
+	type BlockGasLimit = ();
+	type GasLimitPovSizeRatio = ();
+	type MaxBlockProofSize = ();
+	type MaxCodeSize = ();
+	type MaxTransactionGas = ();
+	type OnChargeTransaction = ();
+	type BlockHashMapping = pallet_evm::SubstrateBlockHashMapping<Self>;
+	type FindAuthor = ();
+	type WeightInfo = ();
+}
+
+parameter_types! {
+	pub const AssetDeposit: u64 = 0;
+	pub const ApprovalDeposit: u64 = 0;
+	pub const StringLimit: u32 = 32;
+	pub const MetadataDepositBase: u64 = 0;
+	pub const MetadataDepositPerByte: u64 = 0;
+}
+impl pallet_assets::Config for Test {
+	type Event = Event;
+	type Balance = u64;
+	type AssetId = u128;
+	type Currency = Balances;
+	type ForceOrigin = EnsureRoot<H160>;
+	type AssetDeposit = AssetDeposit;
+	type MetadataDepositBase = MetadataDepositBase;
+	type MetadataDepositPerByte = MetadataDepositPerByte;
+	type ApprovalDeposit = ApprovalDeposit;
+	type StringLimit = StringLimit;
+	type Freezer = ();
+	type Extra = ();
+	type WeightInfo = ();
+}
+
+impl pallet_asset_registry::Config for Test {
+	type Event = Event;
+	type AssetId = u128;
+	type Assets = Assets;
+	type AssetAddress = AssetIdToAddress<u128>;
+	type RegisterOrigin = EnsureRoot<H160>;
+}
+
+type Precompiles = Erc20AssetsPrecompileSet<Test>;
+
+const USDT: u128 = 1984;
+/// Existing asset that is not registered.
+const UNREGISTERED: u128 = 2;
+
+fn usdt() -> H160 {
+	asset_id_to_address(USDT)
+}
+
+fn alice() -> H160 {
+	H160::repeat_byte(0xaa)
+}
+
+fn bob() -> H160 {
+	H160::repeat_byte(0xbb)
+}
+
+fn charlie() -> H160 {
+	H160::repeat_byte(0xcc)
+}
+
+fn new_test_ext() -> sp_io::TestExternalities {
+	let t = frame_system::GenesisConfig::default().build_storage::<Test>().unwrap();
+	let mut ext = sp_io::TestExternalities::new(t);
+	ext.execute_with(|| {
+		assert_ok!(Assets::force_create(Origin::root(), USDT, alice(), true, 1));
+		assert_ok!(Assets::force_set_metadata(
+			Origin::root(),
+			USDT,
+			b"Tether USD".to_vec(),
+			b"USDT".to_vec(),
+			6,
+			false
+		));
+		assert_ok!(Assets::mint(Origin::signed(alice()), USDT, alice(), 1_000));
+		assert_ok!(Assets::mint(Origin::signed(alice()), USDT, bob(), 500));
+		assert_ok!(AssetRegistry::register_asset(
+			Origin::root(),
+			USDT,
+			Box::new(MultiLocation::parent().into())
+		));
+		assert_ok!(Assets::force_create(Origin::root(), UNREGISTERED, alice(), true, 1));
+	});
+	ext
+}
+
+#[test]
+fn asset_addresses_have_prefix_and_id() {
+	let address = asset_id_to_address(USDT);
+	assert_eq!(
+		address,
+		H160::from_slice(&[
+			0xff, 0xff, 0xff, 0xff, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x07, 0xc0
+		])
+	);
+	assert_eq!(address_to_asset_id(address), Some(USDT));
+	assert_eq!(address_to_asset_id(asset_id_to_address(u128::MAX)), Some(u128::MAX));
+	assert_eq!(address_to_asset_id(H160::from_low_u64_be(USDT as u64)), None);
+	assert_eq!(AssetIdToAddress::<u32>::convert(1984), address);
+}
+
+#[test]
+fn only_registered_assets_are_precompiles() {
+	new_test_ext().execute_with(|| {
+		assert!(Precompiles::new().is_precompile(usdt()));
+		assert!(!Precompiles::new().is_precompile(asset_id_to_address(1)));
+		assert!(!Precompiles::new().is_precompile(asset_id_to_address(UNREGISTERED)));
+		assert!(!Precompiles::new().is_precompile(H160::from_low_u64_be(2050)));
+
+		Precompiles::new()
+			.prepare_test(alice(), asset_id_to_address(1), call("totalSupply()").build())
+			.execute_none();
+		Precompiles::new()
+			.prepare_test(alice(), asset_id_to_address(UNREGISTERED), call("totalSupply()").build())
+			.execute_none();
+
+		assert_ok!(AssetRegistry::deregister_asset(Origin::root(), USDT));
+		assert!(!Precompiles::new().is_precompile(usdt()));
+	});
+}
+
+#[test]
+fn metadata_is_the_asset_metadata() {
+	new_test_ext().execute_with(|| {
+		Precompiles::new()
+			.prepare_test(alice(), usdt(), call("name()").build())
+			.expect_cost(read_cost::<Test>())
+			.expect_no_logs()
+			.execute_returns(EvmDataWriter::new().write(Bytes::from("Tether USD")).build());
+		Precompiles::new()
+			.prepare_test(alice(), usdt(), call("symbol()").build())
+			.execute_returns(EvmDataWriter::new().write(Bytes::from("USDT")).build());
+		Precompiles::new()
+			.prepare_test(alice(), usdt(), call("decimals()").build())
+			.execute_returns(EvmDataWriter::new().write(6u8).build());
+	});
+}
+
+#[test]
+fn total_supply_and_balance_of() {
+	new_test_ext().execute_with(|| {
+		Precompiles::new()
+			.prepare_test(alice(), usdt(), call("totalSupply()").build())
+			.expect_cost(read_cost::<Test>())
+			.expect_no_logs()
+			.execute_returns(uint(1_500));
+		Precompiles::new()
+			.prepare_test(
+				charlie(),
+				usdt(),
+				call("balanceOf(address)").write(Address(bob())).build(),
+			)
+			.expect_cost(read_cost::<Test>())
+			.execute_returns(uint(500));
+		Precompiles::new()
+			.prepare_test(
+				alice(),
+				usdt(),
+				call("balanceOf(address)").write(Address(charlie())).build(),
+			)
+			.execute_returns(uint(0));
+	});
+}
+
+#[test]
+fn transfer_moves_asset() {
+	new_test_ext().execute_with(|| {
+		Precompiles::new()
+			.prepare_test(
+				alice(),
+				usdt(),
+				call("transfer(address,uint256)")
+					.write(Address(charlie()))
+					.write(U256::from(400))
+					.build(),
+			)
+			.expect_cost(transfer_cost::<Test>(<() as WeightInfo>::transfer()))
+			.expect_log(transfer_log(usdt(), alice(), charlie(), 400))
+			.execute_returns(EvmDataWriter::new().write(true).build());
+
+		assert_eq!(Assets::balance(USDT, alice()), 600);
+		assert_eq!(Assets::balance(USDT, charlie()), 400);
+		assert_eq!(Balances::free_balance(charlie()), 0);
+	});
+}
+
+#[test]
+fn transfer_more_than_balance_reverts() {
+	new_test_ext().execute_with(|| {
+		Precompiles::new()
+			.prepare_test(
+				bob(),
+				usdt(),
+				call("transfer(address,uint256)")
+					.write(Address(charlie()))
+					.write(U256::from(501))
+					.build(),
+			)
+			.execute_reverts(|message| message == b"Funds are unavailable");
+		Precompiles::new()
+			.prepare_test(
+				bob(),
+				usdt(),
+				call("transfer(address,uint256)")
+					.write(Address(charlie()))
+					.write(U256::from(u64::MAX) + 1)
+					.build(),
+			)
+			.execute_reverts(|message| message == b"value too large for balance type");
+
+		assert_eq!(Assets::balance(USDT, bob()), 500);
+	});
+}
+
+#[test]
+fn approve_and_transfer_from() {
+	new_test_ext().execute_with(|| {
+		Precompiles::new()
+			.prepare_test(
+				alice(),
+				usdt(),
+				call("approve(address,uint256)")
+					.write(Address(bob()))
+					.write(U256::from(300))
+					.build(),
+			)
+			.expect_cost(write_cost::<Test>() + 375 * 4 + 32 * 8)
+			.expect_log(LogsBuilder::new(usdt()).log3(
+				keccak256(APPROVAL_EVENT),
+				alice(),
+				bob(),
+				uint(300),
+			))
+			.execute_returns(EvmDataWriter::new().write(true).build());
+		Precompiles::new()
+			.prepare_test(
+				charlie(),
+				usdt(),
+				call("allowance(address,address)")
+					.write(Address(alice()))
+					.write(Address(bob()))
+					.build(),
+			)
+			.expect_cost(read_cost::<Test>())
+			.execute_returns(uint(300));
+
+		Precompiles::new()
+			.prepare_test(
+				bob(),
+				usdt(),
+				call("transferFrom(address,address,uint256)")
+					.write(Address(alice()))
+					.write(Address(charlie()))
+					.write(U256::from(200))
+					.build(),
+			)
+			.expect_cost(
+				transfer_cost::<Test>(<() as WeightInfo>::transfer()) +
+					read_cost::<Test>() +
+					write_cost::<Test>(),
+			)
+			.expect_log(transfer_log(usdt(), alice(), charlie(), 200))
+			.execute_returns(EvmDataWriter::new().write(true).build());
+
+		assert_eq!(ApprovesStorage::<Test>::get((USDT, alice(), bob())), 100);
+		assert_eq!(Assets::balance(USDT, alice()), 800);
+		assert_eq!(Assets::balance(USDT, charlie()), 200);
+
+		Precompiles::new()
+			.prepare_test(
+				bob(),
+				usdt(),
+				call("transferFrom(address,address,uint256)")
+					.write(Address(alice()))
+					.write(Address(charlie()))
+					.write(U256::from(101))
+					.build(),
+			)
+			.execute_reverts(|message| message == b"trying to spend more than allowed");
+	});
+}
+
+#[test]
+fn allowances_are_per_asset() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(Assets::force_create(Origin::root(), 1, alice(), true, 1));
+		assert_ok!(Assets::mint(Origin::signed(alice()), 1, alice(), 1_000));
+		ApprovesStorage::<Test>::insert((USDT, alice(), bob()), 300);
+
+		Precompiles::new()
+			.prepare_test(
+				bob(),
+				asset_id_to_address(1),
+				call("transferFrom(address,address,uint256)")
+					.write(Address(alice()))
+					.write(Address(charlie()))
+					.write(U256::from(100))
+					.build(),
+			)
+			.execute_reverts(|message| message == b"trying to spend more than allowed");
+
+		assert_eq!(Assets::balance(1, alice()), 1_000);
+	});
+}
+
+#[test]
+fn delegatecall_is_rejected() {
+	new_test_ext().execute_with(|| {
+		let context = Context { address: bob(), caller: alice(), apparent_value: U256::zero() };
+		let input = call("transfer(address,uint256)")
+			.write(Address(charlie()))
+			.write(U256::from(100))
+			.build();
+
+		match Precompiles::new().execute(usdt(), &input, None, &context, false) {
+			Some(Err(fp_evm::PrecompileFailure::Revert { output, .. })) => assert_eq!(
+				decode_revert_message(&output),
+				b"cannot be called with DELEGATECALL or CALLCODE".to_vec()
+			),
+			_ => panic!("delegatecall was not reverted"),
+		}
+		assert_eq!(Assets::balance(USDT, alice()), 1_000);
+	});
+}
+
+#[test]
+fn state_mutability_is_enforced() {
+	new_test_ext().execute_with(|| {
+		Precompiles::new()
+			.prepare_test(
+				alice(),
+				usdt(),
+				call("transfer(address,uint256)")
+					.write(Address(bob()))
+					.write(U256::from(1))
+					.build(),
+			)
+			.with_static_call(true)
+			.execute_reverts(|message| {
+				message == b"can't call non-static function in static context"
+			});
+		Precompiles::new()
+			.prepare_test(alice(), usdt(), call("totalSupply()").build())
+			.with_value(1)
+			.execute_reverts(|message| message == b"function is not payable");
+
+		assert_eq!(Assets::balance(USDT, alice()), 1_000);
+	});
+}
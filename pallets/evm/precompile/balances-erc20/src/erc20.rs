@@ -0,0 +1,367 @@
+// SPDX-License-Identifier: Apache-2.0
+// This file is part of Frontier.
+//
+// Copyright (c) 2020 Parity Technologies (UK) Ltd.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! ERC-20 functions, acting on the token of an [`Erc20Backend`].
+
+use fp_evm::{Context, Log, PrecompileOutput, PrecompileResult};
+use frame_support::weights::Weight;
+use pallet_evm::{AddressMapping, GasWeightMapping};
+use precompile_utils::{
+	keccak256, succeed, Address, Bytes, EvmDataReader, EvmDataWriter, EvmResult, FunctionModifier,
+	Gasometer, LogsBuilder, RuntimeHelper,
+};
+use sp_core::{H256, U256};
+use sp_runtime::{
+	traits::{Bounded, CheckedSub},
+	DispatchError,
+};
+use sp_std::{
+	convert::{TryFrom, TryInto},
+	marker::PhantomData,
+	vec,
+	vec::Vec,
+};
+
+/// Signature of the `Transfer` event.
+pub const TRANSFER_EVENT: &str = "Transfer(address,address,uint256)";
+/// Signature of the `Approval` event.
+pub const APPROVAL_EVENT: &str = "Approval(address,address,uint256)";
+
+type AccountIdOf<Runtime> = <Runtime as frame_system::Config>::AccountId;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+	Name,
+	Symbol,
+	Decimals,
+	TotalSupply,
+	BalanceOf,
+	Allowance,
+	Transfer,
+	Approve,
+	TransferFrom,
+}
+
+impl TryFrom<u32> for Action {
+	type Error = ();
+
+	fn try_from(selector: u32) -> Result<Self, Self::Error> {
+		match selector {
+			0x06fdde03 => Ok(Action::Name),
+			0x95d89b41 => Ok(Action::Symbol),
+			0x313ce567 => Ok(Action::Decimals),
+			0x18160ddd => Ok(Action::TotalSupply),
+			0x70a08231 => Ok(Action::BalanceOf),
+			0xdd62ed3e => Ok(Action::Allowance),
+			0xa9059cbb => Ok(Action::Transfer),
+			0x095ea7b3 => Ok(Action::Approve),
+			0x23b872dd => Ok(Action::TransferFrom),
+			_ => Err(()),
+		}
+	}
+}
+
+/// Token of an ERC-20 precompile: its metadata, balances and allowances.
+pub trait Erc20Backend<Runtime: frame_system::Config> {
+	type Balance: TryFrom<U256> + Into<U256> + CheckedSub + Bounded;
+
+	/// Storage reads needed to get the name, symbol or decimals.
+	const METADATA_READS: u64;
+
+	fn name(&self) -> Vec<u8>;
+	fn symbol(&self) -> Vec<u8>;
+	fn decimals(&self) -> u8;
+	fn total_supply(&self) -> Self::Balance;
+	fn balance_of(&self, owner: &AccountIdOf<Runtime>) -> Self::Balance;
+	fn allowance(
+		&self,
+		owner: &AccountIdOf<Runtime>,
+		spender: &AccountIdOf<Runtime>,
+	) -> Self::Balance;
+	fn set_allowance(
+		&self,
+		owner: &AccountIdOf<Runtime>,
+		spender: &AccountIdOf<Runtime>,
+		amount: Self::Balance,
+	);
+	fn transfer(
+		&self,
+		from: &AccountIdOf<Runtime>,
+		to: &AccountIdOf<Runtime>,
+		amount: Self::Balance,
+	) -> Result<(), DispatchError>;
+	/// Weight of [`Erc20Backend::transfer`].
+	fn transfer_weight(&self) -> Weight;
+}
+
+/// ERC-20 functions of the token of `Backend`, for the accounts mapped from EVM addresses with
+/// `pallet_evm::Config::AddressMapping`.
+///
+/// They act on behalf of `context.caller`, so the precompile using them must reject calls where
+/// `context.address` is not the precompile address (`DELEGATECALL` and `CALLCODE`).
+pub struct Erc20<Runtime, Backend>(PhantomData<(Runtime, Backend)>);
+
+impl<Runtime, Backend> Erc20<Runtime, Backend>
+where
+	Runtime: pallet_evm::Config,
+	Backend: Erc20Backend<Runtime>,
+{
+	pub fn execute(
+		backend: &Backend,
+		input: &[u8],
+		target_gas: Option<u64>,
+		context: &Context,
+		is_static: bool,
+	) -> PrecompileResult {
+		let mut gasometer = Gasometer::new(target_gas);
+		let action = EvmDataReader::read_selector::<Action>(input)?;
+		let input = EvmDataReader::new_skip_selector(input)?;
+
+		let modifier = match action {
+			Action::Transfer | Action::Approve | Action::TransferFrom => {
+				FunctionModifier::NonPayable
+			},
+			_ => FunctionModifier::View,
+		};
+		gasometer.check_function_modifier(context, is_static, modifier)?;
+
+		match action {
+			Action::Name => Self::name(backend, input, &mut gasometer),
+			Action::Symbol => Self::symbol(backend, input, &mut gasometer),
+			Action::Decimals => Self::decimals(backend, input, &mut gasometer),
+			Action::TotalSupply => Self::total_supply(backend, input, &mut gasometer),
+			Action::BalanceOf => Self::balance_of(backend, input, &mut gasometer),
+			Action::Allowance => Self::allowance(backend, input, &mut gasometer),
+			Action::Transfer => Self::transfer(backend, input, &mut gasometer, context),
+			Action::Approve => Self::approve(backend, input, &mut gasometer, context),
+			Action::TransferFrom => Self::transfer_from(backend, input, &mut gasometer, context),
+		}
+	}
+
+	fn name(
+		backend: &Backend,
+		input: EvmDataReader,
+		gasometer: &mut Gasometer,
+	) -> EvmResult<PrecompileOutput> {
+		Self::record_metadata_cost(gasometer)?;
+		input.expect_arguments(0)?;
+
+		let output = EvmDataWriter::new().write(Bytes(backend.name())).build();
+		Ok(succeed(gasometer.used_gas(), output, vec![]))
+	}
+
+	fn symbol(
+		backend: &Backend,
+		input: EvmDataReader,
+		gasometer: &mut Gasometer,
+	) -> EvmResult<PrecompileOutput> {
+		Self::record_metadata_cost(gasometer)?;
+		input.expect_arguments(0)?;
+
+		let output = EvmDataWriter::new().write(Bytes(backend.symbol())).build();
+		Ok(succeed(gasometer.used_gas(), output, vec![]))
+	}
+
+	fn decimals(
+		backend: &Backend,
+		input: EvmDataReader,
+		gasometer: &mut Gasometer,
+	) -> EvmResult<PrecompileOutput> {
+		Self::record_metadata_cost(gasometer)?;
+		input.expect_arguments(0)?;
+
+		let output = EvmDataWriter::new().write(backend.decimals()).build();
+		Ok(succeed(gasometer.used_gas(), output, vec![]))
+	}
+
+	fn total_supply(
+		backend: &Backend,
+		input: EvmDataReader,
+		gasometer: &mut Gasometer,
+	) -> EvmResult<PrecompileOutput> {
+		gasometer.record_cost(RuntimeHelper::<Runtime>::db_read_gas_cost())?;
+		input.expect_arguments(0)?;
+
+		let total_supply: U256 = backend.total_supply().into();
+		let output = EvmDataWriter::new().write(total_supply).build();
+		Ok(succeed(gasometer.used_gas(), output, vec![]))
+	}
+
+	fn balance_of(
+		backend: &Backend,
+		mut input: EvmDataReader,
+		gasometer: &mut Gasometer,
+	) -> EvmResult<PrecompileOutput> {
+		gasometer.record_cost(RuntimeHelper::<Runtime>::db_read_gas_cost())?;
+		input.expect_arguments(1)?;
+		let owner = Runtime::AddressMapping::into_account_id(input.read::<Address>()?.0);
+
+		let balance: U256 = backend.balance_of(&owner).into();
+		let output = EvmDataWriter::new().write(balance).build();
+		Ok(succeed(gasometer.used_gas(), output, vec![]))
+	}
+
+	fn allowance(
+		backend: &Backend,
+		mut input: EvmDataReader,
+		gasometer: &mut Gasometer,
+	) -> EvmResult<PrecompileOutput> {
+		gasometer.record_cost(RuntimeHelper::<Runtime>::db_read_gas_cost())?;
+		input.expect_arguments(2)?;
+		let owner = Runtime::AddressMapping::into_account_id(input.read::<Address>()?.0);
+		let spender = Runtime::AddressMapping::into_account_id(input.read::<Address>()?.0);
+
+		let allowance: U256 = backend.allowance(&owner, &spender).into();
+		let output = EvmDataWriter::new().write(allowance).build();
+		Ok(succeed(gasometer.used_gas(), output, vec![]))
+	}
+
+	fn approve(
+		backend: &Backend,
+		mut input: EvmDataReader,
+		gasometer: &mut Gasometer,
+		context: &Context,
+	) -> EvmResult<PrecompileOutput> {
+		gasometer.record_cost(RuntimeHelper::<Runtime>::db_write_gas_cost())?;
+		gasometer.record_log_costs_manual(3, 32)?;
+		input.expect_arguments(2)?;
+		let spender = input.read::<Address>()?.0;
+		let value = input.read::<U256>()?;
+
+		// Approving more than the balance type can hold is an infinite approval.
+		let amount = Backend::Balance::try_from(value).unwrap_or_else(|_| Bounded::max_value());
+		backend.set_allowance(
+			&Runtime::AddressMapping::into_account_id(context.caller),
+			&Runtime::AddressMapping::into_account_id(spender),
+			amount,
+		);
+
+		let log = LogsBuilder::new(context.address).log3(
+			keccak256(APPROVAL_EVENT),
+			context.caller,
+			spender,
+			EvmDataWriter::new().write(value).build(),
+		);
+		let output = EvmDataWriter::new().write(true).build();
+		Ok(succeed(gasometer.used_gas(), output, vec![log]))
+	}
+
+	fn transfer(
+		backend: &Backend,
+		mut input: EvmDataReader,
+		gasometer: &mut Gasometer,
+		context: &Context,
+	) -> EvmResult<PrecompileOutput> {
+		gasometer.record_cost(Self::transfer_gas_cost(backend))?;
+		gasometer.record_log_costs_manual(3, 32)?;
+		input.expect_arguments(2)?;
+		let from = Address(context.caller);
+		let to = input.read::<Address>()?;
+		let value = input.read::<U256>()?;
+		let amount = Self::amount(value, gasometer)?;
+
+		Self::transfer_amount(backend, from, to, amount, gasometer)?;
+
+		let log = Self::transfer_log(context, from, to, value);
+		let output = EvmDataWriter::new().write(true).build();
+		Ok(succeed(gasometer.used_gas(), output, vec![log]))
+	}
+
+	fn transfer_from(
+		backend: &Backend,
+		mut input: EvmDataReader,
+		gasometer: &mut Gasometer,
+		context: &Context,
+	) -> EvmResult<PrecompileOutput> {
+		gasometer.record_cost(Self::transfer_gas_cost(backend))?;
+		gasometer.record_cost(RuntimeHelper::<Runtime>::db_read_gas_cost())?;
+		gasometer.record_cost(RuntimeHelper::<Runtime>::db_write_gas_cost())?;
+		gasometer.record_log_costs_manual(3, 32)?;
+		input.expect_arguments(3)?;
+		let from = input.read::<Address>()?;
+		let to = input.read::<Address>()?;
+		let value = input.read::<U256>()?;
+		let amount = Self::amount(value, gasometer)?;
+
+		// The EVM rolls back the storage changes of a reverting precompile, but the allowance is
+		// only written once the transfer succeeded so that a failed call also has no effect when
+		// the precompile is called directly, as in tests.
+		let remaining_allowance = if from.0 != context.caller {
+			let owner = Runtime::AddressMapping::into_account_id(from.0);
+			let spender = Runtime::AddressMapping::into_account_id(context.caller);
+			let remaining = backend
+				.allowance(&owner, &spender)
+				.checked_sub(&amount)
+				.ok_or_else(|| gasometer.revert("trying to spend more than allowed"))?;
+			Some((owner, spender, remaining))
+		} else {
+			None
+		};
+
+		Self::transfer_amount(backend, from, to, amount, gasometer)?;
+
+		if let Some((owner, spender, remaining)) = remaining_allowance {
+			backend.set_allowance(&owner, &spender, remaining);
+		}
+
+		let log = Self::transfer_log(context, from, to, value);
+		let output = EvmDataWriter::new().write(true).build();
+		Ok(succeed(gasometer.used_gas(), output, vec![log]))
+	}
+
+	fn record_metadata_cost(gasometer: &mut Gasometer) -> EvmResult {
+		gasometer.record_cost(
+			RuntimeHelper::<Runtime>::db_read_gas_cost().saturating_mul(Backend::METADATA_READS),
+		)
+	}
+
+	/// Gas equivalent of the weight of a transfer.
+	fn transfer_gas_cost(backend: &Backend) -> u64 {
+		Runtime::GasWeightMapping::weight_to_gas(backend.transfer_weight())
+	}
+
+	fn amount(value: U256, gasometer: &Gasometer) -> EvmResult<Backend::Balance> {
+		value
+			.try_into()
+			.map_err(|_| gasometer.revert("value too large for balance type"))
+	}
+
+	fn transfer_amount(
+		backend: &Backend,
+		from: Address,
+		to: Address,
+		amount: Backend::Balance,
+		gasometer: &Gasometer,
+	) -> EvmResult {
+		backend
+			.transfer(
+				&Runtime::AddressMapping::into_account_id(from.0),
+				&Runtime::AddressMapping::into_account_id(to.0),
+				amount,
+			)
+			.map_err(|error| gasometer.revert(<&'static str>::from(error)))
+	}
+
+	fn transfer_log(context: &Context, from: Address, to: Address, value: U256) -> Log {
+		LogsBuilder::new(context.address).log3(
+			keccak256(TRANSFER_EVENT),
+			H256::from(from.0),
+			H256::from(to.0),
+			EvmDataWriter::new().write(value).build(),
+		)
+	}
+}
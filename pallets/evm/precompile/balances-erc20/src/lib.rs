@@ -0,0 +1,175 @@
+// SPDX-License-Identifier: Apache-2.0
+// This file is part of Frontier.
+//
+// Copyright (c) 2020 Parity Technologies (UK) Ltd.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! ERC-20 interface to the native token, backed by `pallet_balances`.
+//!
+//! Balances are those of the accounts mapped from EVM addresses with
+//! `pallet_evm::Config::AddressMapping`. Allowances are kept in the storage of the precompile.
+//!
+//! The precompile acts on behalf of `context.caller`, so the precompile set using it must reject
+//! calls where `context.address` is not the precompile address (`DELEGATECALL` and `CALLCODE`).
+//!
+//! The ERC-20 functions are implemented by [`Erc20`] for any [`Erc20Backend`], so that other
+//! tokens, such as the assets of `pallet_assets`, share them.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+mod erc20;
+#[cfg(feature = "std")]
+pub mod testing;
+#[cfg(test)]
+mod tests;
+
+pub use erc20::{Action, Erc20, Erc20Backend, APPROVAL_EVENT, TRANSFER_EVENT};
+
+use fp_evm::{Context, Precompile, PrecompileResult};
+use frame_support::{
+	storage::types::{StorageDoubleMap, ValueQuery},
+	traits::{Currency, ExistenceRequirement, StorageInstance},
+	weights::Weight,
+	Blake2_128Concat,
+};
+use pallet_balances::WeightInfo;
+use sp_core::U256;
+use sp_runtime::DispatchError;
+use sp_std::{convert::TryFrom, marker::PhantomData, vec::Vec};
+
+/// Name, symbol and decimals of the token.
+pub trait Erc20Metadata {
+	fn name() -> &'static str;
+	fn symbol() -> &'static str;
+	fn decimals() -> u8;
+}
+
+type AccountIdOf<Runtime> = <Runtime as frame_system::Config>::AccountId;
+type BalanceOf<Runtime> = <Runtime as pallet_balances::Config>::Balance;
+
+/// Storage prefix of the allowances.
+pub struct ApprovesPrefix;
+
+impl StorageInstance for ApprovesPrefix {
+	const STORAGE_PREFIX: &'static str = "Approves";
+
+	fn pallet_prefix() -> &'static str {
+		"Erc20BalancesPrecompile"
+	}
+}
+
+/// Allowances, by owner then spender.
+pub type ApprovesStorage<Runtime> = StorageDoubleMap<
+	ApprovesPrefix,
+	Blake2_128Concat,
+	AccountIdOf<Runtime>,
+	Blake2_128Concat,
+	AccountIdOf<Runtime>,
+	BalanceOf<Runtime>,
+	ValueQuery,
+>;
+
+/// Native token of `Runtime`, with the metadata of `Metadata`.
+pub struct NativeBalances<Runtime, Metadata>(PhantomData<(Runtime, Metadata)>);
+
+impl<Runtime, Metadata> Erc20Backend<Runtime> for NativeBalances<Runtime, Metadata>
+where
+	Runtime: pallet_balances::Config,
+	BalanceOf<Runtime>: TryFrom<U256> + Into<U256>,
+	Metadata: Erc20Metadata,
+{
+	type Balance = BalanceOf<Runtime>;
+
+	const METADATA_READS: u64 = 0;
+
+	fn name(&self) -> Vec<u8> {
+		Metadata::name().as_bytes().to_vec()
+	}
+
+	fn symbol(&self) -> Vec<u8> {
+		Metadata::symbol().as_bytes().to_vec()
+	}
+
+	fn decimals(&self) -> u8 {
+		Metadata::decimals()
+	}
+
+	fn total_supply(&self) -> Self::Balance {
+		pallet_balances::Pallet::<Runtime>::total_issuance()
+	}
+
+	fn balance_of(&self, owner: &AccountIdOf<Runtime>) -> Self::Balance {
+		pallet_balances::Pallet::<Runtime>::free_balance(owner)
+	}
+
+	fn allowance(
+		&self,
+		owner: &AccountIdOf<Runtime>,
+		spender: &AccountIdOf<Runtime>,
+	) -> Self::Balance {
+		ApprovesStorage::<Runtime>::get(owner, spender)
+	}
+
+	fn set_allowance(
+		&self,
+		owner: &AccountIdOf<Runtime>,
+		spender: &AccountIdOf<Runtime>,
+		amount: Self::Balance,
+	) {
+		ApprovesStorage::<Runtime>::insert(owner, spender, amount);
+	}
+
+	fn transfer(
+		&self,
+		from: &AccountIdOf<Runtime>,
+		to: &AccountIdOf<Runtime>,
+		amount: Self::Balance,
+	) -> Result<(), DispatchError> {
+		<pallet_balances::Pallet<Runtime> as Currency<_>>::transfer(
+			from,
+			to,
+			amount,
+			ExistenceRequirement::AllowDeath,
+		)
+	}
+
+	fn transfer_weight(&self) -> Weight {
+		<Runtime as pallet_balances::Config>::WeightInfo::transfer()
+	}
+}
+
+/// ERC-20 precompile for the native token of `Runtime`.
+pub struct Erc20BalancesPrecompile<Runtime, Metadata>(PhantomData<(Runtime, Metadata)>);
+
+impl<Runtime, Metadata> Precompile for Erc20BalancesPrecompile<Runtime, Metadata>
+where
+	Runtime: pallet_balances::Config + pallet_evm::Config,
+	BalanceOf<Runtime>: TryFrom<U256> + Into<U256>,
+	Metadata: Erc20Metadata,
+{
+	fn execute(
+		input: &[u8],
+		target_gas: Option<u64>,
+		context: &Context,
+		is_static: bool,
+	) -> PrecompileResult {
+		Erc20::<Runtime, NativeBalances<Runtime, Metadata>>::execute(
+			&NativeBalances(PhantomData),
+			input,
+			target_gas,
+			context,
+			is_static,
+		)
+	}
+}
@@ -0,0 +1,53 @@
+// SPDX-License-Identifier: Apache-2.0
+// This file is part of Frontier.
+//
+// Copyright (c) 2020 Parity Technologies (UK) Ltd.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Inputs, costs and logs shared by the tests of ERC-20 precompiles.
+
+use crate::TRANSFER_EVENT;
+use fp_evm::Log;
+use frame_support::weights::Weight;
+use pallet_evm::GasWeightMapping;
+use precompile_utils::{keccak256, selector, EvmDataWriter, LogsBuilder, RuntimeHelper};
+use sp_core::{H160, U256};
+
+/// Input of a call to the function `signature`, to which the arguments are written.
+pub fn call(signature: &str) -> EvmDataWriter {
+	EvmDataWriter::new_with_selector(selector(signature))
+}
+
+/// ABI encoding of a `uint256`.
+pub fn uint(value: u64) -> Vec<u8> {
+	EvmDataWriter::new().write(U256::from(value)).build()
+}
+
+pub fn read_cost<Runtime: pallet_evm::Config>() -> u64 {
+	RuntimeHelper::<Runtime>::db_read_gas_cost()
+}
+
+pub fn write_cost<Runtime: pallet_evm::Config>() -> u64 {
+	RuntimeHelper::<Runtime>::db_write_gas_cost()
+}
+
+/// Cost of `transfer`, whose transfer has the weight `transfer_weight`, including its log.
+pub fn transfer_cost<Runtime: pallet_evm::Config>(transfer_weight: Weight) -> u64 {
+	Runtime::GasWeightMapping::weight_to_gas(transfer_weight) + 375 * 4 + 32 * 8
+}
+
+/// `Transfer` log of the precompile at `precompile`.
+pub fn transfer_log(precompile: H160, from: H160, to: H160, value: u64) -> Log {
+	LogsBuilder::new(precompile).log3(keccak256(TRANSFER_EVENT), from, to, uint(value))
+}
@@ -0,0 +1,499 @@
+// SPDX-License-Identifier: Apache-2.0
+// This file is part of Frontier.
+//
+// Copyright (c) 2020 Parity Technologies (UK) Ltd.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::*;
+
+use crate::testing::*;
+use frame_support::{parameter_types, traits::GenesisBuild, weights::RuntimeDbWeight};
+use pallet_evm::{EnsureAddressNever, EnsureAddressRoot, IdentityAddressMapping};
+use precompile_utils::{
+	keccak256, selector, testing::*, Address, Bytes, EvmDataWriter, LogsBuilder,
+};
+use sp_core::{H160, H256};
+use sp_runtime::{
+	generic,
+	traits::{BlakeTwo256, IdentityLookup},
+};
+
+type UncheckedExtrinsic = frame_system::mocking::MockUncheckedExtrinsic<Test>;
+type Block = frame_system::mocking::MockBlock<Test>;
+
+frame_support::construct_runtime! {
+	pub enum Test where
+		Block = Block,
+		NodeBlock = Block,
+		UncheckedExtrinsic = UncheckedExtrinsic,
+	{
+		System: frame_system::{Pallet, Call, Config, Storage, Event<T>},
+		Balances: pallet_balances::{Pallet, Call, Storage, Config<T>, Event<T>},
+		Timestamp: pallet_timestamp::{Pallet, Call, Storage},
+		EVM: pallet_evm::{Pallet, Call, Storage, Config, Event<T>},
+	}
+}
+
+parameter_types! {
+	pub const BlockHashCount: u64 = 250;
+	pub const DbWeight: RuntimeDbWeight = RuntimeDbWeight { read: 100, write: 1_000 };
+}
+impl frame_system::Config for Test {
+	type BaseCallFilter = frame_support::traits::Everything;
+	type BlockWeights = ();
+	type BlockLength = ();
+	type DbWeight = DbWeight;
+	type Origin = Origin;
+	type Index = u64;
+	type BlockNumber = u64;
+	type Hash = H256;
+	type Call = Call;
+	type Hashing = BlakeTwo256;
+	type AccountId = H160;
+	type Lookup = IdentityLookup<Self::AccountId>;
+	type Header = generic::Header<u64, BlakeTwo256>;
+	type Event = Event;
+	type BlockHashCount = BlockHashCount;
+	type Version = ();
+	type PalletInfo = PalletInfo;
+	type AccountData = pallet_balances::AccountData<u64>;
+	type OnNewAccount = ();
+	type OnKilledAccount = ();
+	type SystemWeightInfo = ();
+	type SS58Prefix = ();
+	type OnSetCode = ();
+}
+
+parameter_types! {
+	pub const ExistentialDeposit: u64 = 0;
+}
+impl pallet_balances::Config for Test {
+	type MaxLocks = ();
+	type Balance = u64;
+	type DustRemoval = ();
+	type Event = Event;
+	type ExistentialDeposit = ExistentialDeposit;
+	type AccountStore = System;
+	type WeightInfo = ();
+	type MaxReserves = ();
+	type ReserveIdentifier = ();
+}
+
+parameter_types! {
+	pub const MinimumPeriod: u64 = 1000;
+}
+impl pallet_timestamp::Config for Test {
+	type Moment = u64;
+	type OnTimestampSet = ();
+	type MinimumPeriod = MinimumPeriod;
+	type WeightInfo = ();
+}
+
+impl pallet_evm::Config for Test {
+	type FeeCalculator = ();
+	type GasWeightMapping = ();
+	type CallOrigin = EnsureAddressRoot<Self::AccountId>;
+	type WithdrawOrigin = EnsureAddressNever<Self::AccountId>;
+	type AddressMapping = IdentityAddressMapping;
+	type Currency = Balances;
+	type Runner = pallet_evm::runner::stack::Runner<Self>;
+	type Event = Event;
+	type PrecompilesType = ();
+	type PrecompilesValue = ();
+	type ChainId = ();
+	type BlockGasLimit = ();
+	type GasLimitPovSizeRatio = ();
+	type MaxBlockProofSize = ();
+	type MaxCodeSize = ();
+	type MaxTransactionGas = ();
+	type OnChargeTransaction = ();
+	type BlockHashMapping = pallet_evm::SubstrateBlockHashMapping<Self>;
+	type FindAuthor = ();
+	type WeightInfo = ();
+}
+
+pub struct NativeMetadata;
+
+impl Erc20Metadata for NativeMetadata {
+	fn name() -> &'static str {
+		"Unit"
+	}
+
+	fn symbol() -> &'static str {
+		"UNIT"
+	}
+
+	fn decimals() -> u8 {
+		12
+	}
+}
+
+pub struct Precompiles;
+
+impl fp_evm::PrecompileSet for Precompiles {
+	fn execute(
+		&self,
+		address: H160,
+		input: &[u8],
+		target_gas: Option<u64>,
+		context: &Context,
+		is_static: bool,
+	) -> Option<PrecompileResult> {
+		if !self.is_precompile(address) {
+			return None;
+		}
+		Some(Erc20BalancesPrecompile::<Test, NativeMetadata>::execute(
+			input, target_gas, context, is_static,
+		))
+	}
+
+	fn is_precompile(&self, address: H160) -> bool {
+		address == precompile()
+	}
+}
+
+fn precompile() -> H160 {
+	H160::from_low_u64_be(2050)
+}
+
+fn alice() -> H160 {
+	H160::repeat_byte(0xaa)
+}
+
+fn bob() -> H160 {
+	H160::repeat_byte(0xbb)
+}
+
+fn charlie() -> H160 {
+	H160::repeat_byte(0xcc)
+}
+
+fn new_test_ext() -> sp_io::TestExternalities {
+	let mut t = frame_system::GenesisConfig::default().build_storage::<Test>().unwrap();
+	pallet_balances::GenesisConfig::<Test> { balances: vec![(alice(), 1_000), (bob(), 500)] }
+		.assimilate_storage(&mut t)
+		.unwrap();
+	t.into()
+}
+
+#[test]
+fn selectors_match_erc20_interface() {
+	for (signature, action) in &[
+		("name()", Action::Name),
+		("symbol()", Action::Symbol),
+		("decimals()", Action::Decimals),
+		("totalSupply()", Action::TotalSupply),
+		("balanceOf(address)", Action::BalanceOf),
+		("allowance(address,address)", Action::Allowance),
+		("transfer(address,uint256)", Action::Transfer),
+		("approve(address,uint256)", Action::Approve),
+		("transferFrom(address,address,uint256)", Action::TransferFrom),
+	] {
+		assert_eq!(Action::try_from(selector(signature)), Ok(*action));
+	}
+	assert_eq!(
+		keccak256(TRANSFER_EVENT),
+		H256::from_slice(&[
+			0xdd, 0xf2, 0x52, 0xad, 0x1b, 0xe2, 0xc8, 0x9b, 0x69, 0xc2, 0xb0, 0x68, 0xfc, 0x37,
+			0x8d, 0xaa, 0x95, 0x2b, 0xa7, 0xf1, 0x63, 0xc4, 0xa1, 0x16, 0x28, 0xf5, 0x5a, 0x4d,
+			0xf5, 0x23, 0xb3, 0xef,
+		])
+	);
+	assert_eq!(
+		keccak256(APPROVAL_EVENT),
+		H256::from_slice(&[
+			0x8c, 0x5b, 0xe1, 0xe5, 0xeb, 0xec, 0x7d, 0x5b, 0xd1, 0x4f, 0x71, 0x42, 0x7d, 0x1e,
+			0x84, 0xf3, 0xdd, 0x03, 0x14, 0xc0, 0xf7, 0xb2, 0x29, 0x1e, 0x5b, 0x20, 0x0a, 0xc8,
+			0xc7, 0xc3, 0xb9, 0x25,
+		])
+	);
+}
+
+#[test]
+fn metadata() {
+	new_test_ext().execute_with(|| {
+		Precompiles
+			.prepare_test(alice(), precompile(), call("name()").build())
+			.expect_cost(0)
+			.expect_no_logs()
+			.execute_returns(EvmDataWriter::new().write(Bytes::from("Unit")).build());
+		Precompiles
+			.prepare_test(alice(), precompile(), call("symbol()").build())
+			.execute_returns(EvmDataWriter::new().write(Bytes::from("UNIT")).build());
+		Precompiles
+			.prepare_test(alice(), precompile(), call("decimals()").build())
+			.execute_returns(EvmDataWriter::new().write(12u8).build());
+	});
+}
+
+#[test]
+fn total_supply_and_balance_of() {
+	new_test_ext().execute_with(|| {
+		Precompiles
+			.prepare_test(alice(), precompile(), call("totalSupply()").build())
+			.expect_cost(read_cost::<Test>())
+			.expect_no_logs()
+			.execute_returns(uint(1_500));
+		Precompiles
+			.prepare_test(
+				charlie(),
+				precompile(),
+				call("balanceOf(address)").write(Address(bob())).build(),
+			)
+			.expect_cost(read_cost::<Test>())
+			.expect_no_logs()
+			.execute_returns(uint(500));
+		Precompiles
+			.prepare_test(
+				alice(),
+				precompile(),
+				call("balanceOf(address)").write(Address(charlie())).build(),
+			)
+			.execute_returns(uint(0));
+	});
+}
+
+#[test]
+fn transfer_moves_native_balance() {
+	new_test_ext().execute_with(|| {
+		Precompiles
+			.prepare_test(
+				alice(),
+				precompile(),
+				call("transfer(address,uint256)")
+					.write(Address(charlie()))
+					.write(U256::from(400))
+					.build(),
+			)
+			.expect_cost(transfer_cost::<Test>(<() as WeightInfo>::transfer()))
+			.expect_log(transfer_log(precompile(), alice(), charlie(), 400))
+			.execute_returns(EvmDataWriter::new().write(true).build());
+
+		assert_eq!(Balances::free_balance(alice()), 600);
+		assert_eq!(Balances::free_balance(charlie()), 400);
+		assert_eq!(Balances::total_issuance(), 1_500);
+	});
+}
+
+#[test]
+fn transfer_more_than_balance_reverts() {
+	new_test_ext().execute_with(|| {
+		Precompiles
+			.prepare_test(
+				bob(),
+				precompile(),
+				call("transfer(address,uint256)")
+					.write(Address(charlie()))
+					.write(U256::from(501))
+					.build(),
+			)
+			.execute_reverts(|message| message == b"InsufficientBalance");
+		Precompiles
+			.prepare_test(
+				bob(),
+				precompile(),
+				call("transfer(address,uint256)")
+					.write(Address(charlie()))
+					.write(U256::from(u64::MAX) + 1)
+					.build(),
+			)
+			.execute_reverts(|message| message == b"value too large for balance type");
+
+		assert_eq!(Balances::free_balance(bob()), 500);
+		assert_eq!(Balances::free_balance(charlie()), 0);
+	});
+}
+
+#[test]
+fn approve_sets_allowance() {
+	new_test_ext().execute_with(|| {
+		Precompiles
+			.prepare_test(
+				alice(),
+				precompile(),
+				call("approve(address,uint256)")
+					.write(Address(bob()))
+					.write(U256::from(300))
+					.build(),
+			)
+			.expect_cost(write_cost::<Test>() + 375 * 4 + 32 * 8)
+			.expect_log(LogsBuilder::new(precompile()).log3(
+				keccak256(APPROVAL_EVENT),
+				alice(),
+				bob(),
+				uint(300),
+			))
+			.execute_returns(EvmDataWriter::new().write(true).build());
+
+		Precompiles
+			.prepare_test(
+				charlie(),
+				precompile(),
+				call("allowance(address,address)")
+					.write(Address(alice()))
+					.write(Address(bob()))
+					.build(),
+			)
+			.expect_cost(read_cost::<Test>())
+			.execute_returns(uint(300));
+		Precompiles
+			.prepare_test(
+				charlie(),
+				precompile(),
+				call("allowance(address,address)")
+					.write(Address(bob()))
+					.write(Address(alice()))
+					.build(),
+			)
+			.execute_returns(uint(0));
+	});
+}
+
+#[test]
+fn approve_max_is_saturated() {
+	new_test_ext().execute_with(|| {
+		Precompiles
+			.prepare_test(
+				alice(),
+				precompile(),
+				call("approve(address,uint256)")
+					.write(Address(bob()))
+					.write(U256::max_value())
+					.build(),
+			)
+			.execute_some();
+
+		assert_eq!(ApprovesStorage::<Test>::get(alice(), bob()), u64::MAX);
+	});
+}
+
+#[test]
+fn transfer_from_spends_allowance() {
+	new_test_ext().execute_with(|| {
+		ApprovesStorage::<Test>::insert(alice(), bob(), 300);
+
+		Precompiles
+			.prepare_test(
+				bob(),
+				precompile(),
+				call("transferFrom(address,address,uint256)")
+					.write(Address(alice()))
+					.write(Address(charlie()))
+					.write(U256::from(200))
+					.build(),
+			)
+			.expect_cost(
+				transfer_cost::<Test>(<() as WeightInfo>::transfer()) +
+					read_cost::<Test>() +
+					write_cost::<Test>(),
+			)
+			.expect_log(transfer_log(precompile(), alice(), charlie(), 200))
+			.execute_returns(EvmDataWriter::new().write(true).build());
+
+		assert_eq!(ApprovesStorage::<Test>::get(alice(), bob()), 100);
+		assert_eq!(Balances::free_balance(alice()), 800);
+		assert_eq!(Balances::free_balance(charlie()), 200);
+	});
+}
+
+#[test]
+fn transfer_from_more_than_allowance_reverts() {
+	new_test_ext().execute_with(|| {
+		ApprovesStorage::<Test>::insert(alice(), bob(), 100);
+
+		Precompiles
+			.prepare_test(
+				bob(),
+				precompile(),
+				call("transferFrom(address,address,uint256)")
+					.write(Address(alice()))
+					.write(Address(charlie()))
+					.write(U256::from(101))
+					.build(),
+			)
+			.execute_reverts(|message| message == b"trying to spend more than allowed");
+
+		assert_eq!(ApprovesStorage::<Test>::get(alice(), bob()), 100);
+		assert_eq!(Balances::free_balance(alice()), 1_000);
+	});
+}
+
+#[test]
+fn failed_transfer_from_keeps_allowance() {
+	new_test_ext().execute_with(|| {
+		ApprovesStorage::<Test>::insert(bob(), alice(), 1_000);
+
+		Precompiles
+			.prepare_test(
+				alice(),
+				precompile(),
+				call("transferFrom(address,address,uint256)")
+					.write(Address(bob()))
+					.write(Address(charlie()))
+					.write(U256::from(600))
+					.build(),
+			)
+			.execute_reverts(|message| message == b"InsufficientBalance");
+
+		assert_eq!(ApprovesStorage::<Test>::get(bob(), alice()), 1_000);
+	});
+}
+
+#[test]
+fn transfer_from_self_needs_no_allowance() {
+	new_test_ext().execute_with(|| {
+		Precompiles
+			.prepare_test(
+				alice(),
+				precompile(),
+				call("transferFrom(address,address,uint256)")
+					.write(Address(alice()))
+					.write(Address(bob()))
+					.write(U256::from(100))
+					.build(),
+			)
+			.expect_log(transfer_log(precompile(), alice(), bob(), 100))
+			.execute_returns(EvmDataWriter::new().write(true).build());
+
+		assert_eq!(Balances::free_balance(bob()), 600);
+	});
+}
+
+#[test]
+fn state_mutability_is_enforced() {
+	new_test_ext().execute_with(|| {
+		Precompiles
+			.prepare_test(
+				alice(),
+				precompile(),
+				call("transfer(address,uint256)")
+					.write(Address(bob()))
+					.write(U256::from(1))
+					.build(),
+			)
+			.with_static_call(true)
+			.execute_reverts(|message| {
+				message == b"can't call non-static function in static context"
+			});
+		Precompiles
+			.prepare_test(alice(), precompile(), call("totalSupply()").build())
+			.with_value(1)
+			.execute_reverts(|message| message == b"function is not payable");
+		Precompiles
+			.prepare_test(alice(), precompile(), call("mint(uint256)").write(U256::one()).build())
+			.execute_reverts(|message| message == b"unknown selector");
+
+		assert_eq!(Balances::free_balance(alice()), 1_000);
+	});
+}
@@ -0,0 +1,206 @@
+// SPDX-License-Identifier: Apache-2.0
+// This file is part of Frontier.
+//
+// Copyright (c) 2020 Parity Technologies (UK) Ltd.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Precompile executing a batch of calls on behalf of its caller, for instance to approve and
+//! swap with a single signature.
+//!
+//! Subcalls are made with `RuntimeHelper::call`, with the caller as source. Their gas is charged
+//! to the batch and their logs are returned as logs of the batch. A subcall can't use the batch
+//! precompile again.
+//!
+//! Precompiles of evm 0.33 are not given a handle to the `StackExecutor` running them, so the
+//! subcalls can't go through its subcall interface. They run in a new executor on top of the
+//! state of the running one, and only become real subcalls once the precompile interface exposes
+//! the executor. Until then, they differ from `CALL` as follows:
+//! - `ORIGIN` is the caller of the batch, not the sender of the transaction.
+//! - `GASPRICE` is zero.
+//! - Their call depth starts from zero again.
+//! - Accounts and storage slots accessed before the batch are cold again (EIP-2929).
+//!
+//! The precompile set using it must reject calls where `context.address` is not the precompile
+//! address (`DELEGATECALL` and `CALLCODE`).
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+#[cfg(test)]
+mod tests;
+
+use alloc::format;
+use fp_evm::{Context, Log, Precompile, PrecompileResult};
+use frame_support::traits::Get;
+use precompile_utils::{
+	keccak256, log_costs, succeed, Address, Bytes, EvmDataReader, EvmDataWriter, FunctionModifier,
+	Gasometer, LogsBuilder, RuntimeHelper,
+};
+use sp_core::U256;
+use sp_std::{convert::TryFrom, marker::PhantomData, vec::Vec};
+
+/// Signature of the event emitted after a successful subcall.
+pub const SUBCALL_SUCCEEDED_EVENT: &str = "SubcallSucceeded(uint256)";
+/// Signature of the event emitted after a failed subcall.
+pub const SUBCALL_FAILED_EVENT: &str = "SubcallFailed(uint256)";
+
+environmental::environmental!(BATCHING: ());
+
+/// How failed subcalls are handled. The functions of the precompile all take the subcalls as
+/// `(address[] to, uint256[] value, bytes[] callData, uint64[] gasLimit)`. Missing values,
+/// call data and gas limits are zero, and a zero gas limit forwards all the remaining gas.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mode {
+	/// Skip failed subcalls.
+	BatchSome,
+	/// Stop at the first failed subcall, keeping the previous ones.
+	BatchSomeUntilFailure,
+	/// Revert everything if a subcall fails.
+	BatchAll,
+}
+
+impl TryFrom<u32> for Mode {
+	type Error = ();
+
+	fn try_from(selector: u32) -> Result<Self, Self::Error> {
+		match selector {
+			0x79df4b9c => Ok(Mode::BatchSome),
+			0xcf0491c7 => Ok(Mode::BatchSomeUntilFailure),
+			0x96e292b8 => Ok(Mode::BatchAll),
+			_ => Err(()),
+		}
+	}
+}
+
+pub struct Batch<Runtime>(PhantomData<Runtime>);
+
+impl<Runtime> Precompile for Batch<Runtime>
+where
+	Runtime: pallet_evm::Config,
+{
+	fn execute(
+		input: &[u8],
+		target_gas: Option<u64>,
+		context: &Context,
+		is_static: bool,
+	) -> PrecompileResult {
+		let mut gasometer = Gasometer::new(target_gas);
+		let mode = EvmDataReader::read_selector::<Mode>(input)?;
+		let mut input = EvmDataReader::new_skip_selector(input)?;
+		gasometer.check_function_modifier(context, is_static, FunctionModifier::NonPayable)?;
+
+		if BATCHING::with(|_| ()).is_some() {
+			return Err(gasometer.revert("batch is not reentrant"));
+		}
+
+		input.expect_arguments(4)?;
+		let to = input.read::<Vec<Address>>()?;
+		let values = input.read::<Vec<U256>>()?;
+		let call_data = input.read::<Vec<Bytes>>()?;
+		let gas_limits = input.read::<Vec<u64>>()?;
+		if values.len() > to.len() || call_data.len() > to.len() || gas_limits.len() > to.len() {
+			return Err(gasometer.revert("more values, call data or gas limits than addresses"));
+		}
+
+		let mut call_data = call_data.into_iter();
+		let entries = to.into_iter().enumerate().map(|(index, to)| Entry {
+			index,
+			to,
+			value: values.get(index).copied().unwrap_or_default(),
+			call_data: call_data.next().map(|data| data.0).unwrap_or_default(),
+			gas_limit: gas_limits.get(index).copied().unwrap_or_default(),
+		});
+
+		BATCHING::using(&mut (), || Self::batch(mode, entries, &mut gasometer, context))
+	}
+}
+
+struct Entry {
+	index: usize,
+	to: Address,
+	value: U256,
+	call_data: Vec<u8>,
+	gas_limit: u64,
+}
+
+impl<Runtime> Batch<Runtime>
+where
+	Runtime: pallet_evm::Config,
+{
+	fn batch(
+		mode: Mode,
+		entries: impl Iterator<Item = Entry>,
+		gasometer: &mut Gasometer,
+		context: &Context,
+	) -> PrecompileResult {
+		let mut logs = Vec::new();
+		// Gas kept aside for the log emitted after each subcall.
+		let log_cost = log_costs(1, 32)?;
+
+		for entry in entries {
+			let index = entry.index;
+			let available = match gasometer.remaining_gas()? {
+				Some(remaining) => remaining.saturating_sub(log_cost),
+				None => Runtime::BlockGasLimit::get().min(u64::MAX.into()).low_u64(),
+			};
+			let gas_limit = match entry.gas_limit {
+				0 => available,
+				gas_limit => gas_limit,
+			};
+
+			if gas_limit > available {
+				if mode == Mode::BatchAll {
+					return Err(gasometer.revert(format!("not enough gas for subcall {}", index)));
+				}
+				gasometer.record_cost(log_cost)?;
+				logs.push(Self::log(context, SUBCALL_FAILED_EVENT, index));
+				break;
+			}
+
+			let outcome = RuntimeHelper::<Runtime>::call(
+				context.caller,
+				entry.to.0,
+				entry.value,
+				entry.call_data,
+				gas_limit,
+			);
+			gasometer.record_cost(outcome.used_gas)?;
+
+			if outcome.reason.is_succeed() {
+				logs.extend(outcome.logs);
+				gasometer.record_cost(log_cost)?;
+				logs.push(Self::log(context, SUBCALL_SUCCEEDED_EVENT, index));
+				continue;
+			}
+
+			if mode == Mode::BatchAll {
+				return Err(outcome.revert(gasometer, format!("subcall {} failed", index)));
+			}
+
+			gasometer.record_cost(log_cost)?;
+			logs.push(Self::log(context, SUBCALL_FAILED_EVENT, index));
+			if mode == Mode::BatchSomeUntilFailure {
+				break;
+			}
+		}
+
+		Ok(succeed(gasometer.used_gas(), Vec::new(), logs))
+	}
+
+	fn log(context: &Context, event: &str, index: usize) -> Log {
+		LogsBuilder::new(context.address)
+			.log1(keccak256(event), EvmDataWriter::new().write(U256::from(index)).build())
+	}
+}
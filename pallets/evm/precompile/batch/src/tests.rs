@@ -0,0 +1,408 @@
+// SPDX-License-Identifier: Apache-2.0
+// This file is part of Frontier.
+//
+// Copyright (c) 2020 Parity Technologies (UK) Ltd.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::*;
+
+use fp_evm::{CallInfo, PrecompileSet};
+use frame_support::{parameter_types, traits::GenesisBuild};
+use pallet_evm::{
+	EnsureAddressNever, EnsureAddressRoot, ExitReason, ExitRevert, IdentityAddressMapping, Runner,
+};
+use precompile_utils::selector;
+use sp_core::{H160, H256};
+use sp_runtime::{
+	generic,
+	traits::{BlakeTwo256, IdentityLookup},
+};
+
+type UncheckedExtrinsic = frame_system::mocking::MockUncheckedExtrinsic<Test>;
+type Block = frame_system::mocking::MockBlock<Test>;
+
+frame_support::construct_runtime! {
+	pub enum Test where
+		Block = Block,
+		NodeBlock = Block,
+		UncheckedExtrinsic = UncheckedExtrinsic,
+	{
+		System: frame_system::{Pallet, Call, Config, Storage, Event<T>},
+		Balances: pallet_balances::{Pallet, Call, Storage, Config<T>, Event<T>},
+		Timestamp: pallet_timestamp::{Pallet, Call, Storage},
+		EVM: pallet_evm::{Pallet, Call, Storage, Config, Event<T>},
+	}
+}
+
+parameter_types! {
+	pub const BlockHashCount: u64 = 250;
+}
+impl frame_system::Config for Test {
+	type BaseCallFilter = frame_support::traits::Everything;
+	type BlockWeights = ();
+	type BlockLength = ();
+	type DbWeight = ();
+	type Origin = Origin;
+	type Index = u64;
+	type BlockNumber = u64;
+	type Hash = H256;
+	type Call = Call;
+	type Hashing = BlakeTwo256;
+	type AccountId = H160;
+	type Lookup = IdentityLookup<Self::AccountId>;
+	type Header = generic::Header<u64, BlakeTwo256>;
+	type Event = Event;
+	type BlockHashCount = BlockHashCount;
+	type Version = ();
+	type PalletInfo = PalletInfo;
+	type AccountData = pallet_balances::AccountData<u64>;
+	type OnNewAccount = ();
+	type OnKilledAccount = ();
+	type SystemWeightInfo = ();
+	type SS58Prefix = ();
+	type OnSetCode = ();
+}
+
+parameter_types! {
+	pub const ExistentialDeposit: u64 = 0;
+}
+impl pallet_balances::Config for Test {
+	type MaxLocks = ();
+	type Balance = u64;
+	type DustRemoval = ();
+	type Event = Event;
+	type ExistentialDeposit = ExistentialDeposit;
+	type AccountStore = System;
+	type WeightInfo = ();
+	type MaxReserves = ();
+	type ReserveIdentifier = ();
+}
+
+parameter_types! {
+	pub const MinimumPeriod: u64 = 1000;
+}
+impl pallet_timestamp::Config for Test {
+	type Moment = u64;
+	type OnTimestampSet = ();
+	type MinimumPeriod = MinimumPeriod;
+	type WeightInfo = ();
+}
+
+#[derive(Default)]
+pub struct Precompiles;
+
+impl PrecompileSet for Precompiles {
+	fn execute(
+		&self,
+		address: H160,
+		input: &[u8],
+		target_gas: Option<u64>,
+		context: &Context,
+		is_static: bool,
+	) -> Option<PrecompileResult> {
+		if !self.is_precompile(address) {
+			return None;
+		}
+		Some(Batch::<Test>::execute(input, target_gas, context, is_static))
+	}
+
+	fn is_precompile(&self, address: H160) -> bool {
+		address == batch()
+	}
+}
+
+parameter_types! {
+	pub BlockGasLimit: U256 = U256::from(u32::MAX);
+	pub PrecompilesValue: Precompiles = Precompiles;
+}
+impl pallet_evm::Config for Test {
+	type FeeCalculator = ();
+	type GasWeightMapping = ();
+	type CallOrigin = EnsureAddressRoot<Self::AccountId>;
+	type WithdrawOrigin = EnsureAddressNever<Self::AccountId>;
+	type AddressMapping = IdentityAddressMapping;
+	type Currency = Balances;
+	type Runner = pallet_evm::runner::stack::Runner<Self>;
+	type Event = Event;
+	type PrecompilesType = Precompiles;
+	type PrecompilesValue = PrecompilesValue;
+	type ChainId = ();
+	type BlockGasLimit = BlockGasLimit;
+	type GasLimitPovSizeRatio = ();
+	type MaxBlockProofSize = ();
+	type MaxCodeSize = ();
+	type MaxTransactionGas = ();
+	type OnChargeTransaction = ();
+	type BlockHashMapping = pallet_evm::SubstrateBlockHashMapping<Self>;
+	type FindAuthor = ();
+	type WeightInfo = ();
+}
+
+fn batch() -> H160 {
+	H160::from_low_u64_be(2056)
+}
+
+fn alice() -> H160 {
+	H160::repeat_byte(0xaa)
+}
+
+fn bob() -> H160 {
+	H160::repeat_byte(0xbb)
+}
+
+/// Stores its caller at slot 0: `CALLER PUSH1 0 SSTORE STOP`.
+fn store_caller() -> H160 {
+	H160::repeat_byte(0x01)
+}
+
+/// Reverts: `PUSH1 0 PUSH1 0 REVERT`.
+fn reverts() -> H160 {
+	H160::repeat_byte(0x02)
+}
+
+/// Also stores its caller at slot 0.
+fn store_caller_2() -> H160 {
+	H160::repeat_byte(0x03)
+}
+
+/// Stores `ORIGIN` at slot 0 and `GASPRICE` at slot 1:
+/// `ORIGIN PUSH1 0 SSTORE GASPRICE PUSH1 1 SSTORE STOP`.
+fn store_origin_and_gas_price() -> H160 {
+	H160::repeat_byte(0x04)
+}
+
+fn new_test_ext() -> sp_io::TestExternalities {
+	let mut t = frame_system::GenesisConfig::default().build_storage::<Test>().unwrap();
+	pallet_balances::GenesisConfig::<Test> { balances: vec![(alice(), 1_000_000)] }
+		.assimilate_storage(&mut t)
+		.unwrap();
+	let mut ext: sp_io::TestExternalities = t.into();
+	ext.execute_with(|| {
+		pallet_evm::Pallet::<Test>::create_account(
+			store_caller(),
+			vec![0x33, 0x60, 0x00, 0x55, 0x00],
+		);
+		pallet_evm::Pallet::<Test>::create_account(reverts(), vec![0x60, 0x00, 0x60, 0x00, 0xfd]);
+		pallet_evm::Pallet::<Test>::create_account(
+			store_caller_2(),
+			vec![0x33, 0x60, 0x00, 0x55, 0x00],
+		);
+		pallet_evm::Pallet::<Test>::create_account(
+			store_origin_and_gas_price(),
+			vec![0x32, 0x60, 0x00, 0x55, 0x3a, 0x60, 0x01, 0x55, 0x00],
+		);
+	});
+	ext
+}
+
+fn input(signature: &str, subcalls: &[(H160, u64)]) -> Vec<u8> {
+	EvmDataWriter::new_with_selector(selector(signature))
+		.write(subcalls.iter().map(|(to, _)| Address(*to)).collect::<Vec<_>>())
+		.write(subcalls.iter().map(|(_, value)| U256::from(*value)).collect::<Vec<_>>())
+		.write(Vec::<Bytes>::new())
+		.write(Vec::<u64>::new())
+		.build()
+}
+
+fn call(input: Vec<u8>) -> CallInfo {
+	<Test as pallet_evm::Config>::Runner::call(
+		alice(),
+		batch(),
+		input,
+		U256::zero(),
+		1_000_000,
+		None,
+		None,
+		None,
+		Vec::new(),
+		<Test as pallet_evm::Config>::config(),
+	)
+	.unwrap()
+}
+
+fn stored_caller(contract: H160) -> H256 {
+	pallet_evm::Pallet::<Test>::account_storages(contract, H256::zero())
+}
+
+fn stored(contract: H160, slot: u64) -> H256 {
+	pallet_evm::Pallet::<Test>::account_storages(contract, H256::from_low_u64_be(slot))
+}
+
+fn event(event: &str, index: usize) -> Log {
+	Batch::<Test>::log(
+		&Context { address: batch(), caller: alice(), apparent_value: U256::zero() },
+		event,
+		index,
+	)
+}
+
+const BATCH_SOME: &str = "batchSome(address[],uint256[],bytes[],uint64[])";
+const BATCH_SOME_UNTIL_FAILURE: &str =
+	"batchSomeUntilFailure(address[],uint256[],bytes[],uint64[])";
+const BATCH_ALL: &str = "batchAll(address[],uint256[],bytes[],uint64[])";
+
+#[test]
+fn selectors() {
+	assert_eq!(Mode::try_from(selector(BATCH_SOME)), Ok(Mode::BatchSome));
+	assert_eq!(Mode::try_from(selector(BATCH_SOME_UNTIL_FAILURE)), Ok(Mode::BatchSomeUntilFailure));
+	assert_eq!(Mode::try_from(selector(BATCH_ALL)), Ok(Mode::BatchAll));
+}
+
+#[test]
+fn batch_all_runs_subcalls_as_caller() {
+	new_test_ext().execute_with(|| {
+		let info = call(input(BATCH_ALL, &[(store_caller(), 0), (bob(), 100)]));
+
+		assert!(info.exit_reason.is_succeed(), "{:?}", info.exit_reason);
+		assert_eq!(
+			info.logs,
+			vec![event(SUBCALL_SUCCEEDED_EVENT, 0), event(SUBCALL_SUCCEEDED_EVENT, 1)]
+		);
+		assert_eq!(stored_caller(store_caller()), H256::from(alice()));
+		assert_eq!(Balances::free_balance(bob()), 100);
+		// Only the batch transaction itself increments the nonce.
+		assert_eq!(System::account_nonce(alice()), 1);
+	});
+}
+
+#[test]
+fn batch_all_reverts_everything_on_failure() {
+	new_test_ext().execute_with(|| {
+		let info = call(input(BATCH_ALL, &[(store_caller(), 0), (bob(), 100), (reverts(), 0)]));
+
+		assert_eq!(info.exit_reason, ExitReason::Revert(ExitRevert::Reverted));
+		assert_eq!(info.logs, vec![]);
+		assert_eq!(stored_caller(store_caller()), H256::zero());
+		assert_eq!(Balances::free_balance(bob()), 0);
+	});
+}
+
+#[test]
+fn batch_some_skips_failures() {
+	new_test_ext().execute_with(|| {
+		let info = call(input(
+			BATCH_SOME,
+			&[(reverts(), 0), (store_caller(), 0), (bob(), 2_000_000), (store_caller_2(), 0)],
+		));
+
+		assert!(info.exit_reason.is_succeed(), "{:?}", info.exit_reason);
+		assert_eq!(
+			info.logs,
+			vec![
+				event(SUBCALL_FAILED_EVENT, 0),
+				event(SUBCALL_SUCCEEDED_EVENT, 1),
+				event(SUBCALL_FAILED_EVENT, 2),
+				event(SUBCALL_SUCCEEDED_EVENT, 3),
+			]
+		);
+		assert_eq!(stored_caller(store_caller()), H256::from(alice()));
+		assert_eq!(stored_caller(store_caller_2()), H256::from(alice()));
+	});
+}
+
+#[test]
+fn batch_some_until_failure_stops_at_first_failure() {
+	new_test_ext().execute_with(|| {
+		let info = call(input(
+			BATCH_SOME_UNTIL_FAILURE,
+			&[(store_caller(), 0), (reverts(), 0), (store_caller_2(), 0)],
+		));
+
+		assert!(info.exit_reason.is_succeed(), "{:?}", info.exit_reason);
+		assert_eq!(
+			info.logs,
+			vec![event(SUBCALL_SUCCEEDED_EVENT, 0), event(SUBCALL_FAILED_EVENT, 1)]
+		);
+		assert_eq!(stored_caller(store_caller()), H256::from(alice()));
+		assert_eq!(stored_caller(store_caller_2()), H256::zero());
+	});
+}
+
+#[test]
+fn batch_is_not_reentrant() {
+	new_test_ext().execute_with(|| {
+		let nested = input(BATCH_ALL, &[(store_caller(), 0)]);
+		let info = call(
+			EvmDataWriter::new_with_selector(selector(BATCH_SOME))
+				.write(vec![Address(batch())])
+				.write(Vec::<U256>::new())
+				.write(vec![Bytes(nested)])
+				.write(Vec::<u64>::new())
+				.build(),
+		);
+
+		assert!(info.exit_reason.is_succeed(), "{:?}", info.exit_reason);
+		assert_eq!(info.logs, vec![event(SUBCALL_FAILED_EVENT, 0)]);
+		assert_eq!(stored_caller(store_caller()), H256::zero());
+	});
+}
+
+#[test]
+fn more_values_than_addresses_is_rejected() {
+	new_test_ext().execute_with(|| {
+		let info = call(
+			EvmDataWriter::new_with_selector(selector(BATCH_ALL))
+				.write(vec![Address(bob())])
+				.write(vec![U256::one(), U256::one()])
+				.write(Vec::<Bytes>::new())
+				.write(Vec::<u64>::new())
+				.build(),
+		);
+
+		assert_eq!(info.exit_reason, ExitReason::Revert(ExitRevert::Reverted));
+		assert_eq!(Balances::free_balance(bob()), 0);
+	});
+}
+
+#[test]
+fn subcall_origin_is_the_batch_caller() {
+	new_test_ext().execute_with(|| {
+		// The batch called by a contract rather than by the transaction sender.
+		let context =
+			Context { address: batch(), caller: store_caller(), apparent_value: U256::zero() };
+
+		let result = Batch::<Test>::execute(
+			&input(BATCH_ALL, &[(store_origin_and_gas_price(), 0)]),
+			None,
+			&context,
+			false,
+		);
+
+		assert!(result.is_ok(), "{:?}", result);
+		assert_eq!(stored(store_origin_and_gas_price(), 0), H256::from(store_caller()));
+	});
+}
+
+#[test]
+fn subcall_gas_price_is_zero() {
+	new_test_ext().execute_with(|| {
+		let info = <Test as pallet_evm::Config>::Runner::call(
+			alice(),
+			batch(),
+			input(BATCH_ALL, &[(store_origin_and_gas_price(), 0)]),
+			U256::zero(),
+			500_000,
+			Some(U256::one()),
+			None,
+			None,
+			Vec::new(),
+			<Test as pallet_evm::Config>::config(),
+		)
+		.unwrap();
+
+		assert!(info.exit_reason.is_succeed(), "{:?}", info.exit_reason);
+		assert_eq!(stored(store_origin_and_gas_price(), 0), H256::from(alice()));
+		assert_eq!(stored(store_origin_and_gas_price(), 1), H256::zero());
+	});
+}
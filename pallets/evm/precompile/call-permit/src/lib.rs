@@ -0,0 +1,276 @@
+// SPDX-License-Identifier: Apache-2.0
+// This file is part of Frontier.
+//
+// Copyright (c) 2020 Parity Technologies (UK) Ltd.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Precompile dispatching calls authorized by an EIP-712 signed permit, so that a relayer can pay
+//! the fees of an account holding no native tokens.
+//!
+//! A permit allows calling `to` with `value` and `data`, using at most `gaslimit` gas, with
+//! `from` as the caller. It is only valid until `deadline` (a UNIX timestamp in seconds) and with
+//! the current nonce of `from`, which is incremented by each dispatch. The call is made with
+//! `RuntimeHelper::call`, and a call dispatched by the precompile can't dispatch another permit.
+//!
+//! The precompile has no handle to the executor running it, so the call is not a subcall of the
+//! running EVM but runs in a new executor on top of its state. It sees `from` as `ORIGIN` and a
+//! zero `GASPRICE`, starts again at depth zero and does not share the accessed accounts and
+//! storage slots (EIP-2929) of the relayer's transaction.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(test)]
+mod tests;
+
+use fp_evm::{Context, Precompile, PrecompileOutput, PrecompileResult};
+use frame_support::{
+	storage::types::{StorageMap, ValueQuery},
+	traits::{Get, StorageInstance},
+	Blake2_128Concat,
+};
+use precompile_utils::{
+	keccak256, succeed, Address, Bytes, EvmDataReader, EvmDataWriter, EvmResult, FunctionModifier,
+	Gasometer, RuntimeHelper,
+};
+use sp_core::{H160, H256, U256};
+use sp_std::{convert::TryFrom, marker::PhantomData, vec, vec::Vec};
+
+/// Type of the EIP-712 domain.
+pub const EIP712_DOMAIN: &str =
+	"EIP712Domain(string name,string version,uint256 chainId,address verifyingContract)";
+/// Type of a permit.
+pub const PERMIT_TYPE: &str = "CallPermit(address from,address to,uint256 value,bytes data,\
+	uint64 gaslimit,uint256 nonce,uint256 deadline)";
+/// Name of the EIP-712 domain.
+pub const PERMIT_DOMAIN_NAME: &str = "Call Permit Precompile";
+/// Version of the EIP-712 domain.
+pub const PERMIT_DOMAIN_VERSION: &str = "1";
+
+/// Gas cost of recovering the signer, as for the `ECRecover` precompile.
+const ECRECOVER_GAS: u64 = 3_000;
+
+environmental::environmental!(DISPATCHING: ());
+
+/// Storage prefix of the permit nonces.
+pub struct NoncesPrefix;
+
+impl StorageInstance for NoncesPrefix {
+	const STORAGE_PREFIX: &'static str = "Nonces";
+
+	fn pallet_prefix() -> &'static str {
+		"CallPermitPrecompile"
+	}
+}
+
+/// Next permit nonce of each account.
+pub type NoncesStorage = StorageMap<NoncesPrefix, Blake2_128Concat, H160, U256, ValueQuery>;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+	Dispatch,
+	Nonces,
+	DomainSeparator,
+}
+
+impl TryFrom<u32> for Action {
+	type Error = ();
+
+	fn try_from(selector: u32) -> Result<Self, Self::Error> {
+		match selector {
+			0xb5ea0966 => Ok(Action::Dispatch),
+			0x7ecebe00 => Ok(Action::Nonces),
+			0x3644e515 => Ok(Action::DomainSeparator),
+			_ => Err(()),
+		}
+	}
+}
+
+/// Fields of a permit, as signed by `from`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Permit {
+	pub from: H160,
+	pub to: H160,
+	pub value: U256,
+	pub data: Vec<u8>,
+	pub gas_limit: u64,
+	pub nonce: U256,
+	pub deadline: U256,
+}
+
+pub struct CallPermitPrecompile<Runtime>(PhantomData<Runtime>);
+
+impl<Runtime> Precompile for CallPermitPrecompile<Runtime>
+where
+	Runtime: pallet_evm::Config + pallet_timestamp::Config,
+	<Runtime as pallet_timestamp::Config>::Moment: Into<U256>,
+{
+	fn execute(
+		input: &[u8],
+		target_gas: Option<u64>,
+		context: &Context,
+		is_static: bool,
+	) -> PrecompileResult {
+		let mut gasometer = Gasometer::new(target_gas);
+		let action = EvmDataReader::read_selector::<Action>(input)?;
+		let input = EvmDataReader::new_skip_selector(input)?;
+
+		let modifier = match action {
+			Action::Dispatch => FunctionModifier::NonPayable,
+			_ => FunctionModifier::View,
+		};
+		gasometer.check_function_modifier(context, is_static, modifier)?;
+
+		match action {
+			Action::Dispatch => Self::dispatch(input, &mut gasometer, context),
+			Action::Nonces => Self::nonces(input, &mut gasometer),
+			Action::DomainSeparator => Self::domain_separator(input, &mut gasometer, context),
+		}
+	}
+}
+
+impl<Runtime> CallPermitPrecompile<Runtime>
+where
+	Runtime: pallet_evm::Config + pallet_timestamp::Config,
+	<Runtime as pallet_timestamp::Config>::Moment: Into<U256>,
+{
+	/// EIP-712 domain separator of the precompile deployed at `address`.
+	pub fn compute_domain_separator(address: H160) -> H256 {
+		keccak256(
+			EvmDataWriter::new()
+				.write(keccak256(EIP712_DOMAIN))
+				.write(keccak256(PERMIT_DOMAIN_NAME))
+				.write(keccak256(PERMIT_DOMAIN_VERSION))
+				.write(U256::from(Runtime::ChainId::get()))
+				.write(Address(address))
+				.build(),
+		)
+	}
+
+	/// Digest signed by `from` to authorize `permit` on the precompile deployed at `address`.
+	pub fn permit_digest(address: H160, permit: &Permit) -> H256 {
+		let permit = keccak256(
+			EvmDataWriter::new()
+				.write(keccak256(PERMIT_TYPE))
+				.write(Address(permit.from))
+				.write(Address(permit.to))
+				.write(permit.value)
+				.write(keccak256(&permit.data))
+				.write(permit.gas_limit)
+				.write(permit.nonce)
+				.write(permit.deadline)
+				.build(),
+		);
+
+		let mut message = vec![0x19, 0x01];
+		message.extend_from_slice(Self::compute_domain_separator(address).as_bytes());
+		message.extend_from_slice(permit.as_bytes());
+		keccak256(message)
+	}
+
+	fn dispatch(
+		mut input: EvmDataReader,
+		gasometer: &mut Gasometer,
+		context: &Context,
+	) -> EvmResult<PrecompileOutput> {
+		// Nonce and timestamp reads, signature recovery and nonce write.
+		gasometer.record_cost(RuntimeHelper::<Runtime>::db_read_gas_cost().saturating_mul(2))?;
+		gasometer.record_cost(ECRECOVER_GAS)?;
+		gasometer.record_cost(RuntimeHelper::<Runtime>::db_write_gas_cost())?;
+
+		if DISPATCHING::with(|_| ()).is_some() {
+			return Err(gasometer.revert("permit dispatch is not reentrant"));
+		}
+
+		input.expect_arguments(9)?;
+		let from = input.read::<Address>()?.0;
+		let to = input.read::<Address>()?.0;
+		let value = input.read::<U256>()?;
+		let data = input.read::<Bytes>()?.0;
+		let gas_limit = input.read::<u64>()?;
+		let deadline = input.read::<U256>()?;
+		let v = input.read::<u8>()?;
+		let r = input.read::<H256>()?;
+		let s = input.read::<H256>()?;
+
+		let now: U256 = pallet_timestamp::Pallet::<Runtime>::get().into();
+		if deadline < now / 1000 {
+			return Err(gasometer.revert("permit expired"));
+		}
+
+		let nonce = NoncesStorage::get(from);
+		let permit = Permit { from, to, value, data, gas_limit, nonce, deadline };
+		let digest = Self::permit_digest(context.address, &permit);
+		if recover_signer(digest, v, r, s) != Some(from) {
+			return Err(gasometer.revert("invalid permit"));
+		}
+
+		if let Some(remaining) = gasometer.remaining_gas()? {
+			if remaining < gas_limit {
+				return Err(gasometer.revert("gaslimit is too low to dispatch provided call"));
+			}
+		}
+
+		// Reverting the precompile also reverts the nonce increment.
+		NoncesStorage::insert(from, nonce.saturating_add(U256::one()));
+
+		let outcome = DISPATCHING::using(&mut (), || {
+			RuntimeHelper::<Runtime>::call(from, to, value, permit.data, gas_limit)
+		});
+		gasometer.record_cost(outcome.used_gas)?;
+
+		if !outcome.reason.is_succeed() {
+			return Err(outcome.revert(gasometer, "dispatched call failed"));
+		}
+
+		let output = EvmDataWriter::new().write(Bytes(outcome.output)).build();
+		Ok(succeed(gasometer.used_gas(), output, outcome.logs))
+	}
+
+	fn nonces(mut input: EvmDataReader, gasometer: &mut Gasometer) -> EvmResult<PrecompileOutput> {
+		gasometer.record_cost(RuntimeHelper::<Runtime>::db_read_gas_cost())?;
+		input.expect_arguments(1)?;
+		let owner = input.read::<Address>()?.0;
+
+		let output = EvmDataWriter::new().write(NoncesStorage::get(owner)).build();
+		Ok(succeed(gasometer.used_gas(), output, vec![]))
+	}
+
+	fn domain_separator(
+		input: EvmDataReader,
+		gasometer: &mut Gasometer,
+		context: &Context,
+	) -> EvmResult<PrecompileOutput> {
+		input.expect_arguments(0)?;
+
+		let output = EvmDataWriter::new()
+			.write(Self::compute_domain_separator(context.address))
+			.build();
+		Ok(succeed(gasometer.used_gas(), output, vec![]))
+	}
+}
+
+/// Address of the signer of `digest`, with the signature in Ethereum `(v, r, s)` format.
+fn recover_signer(digest: H256, v: u8, r: H256, s: H256) -> Option<H160> {
+	if v != 27 && v != 28 {
+		return None;
+	}
+
+	let mut signature = [0u8; 65];
+	signature[0..32].copy_from_slice(r.as_bytes());
+	signature[32..64].copy_from_slice(s.as_bytes());
+	signature[64] = v - 27;
+
+	let public = sp_io::crypto::secp256k1_ecdsa_recover(&signature, &digest.0).ok()?;
+	Some(H160::from(keccak256(&public[..])))
+}
@@ -0,0 +1,435 @@
+// SPDX-License-Identifier: Apache-2.0
+// This file is part of Frontier.
+//
+// Copyright (c) 2020 Parity Technologies (UK) Ltd.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::*;
+
+use fp_evm::{CallInfo, PrecompileSet};
+use frame_support::{parameter_types, traits::GenesisBuild};
+use pallet_evm::{
+	EnsureAddressNever, EnsureAddressRoot, ExitReason, ExitRevert, IdentityAddressMapping, Runner,
+};
+use precompile_utils::{encode_revert_message, selector};
+use sp_runtime::{
+	generic,
+	traits::{BlakeTwo256, IdentityLookup},
+};
+
+type UncheckedExtrinsic = frame_system::mocking::MockUncheckedExtrinsic<Test>;
+type Block = frame_system::mocking::MockBlock<Test>;
+
+frame_support::construct_runtime! {
+	pub enum Test where
+		Block = Block,
+		NodeBlock = Block,
+		UncheckedExtrinsic = UncheckedExtrinsic,
+	{
+		System: frame_system::{Pallet, Call, Config, Storage, Event<T>},
+		Balances: pallet_balances::{Pallet, Call, Storage, Config<T>, Event<T>},
+		Timestamp: pallet_timestamp::{Pallet, Call, Storage},
+		EVM: pallet_evm::{Pallet, Call, Storage, Config, Event<T>},
+	}
+}
+
+parameter_types! {
+	pub const BlockHashCount: u64 = 250;
+}
+impl frame_system::Config for Test {
+	type BaseCallFilter = frame_support::traits::Everything;
+	type BlockWeights = ();
+	type BlockLength = ();
+	type DbWeight = ();
+	type Origin = Origin;
+	type Index = u64;
+	type BlockNumber = u64;
+	type Hash = H256;
+	type Call = Call;
+	type Hashing = BlakeTwo256;
+	type AccountId = H160;
+	type Lookup = IdentityLookup<Self::AccountId>;
+	type Header = generic::Header<u64, BlakeTwo256>;
+	type Event = Event;
+	type BlockHashCount = BlockHashCount;
+	type Version = ();
+	type PalletInfo = PalletInfo;
+	type AccountData = pallet_balances::AccountData<u64>;
+	type OnNewAccount = ();
+	type OnKilledAccount = ();
+	type SystemWeightInfo = ();
+	type SS58Prefix = ();
+	type OnSetCode = ();
+}
+
+parameter_types! {
+	pub const ExistentialDeposit: u64 = 0;
+}
+impl pallet_balances::Config for Test {
+	type MaxLocks = ();
+	type Balance = u64;
+	type DustRemoval = ();
+	type Event = Event;
+	type ExistentialDeposit = ExistentialDeposit;
+	type AccountStore = System;
+	type WeightInfo = ();
+	type MaxReserves = ();
+	type ReserveIdentifier = ();
+}
+
+parameter_types! {
+	pub const MinimumPeriod: u64 = 1000;
+}
+impl pallet_timestamp::Config for Test {
+	type Moment = u64;
+	type OnTimestampSet = ();
+	type MinimumPeriod = MinimumPeriod;
+	type WeightInfo = ();
+}
+
+#[derive(Default)]
+pub struct Precompiles;
+
+impl PrecompileSet for Precompiles {
+	fn execute(
+		&self,
+		address: H160,
+		input: &[u8],
+		target_gas: Option<u64>,
+		context: &Context,
+		is_static: bool,
+	) -> Option<PrecompileResult> {
+		if !self.is_precompile(address) {
+			return None;
+		}
+		Some(CallPermitPrecompile::<Test>::execute(input, target_gas, context, is_static))
+	}
+
+	fn is_precompile(&self, address: H160) -> bool {
+		address == precompile()
+	}
+}
+
+parameter_types! {
+	pub const ChainId: u64 = 42;
+	pub BlockGasLimit: U256 = U256::from(u32::MAX);
+	pub PrecompilesValue: Precompiles = Precompiles;
+}
+impl pallet_evm::Config for Test {
+	type FeeCalculator = ();
+	type GasWeightMapping = ();
+	type CallOrigin = EnsureAddressRoot<Self::AccountId>;
+	type WithdrawOrigin = EnsureAddressNever<Self::AccountId>;
+	type AddressMapping = IdentityAddressMapping;
+	type Currency = Balances;
+	type Runner = pallet_evm::runner::stack::Runner<Self>;
+	type Event = Event;
+	type PrecompilesType = Precompiles;
+	type PrecompilesValue = PrecompilesValue;
+	type ChainId = ChainId;
+	type BlockGasLimit = BlockGasLimit;
+	type GasLimitPovSizeRatio = ();
+	type MaxBlockProofSize = ();
+	type MaxCodeSize = ();
+	type MaxTransactionGas = ();
+	type OnChargeTransaction = ();
+	type BlockHashMapping = pallet_evm::SubstrateBlockHashMapping<Self>;
+	type FindAuthor = ();
+	type WeightInfo = ();
+}
+
+struct Signer(libsecp256k1::SecretKey);
+
+impl Signer {
+	fn new(seed: u8) -> Self {
+		Signer(libsecp256k1::SecretKey::parse(&[seed; 32]).unwrap())
+	}
+
+	fn address(&self) -> H160 {
+		let public = libsecp256k1::PublicKey::from_secret_key(&self.0).serialize();
+		H160::from(keccak256(&public[1..65]))
+	}
+
+	fn sign(&self, digest: H256) -> (u8, H256, H256) {
+		let (signature, recovery_id) =
+			libsecp256k1::sign(&libsecp256k1::Message::parse(&digest.0), &self.0);
+		let signature = signature.serialize();
+		(
+			recovery_id.serialize() + 27,
+			H256::from_slice(&signature[0..32]),
+			H256::from_slice(&signature[32..64]),
+		)
+	}
+}
+
+fn precompile() -> H160 {
+	H160::from_low_u64_be(2058)
+}
+
+fn alice() -> Signer {
+	Signer::new(1)
+}
+
+fn bob() -> Signer {
+	Signer::new(2)
+}
+
+fn relayer() -> H160 {
+	H160::repeat_byte(0xee)
+}
+
+/// Stores its caller at slot 0: `CALLER PUSH1 0 SSTORE STOP`.
+fn store_caller() -> H160 {
+	H160::repeat_byte(0x01)
+}
+
+/// Reverts: `PUSH1 0 PUSH1 0 REVERT`.
+fn reverts() -> H160 {
+	H160::repeat_byte(0x02)
+}
+
+/// Stores `ORIGIN` at slot 0 and `GASPRICE` at slot 1:
+/// `ORIGIN PUSH1 0 SSTORE GASPRICE PUSH1 1 SSTORE STOP`.
+fn store_origin_and_gas_price() -> H160 {
+	H160::repeat_byte(0x03)
+}
+
+fn new_test_ext() -> sp_io::TestExternalities {
+	let mut t = frame_system::GenesisConfig::default().build_storage::<Test>().unwrap();
+	pallet_balances::GenesisConfig::<Test> {
+		balances: vec![(alice().address(), 1_000), (relayer(), 1_000_000_000)],
+	}
+	.assimilate_storage(&mut t)
+	.unwrap();
+	let mut ext: sp_io::TestExternalities = t.into();
+	ext.execute_with(|| {
+		pallet_evm::Pallet::<Test>::create_account(
+			store_caller(),
+			vec![0x33, 0x60, 0x00, 0x55, 0x00],
+		);
+		pallet_evm::Pallet::<Test>::create_account(reverts(), vec![0x60, 0x00, 0x60, 0x00, 0xfd]);
+		pallet_evm::Pallet::<Test>::create_account(
+			store_origin_and_gas_price(),
+			vec![0x32, 0x60, 0x00, 0x55, 0x3a, 0x60, 0x01, 0x55, 0x00],
+		);
+		Timestamp::set_timestamp(1_000_000);
+	});
+	ext
+}
+
+fn permit(from: &Signer, to: H160, value: u64, nonce: u64, deadline: u64) -> Permit {
+	Permit {
+		from: from.address(),
+		to,
+		value: value.into(),
+		data: vec![],
+		gas_limit: 100_000,
+		nonce: nonce.into(),
+		deadline: deadline.into(),
+	}
+}
+
+fn dispatch_input(permit: &Permit, signer: &Signer) -> Vec<u8> {
+	let (v, r, s) = signer.sign(CallPermitPrecompile::<Test>::permit_digest(precompile(), permit));
+	EvmDataWriter::new_with_selector(selector(
+		"dispatch(address,address,uint256,bytes,uint64,uint256,uint8,bytes32,bytes32)",
+	))
+	.write(Address(permit.from))
+	.write(Address(permit.to))
+	.write(permit.value)
+	.write(Bytes(permit.data.clone()))
+	.write(permit.gas_limit)
+	.write(permit.deadline)
+	.write(v)
+	.write(r)
+	.write(s)
+	.build()
+}
+
+fn call(input: Vec<u8>) -> CallInfo {
+	<Test as pallet_evm::Config>::Runner::call(
+		relayer(),
+		precompile(),
+		input,
+		U256::zero(),
+		1_000_000,
+		None,
+		None,
+		None,
+		Vec::new(),
+		<Test as pallet_evm::Config>::config(),
+	)
+	.unwrap()
+}
+
+fn stored_caller() -> H256 {
+	pallet_evm::Pallet::<Test>::account_storages(store_caller(), H256::zero())
+}
+
+fn assert_reverts(info: CallInfo, message: &str) {
+	assert_eq!(info.exit_reason, ExitReason::Revert(ExitRevert::Reverted));
+	assert_eq!(info.value, encode_revert_message(message.as_bytes()));
+}
+
+#[test]
+fn selectors_and_types() {
+	assert_eq!(
+		Action::try_from(selector(
+			"dispatch(address,address,uint256,bytes,uint64,uint256,uint8,bytes32,bytes32)"
+		)),
+		Ok(Action::Dispatch)
+	);
+	assert_eq!(Action::try_from(selector("nonces(address)")), Ok(Action::Nonces));
+	assert_eq!(Action::try_from(selector("DOMAIN_SEPARATOR()")), Ok(Action::DomainSeparator));
+	assert_eq!(
+		keccak256(PERMIT_TYPE),
+		H256::from_slice(&[
+			0x50, 0x68, 0x04, 0x72, 0x1d, 0x43, 0x8a, 0x24, 0xe9, 0x1e, 0x7d, 0x0d, 0x65, 0xa8,
+			0x10, 0x5f, 0x20, 0x8b, 0x3b, 0x12, 0x62, 0x9b, 0xe3, 0xa1, 0xc4, 0x64, 0xeb, 0xf6,
+			0x1c, 0x8d, 0xf9, 0x2d,
+		])
+	);
+}
+
+#[test]
+fn dispatch_calls_as_signer() {
+	new_test_ext().execute_with(|| {
+		let permit = permit(&alice(), store_caller(), 0, 0, 2_000);
+
+		let info = call(dispatch_input(&permit, &alice()));
+
+		assert!(info.exit_reason.is_succeed(), "{:?}", info.exit_reason);
+		assert_eq!(info.value, EvmDataWriter::new().write(Bytes(vec![])).build());
+		assert_eq!(stored_caller(), H256::from(alice().address()));
+		assert_eq!(NoncesStorage::get(alice().address()), U256::one());
+		assert_eq!(System::account_nonce(alice().address()), 0);
+		assert_eq!(System::account(alice().address()).sufficients, 0);
+	});
+}
+
+#[test]
+fn dispatch_transfers_value_of_signer() {
+	new_test_ext().execute_with(|| {
+		let to = H160::repeat_byte(0xcc);
+		let permit = permit(&alice(), to, 300, 0, 2_000);
+
+		let info = call(dispatch_input(&permit, &alice()));
+
+		assert!(info.exit_reason.is_succeed(), "{:?}", info.exit_reason);
+		assert_eq!(Balances::free_balance(alice().address()), 700);
+		assert_eq!(Balances::free_balance(to), 300);
+	});
+}
+
+#[test]
+fn permit_cannot_be_replayed() {
+	new_test_ext().execute_with(|| {
+		let permit = permit(&alice(), store_caller(), 0, 0, 2_000);
+		let input = dispatch_input(&permit, &alice());
+
+		assert!(call(input.clone()).exit_reason.is_succeed());
+		assert_reverts(call(input), "invalid permit");
+		assert_eq!(NoncesStorage::get(alice().address()), U256::one());
+	});
+}
+
+#[test]
+fn permit_signed_by_another_account_is_rejected() {
+	new_test_ext().execute_with(|| {
+		let permit = permit(&alice(), store_caller(), 0, 0, 2_000);
+
+		assert_reverts(call(dispatch_input(&permit, &bob())), "invalid permit");
+		assert_eq!(stored_caller(), H256::zero());
+	});
+}
+
+#[test]
+fn expired_permit_is_rejected() {
+	new_test_ext().execute_with(|| {
+		// The timestamp is 1_000 seconds.
+		let permit = permit(&alice(), store_caller(), 0, 0, 999);
+
+		assert_reverts(call(dispatch_input(&permit, &alice())), "permit expired");
+		assert_eq!(stored_caller(), H256::zero());
+	});
+}
+
+#[test]
+fn failed_call_keeps_nonce() {
+	new_test_ext().execute_with(|| {
+		let permit = permit(&alice(), reverts(), 0, 0, 2_000);
+
+		let info = call(dispatch_input(&permit, &alice()));
+
+		assert_eq!(info.exit_reason, ExitReason::Revert(ExitRevert::Reverted));
+		assert_eq!(NoncesStorage::get(alice().address()), U256::zero());
+	});
+}
+
+#[test]
+fn nonces_and_domain_separator() {
+	new_test_ext().execute_with(|| {
+		NoncesStorage::insert(alice().address(), U256::from(5));
+
+		let info = call(
+			EvmDataWriter::new_with_selector(selector("nonces(address)"))
+				.write(Address(alice().address()))
+				.build(),
+		);
+		assert_eq!(info.value, EvmDataWriter::new().write(U256::from(5)).build());
+
+		let info = call(EvmDataWriter::new_with_selector(selector("DOMAIN_SEPARATOR()")).build());
+		let expected = keccak256(
+			EvmDataWriter::new()
+				.write(keccak256(EIP712_DOMAIN))
+				.write(keccak256("Call Permit Precompile"))
+				.write(keccak256("1"))
+				.write(U256::from(42))
+				.write(Address(precompile()))
+				.build(),
+		);
+		assert_eq!(info.value, EvmDataWriter::new().write(expected).build());
+	});
+}
+
+#[test]
+fn dispatched_call_sees_signer_as_origin_and_zero_gas_price() {
+	new_test_ext().execute_with(|| {
+		let permit = permit(&alice(), store_origin_and_gas_price(), 0, 0, 2_000);
+
+		let info = <Test as pallet_evm::Config>::Runner::call(
+			relayer(),
+			precompile(),
+			dispatch_input(&permit, &alice()),
+			U256::zero(),
+			1_000_000,
+			Some(U256::one()),
+			None,
+			None,
+			Vec::new(),
+			<Test as pallet_evm::Config>::config(),
+		)
+		.unwrap();
+
+		assert!(info.exit_reason.is_succeed(), "{:?}", info.exit_reason);
+		let stored = |slot: u64| {
+			pallet_evm::Pallet::<Test>::account_storages(
+				store_origin_and_gas_price(),
+				H256::from_low_u64_be(slot),
+			)
+		};
+		assert_eq!(stored(0), H256::from(alice().address()));
+		assert_eq!(stored(1), H256::zero());
+	});
+}
@@ -15,76 +15,84 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+//! Precompile dispatching a SCALE encoded runtime call as the mapped account of the caller.
+//!
+//! - Only calls accepted by the `CallFilter` parameter can be dispatched, and never from a
+//!   static call.
+//! - A call dispatched by the precompile cannot execute EVM code again, be it through the
+//!   precompile or the EVM and Ethereum pallets.
+//! - Failed calls are rolled back and revert with the name of their `DispatchError`.
+//! - Decoding the call is charged on top of its weight.
+
 #![cfg_attr(not(feature = "std"), no_std)]
 
-extern crate alloc;
+#[cfg(test)]
+mod tests;
 
-use codec::Decode;
+use codec::{Decode, DecodeLimit};
 use core::marker::PhantomData;
-use fp_evm::{
-	Context, ExitError, ExitSucceed, Precompile, PrecompileFailure, PrecompileOutput,
-	PrecompileResult,
-};
+use fp_evm::{Context, ExitSucceed, Precompile, PrecompileOutput, PrecompileResult};
 use frame_support::{
 	dispatch::{Dispatchable, GetDispatchInfo, PostDispatchInfo},
+	traits::Contains,
 	weights::{DispatchClass, Pays},
 };
-use pallet_evm::{AddressMapping, GasWeightMapping};
+use pallet_evm::AddressMapping;
+use precompile_utils::{FunctionModifier, Gasometer, RuntimeHelper};
+
+pub use precompile_utils::dispatch_error_message;
 
-pub struct Dispatch<T> {
-	_marker: PhantomData<T>,
+/// Maximum depth of nested calls, such as batches, in a dispatched call.
+pub const DECODE_LIMIT: u32 = 8;
+
+/// Gas charged per 32 bytes word of input for decoding the call.
+pub const DECODE_GAS_PER_WORD: u64 = 3;
+
+pub struct Dispatch<T, CallFilter> {
+	_marker: PhantomData<(T, CallFilter)>,
 }
 
-impl<T> Precompile for Dispatch<T>
+impl<T, CallFilter> Precompile for Dispatch<T, CallFilter>
 where
 	T: pallet_evm::Config,
 	T::Call: Dispatchable<PostInfo = PostDispatchInfo> + GetDispatchInfo + Decode,
 	<T::Call as Dispatchable>::Origin: From<Option<T::AccountId>>,
+	CallFilter: Contains<T::Call>,
 {
 	fn execute(
 		input: &[u8],
 		target_gas: Option<u64>,
 		context: &Context,
-		_is_static: bool,
+		is_static: bool,
 	) -> PrecompileResult {
-		let call = T::Call::decode(&mut &input[..]).map_err(|_| PrecompileFailure::Error {
-			exit_status: ExitError::Other("decode failed".into()),
-		})?;
-		let info = call.get_dispatch_info();
+		let mut gasometer = Gasometer::new(target_gas);
+		gasometer.check_function_modifier(context, is_static, FunctionModifier::NonPayable)?;
+		let words = (input.len() as u64 + 31) / 32;
+		gasometer.record_cost(words.saturating_mul(DECODE_GAS_PER_WORD))?;
 
-		let valid_call = info.pays_fee == Pays::Yes && info.class == DispatchClass::Normal;
-		if !valid_call {
-			return Err(PrecompileFailure::Error {
-				exit_status: ExitError::Other("invalid call".into()),
-			});
+		if pallet_evm::is_dispatching_from_evm() {
+			return Err(gasometer.revert("dispatch is not reentrant"));
 		}
 
-		if let Some(gas) = target_gas {
-			let valid_weight = info.weight <= T::GasWeightMapping::gas_to_weight(gas);
-			if !valid_weight {
-				return Err(PrecompileFailure::Error {
-					exit_status: ExitError::OutOfGas,
-				});
-			}
+		let call = T::Call::decode_with_depth_limit(DECODE_LIMIT, &mut &input[..])
+			.map_err(|_| gasometer.revert("decode failed"))?;
+		let info = call.get_dispatch_info();
+
+		let valid_call = info.pays_fee == Pays::Yes
+			&& info.class == DispatchClass::Normal
+			&& CallFilter::contains(&call);
+		if !valid_call {
+			return Err(gasometer.revert("invalid call"));
 		}
 
 		let origin = T::AddressMapping::into_account_id(context.caller);
+		RuntimeHelper::<T>::try_dispatch(origin, call, &mut gasometer)?;
 
-		match call.dispatch(Some(origin).into()) {
-			Ok(post_info) => {
-				let cost = T::GasWeightMapping::weight_to_gas(
-					post_info.actual_weight.unwrap_or(info.weight),
-				);
-				Ok(PrecompileOutput {
-					exit_status: ExitSucceed::Stopped,
-					cost,
-					output: Default::default(),
-					logs: Default::default(),
-				})
-			}
-			Err(_) => Err(PrecompileFailure::Error {
-				exit_status: ExitError::Other("dispatch execution failed".into()),
-			}),
-		}
+		Ok(PrecompileOutput {
+			exit_status: ExitSucceed::Stopped,
+			cost: gasometer.used_gas(),
+			output: Default::default(),
+			logs: Default::default(),
+		})
 	}
 }
@@ -0,0 +1,311 @@
+// SPDX-License-Identifier: Apache-2.0
+// This file is part of Frontier.
+//
+// Copyright (c) 2020 Parity Technologies (UK) Ltd.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::*;
+
+use codec::Encode;
+use frame_support::{
+	parameter_types,
+	traits::{Everything, GenesisBuild},
+};
+use pallet_evm::{EnsureAddressNever, EnsureAddressRoot, IdentityAddressMapping};
+use precompile_utils::testing::decode_revert_message;
+use sp_core::{H160, H256, U256};
+use sp_runtime::{
+	generic,
+	traits::{BlakeTwo256, IdentityLookup},
+};
+
+type UncheckedExtrinsic = frame_system::mocking::MockUncheckedExtrinsic<Test>;
+type Block = frame_system::mocking::MockBlock<Test>;
+
+frame_support::construct_runtime! {
+	pub enum Test where
+		Block = Block,
+		NodeBlock = Block,
+		UncheckedExtrinsic = UncheckedExtrinsic,
+	{
+		System: frame_system::{Pallet, Call, Config, Storage, Event<T>},
+		Balances: pallet_balances::{Pallet, Call, Storage, Config<T>, Event<T>},
+		Timestamp: pallet_timestamp::{Pallet, Call, Storage},
+		EVM: pallet_evm::{Pallet, Call, Storage, Config, Event<T>},
+	}
+}
+
+parameter_types! {
+	pub const BlockHashCount: u64 = 250;
+}
+impl frame_system::Config for Test {
+	type BaseCallFilter = Everything;
+	type BlockWeights = ();
+	type BlockLength = ();
+	type DbWeight = ();
+	type Origin = Origin;
+	type Index = u64;
+	type BlockNumber = u64;
+	type Hash = H256;
+	type Call = Call;
+	type Hashing = BlakeTwo256;
+	type AccountId = H160;
+	type Lookup = IdentityLookup<Self::AccountId>;
+	type Header = generic::Header<u64, BlakeTwo256>;
+	type Event = Event;
+	type BlockHashCount = BlockHashCount;
+	type Version = ();
+	type PalletInfo = PalletInfo;
+	type AccountData = pallet_balances::AccountData<u64>;
+	type OnNewAccount = ();
+	type OnKilledAccount = ();
+	type SystemWeightInfo = ();
+	type SS58Prefix = ();
+	type OnSetCode = ();
+}
+
+parameter_types! {
+	pub const ExistentialDeposit: u64 = 0;
+}
+impl pallet_balances::Config for Test {
+	type MaxLocks = ();
+	type Balance = u64;
+	type DustRemoval = ();
+	type Event = Event;
+	type ExistentialDeposit = ExistentialDeposit;
+	type AccountStore = System;
+	type WeightInfo = ();
+	type MaxReserves = ();
+	type ReserveIdentifier = ();
+}
+
+parameter_types! {
+	pub const MinimumPeriod: u64 = 1000;
+}
+impl pallet_timestamp::Config for Test {
+	type Moment = u64;
+	type OnTimestampSet = ();
+	type MinimumPeriod = MinimumPeriod;
+	type WeightInfo = ();
+}
+
+impl pallet_evm::Config for Test {
+	type FeeCalculator = ();
+	type GasWeightMapping = ();
+	type CallOrigin = EnsureAddressRoot<Self::AccountId>;
+	type WithdrawOrigin = EnsureAddressNever<Self::AccountId>;
+	type AddressMapping = IdentityAddressMapping;
+	type Currency = Balances;
+	type Runner = pallet_evm::runner::stack::Runner<Self>;
+	type Event = Event;
+	type PrecompilesType = ();
+	type PrecompilesValue = ();
+	type ChainId = ();
+	type BlockGasLimit = ();
+	type GasLimitPovSizeRatio = ();
+	type MaxBlockProofSize = ();
+	type MaxCodeSize = ();
+	type MaxTransactionGas = ();
+	type OnChargeTransaction = ();
+	type BlockHashMapping = pallet_evm::SubstrateBlockHashMapping<Self>;
+	type FindAuthor = ();
+	type WeightInfo = ();
+}
+
+/// Only balance transfers can be dispatched.
+pub struct TransfersOnly;
+
+impl Contains<Call> for TransfersOnly {
+	fn contains(call: &Call) -> bool {
+		matches!(call, Call::Balances(pallet_balances::Call::transfer { .. }))
+	}
+}
+
+type TestDispatch = Dispatch<Test, TransfersOnly>;
+
+fn alice() -> H160 {
+	H160::repeat_byte(0xaa)
+}
+
+fn bob() -> H160 {
+	H160::repeat_byte(0xbb)
+}
+
+fn context() -> Context {
+	Context { address: H160::from_low_u64_be(1026), caller: alice(), apparent_value: U256::zero() }
+}
+
+fn new_test_ext() -> sp_io::TestExternalities {
+	let mut t = frame_system::GenesisConfig::default().build_storage::<Test>().unwrap();
+	pallet_balances::GenesisConfig::<Test> { balances: vec![(alice(), 1_000)] }
+		.assimilate_storage(&mut t)
+		.unwrap();
+	t.into()
+}
+
+fn transfer(value: u64) -> Call {
+	Call::Balances(pallet_balances::Call::transfer { dest: bob(), value })
+}
+
+fn decode_cost(input: &[u8]) -> u64 {
+	(input.len() as u64 + 31) / 32 * DECODE_GAS_PER_WORD
+}
+
+fn revert_message(result: PrecompileResult) -> Vec<u8> {
+	match result {
+		Err(PrecompileFailure::Revert { output, .. }) => decode_revert_message(&output),
+		other => panic!("expected revert, got {:?}", other),
+	}
+}
+
+#[test]
+fn dispatches_call_as_caller() {
+	new_test_ext().execute_with(|| {
+		let call = transfer(100);
+		let input = call.encode();
+
+		let output = TestDispatch::execute(&input, None, &context(), false).unwrap();
+
+		assert_eq!(output.exit_status, ExitSucceed::Stopped);
+		assert_eq!(output.cost, decode_cost(&input) + call.get_dispatch_info().weight);
+		assert_eq!(Balances::free_balance(alice()), 900);
+		assert_eq!(Balances::free_balance(bob()), 100);
+	});
+}
+
+#[test]
+fn filtered_call_is_rejected() {
+	new_test_ext().execute_with(|| {
+		let input = Call::System(frame_system::Call::remark { remark: vec![1, 2, 3] }).encode();
+
+		let result = TestDispatch::execute(&input, None, &context(), false);
+
+		assert_eq!(revert_message(result), b"invalid call".to_vec());
+	});
+}
+
+#[test]
+fn undecodable_call_is_rejected() {
+	new_test_ext().execute_with(|| {
+		let result = TestDispatch::execute(&[0xff, 0xff], None, &context(), false);
+
+		assert_eq!(revert_message(result), b"decode failed".to_vec());
+	});
+}
+
+#[test]
+fn failed_call_reverts_with_error_name() {
+	new_test_ext().execute_with(|| {
+		let input = transfer(1_001).encode();
+
+		let result = TestDispatch::execute(&input, None, &context(), false);
+
+		let message = String::from_utf8(revert_message(result)).unwrap();
+		assert!(message.starts_with("dispatch failed: Module(1, "), "{}", message);
+		assert!(message.ends_with("): InsufficientBalance"), "{}", message);
+		assert_eq!(Balances::free_balance(alice()), 1_000);
+	});
+}
+
+#[test]
+fn weight_must_fit_in_gas_limit() {
+	new_test_ext().execute_with(|| {
+		let call = transfer(100);
+		let input = call.encode();
+		let gas = decode_cost(&input) + call.get_dispatch_info().weight - 1;
+
+		let result = TestDispatch::execute(&input, Some(gas), &context(), false);
+
+		assert_eq!(result, Err(PrecompileFailure::Error { exit_status: ExitError::OutOfGas }));
+		assert_eq!(Balances::free_balance(bob()), 0);
+	});
+}
+
+#[test]
+fn dispatch_is_not_reentrant() {
+	new_test_ext().execute_with(|| {
+		let input = transfer(100).encode();
+
+		let result = pallet_evm::dispatch_from_evm(|| {
+			TestDispatch::execute(&input, None, &context(), false)
+		});
+
+		assert_eq!(revert_message(result), b"dispatch is not reentrant".to_vec());
+		assert_eq!(Balances::free_balance(bob()), 0);
+	});
+}
+
+#[test]
+fn dispatched_call_cannot_execute_evm_code() {
+	new_test_ext().execute_with(|| {
+		let call = Call::EVM(pallet_evm::Call::call {
+			source: alice(),
+			target: bob(),
+			input: Vec::new(),
+			value: U256::zero(),
+			gas_limit: 21_000,
+			max_fee_per_gas: U256::zero(),
+			max_priority_fee_per_gas: None,
+			nonce: None,
+			access_list: Vec::new(),
+		});
+
+		let result = pallet_evm::dispatch_from_evm(|| call.dispatch(Origin::root()));
+
+		assert_eq!(
+			result.map_err(|error| error.error),
+			Err(pallet_evm::Error::<Test>::DispatchedFromEvm.into())
+		);
+	});
+}
+
+#[test]
+fn dispatch_is_rejected_in_static_context() {
+	new_test_ext().execute_with(|| {
+		let input = transfer(100).encode();
+
+		let result = TestDispatch::execute(&input, None, &context(), true);
+
+		assert_eq!(
+			revert_message(result),
+			b"can't call non-static function in static context".to_vec()
+		);
+		assert_eq!(Balances::free_balance(bob()), 0);
+	});
+}
+
+#[test]
+fn dispatch_is_not_payable() {
+	new_test_ext().execute_with(|| {
+		let input = transfer(100).encode();
+		let context = Context { apparent_value: U256::one(), ..context() };
+
+		let result = TestDispatch::execute(&input, None, &context, false);
+
+		assert_eq!(revert_message(result), b"function is not payable".to_vec());
+	});
+}
+
+#[test]
+fn error_messages() {
+	assert_eq!(
+		dispatch_error_message(DispatchError::Module {
+			index: 10,
+			error: 2,
+			message: Some("InsufficientBalance"),
+		}),
+		"dispatch failed: Module(10, 2): InsufficientBalance"
+	);
+	assert_eq!(dispatch_error_message(DispatchError::BadOrigin), "dispatch failed: Bad origin");
+}
@@ -0,0 +1,500 @@
+// SPDX-License-Identifier: Apache-2.0
+// This file is part of Frontier.
+//
+// Copyright (c) 2020 Parity Technologies (UK) Ltd.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Solidity ABI encoding and decoding of precompile inputs and outputs.
+
+use crate::{revert, EvmResult};
+use alloc::string::String;
+use core::ops::Range;
+use sp_core::{H160, H256, U256};
+use sp_std::{convert::TryFrom, vec, vec::Vec};
+
+/// Solidity `address`.
+///
+/// `H160` alone is ambiguous, as `bytes20` is encoded differently.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct Address(pub H160);
+
+impl From<H160> for Address {
+	fn from(address: H160) -> Self {
+		Self(address)
+	}
+}
+
+impl From<Address> for H160 {
+	fn from(address: Address) -> Self {
+		address.0
+	}
+}
+
+/// Solidity `bytes`.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Bytes(pub Vec<u8>);
+
+impl Bytes {
+	pub fn as_bytes(&self) -> &[u8] {
+		&self.0
+	}
+
+	pub fn as_str(&self) -> Result<&str, core::str::Utf8Error> {
+		core::str::from_utf8(&self.0)
+	}
+}
+
+impl From<&[u8]> for Bytes {
+	fn from(bytes: &[u8]) -> Self {
+		Self(bytes.to_vec())
+	}
+}
+
+impl From<&str> for Bytes {
+	fn from(string: &str) -> Self {
+		Self(string.as_bytes().to_vec())
+	}
+}
+
+impl From<Vec<u8>> for Bytes {
+	fn from(bytes: Vec<u8>) -> Self {
+		Self(bytes)
+	}
+}
+
+impl From<Bytes> for Vec<u8> {
+	fn from(bytes: Bytes) -> Self {
+		bytes.0
+	}
+}
+
+/// Reads ABI encoded data.
+///
+/// A reader works on a "frame": offsets of dynamic values are relative to the start of the
+/// frame, which is the start of the call arguments, of an array content or of a tuple.
+#[derive(Clone, Copy, Debug)]
+pub struct EvmDataReader<'a> {
+	input: &'a [u8],
+	cursor: usize,
+}
+
+impl<'a> EvmDataReader<'a> {
+	/// Reader over ABI encoded data, without selector.
+	pub fn new(input: &'a [u8]) -> Self {
+		Self { input, cursor: 0 }
+	}
+
+	/// Reader over the arguments of a call, skipping the 4 bytes selector.
+	pub fn new_skip_selector(input: &'a [u8]) -> EvmResult<Self> {
+		if input.len() < 4 {
+			return Err(revert("tried to parse selector out of bounds"));
+		}
+
+		Ok(Self::new(&input[4..]))
+	}
+
+	/// Read the 4 bytes selector of a call and convert it to the function enum of a precompile.
+	pub fn read_selector<T: TryFrom<u32>>(input: &'a [u8]) -> EvmResult<T> {
+		if input.len() < 4 {
+			return Err(revert("tried to parse selector out of bounds"));
+		}
+
+		let mut buffer = [0u8; 4];
+		buffer.copy_from_slice(&input[0..4]);
+		T::try_from(u32::from_be_bytes(buffer)).map_err(|_| revert("unknown selector"))
+	}
+
+	/// Fails if there are less than `args` 32 bytes words left to read.
+	pub fn expect_arguments(&self, args: usize) -> EvmResult {
+		let needed = args.checked_mul(32).and_then(|len| len.checked_add(self.cursor));
+		match needed {
+			Some(needed) if needed <= self.input.len() => Ok(()),
+			_ => Err(revert("input doesn't match expected length")),
+		}
+	}
+
+	/// Read a value at the cursor.
+	pub fn read<T: EvmData>(&mut self) -> EvmResult<T> {
+		T::read(self)
+	}
+
+	/// Read `len` raw bytes at the cursor.
+	pub fn read_raw_bytes(&mut self, len: usize) -> EvmResult<&'a [u8]> {
+		let range = self.move_cursor(len)?;
+		Ok(&self.input[range])
+	}
+
+	/// Read an offset at the cursor and return a reader on the frame it points to.
+	pub fn read_pointer(&mut self) -> EvmResult<Self> {
+		let offset =
+			self.read_usize().map_err(|_| revert("tried to parse offset out of bounds"))?;
+		if offset >= self.input.len() {
+			return Err(revert("offset points out of bounds"));
+		}
+
+		Ok(Self::new(&self.input[offset..]))
+	}
+
+	/// Read a 32 bytes length or offset.
+	fn read_usize(&mut self) -> EvmResult<usize> {
+		let value: U256 = self.read()?;
+		if value > U256::from(u32::MAX) {
+			return Err(revert("length or offset too large"));
+		}
+
+		Ok(value.low_u32() as usize)
+	}
+
+	/// Read a 32 bytes word at the cursor.
+	fn read_word(&mut self) -> EvmResult<&'a [u8]> {
+		self.read_raw_bytes(32).map_err(|_| revert("tried to parse word out of bounds"))
+	}
+
+	fn move_cursor(&mut self, len: usize) -> EvmResult<Range<usize>> {
+		let start = self.cursor;
+		let end = start.checked_add(len).ok_or_else(|| revert("data reading cursor overflow"))?;
+		if end > self.input.len() {
+			return Err(revert("tried to read out of bounds"));
+		}
+
+		self.cursor = end;
+		Ok(start..end)
+	}
+}
+
+/// Writes ABI encoded data.
+#[derive(Clone, Debug, Default)]
+pub struct EvmDataWriter {
+	data: Vec<u8>,
+	offset_data: Vec<OffsetDatum>,
+	selector: Option<u32>,
+}
+
+/// Dynamic data appended after the head of a frame once it is complete.
+#[derive(Clone, Debug)]
+struct OffsetDatum {
+	/// Position of the offset word in the head.
+	offset_position: usize,
+	/// Encoded dynamic data.
+	data: Vec<u8>,
+	/// Bytes to subtract from the offset, when the frame does not start at the beginning of the
+	/// encoded data (e.g. array elements, which follow the array length).
+	offset_shift: usize,
+}
+
+impl EvmDataWriter {
+	/// Writer for ABI encoded data, e.g. a function output.
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Writer for a call, with its selector.
+	pub fn new_with_selector(selector: impl Into<u32>) -> Self {
+		Self { selector: Some(selector.into()), ..Default::default() }
+	}
+
+	/// Write a value.
+	pub fn write<T: EvmData>(mut self, value: T) -> Self {
+		T::write(&mut self, value);
+		self
+	}
+
+	/// Write raw bytes, which must be a multiple of 32 bytes long to keep a valid encoding.
+	pub fn write_raw_bytes(mut self, bytes: &[u8]) -> Self {
+		self.data.extend_from_slice(bytes);
+		self
+	}
+
+	/// Write an offset to dynamic data, which is appended when the frame is built.
+	pub fn write_pointer(&mut self, data: Vec<u8>) {
+		let offset_position = self.data.len();
+		H256::write(self, H256::repeat_byte(0xff));
+		self.offset_data.push(OffsetDatum { offset_position, data, offset_shift: 0 });
+	}
+
+	/// Encoded data, prefixed with the selector if any.
+	pub fn build(self) -> Vec<u8> {
+		let selector = self.selector;
+		let data = self.bake_data();
+		match selector {
+			Some(selector) => {
+				let mut output = selector.to_be_bytes().to_vec();
+				output.extend_from_slice(&data);
+				output
+			},
+			None => data,
+		}
+	}
+
+	fn bake_data(mut self) -> Vec<u8> {
+		for mut datum in self.offset_data {
+			let position = datum.offset_position;
+			let offset = self.data.len() - datum.offset_shift;
+			U256::from(offset).to_big_endian(&mut self.data[position..position + 32]);
+			self.data.append(&mut datum.data);
+		}
+		self.data
+	}
+}
+
+/// A type that can be ABI encoded and decoded.
+pub trait EvmData: Sized {
+	fn read(reader: &mut EvmDataReader) -> EvmResult<Self>;
+	fn write(writer: &mut EvmDataWriter, value: Self);
+	/// Whether the type is encoded in place (`true`) or behind an offset (`false`).
+	fn has_static_size() -> bool;
+}
+
+impl EvmData for H256 {
+	fn read(reader: &mut EvmDataReader) -> EvmResult<Self> {
+		Ok(H256::from_slice(reader.read_word()?))
+	}
+
+	fn write(writer: &mut EvmDataWriter, value: Self) {
+		writer.data.extend_from_slice(value.as_bytes());
+	}
+
+	fn has_static_size() -> bool {
+		true
+	}
+}
+
+impl EvmData for Address {
+	fn read(reader: &mut EvmDataReader) -> EvmResult<Self> {
+		let word = reader.read_word()?;
+		if word[..12].iter().any(|byte| *byte != 0) {
+			return Err(revert("invalid address"));
+		}
+
+		Ok(Address(H160::from_slice(&word[12..])))
+	}
+
+	fn write(writer: &mut EvmDataWriter, value: Self) {
+		H256::write(writer, value.0.into());
+	}
+
+	fn has_static_size() -> bool {
+		true
+	}
+}
+
+impl EvmData for U256 {
+	fn read(reader: &mut EvmDataReader) -> EvmResult<Self> {
+		Ok(U256::from_big_endian(reader.read_word()?))
+	}
+
+	fn write(writer: &mut EvmDataWriter, value: Self) {
+		let mut buffer = [0u8; 32];
+		value.to_big_endian(&mut buffer);
+		writer.data.extend_from_slice(&buffer);
+	}
+
+	fn has_static_size() -> bool {
+		true
+	}
+}
+
+macro_rules! impl_evmdata_for_uints {
+	($($uint:ty),*) => {$(
+		impl EvmData for $uint {
+			fn read(reader: &mut EvmDataReader) -> EvmResult<Self> {
+				const SIZE: usize = core::mem::size_of::<$uint>();
+				let word = reader.read_word()?;
+				if word[..32 - SIZE].iter().any(|byte| *byte != 0) {
+					return Err(revert(concat!("value too large for ", stringify!($uint))));
+				}
+
+				let mut buffer = [0u8; SIZE];
+				buffer.copy_from_slice(&word[32 - SIZE..]);
+				Ok(<$uint>::from_be_bytes(buffer))
+			}
+
+			fn write(writer: &mut EvmDataWriter, value: Self) {
+				U256::write(writer, value.into());
+			}
+
+			fn has_static_size() -> bool {
+				true
+			}
+		}
+	)*};
+}
+
+impl_evmdata_for_uints!(u8, u16, u32, u64, u128);
+
+macro_rules! impl_evmdata_for_ints {
+	($($int:ty),*) => {$(
+		impl EvmData for $int {
+			fn read(reader: &mut EvmDataReader) -> EvmResult<Self> {
+				const SIZE: usize = core::mem::size_of::<$int>();
+				let word = reader.read_word()?;
+				let mut buffer = [0u8; SIZE];
+				buffer.copy_from_slice(&word[32 - SIZE..]);
+				let value = <$int>::from_be_bytes(buffer);
+
+				// Values are sign-extended to 32 bytes.
+				let padding = if value < 0 { 0xff } else { 0x00 };
+				if word[..32 - SIZE].iter().any(|byte| *byte != padding) {
+					return Err(revert(concat!("value out of range for ", stringify!($int))));
+				}
+
+				Ok(value)
+			}
+
+			fn write(writer: &mut EvmDataWriter, value: Self) {
+				const SIZE: usize = core::mem::size_of::<$int>();
+				let mut buffer = if value < 0 { [0xffu8; 32] } else { [0u8; 32] };
+				buffer[32 - SIZE..].copy_from_slice(&value.to_be_bytes());
+				writer.data.extend_from_slice(&buffer);
+			}
+
+			fn has_static_size() -> bool {
+				true
+			}
+		}
+	)*};
+}
+
+impl_evmdata_for_ints!(i8, i16, i32, i64, i128);
+
+impl EvmData for bool {
+	fn read(reader: &mut EvmDataReader) -> EvmResult<Self> {
+		match u8::read(reader) {
+			Ok(0) => Ok(false),
+			Ok(1) => Ok(true),
+			_ => Err(revert("invalid bool")),
+		}
+	}
+
+	fn write(writer: &mut EvmDataWriter, value: Self) {
+		u8::write(writer, value as u8);
+	}
+
+	fn has_static_size() -> bool {
+		true
+	}
+}
+
+impl EvmData for Bytes {
+	fn read(reader: &mut EvmDataReader) -> EvmResult<Self> {
+		let mut inner = reader.read_pointer()?;
+		let len = inner.read_usize()?;
+		let bytes = inner
+			.read_raw_bytes(len)
+			.map_err(|_| revert("tried to parse bytes out of bounds"))?;
+
+		Ok(Bytes(bytes.to_vec()))
+	}
+
+	fn write(writer: &mut EvmDataWriter, value: Self) {
+		let len = value.0.len();
+		let mut data = value.0;
+		// Content is right-padded to a multiple of 32 bytes.
+		data.resize((len + 31) / 32 * 32, 0);
+
+		let inner = EvmDataWriter::new().write(U256::from(len)).write_raw_bytes(&data);
+		writer.write_pointer(inner.build());
+	}
+
+	fn has_static_size() -> bool {
+		false
+	}
+}
+
+impl EvmData for String {
+	fn read(reader: &mut EvmDataReader) -> EvmResult<Self> {
+		let bytes = Bytes::read(reader)?;
+		String::from_utf8(bytes.0).map_err(|_| revert("invalid utf8 string"))
+	}
+
+	fn write(writer: &mut EvmDataWriter, value: Self) {
+		Bytes::write(writer, Bytes(value.into_bytes()));
+	}
+
+	fn has_static_size() -> bool {
+		false
+	}
+}
+
+impl<T: EvmData> EvmData for Vec<T> {
+	fn read(reader: &mut EvmDataReader) -> EvmResult<Self> {
+		let mut inner = reader.read_pointer()?;
+		let len = inner.read_usize()?;
+		// Offsets of dynamic elements are relative to the first element.
+		let mut elements = EvmDataReader::new(&inner.input[inner.cursor..]);
+
+		let mut array = vec![];
+		for _ in 0..len {
+			array.push(T::read(&mut elements)?);
+		}
+		Ok(array)
+	}
+
+	fn write(writer: &mut EvmDataWriter, value: Self) {
+		let mut inner = EvmDataWriter::new().write(U256::from(value.len()));
+		for element in value {
+			T::write(&mut inner, element);
+		}
+		for datum in &mut inner.offset_data {
+			datum.offset_shift += 32;
+		}
+		writer.write_pointer(inner.bake_data());
+	}
+
+	fn has_static_size() -> bool {
+		false
+	}
+}
+
+macro_rules! impl_evmdata_for_tuples {
+	($(($($ident:ident),+)),+) => {$(
+		#[allow(non_snake_case)]
+		impl<$($ident: EvmData),+> EvmData for ($($ident,)+) {
+			fn read(reader: &mut EvmDataReader) -> EvmResult<Self> {
+				if Self::has_static_size() {
+					Ok(($($ident::read(reader)?,)+))
+				} else {
+					let mut inner = reader.read_pointer()?;
+					Ok(($($ident::read(&mut inner)?,)+))
+				}
+			}
+
+			fn write(writer: &mut EvmDataWriter, value: Self) {
+				let ($($ident,)+) = value;
+				if Self::has_static_size() {
+					$($ident::write(writer, $ident);)+
+				} else {
+					let mut inner = EvmDataWriter::new();
+					$($ident::write(&mut inner, $ident);)+
+					writer.write_pointer(inner.bake_data());
+				}
+			}
+
+			fn has_static_size() -> bool {
+				$($ident::has_static_size())&&+
+			}
+		}
+	)+};
+}
+
+impl_evmdata_for_tuples!(
+	(A),
+	(A, B),
+	(A, B, C),
+	(A, B, C, D),
+	(A, B, C, D, E),
+	(A, B, C, D, E, F),
+	(A, B, C, D, E, F, G),
+	(A, B, C, D, E, F, G, H)
+);
@@ -0,0 +1,427 @@
+// SPDX-License-Identifier: Apache-2.0
+// This file is part of Frontier.
+//
+// Copyright (c) 2020 Parity Technologies (UK) Ltd.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Utilities to write precompiles exposing a Solidity interface.
+//!
+//! - [`EvmDataReader`] and [`EvmDataWriter`] decode call arguments and encode outputs,
+//!   according to the Solidity ABI.
+//! - Functions are selected by converting the first 4 bytes of the input into an enum, using
+//!   [`EvmDataReader::read_selector`].
+//! - [`revert`] and [`Gasometer::revert`] produce an `Error(string)` output that Solidity
+//!   callers and wallets can decode.
+//! - [`Gasometer`] and [`RuntimeHelper`] account for the gas used, including storage accesses.
+//! - [`RuntimeHelper::call`] calls a contract on behalf of an account.
+//! - [`RuntimeHelper::try_dispatch`] dispatches a runtime call, charging its weight as gas.
+//! - [`LogsBuilder`] creates the logs (events) emitted by a precompile.
+//! - The `testing` module calls precompiles and checks their output, cost and logs.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+mod data;
+#[cfg(feature = "std")]
+pub mod testing;
+#[cfg(test)]
+mod tests;
+
+pub use data::{Address, Bytes, EvmData, EvmDataReader, EvmDataWriter};
+
+use evm::{ExitReason, ExitRevert};
+use fp_evm::{Context, ExitError, ExitSucceed, Log, PrecompileFailure, PrecompileOutput};
+use frame_support::{
+	dispatch::{Dispatchable, GetDispatchInfo, PostDispatchInfo},
+	storage::{with_transaction, TransactionOutcome},
+	traits::Get,
+};
+use pallet_evm::{AddressMapping, GasWeightMapping, Runner};
+use sp_core::{H160, H256, U256};
+use sp_runtime::DispatchError;
+use sp_std::{marker::PhantomData, vec, vec::Vec};
+
+/// Result of the functions of a precompile.
+pub type EvmResult<T = ()> = Result<T, PrecompileFailure>;
+
+/// Selector of `Error(string)`, the output of `revert("reason")` in Solidity.
+pub const ERROR_SELECTOR: u32 = 0x08c379a0;
+
+/// Gas charged for a log, as with the `LOG*` opcodes.
+const LOG_GAS: u64 = 375;
+const LOG_TOPIC_GAS: u64 = 375;
+const LOG_DATA_GAS: u64 = 8;
+
+/// Keccak-256 hash, e.g. of an event signature.
+pub fn keccak256(data: impl AsRef<[u8]>) -> H256 {
+	H256(sp_io::hashing::keccak_256(data.as_ref()))
+}
+
+/// Selector of a function signature, such as `"transfer(address,uint256)"`.
+pub fn selector(signature: &str) -> u32 {
+	let hash = keccak256(signature).0;
+	u32::from_be_bytes([hash[0], hash[1], hash[2], hash[3]])
+}
+
+/// `Error(string)` encoding of a revert reason.
+pub fn encode_revert_message(message: &[u8]) -> Vec<u8> {
+	EvmDataWriter::new_with_selector(ERROR_SELECTOR)
+		.write(Bytes(message.to_vec()))
+		.build()
+}
+
+/// Revert with a reason, without charging gas.
+pub fn revert(message: impl AsRef<[u8]>) -> PrecompileFailure {
+	PrecompileFailure::Revert {
+		exit_status: ExitRevert::Reverted,
+		output: encode_revert_message(message.as_ref()),
+		cost: 0,
+	}
+}
+
+/// Successful execution returning `output`.
+pub fn succeed(cost: u64, output: Vec<u8>, logs: Vec<Log>) -> PrecompileOutput {
+	PrecompileOutput { exit_status: ExitSucceed::Returned, cost, output, logs }
+}
+
+/// State mutability of a function, as declared in its Solidity interface.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FunctionModifier {
+	/// Can neither receive value nor modify state.
+	View,
+	/// Can modify state but not receive value.
+	NonPayable,
+	/// Can modify state and receive value.
+	Payable,
+}
+
+/// Gas used by a precompile, bounded by the gas limit of its call.
+#[derive(Clone, Copy, Debug)]
+pub struct Gasometer {
+	target_gas: Option<u64>,
+	used_gas: u64,
+}
+
+impl Gasometer {
+	pub fn new(target_gas: Option<u64>) -> Self {
+		Self { target_gas, used_gas: 0 }
+	}
+
+	pub fn used_gas(&self) -> u64 {
+		self.used_gas
+	}
+
+	/// Charge `cost`, failing with out of gas if the limit is exceeded.
+	pub fn record_cost(&mut self, cost: u64) -> EvmResult {
+		self.used_gas = self
+			.used_gas
+			.checked_add(cost)
+			.ok_or(PrecompileFailure::Error { exit_status: ExitError::OutOfGas })?;
+
+		match self.target_gas {
+			Some(target_gas) if self.used_gas > target_gas => {
+				Err(PrecompileFailure::Error { exit_status: ExitError::OutOfGas })
+			},
+			_ => Ok(()),
+		}
+	}
+
+	/// Charge a log with `topics` topics and `data_len` bytes of data.
+	pub fn record_log_costs_manual(&mut self, topics: usize, data_len: usize) -> EvmResult {
+		self.record_cost(log_costs(topics, data_len)?)
+	}
+
+	/// Charge already built logs.
+	pub fn record_log_costs(&mut self, logs: &[Log]) -> EvmResult {
+		for log in logs {
+			self.record_log_costs_manual(log.topics.len(), log.data.len())?;
+		}
+		Ok(())
+	}
+
+	/// Gas left, if the call has a gas limit.
+	pub fn remaining_gas(&self) -> EvmResult<Option<u64>> {
+		match self.target_gas {
+			Some(target_gas) => target_gas
+				.checked_sub(self.used_gas)
+				.map(Some)
+				.ok_or(PrecompileFailure::Error { exit_status: ExitError::OutOfGas }),
+			None => Ok(None),
+		}
+	}
+
+	/// Revert with a reason, charging the gas used so far.
+	pub fn revert(&self, message: impl AsRef<[u8]>) -> PrecompileFailure {
+		PrecompileFailure::Revert {
+			exit_status: ExitRevert::Reverted,
+			output: encode_revert_message(message.as_ref()),
+			cost: self.used_gas,
+		}
+	}
+
+	/// Check that a call respects the state mutability of the called function.
+	pub fn check_function_modifier(
+		&self,
+		context: &Context,
+		is_static: bool,
+		modifier: FunctionModifier,
+	) -> EvmResult {
+		if is_static && modifier != FunctionModifier::View {
+			return Err(self.revert("can't call non-static function in static context"));
+		}
+
+		if modifier != FunctionModifier::Payable && context.apparent_value > U256::zero() {
+			return Err(self.revert("function is not payable"));
+		}
+
+		Ok(())
+	}
+}
+
+/// Gas cost of a log with `topics` topics and `data_len` bytes of data.
+pub fn log_costs(topics: usize, data_len: usize) -> EvmResult<u64> {
+	let out_of_gas = || PrecompileFailure::Error { exit_status: ExitError::OutOfGas };
+	let topics_cost = LOG_TOPIC_GAS.checked_mul(topics as u64).ok_or_else(out_of_gas)?;
+	let data_cost = LOG_DATA_GAS.checked_mul(data_len as u64).ok_or_else(out_of_gas)?;
+
+	LOG_GAS
+		.checked_add(topics_cost)
+		.and_then(|cost| cost.checked_add(data_cost))
+		.ok_or_else(out_of_gas)
+}
+
+/// Builds the logs emitted by a precompile.
+pub struct LogsBuilder {
+	address: H160,
+}
+
+impl LogsBuilder {
+	/// Builder for the logs of the precompile at `address`.
+	pub fn new(address: H160) -> Self {
+		Self { address }
+	}
+
+	pub fn log0(&self, data: impl Into<Vec<u8>>) -> Log {
+		Log { address: self.address, topics: vec![], data: data.into() }
+	}
+
+	pub fn log1(&self, topic0: impl Into<H256>, data: impl Into<Vec<u8>>) -> Log {
+		Log { address: self.address, topics: vec![topic0.into()], data: data.into() }
+	}
+
+	pub fn log2(
+		&self,
+		topic0: impl Into<H256>,
+		topic1: impl Into<H256>,
+		data: impl Into<Vec<u8>>,
+	) -> Log {
+		Log { address: self.address, topics: vec![topic0.into(), topic1.into()], data: data.into() }
+	}
+
+	pub fn log3(
+		&self,
+		topic0: impl Into<H256>,
+		topic1: impl Into<H256>,
+		topic2: impl Into<H256>,
+		data: impl Into<Vec<u8>>,
+	) -> Log {
+		Log {
+			address: self.address,
+			topics: vec![topic0.into(), topic1.into(), topic2.into()],
+			data: data.into(),
+		}
+	}
+
+	pub fn log4(
+		&self,
+		topic0: impl Into<H256>,
+		topic1: impl Into<H256>,
+		topic2: impl Into<H256>,
+		topic3: impl Into<H256>,
+		data: impl Into<Vec<u8>>,
+	) -> Log {
+		Log {
+			address: self.address,
+			topics: vec![topic0.into(), topic1.into(), topic2.into(), topic3.into()],
+			data: data.into(),
+		}
+	}
+}
+
+/// Revert reason of a failed dispatch, naming the error of the pallet when there is one.
+pub fn dispatch_error_message(error: DispatchError) -> alloc::string::String {
+	match error {
+		DispatchError::Module { index, error, message } => alloc::format!(
+			"dispatch failed: Module({}, {}): {}",
+			index,
+			error,
+			message.unwrap_or("unknown error")
+		),
+		error => alloc::format!("dispatch failed: {}", <&'static str>::from(error)),
+	}
+}
+
+/// Gas costs depending on the runtime configuration.
+pub struct RuntimeHelper<Runtime>(PhantomData<Runtime>);
+
+impl<Runtime: pallet_evm::Config> RuntimeHelper<Runtime> {
+	/// Gas equivalent of the weight of a storage read.
+	pub fn db_read_gas_cost() -> u64 {
+		<Runtime as pallet_evm::Config>::GasWeightMapping::weight_to_gas(
+			<Runtime as frame_system::Config>::DbWeight::get().read,
+		)
+	}
+
+	/// Gas equivalent of the weight of a storage write.
+	pub fn db_write_gas_cost() -> u64 {
+		<Runtime as pallet_evm::Config>::GasWeightMapping::weight_to_gas(
+			<Runtime as frame_system::Config>::DbWeight::get().write,
+		)
+	}
+
+	/// Dispatch `call` as `origin`, charging its weight as gas.
+	///
+	/// Fails with out of gas if the weight of the call exceeds the remaining gas. The call is
+	/// dispatched with `pallet_evm::dispatch_from_evm`, so that it cannot execute EVM code again.
+	/// A failed call is rolled back and reverts with `dispatch_error_message`.
+	pub fn try_dispatch(
+		origin: Runtime::AccountId,
+		call: Runtime::Call,
+		gasometer: &mut Gasometer,
+	) -> EvmResult<PostDispatchInfo>
+	where
+		Runtime::Call: Dispatchable<PostInfo = PostDispatchInfo> + GetDispatchInfo,
+		<Runtime::Call as Dispatchable>::Origin: From<Option<Runtime::AccountId>>,
+	{
+		let info = call.get_dispatch_info();
+		if let Some(gas) = gasometer.remaining_gas()? {
+			if info.weight > Runtime::GasWeightMapping::gas_to_weight(gas) {
+				return Err(PrecompileFailure::Error { exit_status: ExitError::OutOfGas });
+			}
+		}
+
+		let result = pallet_evm::dispatch_from_evm(|| {
+			with_transaction(|| match call.dispatch(Some(origin).into()) {
+				Ok(post_info) => TransactionOutcome::Commit(Ok(post_info)),
+				Err(error) => TransactionOutcome::Rollback(Err(error)),
+			})
+		});
+
+		match result {
+			Ok(post_info) => {
+				gasometer.record_cost(Runtime::GasWeightMapping::weight_to_gas(
+					post_info.actual_weight.unwrap_or(info.weight),
+				))?;
+				Ok(post_info)
+			},
+			Err(error) => {
+				gasometer.record_cost(Runtime::GasWeightMapping::weight_to_gas(
+					error.post_info.actual_weight.unwrap_or(info.weight),
+				))?;
+				Err(gasometer.revert(dispatch_error_message(error.error)))
+			},
+		}
+	}
+
+	/// Call `to` from `source` on top of the current state, with at most `gas_limit` gas.
+	///
+	/// Precompiles have no access to the executor running them, so the call is made by
+	/// `pallet_evm::Config::Runner`. It pays no fee, the intrinsic gas of a transaction is not
+	/// part of its used gas and the nonce increment of `source` is undone.
+	///
+	/// Unlike a `CALL` made by the running EVM, the call sees `source` as `ORIGIN` and a zero
+	/// `GASPRICE`, starts again at depth zero and does not share the accessed accounts and
+	/// storage slots (EIP-2929) of the running EVM. Its storage changes are still reverted with
+	/// the calling frame.
+	pub fn call(source: H160, to: H160, value: U256, data: Vec<u8>, gas_limit: u64) -> Subcall {
+		let config = pallet_evm::Pallet::<Runtime>::evm_config();
+		let intrinsic_gas = intrinsic_gas(&config, &data);
+		let account = Runtime::AddressMapping::into_account_id(source);
+		let nonce = frame_system::Pallet::<Runtime>::account_nonce(&account);
+		let nonce_reference = pallet_evm::Pallet::<Runtime>::has_nonce_reference(&account);
+
+		let result = Runtime::Runner::call(
+			source,
+			to,
+			data,
+			value,
+			gas_limit.saturating_add(intrinsic_gas),
+			None,
+			None,
+			None,
+			Vec::new(),
+			&config,
+		);
+		if frame_system::Pallet::<Runtime>::account_nonce(&account) != nonce {
+			frame_system::Account::<Runtime>::mutate(&account, |info| info.nonce = nonce);
+			// Incrementing the nonce gave the account its nonce reference.
+			if !nonce_reference {
+				pallet_evm::Pallet::<Runtime>::release_nonce_reference(&account);
+			}
+		}
+
+		match result {
+			Ok(info) => Subcall {
+				reason: info.exit_reason,
+				output: info.value,
+				used_gas: info.used_gas.low_u64().saturating_sub(intrinsic_gas),
+				logs: info.logs,
+			},
+			Err(error) => {
+				let error: DispatchError = error.into();
+				Subcall {
+					reason: ExitReason::Revert(ExitRevert::Reverted),
+					output: encode_revert_message(<&'static str>::from(error).as_bytes()),
+					used_gas: 0,
+					logs: Vec::new(),
+				}
+			},
+		}
+	}
+}
+
+/// Outcome of [`RuntimeHelper::call`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Subcall {
+	pub reason: ExitReason,
+	pub output: Vec<u8>,
+	pub used_gas: u64,
+	pub logs: Vec<Log>,
+}
+
+impl Subcall {
+	/// Revert with the output of the call if it reverted, or with `message` otherwise.
+	pub fn revert(self, gasometer: &Gasometer, message: impl AsRef<[u8]>) -> PrecompileFailure {
+		match self.reason {
+			ExitReason::Revert(_) => PrecompileFailure::Revert {
+				exit_status: ExitRevert::Reverted,
+				output: self.output,
+				cost: gasometer.used_gas(),
+			},
+			_ => gasometer.revert(message),
+		}
+	}
+}
+
+/// Gas charged by the runner to start a call.
+fn intrinsic_gas(config: &evm::Config, data: &[u8]) -> u64 {
+	let zero_bytes = data.iter().filter(|byte| **byte == 0).count() as u64;
+	let non_zero_bytes = data.len() as u64 - zero_bytes;
+
+	config
+		.gas_transaction_call
+		.saturating_add(zero_bytes.saturating_mul(config.gas_transaction_zero_data))
+		.saturating_add(non_zero_bytes.saturating_mul(config.gas_transaction_non_zero_data))
+}
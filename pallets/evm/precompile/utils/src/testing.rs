@@ -0,0 +1,210 @@
+// SPDX-License-Identifier: Apache-2.0
+// This file is part of Frontier.
+//
+// Copyright (c) 2020 Parity Technologies (UK) Ltd.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Test harness calling a precompile set and checking its output, cost and logs.
+//!
+//! ```ignore
+//! precompiles()
+//!     .prepare_test(alice, precompile_address, EvmDataWriter::new_with_selector(Action::BalanceOf)
+//!         .write(Address(alice))
+//!         .build())
+//!     .expect_cost(1_000)
+//!     .expect_no_logs()
+//!     .execute_returns(EvmDataWriter::new().write(U256::from(1_000)).build());
+//! ```
+
+use crate::{Bytes, EvmDataReader, ERROR_SELECTOR};
+use fp_evm::{
+	Context, ExitError, ExitSucceed, Log, PrecompileFailure, PrecompileOutput, PrecompileResult,
+	PrecompileSet,
+};
+use sp_core::{H160, U256};
+
+/// Decode the reason of an `Error(string)` revert output, or return the raw output.
+pub fn decode_revert_message(output: &[u8]) -> Vec<u8> {
+	if output.len() >= 4 && output[..4] == ERROR_SELECTOR.to_be_bytes() {
+		if let Ok(Bytes(message)) = EvmDataReader::new(&output[4..]).read::<Bytes>() {
+			return message;
+		}
+	}
+	output.to_vec()
+}
+
+/// A call to a precompile set, along with the expected effects.
+pub struct PrecompilesTester<'p, P> {
+	precompiles: &'p P,
+	to: H160,
+	context: Context,
+	input: Vec<u8>,
+	target_gas: Option<u64>,
+	is_static: bool,
+	expected_cost: Option<u64>,
+	expected_logs: Option<Vec<Log>>,
+}
+
+impl<'p, P: PrecompileSet> PrecompilesTester<'p, P> {
+	pub fn new(
+		precompiles: &'p P,
+		from: impl Into<H160>,
+		to: impl Into<H160>,
+		input: Vec<u8>,
+	) -> Self {
+		let to = to.into();
+		Self {
+			precompiles,
+			to,
+			context: Context { address: to, caller: from.into(), apparent_value: U256::zero() },
+			input,
+			target_gas: None,
+			is_static: false,
+			expected_cost: None,
+			expected_logs: None,
+		}
+	}
+
+	pub fn with_value(mut self, value: impl Into<U256>) -> Self {
+		self.context.apparent_value = value.into();
+		self
+	}
+
+	pub fn with_target_gas(mut self, target_gas: Option<u64>) -> Self {
+		self.target_gas = target_gas;
+		self
+	}
+
+	pub fn with_static_call(mut self, is_static: bool) -> Self {
+		self.is_static = is_static;
+		self
+	}
+
+	pub fn expect_cost(mut self, cost: u64) -> Self {
+		self.expected_cost = Some(cost);
+		self
+	}
+
+	pub fn expect_no_logs(mut self) -> Self {
+		self.expected_logs = Some(vec![]);
+		self
+	}
+
+	pub fn expect_log(mut self, log: Log) -> Self {
+		self.expected_logs = Some({
+			let mut logs = self.expected_logs.unwrap_or_default();
+			logs.push(log);
+			logs
+		});
+		self
+	}
+
+	fn execute(&self) -> Option<PrecompileResult> {
+		self.precompiles.execute(
+			self.to,
+			&self.input,
+			self.target_gas,
+			&self.context,
+			self.is_static,
+		)
+	}
+
+	fn assert_output(&self, output: &PrecompileOutput) {
+		if let Some(cost) = self.expected_cost {
+			assert_eq!(output.cost, cost, "wrong cost");
+		}
+		if let Some(logs) = &self.expected_logs {
+			assert_eq!(&output.logs, logs, "wrong logs");
+		}
+	}
+
+	/// Execute the call, expecting it to succeed with `expected` as output.
+	pub fn execute_returns(self, expected: Vec<u8>) {
+		match self.execute() {
+			Some(Ok(output)) => {
+				assert_eq!(output.exit_status, ExitSucceed::Returned);
+				assert_eq!(
+					output.output,
+					expected,
+					"wrong output: {:?} ({})",
+					to_hex(&output.output),
+					String::from_utf8_lossy(&decode_revert_message(&output.output)),
+				);
+				self.assert_output(&output);
+			},
+			other => panic!("expected success, got {:?}", other),
+		}
+	}
+
+	/// Execute the call, expecting a revert whose decoded reason passes `check`.
+	pub fn execute_reverts(self, check: impl Fn(&[u8]) -> bool) {
+		match self.execute() {
+			Some(Err(PrecompileFailure::Revert { output, cost, .. })) => {
+				let message = decode_revert_message(&output);
+				assert!(
+					check(&message),
+					"unexpected revert reason: {}",
+					String::from_utf8_lossy(&message)
+				);
+				if let Some(expected_cost) = self.expected_cost {
+					assert_eq!(cost, expected_cost, "wrong cost");
+				}
+			},
+			other => panic!("expected revert, got {:?}", other),
+		}
+	}
+
+	/// Execute the call, expecting it to fail with `error`.
+	pub fn execute_error(self, error: ExitError) {
+		match self.execute() {
+			Some(Err(PrecompileFailure::Error { exit_status })) => assert_eq!(exit_status, error),
+			other => panic!("expected error {:?}, got {:?}", error, other),
+		}
+	}
+
+	/// Execute the call, expecting the address to be a precompile.
+	pub fn execute_some(self) {
+		assert!(self.execute().is_some(), "expected a precompile at {:?}", self.to);
+	}
+
+	/// Execute the call, expecting the address not to be a precompile.
+	pub fn execute_none(self) {
+		assert!(self.execute().is_none(), "expected no precompile at {:?}", self.to);
+	}
+}
+
+fn to_hex(data: &[u8]) -> String {
+	data.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Entry point of the harness, for any precompile set.
+pub trait PrecompileTesterExt: PrecompileSet + Sized {
+	fn prepare_test(
+		&self,
+		from: impl Into<H160>,
+		to: impl Into<H160>,
+		input: impl Into<Vec<u8>>,
+	) -> PrecompilesTester<'_, Self>;
+}
+
+impl<T: PrecompileSet> PrecompileTesterExt for T {
+	fn prepare_test(
+		&self,
+		from: impl Into<H160>,
+		to: impl Into<H160>,
+		input: impl Into<Vec<u8>>,
+	) -> PrecompilesTester<'_, Self> {
+		PrecompilesTester::new(self, from, to, input.into())
+	}
+}
@@ -0,0 +1,324 @@
+// SPDX-License-Identifier: Apache-2.0
+// This file is part of Frontier.
+//
+// Copyright (c) 2020 Parity Technologies (UK) Ltd.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::*;
+use crate::testing::*;
+use fp_evm::{PrecompileResult, PrecompileSet};
+
+fn hex(data: &str) -> Vec<u8> {
+	hex::decode(data.replace(|c: char| c.is_whitespace(), "")).unwrap()
+}
+
+#[test]
+fn selector_matches_signature() {
+	assert_eq!(selector("sam(bytes,bool,uint256[])"), 0xa5643bf2);
+	assert_eq!(selector("Error(string)"), ERROR_SELECTOR);
+}
+
+#[test]
+fn write_and_read_static_values() {
+	let address = Address(H160::repeat_byte(0x11));
+	let data = EvmDataWriter::new()
+		.write(U256::from(42))
+		.write(7u8)
+		.write(true)
+		.write(address)
+		.write(H256::repeat_byte(0x22))
+		.build();
+	assert_eq!(data.len(), 5 * 32);
+	assert_eq!(&data[32 * 3..32 * 3 + 12], &[0u8; 12]);
+
+	let mut reader = EvmDataReader::new(&data);
+	assert!(reader.expect_arguments(5).is_ok());
+	assert!(reader.expect_arguments(6).is_err());
+	assert_eq!(reader.read::<U256>().unwrap(), U256::from(42));
+	assert_eq!(reader.read::<u8>().unwrap(), 7);
+	assert!(reader.read::<bool>().unwrap());
+	assert_eq!(reader.read::<Address>().unwrap(), address);
+	assert_eq!(reader.read::<H256>().unwrap(), H256::repeat_byte(0x22));
+	assert!(reader.read::<U256>().is_err());
+}
+
+#[test]
+fn signed_integers_are_sign_extended() {
+	let data = EvmDataWriter::new().write(-1i32).write(5i64).build();
+	assert_eq!(&data[..32], &[0xffu8; 32]);
+
+	let mut reader = EvmDataReader::new(&data);
+	assert_eq!(reader.read::<i32>().unwrap(), -1);
+	assert_eq!(reader.read::<i64>().unwrap(), 5);
+
+	// 128 does not fit in an i8.
+	let data = EvmDataWriter::new().write(128u32).build();
+	assert!(EvmDataReader::new(&data).read::<i8>().is_err());
+}
+
+#[test]
+fn out_of_range_values_are_rejected() {
+	let data = EvmDataWriter::new().write(256u32).build();
+	assert!(EvmDataReader::new(&data).read::<u8>().is_err());
+	assert!(EvmDataReader::new(&data).read::<bool>().is_err());
+
+	let data = EvmDataWriter::new().write(H256::repeat_byte(0xff)).build();
+	assert!(EvmDataReader::new(&data).read::<Address>().is_err());
+}
+
+#[test]
+fn encode_solidity_documentation_example() {
+	// sam("dave", true, [1, 2, 3])
+	let expected = hex("a5643bf2
+		0000000000000000000000000000000000000000000000000000000000000060
+		0000000000000000000000000000000000000000000000000000000000000001
+		00000000000000000000000000000000000000000000000000000000000000a0
+		0000000000000000000000000000000000000000000000000000000000000004
+		6461766500000000000000000000000000000000000000000000000000000000
+		0000000000000000000000000000000000000000000000000000000000000003
+		0000000000000000000000000000000000000000000000000000000000000001
+		0000000000000000000000000000000000000000000000000000000000000002
+		0000000000000000000000000000000000000000000000000000000000000003");
+	let data = EvmDataWriter::new_with_selector(selector("sam(bytes,bool,uint256[])"))
+		.write(Bytes::from("dave"))
+		.write(true)
+		.write(vec![U256::from(1), U256::from(2), U256::from(3)])
+		.build();
+	assert_eq!(data, expected);
+
+	assert_eq!(EvmDataReader::read_selector::<u32>(&data).unwrap(), 0xa5643bf2);
+	let mut reader = EvmDataReader::new_skip_selector(&data).unwrap();
+	assert_eq!(reader.read::<Bytes>().unwrap().as_str(), Ok("dave"));
+	assert!(reader.read::<bool>().unwrap());
+	assert_eq!(
+		reader.read::<Vec<U256>>().unwrap(),
+		vec![U256::from(1), U256::from(2), U256::from(3)]
+	);
+}
+
+#[test]
+fn encode_nested_dynamic_arrays() {
+	// g([[1, 2], [3]], ["one", "two", "three"])
+	let expected = hex("0000000000000000000000000000000000000000000000000000000000000040
+		0000000000000000000000000000000000000000000000000000000000000140
+		0000000000000000000000000000000000000000000000000000000000000002
+		0000000000000000000000000000000000000000000000000000000000000040
+		00000000000000000000000000000000000000000000000000000000000000a0
+		0000000000000000000000000000000000000000000000000000000000000002
+		0000000000000000000000000000000000000000000000000000000000000001
+		0000000000000000000000000000000000000000000000000000000000000002
+		0000000000000000000000000000000000000000000000000000000000000001
+		0000000000000000000000000000000000000000000000000000000000000003
+		0000000000000000000000000000000000000000000000000000000000000003
+		0000000000000000000000000000000000000000000000000000000000000060
+		00000000000000000000000000000000000000000000000000000000000000a0
+		00000000000000000000000000000000000000000000000000000000000000e0
+		0000000000000000000000000000000000000000000000000000000000000003
+		6f6e650000000000000000000000000000000000000000000000000000000000
+		0000000000000000000000000000000000000000000000000000000000000003
+		74776f0000000000000000000000000000000000000000000000000000000000
+		0000000000000000000000000000000000000000000000000000000000000005
+		7468726565000000000000000000000000000000000000000000000000000000");
+	let numbers = vec![vec![U256::from(1), U256::from(2)], vec![U256::from(3)]];
+	let strings = vec![String::from("one"), String::from("two"), String::from("three")];
+	let data = EvmDataWriter::new().write(numbers.clone()).write(strings.clone()).build();
+	assert_eq!(data, expected);
+
+	let mut reader = EvmDataReader::new(&data);
+	assert_eq!(reader.read::<Vec<Vec<U256>>>().unwrap(), numbers);
+	assert_eq!(reader.read::<Vec<String>>().unwrap(), strings);
+}
+
+#[test]
+fn encode_tuples() {
+	// Static tuples are encoded in place.
+	let data = EvmDataWriter::new()
+		.write((U256::from(1), Address(H160::repeat_byte(0x11))))
+		.build();
+	assert_eq!(data.len(), 64);
+	assert_eq!(
+		EvmDataReader::new(&data).read::<(U256, Address)>().unwrap(),
+		(U256::from(1), Address(H160::repeat_byte(0x11)))
+	);
+
+	// Dynamic tuples are encoded behind an offset.
+	let value = vec![(U256::from(1), Bytes::from("a")), (U256::from(2), Bytes::from("bc"))];
+	let data = EvmDataWriter::new().write(value.clone()).build();
+	assert_eq!(EvmDataReader::new(&data).read::<Vec<(U256, Bytes)>>().unwrap(), value);
+}
+
+#[test]
+fn malformed_dynamic_data_is_rejected() {
+	// Offset pointing after the end of the input.
+	let data = EvmDataWriter::new().write(U256::from(64)).build();
+	assert!(EvmDataReader::new(&data).read::<Bytes>().is_err());
+
+	// Length larger than the data.
+	let data = EvmDataWriter::new()
+		.write(U256::from(32))
+		.write(U256::from(33))
+		.write(H256::zero())
+		.build();
+	assert!(EvmDataReader::new(&data).read::<Bytes>().is_err());
+
+	// Huge array length.
+	let data = EvmDataWriter::new().write(U256::from(32)).write(U256::from(u32::MAX)).build();
+	assert!(EvmDataReader::new(&data).read::<Vec<U256>>().is_err());
+
+	// Missing selector.
+	assert!(EvmDataReader::read_selector::<u32>(&[0x12, 0x34]).is_err());
+}
+
+#[test]
+fn revert_outputs_error_string() {
+	let expected = hex("08c379a0
+		0000000000000000000000000000000000000000000000000000000000000020
+		0000000000000000000000000000000000000000000000000000000000000004
+		7465737400000000000000000000000000000000000000000000000000000000");
+	match revert("test") {
+		PrecompileFailure::Revert { output, cost, .. } => {
+			assert_eq!(output, expected);
+			assert_eq!(cost, 0);
+			assert_eq!(decode_revert_message(&output), b"test".to_vec());
+		},
+		other => panic!("unexpected failure {:?}", other),
+	}
+}
+
+#[test]
+fn gasometer_enforces_target_gas() {
+	let mut gasometer = Gasometer::new(Some(1_000));
+	assert!(gasometer.record_cost(600).is_ok());
+	assert_eq!(gasometer.remaining_gas().unwrap(), Some(400));
+	assert!(gasometer.record_log_costs_manual(0, 0).is_ok());
+	assert_eq!(gasometer.used_gas(), 975);
+	assert_eq!(
+		gasometer.record_cost(26),
+		Err(PrecompileFailure::Error { exit_status: ExitError::OutOfGas })
+	);
+
+	let mut unbounded = Gasometer::new(None);
+	assert!(unbounded.record_cost(u64::MAX).is_ok());
+	assert!(unbounded.record_cost(1).is_err());
+}
+
+#[test]
+fn log_costs_follow_log_opcodes() {
+	assert_eq!(log_costs(0, 0).unwrap(), 375);
+	assert_eq!(log_costs(3, 32).unwrap(), 375 + 3 * 375 + 32 * 8);
+
+	let log = LogsBuilder::new(H160::repeat_byte(0x01)).log2(
+		H256::repeat_byte(0x02),
+		H160::repeat_byte(0x03),
+		EvmDataWriter::new().write(U256::from(1)).build(),
+	);
+	assert_eq!(log.topics.len(), 2);
+	assert_eq!(log.topics[1], H256::from(H160::repeat_byte(0x03)));
+
+	let mut gasometer = Gasometer::new(None);
+	gasometer.record_log_costs(&[log]).unwrap();
+	assert_eq!(gasometer.used_gas(), 375 + 2 * 375 + 32 * 8);
+}
+
+#[test]
+fn function_modifiers_are_checked() {
+	let context =
+		Context { address: H160::zero(), caller: H160::zero(), apparent_value: U256::from(1) };
+	let gasometer = Gasometer::new(None);
+	assert!(gasometer
+		.check_function_modifier(&context, false, FunctionModifier::Payable)
+		.is_ok());
+	assert!(gasometer
+		.check_function_modifier(&context, false, FunctionModifier::NonPayable)
+		.is_err());
+
+	let context = Context { apparent_value: U256::zero(), ..context };
+	assert!(gasometer
+		.check_function_modifier(&context, true, FunctionModifier::View)
+		.is_ok());
+	assert!(gasometer
+		.check_function_modifier(&context, true, FunctionModifier::NonPayable)
+		.is_err());
+}
+
+/// Precompile at address 1 returning the sum of two numbers and logging the caller.
+struct Adder;
+
+const ADD_GAS: u64 = 100;
+
+impl PrecompileSet for Adder {
+	fn execute(
+		&self,
+		address: H160,
+		input: &[u8],
+		target_gas: Option<u64>,
+		context: &Context,
+		_is_static: bool,
+	) -> Option<PrecompileResult> {
+		if !self.is_precompile(address) {
+			return None;
+		}
+
+		let mut gasometer = Gasometer::new(target_gas);
+		Some((|| {
+			gasometer.record_cost(ADD_GAS)?;
+			if EvmDataReader::read_selector::<u32>(input)? != selector("add(uint256,uint256)") {
+				return Err(gasometer.revert("unknown selector"));
+			}
+			let mut reader = EvmDataReader::new_skip_selector(input)?;
+			reader.expect_arguments(2)?;
+			let (a, b): (U256, U256) = (reader.read()?, reader.read()?);
+			let sum = a.checked_add(b).ok_or_else(|| gasometer.revert("overflow"))?;
+
+			let log = LogsBuilder::new(address).log1(context.caller, vec![]);
+			gasometer.record_log_costs(core::slice::from_ref(&log))?;
+			Ok(succeed(gasometer.used_gas(), EvmDataWriter::new().write(sum).build(), vec![log]))
+		})())
+	}
+
+	fn is_precompile(&self, address: H160) -> bool {
+		address == H160::from_low_u64_be(1)
+	}
+}
+
+fn add_input(a: U256, b: U256) -> Vec<u8> {
+	EvmDataWriter::new_with_selector(selector("add(uint256,uint256)"))
+		.write(a)
+		.write(b)
+		.build()
+}
+
+#[test]
+fn tester_checks_output_cost_and_logs() {
+	let caller = H160::repeat_byte(0xaa);
+	let precompile = H160::from_low_u64_be(1);
+
+	Adder
+		.prepare_test(caller, precompile, add_input(2.into(), 3.into()))
+		.expect_cost(ADD_GAS + 375 + 375)
+		.expect_log(LogsBuilder::new(precompile).log1(caller, vec![]))
+		.execute_returns(EvmDataWriter::new().write(U256::from(5)).build());
+
+	Adder
+		.prepare_test(caller, precompile, add_input(U256::max_value(), 1.into()))
+		.expect_cost(ADD_GAS)
+		.execute_reverts(|message| message == b"overflow");
+
+	Adder
+		.prepare_test(caller, precompile, add_input(2.into(), 3.into()))
+		.with_target_gas(Some(ADD_GAS))
+		.execute_error(ExitError::OutOfGas);
+
+	Adder.prepare_test(caller, H160::from_low_u64_be(2), vec![]).execute_none();
+}
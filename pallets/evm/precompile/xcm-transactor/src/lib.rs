@@ -0,0 +1,242 @@
+// SPDX-License-Identifier: Apache-2.0
+// This file is part of Frontier.
+//
+// Copyright (c) 2020 Parity Technologies (UK) Ltd.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Precompile giving contracts access to XCM: transfers of the native asset to the relay chain or
+//! to sibling parachains, and remote calls with `Transact`.
+//!
+//! Locations are encoded as `(uint8 parents, bytes[] interior)`, see [`location`]. XCM is
+//! executed and sent as the mapped account of the caller, so other chains see the caller as an
+//! `AccountId32` junction under this parachain. Its weight is charged as gas.
+//!
+//! The native asset is a reserve-backed derivative of the asset of `NativeLocation`, its reserve,
+//! so it can neither be teleported nor be reserve-transferred from this chain. A transfer instead
+//! withdraws it from the reserve, which deposits it to the beneficiary, directly when the reserve
+//! is the destination or through a reserve transfer to the destination otherwise. Half of the
+//! amount is set aside for the execution fees of each chain on the way, the rest of what is left
+//! is deposited. A transfer failing while executing locally reverts.
+//!
+//! The precompile set using it must reject calls where `context.address` is not the precompile
+//! address (`DELEGATECALL` and `CALLCODE`).
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+pub mod location;
+#[cfg(test)]
+mod tests;
+
+pub use location::Location;
+
+use alloc::format;
+use fp_evm::{Context, Precompile, PrecompileResult};
+use frame_support::{
+	dispatch::{Dispatchable, GetDispatchInfo, PostDispatchInfo},
+	traits::{EnsureOrigin, Get},
+};
+use pallet_evm::{AddressMapping, GasWeightMapping};
+use precompile_utils::{
+	revert, succeed, Bytes, EvmDataReader, EvmResult, FunctionModifier, Gasometer, RuntimeHelper,
+};
+use sp_core::U256;
+use sp_std::{boxed::Box, convert::TryFrom, marker::PhantomData, vec};
+use xcm::{latest::prelude::*, VersionedXcm};
+use xcm_executor::traits::{InvertLocation, WeightBounds};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+	/// `transferNative((uint8,bytes[]) dest, (uint8,bytes[]) beneficiary, uint256 amount)`
+	TransferNative,
+	/// `transactThroughDerivative((uint8,bytes[]) dest, (uint8,bytes[]) feeAsset,
+	/// uint256 feeAmount, uint64 transactWeight, bytes call)`
+	TransactThroughDerivative,
+}
+
+impl TryFrom<u32> for Action {
+	type Error = ();
+
+	fn try_from(selector: u32) -> Result<Self, Self::Error> {
+		match selector {
+			0xc660d0a2 => Ok(Action::TransferNative),
+			0xb2cb0fe5 => Ok(Action::TransactThroughDerivative),
+			_ => Err(()),
+		}
+	}
+}
+
+/// XCM precompile, where `NativeLocation` is the location of the native balance as matched by the
+/// asset transactor of the XCM executor, which also is its reserve.
+pub struct XcmTransactorPrecompile<Runtime, NativeLocation>(PhantomData<(Runtime, NativeLocation)>);
+
+impl<Runtime, NativeLocation> Precompile for XcmTransactorPrecompile<Runtime, NativeLocation>
+where
+	Runtime: pallet_evm::Config + pallet_xcm::Config,
+	<Runtime as frame_system::Config>::Call: Dispatchable<PostInfo = PostDispatchInfo>
+		+ GetDispatchInfo
+		+ From<pallet_xcm::Call<Runtime>>,
+	<<Runtime as frame_system::Config>::Call as Dispatchable>::Origin:
+		From<Option<Runtime::AccountId>>,
+	NativeLocation: Get<MultiLocation>,
+{
+	fn execute(
+		input: &[u8],
+		target_gas: Option<u64>,
+		context: &Context,
+		is_static: bool,
+	) -> PrecompileResult {
+		let mut gasometer = Gasometer::new(target_gas);
+		let action = EvmDataReader::read_selector::<Action>(input)?;
+		let mut input = EvmDataReader::new_skip_selector(input)?;
+		gasometer.check_function_modifier(context, is_static, FunctionModifier::NonPayable)?;
+
+		let origin = Runtime::AddressMapping::into_account_id(context.caller);
+		match action {
+			Action::TransferNative => {
+				let message = Self::read_transfer(&mut input)?;
+				Self::execute_xcm(origin, message, &mut gasometer)?;
+			},
+			Action::TransactThroughDerivative => {
+				let (dest, message) = Self::read_transact(&mut input)?;
+				let call = pallet_xcm::Call::<Runtime>::send {
+					dest: Box::new(dest.into()),
+					message: Box::new(VersionedXcm::from(message)),
+				};
+				RuntimeHelper::<Runtime>::try_dispatch(origin, call.into(), &mut gasometer)?;
+			},
+		}
+
+		Ok(succeed(gasometer.used_gas(), vec![], vec![]))
+	}
+}
+
+impl<Runtime, NativeLocation> XcmTransactorPrecompile<Runtime, NativeLocation>
+where
+	Runtime: pallet_evm::Config + pallet_xcm::Config,
+	NativeLocation: Get<MultiLocation>,
+{
+	/// Local message transferring the native asset to a beneficiary through its reserve.
+	fn read_transfer(
+		input: &mut EvmDataReader,
+	) -> EvmResult<Xcm<<Runtime as frame_system::Config>::Call>> {
+		input.expect_arguments(3)?;
+		let dest = input.read::<Location>()?.0;
+		let beneficiary = input.read::<Location>()?.0;
+		let amount = read_amount(input)?;
+
+		let reserve = NativeLocation::get();
+		let fees_at = |chain: &MultiLocation| -> EvmResult<MultiAsset> {
+			Ok((reanchored::<Runtime>(&reserve, chain)?, amount / 2).into())
+		};
+		let deposit = Xcm(vec![
+			BuyExecution { fees: fees_at(&dest)?, weight_limit: Unlimited },
+			DepositAsset { assets: Wild(All), max_assets: 1, beneficiary },
+		]);
+		let xcm = if dest == reserve {
+			deposit
+		} else {
+			Xcm(vec![
+				BuyExecution { fees: fees_at(&reserve)?, weight_limit: Unlimited },
+				DepositReserveAsset {
+					assets: Wild(All),
+					max_assets: 1,
+					dest: reanchored::<Runtime>(&dest, &reserve)?,
+					xcm: deposit,
+				},
+			])
+		};
+
+		Ok(Xcm(vec![
+			WithdrawAsset((reserve.clone(), amount).into()),
+			InitiateReserveWithdraw { assets: Wild(All), reserve, xcm },
+		]))
+	}
+
+	/// Destination and message of a remote call. The caller buys the execution of the call with
+	/// assets that its derivative account holds on the destination chain.
+	fn read_transact(input: &mut EvmDataReader) -> EvmResult<(MultiLocation, Xcm<()>)> {
+		input.expect_arguments(5)?;
+		let dest = input.read::<Location>()?.0;
+		let fee_location = input.read::<Location>()?.0;
+		let fee_amount = read_amount(input)?;
+		let transact_weight = input.read::<u64>()?;
+		let call = input.read::<Bytes>()?.0;
+
+		let fee: MultiAsset = (fee_location, fee_amount).into();
+		let message = Xcm(vec![
+			WithdrawAsset(fee.clone().into()),
+			BuyExecution { fees: fee, weight_limit: Unlimited },
+			Transact {
+				origin_type: OriginKind::SovereignAccount,
+				require_weight_at_most: transact_weight,
+				call: call.into(),
+			},
+		]);
+
+		Ok((dest, message))
+	}
+
+	/// Execute `message` locally as `origin`, as `pallet_xcm` does for its transfers.
+	fn execute_xcm(
+		origin: Runtime::AccountId,
+		mut message: Xcm<<Runtime as frame_system::Config>::Call>,
+		gasometer: &mut Gasometer,
+	) -> EvmResult {
+		let origin_location = <Runtime as pallet_xcm::Config>::ExecuteXcmOrigin::ensure_origin(
+			frame_system::RawOrigin::Signed(origin).into(),
+		)
+		.map_err(|_| gasometer.revert("caller has no location"))?;
+		let weight = <Runtime as pallet_xcm::Config>::Weigher::weight(&mut message)
+			.map_err(|()| gasometer.revert("message is too complex"))?;
+		gasometer.record_cost(<Runtime as pallet_evm::Config>::GasWeightMapping::weight_to_gas(
+			weight,
+		))?;
+
+		<Runtime as pallet_xcm::Config>::XcmExecutor::execute_xcm_in_credit(
+			origin_location,
+			message,
+			weight,
+			weight,
+		)
+		.ensure_complete()
+		.map_err(|error| gasometer.revert(format!("XCM execution failed: {:?}", error)))?;
+
+		Ok(())
+	}
+}
+
+/// `location` as seen from `chain`.
+fn reanchored<Runtime: pallet_xcm::Config>(
+	location: &MultiLocation,
+	chain: &MultiLocation,
+) -> EvmResult<MultiLocation> {
+	let not_reachable = || revert("destination is not reachable");
+	let inverted = <Runtime as pallet_xcm::Config>::LocationInverter::invert_location(chain)
+		.map_err(|()| not_reachable())?;
+	let mut location = location.clone();
+	location.prepend_with(inverted).map_err(|_| not_reachable())?;
+
+	Ok(location)
+}
+
+fn read_amount(input: &mut EvmDataReader) -> EvmResult<u128> {
+	let amount = input.read::<U256>()?;
+	if amount > U256::from(u128::MAX) {
+		return Err(revert("amount is too large"));
+	}
+
+	Ok(amount.low_u128())
+}
@@ -0,0 +1,186 @@
+// SPDX-License-Identifier: Apache-2.0
+// This file is part of Frontier.
+//
+// Copyright (c) 2020 Parity Technologies (UK) Ltd.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Solidity encoding of a `MultiLocation`: `(uint8 parents, bytes[] interior)`.
+//!
+//! Each junction of the interior starts with a selector byte:
+//!
+//! | Junction          | Encoding                                     |
+//! |-------------------|----------------------------------------------|
+//! | `Parachain`       | `0x00` ++ `u32` (big endian)                 |
+//! | `AccountId32`     | `0x01` ++ 32 bytes account ++ network        |
+//! | `AccountIndex64`  | `0x02` ++ `u64` (big endian) ++ network      |
+//! | `AccountKey20`    | `0x03` ++ 20 bytes account ++ network        |
+//! | `PalletInstance`  | `0x04` ++ `u8`                               |
+//! | `GeneralIndex`    | `0x05` ++ `u128` (big endian)                |
+//! | `GeneralKey`      | `0x06` ++ key                                |
+//! | `OnlyChild`       | `0x07`                                       |
+//! | `Plurality`       | `0x08` ++ SCALE encoded `(BodyId, BodyPart)` |
+//!
+//! A network is `0x00` for `Any`, `0x01` ++ name for `Named`, `0x02` for `Polkadot` and `0x03`
+//! for `Kusama`.
+
+use codec::{Decode, Encode};
+use precompile_utils::{revert, Bytes, EvmData, EvmDataReader, EvmDataWriter, EvmResult};
+use sp_std::{vec, vec::Vec};
+use xcm::latest::{Junction, Junctions, MultiLocation, NetworkId};
+
+/// A `MultiLocation` read from or written to Solidity.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Location(pub MultiLocation);
+
+impl EvmData for Location {
+	fn read(reader: &mut EvmDataReader) -> EvmResult<Self> {
+		let (parents, interior) = <(u8, Vec<Bytes>)>::read(reader)?;
+
+		let mut junctions = Junctions::Here;
+		for junction in interior {
+			junctions
+				.push(decode_junction(&junction.0)?)
+				.map_err(|_| revert("too many junctions"))?;
+		}
+
+		Ok(Location(MultiLocation { parents, interior: junctions }))
+	}
+
+	fn write(writer: &mut EvmDataWriter, value: Self) {
+		let interior = value.0.interior.iter().map(|junction| Bytes(encode_junction(junction)));
+		<(u8, Vec<Bytes>)>::write(writer, (value.0.parents, interior.collect()));
+	}
+
+	fn has_static_size() -> bool {
+		false
+	}
+}
+
+fn decode_junction(data: &[u8]) -> EvmResult<Junction> {
+	let (selector, data) = data.split_first().ok_or_else(|| revert("empty junction"))?;
+
+	Ok(match selector {
+		0x00 => Junction::Parachain(u32::from_be_bytes(take_exact(data)?)),
+		0x01 => {
+			let (id, network) = take(data)?;
+			Junction::AccountId32 { network: decode_network(network)?, id }
+		},
+		0x02 => {
+			let (index, network) = take(data)?;
+			Junction::AccountIndex64 {
+				network: decode_network(network)?,
+				index: u64::from_be_bytes(index),
+			}
+		},
+		0x03 => {
+			let (key, network) = take(data)?;
+			Junction::AccountKey20 { network: decode_network(network)?, key }
+		},
+		0x04 => Junction::PalletInstance(u8::from_be_bytes(take_exact(data)?)),
+		0x05 => Junction::GeneralIndex(u128::from_be_bytes(take_exact(data)?)),
+		0x06 => Junction::GeneralKey(data.to_vec()),
+		0x07 if data.is_empty() => Junction::OnlyChild,
+		0x08 => {
+			let (id, part) =
+				Decode::decode(&mut &data[..]).map_err(|_| revert("invalid plurality junction"))?;
+			Junction::Plurality { id, part }
+		},
+		_ => return Err(revert("invalid junction")),
+	})
+}
+
+fn encode_junction(junction: &Junction) -> Vec<u8> {
+	let mut data = vec![];
+	match junction {
+		Junction::Parachain(id) => {
+			data.push(0x00);
+			data.extend_from_slice(&id.to_be_bytes());
+		},
+		Junction::AccountId32 { network, id } => {
+			data.push(0x01);
+			data.extend_from_slice(id);
+			encode_network(network, &mut data);
+		},
+		Junction::AccountIndex64 { network, index } => {
+			data.push(0x02);
+			data.extend_from_slice(&index.to_be_bytes());
+			encode_network(network, &mut data);
+		},
+		Junction::AccountKey20 { network, key } => {
+			data.push(0x03);
+			data.extend_from_slice(key);
+			encode_network(network, &mut data);
+		},
+		Junction::PalletInstance(index) => {
+			data.push(0x04);
+			data.push(*index);
+		},
+		Junction::GeneralIndex(index) => {
+			data.push(0x05);
+			data.extend_from_slice(&index.to_be_bytes());
+		},
+		Junction::GeneralKey(key) => {
+			data.push(0x06);
+			data.extend_from_slice(key);
+		},
+		Junction::OnlyChild => data.push(0x07),
+		Junction::Plurality { id, part } => {
+			data.push(0x08);
+			(id, part).encode_to(&mut data);
+		},
+	}
+	data
+}
+
+fn decode_network(data: &[u8]) -> EvmResult<NetworkId> {
+	match data.split_first() {
+		Some((0x00, [])) => Ok(NetworkId::Any),
+		Some((0x01, name)) => Ok(NetworkId::Named(name.to_vec())),
+		Some((0x02, [])) => Ok(NetworkId::Polkadot),
+		Some((0x03, [])) => Ok(NetworkId::Kusama),
+		_ => Err(revert("invalid network")),
+	}
+}
+
+fn encode_network(network: &NetworkId, data: &mut Vec<u8>) {
+	match network {
+		NetworkId::Any => data.push(0x00),
+		NetworkId::Named(name) => {
+			data.push(0x01);
+			data.extend_from_slice(name);
+		},
+		NetworkId::Polkadot => data.push(0x02),
+		NetworkId::Kusama => data.push(0x03),
+	}
+}
+
+/// Split the first `N` bytes of `data`.
+fn take<const N: usize>(data: &[u8]) -> EvmResult<([u8; N], &[u8])> {
+	if data.len() < N {
+		return Err(revert("junction is too short"));
+	}
+
+	let (head, tail) = data.split_at(N);
+	let mut array = [0u8; N];
+	array.copy_from_slice(head);
+	Ok((array, tail))
+}
+
+/// Read `data` as exactly `N` bytes.
+fn take_exact<const N: usize>(data: &[u8]) -> EvmResult<[u8; N]> {
+	match take(data)? {
+		(array, []) => Ok(array),
+		_ => Err(revert("junction is too long")),
+	}
+}
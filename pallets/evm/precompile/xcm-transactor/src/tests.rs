@@ -0,0 +1,467 @@
+// SPDX-License-Identifier: Apache-2.0
+// This file is part of Frontier.
+//
+// Copyright (c) 2020 Parity Technologies (UK) Ltd.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::*;
+
+use fp_evm::{ExitError, PrecompileSet};
+use frame_support::{
+	parameter_types,
+	traits::{Everything, GenesisBuild, Nothing},
+};
+use pallet_evm::{EnsureAddressNever, EnsureAddressRoot, HashedAddressMapping};
+use polkadot_parachain::primitives::Sibling;
+use precompile_utils::{selector, testing::*, EvmDataWriter};
+use sp_core::{H160, H256};
+use sp_runtime::{
+	generic,
+	traits::{AccountIdConversion, BlakeTwo256, IdentityLookup},
+};
+use std::cell::RefCell;
+use xcm_builder::{
+	AccountId32Aliases, CurrencyAdapter, EnsureXcmOrigin, FixedWeightBounds, IsConcrete,
+	LocationInverter, ParentIsDefault, SiblingParachainConvertsVia, SignedAccountId32AsNative,
+	SignedToAccountId32, TakeWeightCredit,
+};
+use xcm_executor::XcmExecutor;
+
+type UncheckedExtrinsic = frame_system::mocking::MockUncheckedExtrinsic<Test>;
+type Block = frame_system::mocking::MockBlock<Test>;
+type AccountId = sp_runtime::AccountId32;
+
+frame_support::construct_runtime! {
+	pub enum Test where
+		Block = Block,
+		NodeBlock = Block,
+		UncheckedExtrinsic = UncheckedExtrinsic,
+	{
+		System: frame_system::{Pallet, Call, Config, Storage, Event<T>},
+		Balances: pallet_balances::{Pallet, Call, Storage, Config<T>, Event<T>},
+		Timestamp: pallet_timestamp::{Pallet, Call, Storage},
+		EVM: pallet_evm::{Pallet, Call, Storage, Config, Event<T>},
+		XcmPallet: pallet_xcm::{Pallet, Call, Storage, Event<T>, Origin},
+	}
+}
+
+parameter_types! {
+	pub const BlockHashCount: u64 = 250;
+}
+impl frame_system::Config for Test {
+	type BaseCallFilter = Everything;
+	type BlockWeights = ();
+	type BlockLength = ();
+	type DbWeight = ();
+	type Origin = Origin;
+	type Index = u64;
+	type BlockNumber = u64;
+	type Hash = H256;
+	type Call = Call;
+	type Hashing = BlakeTwo256;
+	type AccountId = AccountId;
+	type Lookup = IdentityLookup<Self::AccountId>;
+	type Header = generic::Header<u64, BlakeTwo256>;
+	type Event = Event;
+	type BlockHashCount = BlockHashCount;
+	type Version = ();
+	type PalletInfo = PalletInfo;
+	type AccountData = pallet_balances::AccountData<u64>;
+	type OnNewAccount = ();
+	type OnKilledAccount = ();
+	type SystemWeightInfo = ();
+	type SS58Prefix = ();
+	type OnSetCode = ();
+}
+
+parameter_types! {
+	pub const ExistentialDeposit: u64 = 1;
+}
+impl pallet_balances::Config for Test {
+	type MaxLocks = ();
+	type Balance = u64;
+	type DustRemoval = ();
+	type Event = Event;
+	type ExistentialDeposit = ExistentialDeposit;
+	type AccountStore = System;
+	type WeightInfo = ();
+	type MaxReserves = ();
+	type ReserveIdentifier = ();
+}
+
+parameter_types! {
+	pub const MinimumPeriod: u64 = 1000;
+}
+impl pallet_timestamp::Config for Test {
+	type Moment = u64;
+	type OnTimestampSet = ();
+	type MinimumPeriod = MinimumPeriod;
+	type WeightInfo = ();
+}
+
+impl pallet_evm::Config for Test {
+	type FeeCalculator = ();
+	type GasWeightMapping = ();
+	type CallOrigin = EnsureAddressRoot<Self::AccountId>;
+	type WithdrawOrigin = EnsureAddressNever<Self::AccountId>;
+	type AddressMapping = HashedAddressMapping<BlakeTwo256>;
+	type Currency = Balances;
+	type Runner = pallet_evm::runner::stack::Runner<Self>;
+	type Event = Event;
+	type PrecompilesType = ();
+	type PrecompilesValue = ();
+	type ChainId = ();
+	type BlockGasLimit = ();
+	type GasLimitPovSizeRatio = ();
+	type MaxBlockProofSize = ();
+	type MaxCodeSize = ();
+	type MaxTransactionGas = ();
+	type OnChargeTransaction = ();
+	type BlockHashMapping = pallet_evm::SubstrateBlockHashMapping<Self>;
+	type FindAuthor = ();
+	type WeightInfo = ();
+}
+
+thread_local! {
+	static SENT_XCM: RefCell<Vec<(MultiLocation, Xcm<()>)>> = RefCell::new(Vec::new());
+}
+
+/// Router keeping the sent messages.
+pub struct TestSendXcm;
+
+impl SendXcm for TestSendXcm {
+	fn send_xcm(dest: MultiLocation, message: Xcm<()>) -> SendResult {
+		SENT_XCM.with(|sent| sent.borrow_mut().push((dest, message)));
+		Ok(())
+	}
+}
+
+fn sent_xcm() -> Vec<(MultiLocation, Xcm<()>)> {
+	SENT_XCM.with(|sent| sent.borrow().clone())
+}
+
+parameter_types! {
+	pub const RelayLocation: MultiLocation = MultiLocation::parent();
+	pub const AnyNetwork: NetworkId = NetworkId::Any;
+	pub Ancestry: MultiLocation = Parachain(2000).into();
+	pub const UnitWeightCost: u64 = 10;
+	pub const MaxInstructions: u32 = 100;
+}
+
+pub type LocationToAccountId = (
+	ParentIsDefault<AccountId>,
+	SiblingParachainConvertsVia<Sibling, AccountId>,
+	AccountId32Aliases<AnyNetwork, AccountId>,
+);
+
+pub type LocalOriginToLocation = SignedToAccountId32<Origin, AccountId, AnyNetwork>;
+
+pub struct XcmConfig;
+impl xcm_executor::Config for XcmConfig {
+	type Call = Call;
+	type XcmSender = TestSendXcm;
+	type AssetTransactor =
+		CurrencyAdapter<Balances, IsConcrete<RelayLocation>, LocationToAccountId, AccountId, ()>;
+	type OriginConverter = SignedAccountId32AsNative<AnyNetwork, Origin>;
+	type IsReserve = ();
+	type IsTeleporter = ();
+	type LocationInverter = LocationInverter<Ancestry>;
+	type Barrier = TakeWeightCredit;
+	type Weigher = FixedWeightBounds<UnitWeightCost, Call, MaxInstructions>;
+	type Trader = ();
+	type ResponseHandler = XcmPallet;
+	type AssetTrap = XcmPallet;
+	type AssetClaims = XcmPallet;
+	type SubscriptionService = XcmPallet;
+}
+
+impl pallet_xcm::Config for Test {
+	type Event = Event;
+	type SendXcmOrigin = EnsureXcmOrigin<Origin, LocalOriginToLocation>;
+	type XcmRouter = TestSendXcm;
+	type ExecuteXcmOrigin = EnsureXcmOrigin<Origin, LocalOriginToLocation>;
+	type XcmExecuteFilter = Nothing;
+	type XcmExecutor = XcmExecutor<XcmConfig>;
+	type XcmTeleportFilter = Everything;
+	type XcmReserveTransferFilter = Nothing;
+	type Weigher = FixedWeightBounds<UnitWeightCost, Call, MaxInstructions>;
+	type LocationInverter = LocationInverter<Ancestry>;
+	type Origin = Origin;
+	type Call = Call;
+
+	const VERSION_DISCOVERY_QUEUE_SIZE: u32 = 100;
+	type AdvertisedXcmVersion = pallet_xcm::CurrentXcmVersion;
+}
+
+pub struct Precompiles;
+
+impl PrecompileSet for Precompiles {
+	fn execute(
+		&self,
+		address: H160,
+		input: &[u8],
+		target_gas: Option<u64>,
+		context: &Context,
+		is_static: bool,
+	) -> Option<PrecompileResult> {
+		if !self.is_precompile(address) {
+			return None;
+		}
+		Some(XcmTransactorPrecompile::<Test, RelayLocation>::execute(
+			input, target_gas, context, is_static,
+		))
+	}
+
+	fn is_precompile(&self, address: H160) -> bool {
+		address == precompile()
+	}
+}
+
+fn precompile() -> H160 {
+	H160::from_low_u64_be(2054)
+}
+
+fn alice() -> H160 {
+	H160::repeat_byte(0xaa)
+}
+
+fn account(address: H160) -> AccountId {
+	<Test as pallet_evm::Config>::AddressMapping::into_account_id(address)
+}
+
+fn new_test_ext() -> sp_io::TestExternalities {
+	let mut t = frame_system::GenesisConfig::default().build_storage::<Test>().unwrap();
+	pallet_balances::GenesisConfig::<Test> { balances: vec![(account(alice()), 1_000)] }
+		.assimilate_storage(&mut t)
+		.unwrap();
+	let mut ext: sp_io::TestExternalities = t.into();
+	ext.execute_with(|| SENT_XCM.with(|sent| sent.borrow_mut().clear()));
+	ext
+}
+
+fn relay() -> MultiLocation {
+	MultiLocation::parent()
+}
+
+fn sibling() -> MultiLocation {
+	MultiLocation::new(1, X1(Parachain(2001)))
+}
+
+fn beneficiary() -> MultiLocation {
+	X1(AccountId32 { network: NetworkId::Any, id: [0xbb; 32] }).into()
+}
+
+fn transfer_input(signature: &str, dest: MultiLocation, amount: U256) -> Vec<u8> {
+	EvmDataWriter::new_with_selector(selector(signature))
+		.write(Location(dest))
+		.write(Location(beneficiary()))
+		.write(amount)
+		.build()
+}
+
+const TRANSFER: &str = "transferNative((uint8,bytes[]),(uint8,bytes[]),uint256)";
+const TRANSACT: &str =
+	"transactThroughDerivative((uint8,bytes[]),(uint8,bytes[]),uint256,uint64,bytes)";
+
+#[test]
+fn selectors() {
+	assert_eq!(Action::try_from(selector(TRANSFER)), Ok(Action::TransferNative));
+	assert_eq!(Action::try_from(selector(TRANSACT)), Ok(Action::TransactThroughDerivative));
+}
+
+#[test]
+fn location_is_encoded_as_parents_and_junctions() {
+	let location = MultiLocation::new(
+		1,
+		X3(
+			Parachain(2001),
+			PalletInstance(10),
+			AccountKey20 { network: NetworkId::Named(b"evm".to_vec()), key: [0xaa; 20] },
+		),
+	);
+	let encoded = EvmDataWriter::new().write(Location(location.clone())).build();
+	let expected = EvmDataWriter::new()
+		.write((
+			1u8,
+			vec![
+				Bytes(vec![0x00, 0x00, 0x00, 0x07, 0xd1]),
+				Bytes(vec![0x04, 0x0a]),
+				Bytes([&[0x03][..], &[0xaa; 20][..], &[0x01][..], &b"evm"[..]].concat()),
+			],
+		))
+		.build();
+
+	assert_eq!(encoded, expected);
+	assert_eq!(EvmDataReader::new(&encoded).read::<Location>().unwrap(), Location(location));
+}
+
+#[test]
+fn invalid_junction_reverts() {
+	new_test_ext().execute_with(|| {
+		let input = EvmDataWriter::new_with_selector(selector(TRANSFER))
+			.write((1u8, vec![Bytes(vec![0x00, 0x07, 0xd1])]))
+			.write(Location(beneficiary()))
+			.write(U256::from(100))
+			.build();
+
+		Precompiles
+			.prepare_test(alice(), precompile(), input)
+			.execute_reverts(|output| output == b"junction is too short");
+	});
+}
+
+#[test]
+fn transfer_to_relay_withdraws_from_reserve() {
+	new_test_ext().execute_with(|| {
+		Precompiles
+			.prepare_test(alice(), precompile(), transfer_input(TRANSFER, relay(), 100.into()))
+			.expect_no_logs()
+			.execute_returns(vec![]);
+
+		assert_eq!(Balances::free_balance(account(alice())), 900);
+		assert_eq!(Balances::total_issuance(), 900);
+		let fees: MultiAsset = (MultiLocation::here(), 50).into();
+		assert_eq!(
+			sent_xcm(),
+			vec![(
+				relay(),
+				Xcm(vec![
+					WithdrawAsset((MultiLocation::here(), 100).into()),
+					ClearOrigin,
+					BuyExecution { fees, weight_limit: Unlimited },
+					DepositAsset { assets: Wild(All), max_assets: 1, beneficiary: beneficiary() },
+				])
+			)]
+		);
+	});
+}
+
+#[test]
+fn transfer_to_sibling_goes_through_reserve() {
+	new_test_ext().execute_with(|| {
+		Precompiles
+			.prepare_test(alice(), precompile(), transfer_input(TRANSFER, sibling(), 100.into()))
+			.execute_returns(vec![]);
+
+		assert_eq!(Balances::free_balance(account(alice())), 900);
+		let fees_at_relay: MultiAsset = (MultiLocation::here(), 50).into();
+		let fees_at_sibling: MultiAsset = (MultiLocation::parent(), 50).into();
+		assert_eq!(
+			sent_xcm(),
+			vec![(
+				relay(),
+				Xcm(vec![
+					WithdrawAsset((MultiLocation::here(), 100).into()),
+					ClearOrigin,
+					BuyExecution { fees: fees_at_relay, weight_limit: Unlimited },
+					DepositReserveAsset {
+						assets: Wild(All),
+						max_assets: 1,
+						dest: X1(Parachain(2001)).into(),
+						xcm: Xcm(vec![
+							BuyExecution { fees: fees_at_sibling, weight_limit: Unlimited },
+							DepositAsset {
+								assets: Wild(All),
+								max_assets: 1,
+								beneficiary: beneficiary(),
+							},
+						]),
+					},
+				])
+			)]
+		);
+	});
+}
+
+#[test]
+fn failed_transfer_reverts() {
+	new_test_ext().execute_with(|| {
+		Precompiles
+			.prepare_test(alice(), precompile(), transfer_input(TRANSFER, relay(), 2_000.into()))
+			.execute_reverts(|output| output.starts_with(b"XCM execution failed"));
+
+		assert!(sent_xcm().is_empty());
+	});
+}
+
+#[test]
+fn amount_larger_than_u128_reverts() {
+	new_test_ext().execute_with(|| {
+		let amount = U256::from(u128::MAX) + 1;
+
+		Precompiles
+			.prepare_test(alice(), precompile(), transfer_input(TRANSFER, relay(), amount))
+			.execute_reverts(|output| output == b"amount is too large");
+	});
+}
+
+#[test]
+fn transfer_requires_gas_for_its_weight() {
+	new_test_ext().execute_with(|| {
+		Precompiles
+			.prepare_test(alice(), precompile(), transfer_input(TRANSFER, relay(), 100.into()))
+			.with_target_gas(Some(1_000))
+			.execute_error(ExitError::OutOfGas);
+
+		assert_eq!(Balances::free_balance(account(alice())), 1_000);
+		assert!(sent_xcm().is_empty());
+	});
+}
+
+#[test]
+fn transfer_in_static_context_reverts() {
+	new_test_ext().execute_with(|| {
+		Precompiles
+			.prepare_test(alice(), precompile(), transfer_input(TRANSFER, relay(), 100.into()))
+			.with_static_call(true)
+			.execute_reverts(|output| {
+				output == b"can't call non-static function in static context"
+			});
+	});
+}
+
+#[test]
+fn transact_through_derivative() {
+	new_test_ext().execute_with(|| {
+		let input = EvmDataWriter::new_with_selector(selector(TRANSACT))
+			.write(Location(relay()))
+			.write(Location(MultiLocation::here()))
+			.write(U256::from(1_000))
+			.write(4_000u64)
+			.write(Bytes(vec![0x04, 0x01]))
+			.build();
+
+		Precompiles.prepare_test(alice(), precompile(), input).execute_returns(vec![]);
+
+		let fee: MultiAsset = (MultiLocation::here(), 1_000).into();
+		let derivative = X1(AccountId32 { network: NetworkId::Any, id: account(alice()).into() });
+		assert_eq!(
+			sent_xcm(),
+			vec![(
+				relay(),
+				Xcm(vec![
+					DescendOrigin(derivative),
+					WithdrawAsset(fee.clone().into()),
+					BuyExecution { fees: fee, weight_limit: Unlimited },
+					Transact {
+						origin_type: OriginKind::SovereignAccount,
+						require_weight_at_most: 4_000,
+						call: vec![0x04, 0x01].into(),
+					},
+				])
+			)]
+		);
+		assert_eq!(Balances::free_balance(account(alice())), 1_000);
+	});
+}
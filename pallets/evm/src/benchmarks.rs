@@ -18,15 +18,48 @@
 #![cfg(feature = "runtime-benchmarks")]
 
 //! Benchmarking
-use crate::{runner::Runner, Config, FeeCalculator, Module};
-use frame_benchmarking::{account, benchmarks};
+use crate::{
+	runner::Runner, AccountStorages, AddressMapping, BalanceOf, Call, Config, EnsureAddressOrigin,
+	ForkConfig, Hardfork, Pallet, MAX_FORKS,
+};
+use frame_benchmarking::{benchmarks, whitelisted_caller};
+use frame_support::{traits::Currency, weights::Weight};
+use frame_system::RawOrigin;
 use rlp::RlpStream;
 use sha3::{Digest, Keccak256};
-use sp_core::{H160, U256};
+use sp_core::{H160, H256, U256};
 use sp_std::prelude::*;
 
+/// Amount moved by the balance benchmarks.
+fn transfer_value<T: Config>() -> BalanceOf<T> {
+	T::Currency::minimum_balance() * 100u32.into() + 1_000u32.into()
+}
+
 benchmarks! {
 
+	withdraw {
+		let address = H160::repeat_byte(0x11);
+		let origin = T::WithdrawOrigin::successful_origin(&address);
+		let value = transfer_value::<T>();
+		T::Currency::make_free_balance_be(
+			&T::AddressMapping::into_account_id(address),
+			value * 2u32.into(),
+		);
+	}: _(origin, address, value)
+
+	deposit {
+		let caller: T::AccountId = whitelisted_caller();
+		let address = H160::repeat_byte(0x11);
+		let value = transfer_value::<T>();
+		T::Currency::make_free_balance_be(&caller, value * 2u32.into());
+	}: _(RawOrigin::Signed(caller), address, value)
+	verify {
+		assert_eq!(
+			T::Currency::free_balance(&T::AddressMapping::into_account_id(address)),
+			value,
+		);
+	}
+
 	// This benchmark tests the relationship between gas and weight. It deploys a contract which
 	// has an infinite loop in a public function. We then call this function with varying amounts
 	// of gas, expecting it to OOG. The benchmarking framework measures the amount of time (aka
@@ -81,7 +114,9 @@ benchmarks! {
 			value,
 			gas_limit_create,
 			None,
+			None,
 			Some(nonce_as_u256),
+			Vec::new(),
 			T::config(),
 		);
 		assert_eq!(create_runner_results.is_ok(), true, "create() failed");
@@ -110,11 +145,79 @@ benchmarks! {
 			value,
 			gas_limit_call,
 			None,
+			None,
 			Some(nonce_as_u256),
+			Vec::new(),
+			T::config(),
+		);
+		assert_eq!(call_runner_results.is_ok(), true, "call() failed");
+	}
+
+	// Like `runner_execute`, for a contract spending its gas on computation and memory rather
+	// than storage: an infinite loop hashing the first word of its memory into it. Weight per gas
+	// is taken from the most expensive of both.
+	runner_execute_keccak {
+
+		let x in 1..10000000;
+
+		// JUMPDEST PUSH1 0x20 PUSH1 0 SHA3 PUSH1 0 MSTORE PUSH1 0 JUMP
+		let contract_bytecode = hex::decode("5b6020600020600052600056").expect("Bad hex string");
+		let contract_address = H160::repeat_byte(0xcc);
+		Pallet::<T>::create_account(contract_address, contract_bytecode);
+
+		let caller = H160::default();
+		let value = U256::default();
+		let gas_limit_call = x as u64;
+
+	}: {
+
+		let call_runner_results = T::Runner::call(
+			caller,
+			contract_address,
+			Vec::new(),
+			value,
+			gas_limit_call,
+			None,
+			None,
+			None,
+			Vec::new(),
 			T::config(),
 		);
 		assert_eq!(call_runner_results.is_ok(), true, "call() failed");
 	}
+
+	// Deletion, by `on_idle`, of the `k` storage items of a removed account.
+	process_deletion_queue {
+		let k in 1..1000;
+
+		let address = H160::repeat_byte(0xdd);
+		Pallet::<T>::create_account(address, vec![0x00]);
+		for i in 0..k {
+			let index = H256::from_low_u64_be(i as u64);
+			AccountStorages::<T>::insert(address, index, H256::repeat_byte(1));
+		}
+		Pallet::<T>::remove_account(&address);
+	}: {
+		Pallet::<T>::process_deletion_queue(Weight::max_value());
+	}
+	verify {
+		assert_eq!(Pallet::<T>::deletion_queue_count(), 0);
+	}
+
+	set_fork_schedule {
+		let n in 0..MAX_FORKS;
+
+		let fork = ForkConfig {
+			hardfork: Hardfork::London,
+			contract_size_limit: Some(49_152),
+			limit_initcode_size: true,
+		};
+		let next = frame_system::Pallet::<T>::block_number() + 1u32.into();
+		let schedule = (0..n).map(|i| (next + i.into(), fork)).collect::<Vec<(T::BlockNumber, _)>>();
+	}: _(RawOrigin::Root, schedule)
+	verify {
+		assert_eq!(Pallet::<T>::fork_schedule().len(), n as usize);
+	}
 }
 
 #[cfg(test)]
@@ -137,4 +240,39 @@ mod tests {
 			assert_ok!(test_benchmark_runner_execute::<Test>());
 		});
 	}
+
+	#[test]
+	fn test_runner_execute_keccak() {
+		new_test_ext().execute_with(|| {
+			assert_ok!(test_benchmark_runner_execute_keccak::<Test>());
+		});
+	}
+
+	#[test]
+	fn test_process_deletion_queue() {
+		new_test_ext().execute_with(|| {
+			assert_ok!(test_benchmark_process_deletion_queue::<Test>());
+		});
+	}
+
+	#[test]
+	fn test_set_fork_schedule() {
+		new_test_ext().execute_with(|| {
+			assert_ok!(test_benchmark_set_fork_schedule::<Test>());
+		});
+	}
+
+	#[test]
+	fn test_withdraw() {
+		new_test_ext().execute_with(|| {
+			assert_ok!(test_benchmark_withdraw::<Test>());
+		});
+	}
+
+	#[test]
+	fn test_deposit() {
+		new_test_ext().execute_with(|| {
+			assert_ok!(test_benchmark_deposit::<Test>());
+		});
+	}
 }
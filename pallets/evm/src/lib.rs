@@ -61,45 +61,69 @@ mod tests;
 
 #[cfg(any(test, feature = "runtime-benchmarks"))]
 pub mod benchmarks;
+pub mod weights;
 
 pub use crate::runner::Runner;
+pub use crate::weights::WeightInfo;
 pub use evm::{Context, ExitError, ExitFatal, ExitReason, ExitRevert, ExitSucceed};
 pub use fp_evm::{
 	Account, CallInfo, CreateInfo, ExecutionInfo, LinearCostPrecompile, Log, Precompile,
 	PrecompileFailure, PrecompileOutput, PrecompileResult, PrecompileSet, Vicinity,
 };
 
-#[cfg(feature = "std")]
 use codec::{Decode, Encode};
 use evm::Config as EvmConfig;
 use frame_support::{
 	dispatch::DispatchResultWithPostInfo,
 	traits::{
 		tokens::fungible::Inspect, Currency, ExistenceRequirement, FindAuthor, Get, Imbalance,
-		OnUnbalanced, SignedImbalance, WithdrawReasons,
+		OnUnbalanced, PalletInfoAccess, SignedImbalance, WithdrawReasons,
 	},
-	weights::{Pays, PostDispatchInfo, Weight},
+	weights::{DispatchClass, Pays, PostDispatchInfo, Weight},
+	Blake2_128Concat, StorageHasher,
 };
 use frame_system::RawOrigin;
+use scale_info::TypeInfo;
 #[cfg(feature = "std")]
 use serde::{Deserialize, Serialize};
+use sha3::{Digest, Keccak256};
 use sp_core::{Hasher, H160, H256, U256};
+use sp_io::KillStorageResult;
 use sp_runtime::{
 	traits::{BadOrigin, Saturating, UniqueSaturatedInto, Zero},
-	AccountId32,
+	AccountId32, RuntimeDebug,
 };
 use sp_std::vec::Vec;
 
 pub use pallet::*;
 
+environmental::environmental!(DISPATCHING_FROM_EVM: ());
+
+/// Run `f`, which dispatches runtime calls on behalf of EVM code.
+///
+/// Calls dispatched by `f` cannot execute EVM code again, which would otherwise escape the
+/// depth limit and gas accounting of the outer execution.
+pub fn dispatch_from_evm<R>(f: impl FnOnce() -> R) -> R {
+	DISPATCHING_FROM_EVM::using(&mut (), f)
+}
+
+/// Whether the current call was dispatched by EVM code, see `dispatch_from_evm`.
+pub fn is_dispatching_from_evm() -> bool {
+	DISPATCHING_FROM_EVM::with(|_| ()).is_some()
+}
+
 #[frame_support::pallet]
 pub mod pallet {
 	use super::*;
 	use frame_support::pallet_prelude::*;
 	use frame_system::pallet_prelude::*;
 
+	/// The current storage version.
+	const STORAGE_VERSION: StorageVersion = StorageVersion::new(2);
+
 	#[pallet::pallet]
 	#[pallet::generate_store(pub(super) trait Store)]
+	#[pallet::storage_version(STORAGE_VERSION)]
 	pub struct Pallet<T>(_);
 
 	#[pallet::config]
@@ -132,6 +156,19 @@ pub mod pallet {
 		type ChainId: Get<u64>;
 		/// The block gas limit. Can be a simple constant, or an adjustment algorithm in another pallet.
 		type BlockGasLimit: Get<U256>;
+		/// Gas charged per byte of the estimated proof size of the storage reads of an execution.
+		/// Zero disables proof size metering.
+		type GasLimitPovSizeRatio: Get<u64>;
+		/// Estimated proof size the EVM executions of a block may reach, enforced before executing
+		/// if `GasLimitPovSizeRatio` isn't zero.
+		type MaxBlockProofSize: Get<u64>;
+		/// Maximum size of contract code in bytes, unless the active fork of `ForkSchedule` sets its
+		/// own. Initcode may be twice as large, under a fork limiting it. Zero keeps the limit of
+		/// the hardfork.
+		type MaxCodeSize: Get<u32>;
+		/// Maximum gas limit of an Ethereum transaction or an EVM call of this pallet. Zero
+		/// disables the limit.
+		type MaxTransactionGas: Get<u64>;
 		/// EVM execution runner.
 		type Runner: Runner<Self>;
 
@@ -143,16 +180,96 @@ pub mod pallet {
 		/// Find author for the current block.
 		type FindAuthor: FindAuthor<H160>;
 
-		/// EVM config used in the module.
+		/// Weight information for the extrinsics of this pallet.
+		type WeightInfo: WeightInfo;
+
+		/// EVM config used in the module before the first fork of `ForkSchedule`.
 		fn config() -> &'static EvmConfig {
 			&LONDON_CONFIG
 		}
 	}
 
+	#[pallet::hooks]
+	impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
+		fn on_initialize(_: T::BlockNumber) -> Weight {
+			BlockProofSize::<T>::kill();
+			T::DbWeight::get().writes(1)
+		}
+
+		fn on_runtime_upgrade() -> Weight {
+			let version = StorageVersion::get::<Pallet<T>>();
+			if version >= 2 {
+				return T::DbWeight::get().reads(1);
+			}
+
+			// The migrations are run by `on_idle`, over as many blocks as they take.
+			if version < 1 {
+				<MigratingAccountCodes<T>>::put(true);
+			}
+			<NonceReferencesCursor<T>>::put(Vec::<u8>::new());
+			STORAGE_VERSION.put::<Pallet<T>>();
+			T::DbWeight::get().reads_writes(1, 3)
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn pre_upgrade() -> Result<(), &'static str> {
+			let version = StorageVersion::get::<Pallet<T>>();
+			if version < 1 {
+				ensure!(
+					<AccountCodesMetadata<T>>::iter().next().is_none(),
+					"account code metadata exists before the account code migration"
+				);
+			}
+			if version < 2 {
+				ensure!(
+					<NonceReferencesCursor<T>>::get().is_none(),
+					"nonce reference migration runs before the upgrade"
+				);
+			}
+			Ok(())
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn post_upgrade() -> Result<(), &'static str> {
+			ensure!(
+				StorageVersion::get::<Pallet<T>>() == STORAGE_VERSION,
+				"storage version is not the current one"
+			);
+			// The codes are moved by `on_idle`, so they must be left in `AccountCodes` only while
+			// the migration runs.
+			let unmigrated_codes = frame_support::storage::migration::storage_iter::<Vec<u8>>(
+				<Pallet<T> as PalletInfoAccess>::name().as_bytes(),
+				b"AccountCodes",
+			)
+			.next()
+			.is_some();
+			ensure!(
+				!unmigrated_codes || Pallet::<T>::migrating_account_codes(),
+				"account codes are left without a running migration"
+			);
+			ensure!(
+				<NonceReferences<T>>::iter()
+					.all(|(who, ())| frame_system::Pallet::<T>::account(&who).sufficients > 0),
+				"a nonce reference is not counted in the sufficients of its account"
+			);
+			Ok(())
+		}
+
+		fn on_idle(_: T::BlockNumber, remaining_weight: Weight) -> Weight {
+			let mut weight = Pallet::<T>::migrate_account_codes(remaining_weight);
+			weight = weight.saturating_add(Pallet::<T>::migrate_nonce_references(
+				remaining_weight.saturating_sub(weight),
+			));
+			weight.saturating_add(Pallet::<T>::process_deletion_queue(
+				remaining_weight.saturating_sub(weight),
+			))
+		}
+	}
+
 	#[pallet::call]
 	impl<T: Config> Pallet<T> {
 		/// Withdraw balance from EVM into currency/balances pallet.
-		#[pallet::weight(0)]
+		#[pallet::weight(T::WeightInfo::withdraw())]
 		pub fn withdraw(
 			origin: OriginFor<T>,
 			address: H160,
@@ -168,6 +285,38 @@ pub mod pallet {
 				ExistenceRequirement::AllowDeath,
 			)?;
 
+			Pallet::<T>::deposit_event(Event::<T>::BalanceWithdraw(
+				destination,
+				address,
+				U256::from(UniqueSaturatedInto::<u128>::unique_saturated_into(value)),
+			));
+
+			Ok(())
+		}
+
+		/// Deposit balance from currency/balances pallet into EVM.
+		#[pallet::weight(T::WeightInfo::deposit())]
+		pub fn deposit(
+			origin: OriginFor<T>,
+			address: H160,
+			value: BalanceOf<T>,
+		) -> DispatchResult {
+			let source = ensure_signed(origin)?;
+			let address_account_id = T::AddressMapping::into_account_id(address);
+
+			T::Currency::transfer(
+				&source,
+				&address_account_id,
+				value,
+				ExistenceRequirement::AllowDeath,
+			)?;
+
+			Pallet::<T>::deposit_event(Event::<T>::BalanceDeposit(
+				source,
+				address,
+				U256::from(UniqueSaturatedInto::<u128>::unique_saturated_into(value)),
+			));
+
 			Ok(())
 		}
 
@@ -186,6 +335,15 @@ pub mod pallet {
 			access_list: Vec<(H160, Vec<H256>)>,
 		) -> DispatchResultWithPostInfo {
 			T::CallOrigin::ensure_address_origin(&source, origin)?;
+			ensure!(!is_dispatching_from_evm(), Error::<T>::DispatchedFromEvm);
+			ensure!(
+				Pallet::<T>::gas_limit_fits_in_transaction(gas_limit.into()),
+				Error::<T>::GasLimitTooHigh
+			);
+			ensure!(
+				Pallet::<T>::proof_size_fits_in_block(gas_limit.into()),
+				Error::<T>::BlockProofSizeExhausted
+			);
 
 			let info = T::Runner::call(
 				source,
@@ -197,7 +355,7 @@ pub mod pallet {
 				max_priority_fee_per_gas,
 				nonce,
 				access_list,
-				T::config(),
+				&Pallet::<T>::evm_config(),
 			)?;
 
 			match info.exit_reason {
@@ -232,6 +390,15 @@ pub mod pallet {
 			access_list: Vec<(H160, Vec<H256>)>,
 		) -> DispatchResultWithPostInfo {
 			T::CallOrigin::ensure_address_origin(&source, origin)?;
+			ensure!(!is_dispatching_from_evm(), Error::<T>::DispatchedFromEvm);
+			ensure!(
+				Pallet::<T>::gas_limit_fits_in_transaction(gas_limit.into()),
+				Error::<T>::GasLimitTooHigh
+			);
+			ensure!(
+				Pallet::<T>::proof_size_fits_in_block(gas_limit.into()),
+				Error::<T>::BlockProofSizeExhausted
+			);
 
 			let info = T::Runner::create(
 				source,
@@ -242,7 +409,7 @@ pub mod pallet {
 				max_priority_fee_per_gas,
 				nonce,
 				access_list,
-				T::config(),
+				&Pallet::<T>::evm_config(),
 			)?;
 
 			match info {
@@ -285,6 +452,15 @@ pub mod pallet {
 			access_list: Vec<(H160, Vec<H256>)>,
 		) -> DispatchResultWithPostInfo {
 			T::CallOrigin::ensure_address_origin(&source, origin)?;
+			ensure!(!is_dispatching_from_evm(), Error::<T>::DispatchedFromEvm);
+			ensure!(
+				Pallet::<T>::gas_limit_fits_in_transaction(gas_limit.into()),
+				Error::<T>::GasLimitTooHigh
+			);
+			ensure!(
+				Pallet::<T>::proof_size_fits_in_block(gas_limit.into()),
+				Error::<T>::BlockProofSizeExhausted
+			);
 
 			let info = T::Runner::create2(
 				source,
@@ -296,7 +472,7 @@ pub mod pallet {
 				max_priority_fee_per_gas,
 				nonce,
 				access_list,
-				T::config(),
+				&Pallet::<T>::evm_config(),
 			)?;
 
 			match info {
@@ -323,6 +499,35 @@ pub mod pallet {
 				pays_fee: Pays::No,
 			})
 		}
+
+		/// Set the forks of the EVM rules, by ascending activation block. The forks activated
+		/// already must be kept as they are, and the others must activate after the current block.
+		#[pallet::weight(T::WeightInfo::set_fork_schedule(schedule.len() as u32))]
+		pub fn set_fork_schedule(
+			origin: OriginFor<T>,
+			schedule: Vec<(T::BlockNumber, ForkConfig)>,
+		) -> DispatchResult {
+			ensure_root(origin)?;
+			ensure!(
+				schedule.len() as u32 <= MAX_FORKS
+					&& schedule.windows(2).all(|forks| forks[0].0 < forks[1].0),
+				Error::<T>::InvalidForkSchedule
+			);
+
+			let now = frame_system::Pallet::<T>::block_number();
+			let current = <ForkSchedule<T>>::get();
+			let activated = current.iter().take_while(|(activation, _)| *activation <= now).count();
+			ensure!(
+				schedule.get(..activated) == current.get(..activated)
+					&& schedule.get(activated).map_or(true, |(activation, _)| *activation > now),
+				Error::<T>::ForkActivated
+			);
+
+			<ForkSchedule<T>>::put(schedule);
+			Pallet::<T>::deposit_event(Event::<T>::ForkScheduleSet);
+
+			Ok(())
+		}
 	}
 
 	#[pallet::event]
@@ -342,6 +547,8 @@ pub mod pallet {
 		BalanceDeposit(T::AccountId, H160, U256),
 		/// A withdrawal has been made from a given address. \[sender, address, value\]
 		BalanceWithdraw(T::AccountId, H160, U256),
+		/// The fork schedule of the EVM rules has been set.
+		ForkScheduleSet,
 	}
 
 	#[pallet::error]
@@ -358,6 +565,18 @@ pub mod pallet {
 		GasPriceTooLow,
 		/// Nonce is invalid
 		InvalidNonce,
+		/// Fork schedule is too long or not by ascending activation block
+		InvalidForkSchedule,
+		/// Fork schedule changes activated forks or activates a fork by the current block
+		ForkActivated,
+		/// Initcode is larger than twice the maximum contract code size
+		InitcodeTooLarge,
+		/// EVM execution requested by a call the EVM dispatched
+		DispatchedFromEvm,
+		/// Gas limit may reach a larger proof size than the block has left
+		BlockProofSizeExhausted,
+		/// Gas limit is above the maximum gas of a transaction
+		GasLimitTooHigh,
 	}
 
 	#[pallet::genesis_config]
@@ -383,7 +602,7 @@ pub mod pallet {
 				// ASSUME: in one single EVM transaction, the nonce will not increase more than
 				// `u128::max_value()`.
 				for _ in 0..account.nonce.low_u128() {
-					frame_system::Pallet::<T>::inc_account_nonce(&account_id);
+					Pallet::<T>::inc_nonce(address);
 				}
 
 				T::Currency::deposit_creating(
@@ -400,14 +619,64 @@ pub mod pallet {
 		}
 	}
 
+	/// Hash and length of the code of the accounts with code.
 	#[pallet::storage]
-	#[pallet::getter(fn account_codes)]
-	pub type AccountCodes<T: Config> = StorageMap<_, Blake2_128Concat, H160, Vec<u8>, ValueQuery>;
+	#[pallet::getter(fn account_code_metadata)]
+	pub type AccountCodesMetadata<T: Config> =
+		StorageMap<_, Blake2_128Concat, H160, CodeMetadata, OptionQuery>;
+
+	/// Codes of the accounts, stored once by Keccak-256 hash.
+	#[pallet::storage]
+	#[pallet::getter(fn code_by_hash)]
+	pub type Codes<T: Config> = StorageMap<_, Identity, H256, Vec<u8>, ValueQuery>;
+
+	/// Number of accounts with each code of `Codes`.
+	#[pallet::storage]
+	#[pallet::getter(fn code_refcount)]
+	pub type CodeRefcounts<T: Config> = StorageMap<_, Identity, H256, u32, ValueQuery>;
 
 	#[pallet::storage]
 	#[pallet::getter(fn account_storages)]
 	pub type AccountStorages<T: Config> =
 		StorageDoubleMap<_, Blake2_128Concat, H160, Blake2_128Concat, H256, H256, ValueQuery>;
+
+	/// Removed accounts whose storage is yet to be deleted by `on_idle`. Their storage reads as
+	/// empty meanwhile, and creating a contract at their address runs out of gas.
+	#[pallet::storage]
+	pub type DeletionQueue<T: Config> = StorageMap<_, Blake2_128Concat, H160, (), OptionQuery>;
+
+	/// Number of accounts in `DeletionQueue`.
+	#[pallet::storage]
+	#[pallet::getter(fn deletion_queue_count)]
+	pub type DeletionQueueCount<T: Config> = StorageValue<_, u32, ValueQuery>;
+
+	/// Forks of the EVM rules, by ascending activation block.
+	#[pallet::storage]
+	#[pallet::getter(fn fork_schedule)]
+	pub type ForkSchedule<T: Config> =
+		StorageValue<_, Vec<(T::BlockNumber, ForkConfig)>, ValueQuery>;
+
+	/// Whether codes of the version 0 `AccountCodes` map are left for `on_idle` to move to `Codes`.
+	/// They are read from `AccountCodes` meanwhile.
+	#[pallet::storage]
+	#[pallet::getter(fn migrating_account_codes)]
+	pub type MigratingAccountCodes<T: Config> = StorageValue<_, bool, ValueQuery>;
+
+	/// Accounts holding a sufficient reference for their EVM nonce, so that reaping their balance
+	/// doesn't reset it.
+	#[pallet::storage]
+	pub type NonceReferences<T: Config> =
+		StorageMap<_, Blake2_128Concat, T::AccountId, (), OptionQuery>;
+
+	/// Raw key of the last account of `frame_system::Account` given a nonce reference by
+	/// `on_idle`, while the accounts with a nonce from before `NonceReferences` are migrated.
+	#[pallet::storage]
+	pub type NonceReferencesCursor<T: Config> = StorageValue<_, Vec<u8>, OptionQuery>;
+
+	/// Estimated proof size of the EVM executions of the current block.
+	#[pallet::storage]
+	#[pallet::getter(fn block_proof_size)]
+	pub type BlockProofSize<T: Config> = StorageValue<_, u64, ValueQuery>;
 }
 
 /// Type alias for currency balance.
@@ -447,6 +716,12 @@ pub trait EnsureAddressOrigin<OuterOrigin> {
 		address: &H160,
 		origin: OuterOrigin,
 	) -> Result<Self::Success, OuterOrigin>;
+
+	/// Returns an outer origin capable of passing `try_address_origin` for the address.
+	///
+	/// ** Should be used for benchmarking only!!! **
+	#[cfg(feature = "runtime-benchmarks")]
+	fn successful_origin(address: &H160) -> OuterOrigin;
 }
 
 /// Ensure that the EVM address is the same as the Substrate address. This only works if the account
@@ -465,6 +740,11 @@ where
 			r => Err(OuterOrigin::from(r)),
 		})
 	}
+
+	#[cfg(feature = "runtime-benchmarks")]
+	fn successful_origin(address: &H160) -> OuterOrigin {
+		OuterOrigin::from(RawOrigin::Signed(*address))
+	}
 }
 
 /// Ensure that the origin is root.
@@ -482,6 +762,11 @@ where
 			r => Err(OuterOrigin::from(r)),
 		})
 	}
+
+	#[cfg(feature = "runtime-benchmarks")]
+	fn successful_origin(_address: &H160) -> OuterOrigin {
+		OuterOrigin::from(RawOrigin::<AccountId>::Root)
+	}
 }
 
 /// Ensure that the origin never happens.
@@ -493,6 +778,11 @@ impl<OuterOrigin, AccountId> EnsureAddressOrigin<OuterOrigin> for EnsureAddressN
 	fn try_address_origin(_address: &H160, origin: OuterOrigin) -> Result<AccountId, OuterOrigin> {
 		Err(origin)
 	}
+
+	#[cfg(feature = "runtime-benchmarks")]
+	fn successful_origin(_address: &H160) -> OuterOrigin {
+		unimplemented!()
+	}
 }
 
 /// Ensure that the address is truncated hash of the origin. Only works if the account id is
@@ -513,10 +803,27 @@ where
 			r => Err(OuterOrigin::from(r)),
 		})
 	}
+
+	#[cfg(feature = "runtime-benchmarks")]
+	fn successful_origin(address: &H160) -> OuterOrigin {
+		let mut data = [0u8; 32];
+		data[0..20].copy_from_slice(&address[..]);
+		OuterOrigin::from(RawOrigin::Signed(AccountId32::from(data)))
+	}
 }
 
 pub trait AddressMapping<A> {
+	/// Proof size of the storage read by `into_account_id`, besides trie nodes; zero when the
+	/// mapping reads no storage.
+	const PROOF_SIZE: u64 = 0;
+
 	fn into_account_id(address: H160) -> A;
+
+	/// Whether the account of `address` is only the account of the address, and not also the
+	/// account of a Substrate key. Removing the address only resets the nonce of such accounts.
+	fn is_address_account(_address: &H160) -> bool {
+		true
+	}
 }
 
 /// Identity address mapping.
@@ -571,8 +878,91 @@ impl GasWeightMapping for () {
 	}
 }
 
+/// Gas to weight conversion measured by the `runner_execute` benchmarks.
+///
+/// A unit of gas weighs the weight per gas of the most expensive benchmarked contract, so that
+/// no contract executes more weight than the gas it pays for. The mapping is only as accurate as
+/// the weights of `W`; the default weights of this pallet are placeholders until regenerated.
+pub struct BenchmarkedGasWeightMapping<W>(sp_std::marker::PhantomData<W>);
+
+impl<W: WeightInfo> BenchmarkedGasWeightMapping<W> {
+	/// Gas over which the slopes of the benchmarks are measured.
+	const GAS_SAMPLE: u32 = 1_000_000;
+
+	/// Weight of a unit of gas, never zero.
+	pub fn weight_per_gas() -> Weight {
+		let slope = |weight: fn(u32) -> Weight| weight(Self::GAS_SAMPLE).saturating_sub(weight(0));
+		let max_slope = slope(W::runner_execute).max(slope(W::runner_execute_keccak));
+		(max_slope / Self::GAS_SAMPLE as Weight).max(1)
+	}
+}
+
+impl<W: WeightInfo> GasWeightMapping for BenchmarkedGasWeightMapping<W> {
+	fn gas_to_weight(gas: u64) -> Weight {
+		gas.saturating_mul(Self::weight_per_gas())
+	}
+	fn weight_to_gas(weight: Weight) -> u64 {
+		weight / Self::weight_per_gas()
+	}
+}
+
+/// Block gas limit of the gas weighing as much as the maximum weight of normal dispatches in a
+/// block, so that a block full of gas is never overweight.
+pub struct BlockGasLimitFromWeight<T>(sp_std::marker::PhantomData<T>);
+
+impl<T: Config> Get<U256> for BlockGasLimitFromWeight<T> {
+	fn get() -> U256 {
+		let weights = T::BlockWeights::get();
+		let max_weight = weights.get(DispatchClass::Normal).max_total.unwrap_or(weights.max_block);
+		U256::from(T::GasWeightMapping::weight_to_gas(max_weight))
+	}
+}
+
 static LONDON_CONFIG: EvmConfig = EvmConfig::london();
 
+/// Maximum number of forks in `ForkSchedule`.
+pub const MAX_FORKS: u32 = 32;
+
+/// Bytes of code `on_idle` moves from `AccountCodes` to `Codes` per block at most, bounding the
+/// proof size of the migration.
+pub const MAX_MIGRATED_CODE_SIZE: u64 = 512 * 1024;
+
+/// A rule set of the EVM.
+#[derive(Clone, Copy, Eq, PartialEq, Encode, Decode, RuntimeDebug, TypeInfo)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub enum Hardfork {
+	Istanbul,
+	Berlin,
+	London,
+}
+
+/// The EVM rules from a fork of `ForkSchedule` on: a hardfork and chain-specific tweaks.
+#[derive(Clone, Copy, Eq, PartialEq, Encode, Decode, RuntimeDebug, TypeInfo)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub struct ForkConfig {
+	/// Rule set of the fork.
+	pub hardfork: Hardfork,
+	/// Maximum size of contract code in bytes, instead of the one of the hardfork.
+	pub contract_size_limit: Option<u32>,
+	/// Whether initcode is limited to twice the maximum size of contract code, as in EIP-3860.
+	pub limit_initcode_size: bool,
+}
+
+impl ForkConfig {
+	/// The EVM config of the fork.
+	pub fn evm_config(&self) -> EvmConfig {
+		let mut config = match self.hardfork {
+			Hardfork::Istanbul => EvmConfig::istanbul(),
+			Hardfork::Berlin => EvmConfig::berlin(),
+			Hardfork::London => EvmConfig::london(),
+		};
+		if let Some(limit) = self.contract_size_limit {
+			config.create_contract_limit = Some(limit as usize);
+		}
+		config
+	}
+}
+
 #[cfg(feature = "std")]
 #[derive(Clone, Eq, PartialEq, Encode, Decode, Debug, Serialize, Deserialize)]
 /// Account definition used for genesis block construction.
@@ -587,13 +977,160 @@ pub struct GenesisAccount {
 	pub code: Vec<u8>,
 }
 
+/// Hash and length of the code of an account, the code itself being in `Codes`.
+#[derive(Clone, Copy, Eq, PartialEq, Encode, Decode, RuntimeDebug, TypeInfo)]
+pub struct CodeMetadata {
+	/// Keccak-256 hash of the code.
+	pub hash: H256,
+	/// Length of the code in bytes.
+	pub size: u64,
+}
+
+impl CodeMetadata {
+	/// Metadata of `code`.
+	pub fn from_code(code: &[u8]) -> Self {
+		CodeMetadata {
+			hash: H256::from_slice(Keccak256::digest(code).as_slice()),
+			size: code.len() as u64,
+		}
+	}
+}
+
 impl<T: Config> Pallet<T> {
 	/// Check whether an account is empty.
 	pub fn is_account_empty(address: &H160) -> bool {
 		let account = Self::account_basic(address);
-		let code_len = <AccountCodes<T>>::decode_len(address).unwrap_or(0);
 
-		account.nonce == U256::zero() && account.balance == U256::zero() && code_len == 0
+		account.nonce == U256::zero() && account.balance == U256::zero() && !Self::has_code(address)
+	}
+
+	/// Check whether an account has code.
+	pub fn has_code(address: &H160) -> bool {
+		<AccountCodesMetadata<T>>::contains_key(address)
+			|| (Self::migrating_account_codes()
+				&& frame_support::storage::migration::have_storage_value(
+					<Pallet<T> as PalletInfoAccess>::name().as_bytes(),
+					b"AccountCodes",
+					&Blake2_128Concat::hash(&address.encode()),
+				))
+	}
+
+	/// The EVM config of the current block: the one of the last activated fork of `ForkSchedule`,
+	/// or `Config::config` before the first one, with the contract size limit of `MaxCodeSize`
+	/// unless the fork sets its own.
+	pub fn evm_config() -> EvmConfig {
+		let fork = Self::fork_at(frame_system::Pallet::<T>::block_number());
+		let mut config = fork
+			.map(|fork| fork.evm_config())
+			.unwrap_or_else(|| T::config().clone());
+
+		let max_code_size = T::MaxCodeSize::get();
+		if max_code_size != 0 && fork.and_then(|fork| fork.contract_size_limit).is_none() {
+			config.create_contract_limit = Some(max_code_size as usize);
+		}
+		config
+	}
+
+	/// Whether an execution with `gas_limit` fits in the proof size the block has left: its proof
+	/// size is at most the proof size its gas limit can pay for.
+	pub fn proof_size_fits_in_block(gas_limit: U256) -> bool {
+		let gas_per_proof_byte = T::GasLimitPovSizeRatio::get();
+		if gas_per_proof_byte == 0 {
+			return true;
+		}
+		let max_proof_size = (gas_limit / gas_per_proof_byte).min(U256::from(u64::MAX)).low_u64();
+		Self::block_proof_size().saturating_add(max_proof_size) <= T::MaxBlockProofSize::get()
+	}
+
+	/// Maximum size of initcode under `config`, if the fork of the current block limits it as in
+	/// EIP-3860: twice its contract size limit.
+	pub fn max_initcode_size(config: &EvmConfig) -> Option<usize> {
+		let fork = Self::fork_at(frame_system::Pallet::<T>::block_number())?;
+		if !fork.limit_initcode_size {
+			return None;
+		}
+		config.create_contract_limit.map(|limit| limit.saturating_mul(2))
+	}
+
+	/// Whether `gas_limit` is at most `MaxTransactionGas`.
+	pub fn gas_limit_fits_in_transaction(gas_limit: U256) -> bool {
+		let max_transaction_gas = T::MaxTransactionGas::get();
+		max_transaction_gas == 0 || gas_limit <= U256::from(max_transaction_gas)
+	}
+
+	/// The last fork of `ForkSchedule` activated at or before `block`.
+	pub fn fork_at(block: T::BlockNumber) -> Option<ForkConfig> {
+		Self::fork_schedule()
+			.into_iter()
+			.rev()
+			.find(|(activation, _)| *activation <= block)
+			.map(|(_, fork)| fork)
+	}
+
+	/// Increment the nonce of an account, which holds a nonce reference from then on.
+	pub fn inc_nonce(address: &H160) {
+		let account_id = T::AddressMapping::into_account_id(*address);
+		Self::hold_nonce_reference(&account_id);
+		frame_system::Pallet::<T>::inc_account_nonce(&account_id);
+	}
+
+	/// Give an account a sufficient reference for its nonce, unless it holds one, so that reaping
+	/// its balance doesn't reset its nonce and make its past transactions replayable.
+	pub fn hold_nonce_reference(who: &T::AccountId) {
+		if !<NonceReferences<T>>::contains_key(who) {
+			let _ = frame_system::Pallet::<T>::inc_sufficients(who);
+			<NonceReferences<T>>::insert(who, ());
+		}
+	}
+
+	/// Release the nonce reference of an account, if it holds one.
+	pub fn release_nonce_reference(who: &T::AccountId) {
+		if <NonceReferences<T>>::take(who).is_some() {
+			let _ = frame_system::Pallet::<T>::dec_sufficients(who);
+		}
+	}
+
+	/// Check whether an account holds a nonce reference.
+	pub fn has_nonce_reference(who: &T::AccountId) -> bool {
+		<NonceReferences<T>>::contains_key(who)
+	}
+
+	/// Give the accounts with a nonce from before `NonceReferences` their nonce reference, as much
+	/// as `remaining_weight` allows, and return the weight used.
+	pub(crate) fn migrate_nonce_references(remaining_weight: Weight) -> Weight {
+		let cursor = match <NonceReferencesCursor<T>>::get() {
+			Some(cursor) => cursor,
+			None => return T::DbWeight::get().reads(1),
+		};
+
+		// Each account: read it and its nonce reference, write its sufficients and its nonce
+		// reference.
+		let base_weight = T::DbWeight::get().reads_writes(1, 1);
+		let item_weight = T::DbWeight::get().reads_writes(2, 2);
+		let mut weight = base_weight;
+		let mut accounts = if cursor.is_empty() {
+			frame_system::Account::<T>::iter()
+		} else {
+			frame_system::Account::<T>::iter_from(cursor)
+		};
+
+		while weight.saturating_add(item_weight) <= remaining_weight {
+			let (who, info) = match accounts.next() {
+				Some(account) => account,
+				None => {
+					<NonceReferencesCursor<T>>::kill();
+					return weight;
+				}
+			};
+
+			weight = weight.saturating_add(item_weight);
+			if !info.nonce.is_zero() {
+				Self::hold_nonce_reference(&who);
+			}
+		}
+
+		<NonceReferencesCursor<T>>::put(accounts.last_raw_key().to_vec());
+		weight
 	}
 
 	/// Remove an account if its empty.
@@ -603,15 +1140,88 @@ impl<T: Config> Pallet<T> {
 		}
 	}
 
-	/// Remove an account.
+	/// Remove an account. The nonce of an account of the address only is reset, so that a
+	/// contract can be created at its address again, while the nonce of an account shared with a
+	/// Substrate key is kept, so that its extrinsics can't be replayed.
 	pub fn remove_account(address: &H160) {
-		if <AccountCodes<T>>::contains_key(address) {
-			let account_id = T::AddressMapping::into_account_id(*address);
+		let account_id = T::AddressMapping::into_account_id(*address);
+		if T::AddressMapping::is_address_account(address) {
+			if !frame_system::Pallet::<T>::account_nonce(&account_id).is_zero() {
+				frame_system::Account::<T>::mutate(&account_id, |info| info.nonce = Zero::zero());
+			}
+			Self::release_nonce_reference(&account_id);
+		}
+
+		Self::migrate_account_code(*address);
+		if let Some(metadata) = <AccountCodesMetadata<T>>::take(address) {
 			let _ = frame_system::Pallet::<T>::dec_sufficients(&account_id);
+			Self::release_code(metadata.hash);
 		}
 
-		<AccountCodes<T>>::remove(address);
-		<AccountStorages<T>>::remove_prefix(address, None);
+		if <AccountStorages<T>>::iter_prefix(address).next().is_some() {
+			Self::queue_deletion(*address);
+		}
+	}
+
+	/// Get a storage slot of an account, empty while the account is pending deletion.
+	pub fn account_storage(address: H160, index: H256) -> H256 {
+		if Self::is_pending_deletion(&address) {
+			return H256::default();
+		}
+
+		<AccountStorages<T>>::get(address, index)
+	}
+
+	/// Check whether the storage of an account is pending deletion.
+	pub fn is_pending_deletion(address: &H160) -> bool {
+		<DeletionQueue<T>>::contains_key(address)
+	}
+
+	/// Queue the storage of an account for deletion by `on_idle`.
+	fn queue_deletion(address: H160) {
+		if !Self::is_pending_deletion(&address) {
+			<DeletionQueue<T>>::insert(address, ());
+			<DeletionQueueCount<T>>::mutate(|count| *count = count.saturating_add(1));
+		}
+	}
+
+	/// Delete the storage of queued accounts, as much as `remaining_weight` allows, and return the
+	/// weight used. Dequeuing an account counts as deleting one item.
+	pub(crate) fn process_deletion_queue(remaining_weight: Weight) -> Weight {
+		if Self::deletion_queue_count() == 0 {
+			return T::DbWeight::get().reads(1);
+		}
+
+		let base_weight = T::WeightInfo::process_deletion_queue(0);
+		let item_weight =
+			T::WeightInfo::process_deletion_queue(1).saturating_sub(base_weight).max(1);
+		let mut budget = match remaining_weight.checked_sub(base_weight) {
+			Some(weight) => (weight / item_weight).min(u32::MAX as Weight) as u32,
+			None => return T::DbWeight::get().reads(1),
+		};
+
+		let mut deleted: u32 = 0;
+		while budget > 0 {
+			let address = match <DeletionQueue<T>>::iter_keys().next() {
+				Some(address) => address,
+				None => break,
+			};
+
+			match <AccountStorages<T>>::remove_prefix(address, Some(budget)) {
+				KillStorageResult::AllRemoved(removed) => {
+					<DeletionQueue<T>>::remove(address);
+					<DeletionQueueCount<T>>::mutate(|count| *count = count.saturating_sub(1));
+					deleted = deleted.saturating_add(removed).saturating_add(1);
+					budget = budget.saturating_sub(removed).saturating_sub(1);
+				}
+				KillStorageResult::SomeRemaining(removed) => {
+					deleted = deleted.saturating_add(removed);
+					break;
+				}
+			}
+		}
+
+		T::WeightInfo::process_deletion_queue(deleted)
 	}
 
 	/// Create an account.
@@ -620,12 +1230,129 @@ impl<T: Config> Pallet<T> {
 			return;
 		}
 
-		if !<AccountCodes<T>>::contains_key(&address) {
-			let account_id = T::AddressMapping::into_account_id(address);
-			let _ = frame_system::Pallet::<T>::inc_sufficients(&account_id);
+		Self::migrate_account_code(address);
+		let metadata = CodeMetadata::from_code(&code);
+		match <AccountCodesMetadata<T>>::get(&address) {
+			Some(previous) if previous.hash == metadata.hash => return,
+			Some(previous) => Self::release_code(previous.hash),
+			None => {
+				let account_id = T::AddressMapping::into_account_id(address);
+				let _ = frame_system::Pallet::<T>::inc_sufficients(&account_id);
+			}
 		}
 
-		<AccountCodes<T>>::insert(address, code);
+		Self::retain_code(metadata.hash, code);
+		<AccountCodesMetadata<T>>::insert(address, metadata);
+	}
+
+	/// Move codes of the version 0 `AccountCodes` map to `Codes`, keyed by hash, as much as
+	/// `remaining_weight` and `MAX_MIGRATED_CODE_SIZE` allow, and return the weight used.
+	pub(crate) fn migrate_account_codes(remaining_weight: Weight) -> Weight {
+		if !Self::migrating_account_codes() {
+			return T::DbWeight::get().reads(1);
+		}
+
+		// Each account: read and remove its code, read the refcount, write the refcount, the
+		// metadata and the code if it is the first account with it.
+		let base_weight = T::DbWeight::get().reads_writes(1, 1);
+		let item_weight = T::DbWeight::get().reads_writes(2, 4);
+		let mut weight = base_weight;
+		let mut size: u64 = 0;
+		let mut codes = frame_support::storage::migration::storage_key_iter::<
+			H160,
+			Vec<u8>,
+			Blake2_128Concat,
+		>(<Pallet<T> as PalletInfoAccess>::name().as_bytes(), b"AccountCodes")
+		.drain();
+
+		while size < MAX_MIGRATED_CODE_SIZE
+			&& weight.saturating_add(item_weight) <= remaining_weight
+		{
+			let (address, code) = match codes.next() {
+				Some(entry) => entry,
+				None => {
+					<MigratingAccountCodes<T>>::kill();
+					break;
+				}
+			};
+
+			weight = weight.saturating_add(item_weight);
+			size = size.saturating_add(code.len() as u64);
+			Self::insert_account_code(address, code);
+		}
+
+		weight
+	}
+
+	/// Move the code of `address` from the version 0 `AccountCodes` map to `Codes`, if it wasn't
+	/// yet.
+	fn migrate_account_code(address: H160) {
+		if !Self::migrating_account_codes() {
+			return;
+		}
+
+		if let Some(code) = frame_support::storage::migration::take_storage_value::<Vec<u8>>(
+			<Pallet<T> as PalletInfoAccess>::name().as_bytes(),
+			b"AccountCodes",
+			&Blake2_128Concat::hash(&address.encode()),
+		) {
+			Self::insert_account_code(address, code);
+		}
+	}
+
+	/// Store `code` as the code of `address`, which has none, without changing its references.
+	fn insert_account_code(address: H160, code: Vec<u8>) {
+		if code.is_empty() {
+			return;
+		}
+
+		let metadata = CodeMetadata::from_code(&code);
+		Self::retain_code(metadata.hash, code);
+		<AccountCodesMetadata<T>>::insert(address, metadata);
+	}
+
+	/// Get the code of `address` in the version 0 `AccountCodes` map, until it is moved to `Codes`.
+	pub fn unmigrated_account_code(address: H160) -> Option<Vec<u8>> {
+		if !Self::migrating_account_codes() {
+			return None;
+		}
+
+		frame_support::storage::migration::get_storage_value(
+			<Pallet<T> as PalletInfoAccess>::name().as_bytes(),
+			b"AccountCodes",
+			&Blake2_128Concat::hash(&address.encode()),
+		)
+	}
+
+	/// Get the code of an account.
+	pub fn account_codes(address: H160) -> Vec<u8> {
+		match <AccountCodesMetadata<T>>::get(address) {
+			Some(metadata) => <Codes<T>>::get(metadata.hash),
+			None => Self::unmigrated_account_code(address).unwrap_or_default(),
+		}
+	}
+
+	/// Add a reference to the code of `hash`, storing it if it is the first.
+	fn retain_code(hash: H256, code: Vec<u8>) {
+		<CodeRefcounts<T>>::mutate(hash, |refcount| {
+			if *refcount == 0 {
+				<Codes<T>>::insert(hash, code);
+			}
+			*refcount = refcount.saturating_add(1);
+		});
+	}
+
+	/// Remove a reference to the code of `hash`, removing it if it was the last.
+	fn release_code(hash: H256) {
+		<CodeRefcounts<T>>::mutate_exists(hash, |refcount| {
+			match refcount.unwrap_or(0).saturating_sub(1) {
+				0 => {
+					<Codes<T>>::remove(hash);
+					*refcount = None;
+				}
+				remaining => *refcount = Some(remaining),
+			}
+		});
 	}
 
 	/// Get the account basic in EVM format.
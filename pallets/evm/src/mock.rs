@@ -16,8 +16,10 @@
 // limitations under the License.
 
 //! Test mock for unit tests and benchmarking
-use crate::{EnsureAddressNever, EnsureAddressRoot, FeeCalculator, IdentityAddressMapping};
-use frame_support::{parameter_types, traits::FindAuthor, ConsensusEngineId};
+use crate::{EnsureAddressRoot, EnsureAddressSame, FeeCalculator, IdentityAddressMapping};
+use frame_support::{
+	parameter_types, traits::FindAuthor, weights::RuntimeDbWeight, ConsensusEngineId,
+};
 use sp_core::{H160, H256, U256};
 use sp_runtime::{
 	generic,
@@ -45,12 +47,13 @@ parameter_types! {
 	pub const BlockHashCount: u64 = 250;
 	pub BlockWeights: frame_system::limits::BlockWeights =
 		frame_system::limits::BlockWeights::simple_max(1024);
+	pub static DbWeight: RuntimeDbWeight = RuntimeDbWeight { read: 0, write: 0 };
 }
 impl frame_system::Config for Test {
 	type BaseCallFilter = frame_support::traits::Everything;
 	type BlockWeights = ();
 	type BlockLength = ();
-	type DbWeight = ();
+	type DbWeight = DbWeight;
 	type Origin = Origin;
 	type Index = u64;
 	type BlockNumber = u64;
@@ -115,12 +118,19 @@ impl FindAuthor<H160> for FindAuthorTruncated {
 	}
 }
 
+parameter_types! {
+	pub static GasLimitPovSizeRatio: u64 = 0;
+	pub static MaxBlockProofSize: u64 = 0;
+	pub static MaxCodeSize: u32 = 0;
+	pub static MaxTransactionGas: u64 = 0;
+}
+
 impl crate::Config for Test {
 	type FeeCalculator = FixedGasPrice;
 	type GasWeightMapping = ();
 
 	type CallOrigin = EnsureAddressRoot<Self::AccountId>;
-	type WithdrawOrigin = EnsureAddressNever<Self::AccountId>;
+	type WithdrawOrigin = EnsureAddressSame;
 
 	type AddressMapping = IdentityAddressMapping;
 	type Currency = Balances;
@@ -131,7 +141,12 @@ impl crate::Config for Test {
 	type PrecompilesValue = ();
 	type ChainId = ();
 	type BlockGasLimit = ();
+	type GasLimitPovSizeRatio = GasLimitPovSizeRatio;
+	type MaxBlockProofSize = MaxBlockProofSize;
+	type MaxCodeSize = MaxCodeSize;
+	type MaxTransactionGas = MaxTransactionGas;
 	type OnChargeTransaction = ();
 	type BlockHashMapping = crate::SubstrateBlockHashMapping<Self>;
 	type FindAuthor = FindAuthorTruncated;
+	type WeightInfo = ();
 }
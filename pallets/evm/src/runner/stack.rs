@@ -18,8 +18,9 @@
 //! EVM stack-based runner.
 
 use crate::{
-	runner::Runner as RunnerT, AccountCodes, AccountStorages, AddressMapping, BlockHashMapping,
-	Config, Error, Event, FeeCalculator, OnChargeEVMTransaction, Pallet,
+	runner::Runner as RunnerT, AccountCodesMetadata, AccountStorages, AddressMapping,
+	BlockHashMapping, BlockProofSize, Codes, Config, Error, Event, FeeCalculator,
+	OnChargeEVMTransaction, Pallet,
 };
 use evm::{
 	backend::Backend as BackendT,
@@ -34,7 +35,34 @@ use frame_support::{
 use sha3::{Digest, Keccak256};
 use sp_core::{H160, H256, U256};
 use sp_runtime::traits::UniqueSaturatedInto;
-use sp_std::{boxed::Box, collections::btree_set::BTreeSet, marker::PhantomData, mem, vec::Vec};
+use sp_std::{
+	boxed::Box,
+	cell::{Cell, RefCell},
+	collections::btree_set::BTreeSet,
+	marker::PhantomData,
+	mem,
+	rc::Rc,
+	vec::Vec,
+};
+
+/// Estimated size, in a storage proof, of the trie nodes leading to an item besides the ones
+/// shared with other items.
+pub const TRIE_NODES_PROOF_SIZE: u64 = 128;
+/// Proof size of an account read by `basic`, besides trie nodes: its key and value in
+/// `frame_system::Account`.
+pub const ACCOUNT_BASIC_PROOF_SIZE: u64 = 160;
+/// Proof size of a storage slot, besides trie nodes: its key and value in `AccountStorages`. The
+/// first read of the storage of an account also adds `DELETION_QUEUE_PROOF_SIZE`.
+pub const ACCOUNT_STORAGE_PROOF_SIZE: u64 = 148;
+/// Proof size of the code metadata of an account, besides trie nodes: its key and value in
+/// `AccountCodesMetadata`.
+pub const ACCOUNT_CODE_METADATA_PROOF_SIZE: u64 = 108;
+/// Proof size of a code, besides trie nodes and the code itself: its key and length prefix in
+/// `Codes`.
+pub const CODE_PROOF_SIZE: u64 = 68;
+/// Proof size of checking whether an account is pending deletion, as reading its storage or
+/// creating a contract at its address does, besides trie nodes: its key in `DeletionQueue`.
+pub const DELETION_QUEUE_PROOF_SIZE: u64 = 68;
 
 #[derive(Default)]
 pub struct Runner<T: Config> {
@@ -115,7 +143,31 @@ impl<T: Config> Runner<T> {
 		let fee = T::OnChargeTransaction::withdraw_fee(&source, total_fee)?;
 
 		// Execute the EVM call.
-		let (reason, retv) = f(&mut executor);
+		sp_io::storage::start_transaction();
+		let (mut reason, retv) = match Pallet::<T>::max_initcode_size(config) {
+			Some(limit) => {
+				let mut listener = InitcodeSizeListener {
+					limit,
+					exceeded: executor.state().initcode_size_exceeded.clone(),
+				};
+				evm::tracing::using(&mut listener, || f(&mut executor))
+			}
+			None => f(&mut executor),
+		};
+
+		// The reads after the last substate exited are charged to the transaction itself. Running
+		// out of gas for them fails the execution, whose changes were already committed to its
+		// storage transaction.
+		let out_of_gas = executor.state_mut().charge_proof_size().is_err();
+		if out_of_gas {
+			sp_io::storage::rollback_transaction();
+			// The nonce of `source` was incremented by the executor, and stays incremented like
+			// that of any failed execution.
+			Pallet::<T>::inc_nonce(&source);
+			reason = ExitReason::Error(ExitError::OutOfGas);
+		} else {
+			sp_io::storage::commit_transaction();
+		}
 
 		let used_gas = U256::from(executor.used_gas());
 		let (actual_fee, actual_priority_fee) =
@@ -166,7 +218,12 @@ impl<T: Config> Runner<T> {
 			T::OnChargeTransaction::pay_priority_fee(actual_priority_fee);
 		}
 
-		let state = executor.into_state();
+		let mut state = executor.into_state();
+		BlockProofSize::<T>::mutate(|size| *size = size.saturating_add(state.proof_size()));
+		if out_of_gas {
+			state.substate.deletes.clear();
+			state.substate.logs.clear();
+		}
 
 		for address in state.substate.deletes {
 			log::debug!(
@@ -201,6 +258,15 @@ impl<T: Config> Runner<T> {
 			logs: state.substate.logs,
 		})
 	}
+
+	/// Ensure `init` is no larger than the initcode size limit of `config`. The initcode of the
+	/// CREATE and CREATE2 of the execution is checked by `InitcodeSizeListener`.
+	fn ensure_initcode_size(init: &[u8], config: &evm::Config) -> Result<(), Error<T>> {
+		match Pallet::<T>::max_initcode_size(config) {
+			Some(limit) if init.len() > limit => Err(Error::<T>::InitcodeTooLarge),
+			_ => Ok(()),
+		}
+	}
 }
 
 impl<T: Config> RunnerT<T> for Runner<T> {
@@ -243,6 +309,7 @@ impl<T: Config> RunnerT<T> for Runner<T> {
 		access_list: Vec<(H160, Vec<H256>)>,
 		config: &evm::Config,
 	) -> Result<CreateInfo, Self::Error> {
+		Self::ensure_initcode_size(&init, config)?;
 		let precompiles = T::PrecompilesValue::get();
 		Self::execute(
 			source,
@@ -275,6 +342,7 @@ impl<T: Config> RunnerT<T> for Runner<T> {
 		access_list: Vec<(H160, Vec<H256>)>,
 		config: &evm::Config,
 	) -> Result<CreateInfo, Self::Error> {
+		Self::ensure_initcode_size(&init, config)?;
 		let precompiles = T::PrecompilesValue::get();
 		let code_hash = H256::from_slice(Keccak256::digest(&init).as_slice());
 		Self::execute(
@@ -398,10 +466,55 @@ impl<'config> SubstrateStackSubstate<'config> {
 	}
 }
 
+/// Listener of the EVM events flagging the CREATE and CREATE2 of initcode larger than `limit`, for
+/// `SubstrateStackState` to fail the frame creating it as in EIP-3860.
+struct InitcodeSizeListener {
+	limit: usize,
+	exceeded: Rc<Cell<bool>>,
+}
+
+impl evm::tracing::EventListener for InitcodeSizeListener {
+	fn event(&mut self, event: evm::tracing::Event) {
+		if let evm::tracing::Event::Create { init_code, .. } = event {
+			if init_code.len() > self.limit {
+				self.exceeded.set(true);
+			}
+		}
+	}
+}
+
+/// Item of the storage proof read by an execution.
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+enum ProofItem {
+	AddressMapping(H160),
+	Basic(H160),
+	CodeMetadata(H160),
+	Code(H256),
+	UnmigratedCode(H160),
+	DeletionQueue(H160),
+	Storage(H160, H256),
+}
+
+/// Estimated proof size of the storage reads of an execution.
+#[derive(Default)]
+struct ProofSizeMeter {
+	/// Items already in the proof, whose reads add nothing to it.
+	recorded: BTreeSet<ProofItem>,
+	/// Proof size of the recorded items.
+	proof_size: u64,
+	/// Part of `proof_size` whose gas isn't charged yet.
+	uncharged: u64,
+}
+
 /// Substrate backend for EVM.
 pub struct SubstrateStackState<'vicinity, 'config, T> {
 	vicinity: &'vicinity Vicinity,
 	substate: SubstrateStackSubstate<'config>,
+	// Reads go through `&self`, their proof size is charged on the next mutable access.
+	proof_size_meter: RefCell<ProofSizeMeter>,
+	// Set by `InitcodeSizeListener`, the frame creating the initcode runs out of gas on the next
+	// mutable access, which charges the gas of the creation.
+	initcode_size_exceeded: Rc<Cell<bool>>,
 	_marker: PhantomData<T>,
 }
 
@@ -416,9 +529,49 @@ impl<'vicinity, 'config, T: Config> SubstrateStackState<'vicinity, 'config, T> {
 				logs: Vec::new(),
 				parent: None,
 			},
+			proof_size_meter: RefCell::new(ProofSizeMeter::default()),
+			initcode_size_exceeded: Rc::new(Cell::new(false)),
 			_marker: PhantomData,
 		}
 	}
+
+	/// Estimated proof size of the storage reads so far.
+	pub fn proof_size(&self) -> u64 {
+		self.proof_size_meter.borrow().proof_size
+	}
+
+	/// Charge the gas of the proof size recorded since the last charge to the current substate.
+	///
+	/// Running out of gas leaves the gasometer of the substate out of gas, so that its next
+	/// operation fails, and returns `ExitError::OutOfGas`.
+	pub fn charge_proof_size(&mut self) -> Result<(), ExitError> {
+		let uncharged = mem::take(&mut self.proof_size_meter.get_mut().uncharged);
+		let gas = uncharged.saturating_mul(T::GasLimitPovSizeRatio::get());
+		if gas > 0 {
+			self.substate.metadata_mut().gasometer_mut().record_cost(gas)?;
+		}
+		Ok(())
+	}
+
+	/// Record the proof size of mapping `address` to its account, if the mapping reads storage.
+	fn record_address_mapping(&self, address: H160) {
+		if T::AddressMapping::PROOF_SIZE > 0 {
+			self.record_proof_size(
+				ProofItem::AddressMapping(address),
+				T::AddressMapping::PROOF_SIZE,
+			);
+		}
+	}
+
+	/// Record the proof size of a read of `item`, unless it is already in the proof.
+	fn record_proof_size(&self, item: ProofItem, size: u64) {
+		let mut meter = self.proof_size_meter.borrow_mut();
+		if meter.recorded.insert(item) {
+			let size = size.saturating_add(TRIE_NODES_PROOF_SIZE);
+			meter.proof_size = meter.proof_size.saturating_add(size);
+			meter.uncharged = meter.uncharged.saturating_add(size);
+		}
+	}
 }
 
 impl<'vicinity, 'config, T: Config> BackendT for SubstrateStackState<'vicinity, 'config, T> {
@@ -468,6 +621,8 @@ impl<'vicinity, 'config, T: Config> BackendT for SubstrateStackState<'vicinity,
 	}
 
 	fn basic(&self, address: H160) -> evm::backend::Basic {
+		self.record_address_mapping(address);
+		self.record_proof_size(ProofItem::Basic(address), ACCOUNT_BASIC_PROOF_SIZE);
 		let account = Pallet::<T>::account_basic(&address);
 
 		evm::backend::Basic {
@@ -477,10 +632,37 @@ impl<'vicinity, 'config, T: Config> BackendT for SubstrateStackState<'vicinity,
 	}
 
 	fn code(&self, address: H160) -> Vec<u8> {
-		<AccountCodes<T>>::get(&address)
+		self.record_proof_size(ProofItem::CodeMetadata(address), ACCOUNT_CODE_METADATA_PROOF_SIZE);
+		match <AccountCodesMetadata<T>>::get(&address) {
+			Some(metadata) => {
+				// Accounts with the same code share its proof.
+				self.record_proof_size(
+					ProofItem::Code(metadata.hash),
+					CODE_PROOF_SIZE.saturating_add(metadata.size),
+				);
+				<Codes<T>>::get(metadata.hash)
+			}
+			None => match Pallet::<T>::unmigrated_account_code(address) {
+				Some(code) => {
+					// Its key in `AccountCodes` has the size of its key in `Codes`.
+					self.record_proof_size(
+						ProofItem::UnmigratedCode(address),
+						CODE_PROOF_SIZE.saturating_add(code.len() as u64),
+					);
+					code
+				}
+				None => Vec::new(),
+			},
+		}
 	}
 
 	fn storage(&self, address: H160, index: H256) -> H256 {
+		self.record_proof_size(ProofItem::DeletionQueue(address), DELETION_QUEUE_PROOF_SIZE);
+		if Pallet::<T>::is_pending_deletion(&address) {
+			return H256::default();
+		}
+
+		self.record_proof_size(ProofItem::Storage(address, index), ACCOUNT_STORAGE_PROOF_SIZE);
 		<AccountStorages<T>>::get(address, index)
 	}
 
@@ -501,26 +683,38 @@ impl<'vicinity, 'config, T: Config> StackStateT<'config>
 	}
 
 	fn metadata_mut(&mut self) -> &mut StackSubstateMetadata<'config> {
+		let _ = self.charge_proof_size();
+		if self.initcode_size_exceeded.replace(false) {
+			let gasometer = self.substate.metadata_mut().gasometer_mut();
+			let _ = gasometer.record_cost(gasometer.gas().saturating_add(1));
+		}
 		self.substate.metadata_mut()
 	}
 
 	fn enter(&mut self, gas_limit: u64, is_static: bool) {
+		let _ = self.charge_proof_size();
 		self.substate.enter(gas_limit, is_static)
 	}
 
 	fn exit_commit(&mut self) -> Result<(), ExitError> {
+		let _ = self.charge_proof_size();
 		self.substate.exit_commit()
 	}
 
 	fn exit_revert(&mut self) -> Result<(), ExitError> {
+		let _ = self.charge_proof_size();
 		self.substate.exit_revert()
 	}
 
 	fn exit_discard(&mut self) -> Result<(), ExitError> {
+		let _ = self.charge_proof_size();
 		self.substate.exit_discard()
 	}
 
 	fn is_empty(&self, address: H160) -> bool {
+		self.record_address_mapping(address);
+		self.record_proof_size(ProofItem::Basic(address), ACCOUNT_BASIC_PROOF_SIZE);
+		self.record_proof_size(ProofItem::CodeMetadata(address), ACCOUNT_CODE_METADATA_PROOF_SIZE);
 		Pallet::<T>::is_account_empty(&address)
 	}
 
@@ -529,8 +723,7 @@ impl<'vicinity, 'config, T: Config> StackStateT<'config>
 	}
 
 	fn inc_nonce(&mut self, address: H160) {
-		let account_id = T::AddressMapping::into_account_id(address);
-		frame_system::Pallet::<T>::inc_account_nonce(&account_id);
+		Pallet::<T>::inc_nonce(&address);
 	}
 
 	fn set_storage(&mut self, address: H160, index: H256, value: H256) {
@@ -555,6 +748,17 @@ impl<'vicinity, 'config, T: Config> StackStateT<'config>
 	}
 
 	fn reset_storage(&mut self, address: H160) {
+		// Only called when creating a contract at `address`. The storage of a removed account is
+		// deleted lazily, and a new contract must not share it. The backend can't fail with
+		// another error, so creating one at an address still pending deletion, as CREATE2 can
+		// after a self-destruct, runs out of gas.
+		self.record_proof_size(ProofItem::DeletionQueue(address), DELETION_QUEUE_PROOF_SIZE);
+		if Pallet::<T>::is_pending_deletion(&address) {
+			let gasometer = self.substate.metadata_mut().gasometer_mut();
+			let _ = gasometer.record_cost(gasometer.gas().saturating_add(1));
+			return;
+		}
+
 		<AccountStorages<T>>::remove_prefix(address, None);
 	}
 
@@ -577,6 +781,8 @@ impl<'vicinity, 'config, T: Config> StackStateT<'config>
 	}
 
 	fn transfer(&mut self, transfer: Transfer) -> Result<(), ExitError> {
+		self.record_address_mapping(transfer.source);
+		self.record_address_mapping(transfer.target);
 		let source = T::AddressMapping::into_account_id(transfer.source);
 		let target = T::AddressMapping::into_account_id(transfer.target);
 
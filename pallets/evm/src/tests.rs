@@ -21,8 +21,12 @@ use super::*;
 use crate::mock::*;
 
 use frame_support::{
-	assert_ok,
-	traits::{GenesisBuild, LockIdentifier, LockableCurrency, WithdrawReasons},
+	assert_noop, assert_ok,
+	traits::{
+		GenesisBuild, Hooks, LockIdentifier, LockableCurrency, StorageVersion, WithdrawReasons,
+	},
+	weights::RuntimeDbWeight,
+	StorageHasher,
 };
 use std::{collections::BTreeMap, str::FromStr};
 
@@ -338,7 +342,7 @@ fn handle_sufficient_reference() {
 		let substrate_addr_2 = <Test as Config>::AddressMapping::into_account_id(addr_2);
 
 		// Sufficients should increase when creating EVM accounts.
-		let _ = <crate::AccountCodes<Test>>::insert(addr, &vec![0]);
+		let _ = <crate::AccountCodesMetadata<Test>>::insert(addr, CodeMetadata::from_code(&[0]));
 		let account = frame_system::Account::<Test>::get(substrate_addr);
 		// Using storage is not correct as it leads to a sufficient reference mismatch.
 		assert_eq!(account.sufficients, 0);
@@ -354,3 +358,893 @@ fn handle_sufficient_reference() {
 		assert_eq!(account_2.sufficients, 0);
 	});
 }
+
+#[test]
+fn deposit_works() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		let source = H160::from_str("1000000000000000000000000000000000000001").unwrap();
+		let address = H160::from_str("1000000000000000000000000000000000000003").unwrap();
+
+		assert_ok!(EVM::deposit(Origin::signed(source), address, 1000));
+
+		assert_eq!(Balances::free_balance(&source), 999_000);
+		assert_eq!(EVM::account_basic(&address).balance, U256::from(1000));
+		System::assert_last_event(Event::EVM(crate::Event::BalanceDeposit(
+			source,
+			address,
+			U256::from(1000),
+		)));
+	});
+}
+
+#[test]
+fn deposit_fails_without_funds() {
+	new_test_ext().execute_with(|| {
+		let source = H160::from_str("1000000000000000000000000000000000000003").unwrap();
+		let address = H160::from_str("1000000000000000000000000000000000000004").unwrap();
+
+		assert_noop!(
+			EVM::deposit(Origin::signed(source), address, 1000),
+			pallet_balances::Error::<Test>::InsufficientBalance
+		);
+	});
+}
+
+#[test]
+fn withdraw_works() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		let address = H160::from_str("1000000000000000000000000000000000000001").unwrap();
+		let other = H160::from_str("1000000000000000000000000000000000000003").unwrap();
+
+		assert_noop!(EVM::withdraw(Origin::signed(other), address, 1000), BadOrigin);
+		assert_ok!(EVM::withdraw(Origin::signed(address), address, 1000));
+
+		System::assert_last_event(Event::EVM(crate::Event::BalanceWithdraw(
+			address,
+			address,
+			U256::from(1000),
+		)));
+	});
+}
+
+#[test]
+fn benchmarked_gas_weight_mapping_uses_steepest_benchmark() {
+	type Mapping = BenchmarkedGasWeightMapping<()>;
+
+	assert_eq!(Mapping::weight_per_gas(), 23_518);
+	assert_eq!(Mapping::gas_to_weight(21_000), 21_000 * 23_518);
+	assert_eq!(Mapping::weight_to_gas(21_000 * 23_518 + 1), 21_000);
+	assert_eq!(Mapping::gas_to_weight(u64::MAX), Weight::MAX);
+}
+
+#[test]
+fn block_gas_limit_is_gas_of_normal_block_weight() {
+	let max_normal_weight = <Test as frame_system::Config>::BlockWeights::get()
+		.get(DispatchClass::Normal)
+		.max_total
+		.unwrap();
+	assert_eq!(
+		BlockGasLimitFromWeight::<Test>::get(),
+		U256::from(<Test as Config>::GasWeightMapping::weight_to_gas(max_normal_weight)),
+	);
+}
+
+/// Call a contract of `code` charging `ratio` gas per byte of proof, returning the result of
+/// the call and the proof size of the block.
+fn call_with_proof_size_ratio(code: &str, ratio: u64) -> (CallInfo, u64) {
+	new_test_ext().execute_with(|| {
+		GasLimitPovSizeRatio::set(ratio);
+		let contract = H160::repeat_byte(0xcc);
+		EVM::create_account(contract, hex::decode(code).unwrap());
+
+		let info = <Test as Config>::Runner::call(
+			H160::default(),
+			contract,
+			Vec::new(),
+			U256::zero(),
+			1_000_000,
+			None,
+			None,
+			None,
+			Vec::new(),
+			<Test as Config>::config(),
+		)
+		.unwrap();
+		(info, EVM::block_proof_size())
+	})
+}
+
+#[test]
+fn storage_reads_are_charged_by_proof_size() {
+	// PUSH1 0 SLOAD STOP
+	let (info, proof_size) = call_with_proof_size_ratio("60005400", 0);
+	let (charged_info, charged_proof_size) = call_with_proof_size_ratio("60005400", 10);
+
+	assert!(proof_size > 0);
+	assert_eq!(charged_proof_size, proof_size);
+	assert_eq!(charged_info.exit_reason, ExitReason::Succeed(ExitSucceed::Stopped));
+	assert_eq!(charged_info.used_gas, info.used_gas + U256::from(proof_size * 10));
+}
+
+#[test]
+fn reads_already_in_proof_add_no_proof_size() {
+	use crate::runner::stack::{ACCOUNT_STORAGE_PROOF_SIZE, TRIE_NODES_PROOF_SIZE};
+
+	let (_, one_read) = call_with_proof_size_ratio("60005400", 0);
+	// PUSH1 0 SLOAD PUSH1 0 SLOAD STOP
+	let (_, same_slot) = call_with_proof_size_ratio("60005460005400", 0);
+	// PUSH1 0 SLOAD PUSH1 1 SLOAD STOP
+	let (_, other_slot) = call_with_proof_size_ratio("60005460015400", 0);
+
+	assert_eq!(same_slot, one_read);
+	assert_eq!(other_slot, one_read + ACCOUNT_STORAGE_PROOF_SIZE + TRIE_NODES_PROOF_SIZE);
+}
+
+#[test]
+fn first_storage_read_of_account_checks_deletion_queue() {
+	use crate::runner::stack::{
+		ACCOUNT_STORAGE_PROOF_SIZE, DELETION_QUEUE_PROOF_SIZE, TRIE_NODES_PROOF_SIZE,
+	};
+	use evm::backend::Backend;
+
+	new_test_ext().execute_with(|| {
+		let contract = H160::repeat_byte(0xcc);
+		let precompiles = <Test as Config>::PrecompilesValue::get();
+		let info = crate::runner::stack::Runner::<Test>::execute(
+			H160::default(),
+			U256::zero(),
+			1_000_000,
+			None,
+			None,
+			None,
+			<Test as Config>::config(),
+			&precompiles,
+			|executor| {
+				let state = executor.state();
+				let before = state.proof_size();
+				state.storage(contract, H256::zero());
+				let first = state.proof_size() - before;
+				state.storage(contract, H256::from_low_u64_be(1));
+				let second = state.proof_size() - before - first;
+				(ExitReason::Succeed(ExitSucceed::Stopped), (first, second))
+			},
+		)
+		.unwrap();
+
+		let slot = ACCOUNT_STORAGE_PROOF_SIZE + TRIE_NODES_PROOF_SIZE;
+		assert_eq!(info.value, (DELETION_QUEUE_PROOF_SIZE + TRIE_NODES_PROOF_SIZE + slot, slot));
+	});
+}
+
+#[test]
+fn proof_size_beyond_gas_limit_runs_out_of_gas() {
+	let (info, _) = call_with_proof_size_ratio("60005400", 1_000_000);
+
+	assert_eq!(info.exit_reason, ExitReason::Error(ExitError::OutOfGas));
+	assert_eq!(info.used_gas, U256::from(1_000_000));
+}
+
+#[test]
+fn reads_after_execution_beyond_gas_limit_roll_it_back() {
+	use evm::backend::Backend;
+
+	let contract = H160::repeat_byte(0xcc);
+	let execute = |gas_limit: u64| {
+		GasLimitPovSizeRatio::set(10);
+		// PUSH1 1 PUSH1 0 SSTORE STOP
+		EVM::create_account(contract, hex::decode("600160005500").unwrap());
+
+		let precompiles = <Test as Config>::PrecompilesValue::get();
+		crate::runner::stack::Runner::<Test>::execute(
+			H160::default(),
+			U256::zero(),
+			gas_limit,
+			None,
+			None,
+			None,
+			<Test as Config>::config(),
+			&precompiles,
+			|executor| {
+				let result = executor.transact_call(
+					H160::default(),
+					contract,
+					U256::zero(),
+					Vec::new(),
+					gas_limit,
+					Vec::new(),
+				);
+				// Read after the call, charged to the transaction itself.
+				executor.state().basic(H160::repeat_byte(0xdd));
+				result
+			},
+		)
+		.unwrap()
+	};
+
+	let used_gas = new_test_ext().execute_with(|| {
+		let info = execute(1_000_000);
+		assert_eq!(info.exit_reason, ExitReason::Succeed(ExitSucceed::Stopped));
+		assert_eq!(EVM::account_storages(contract, H256::zero()), H256::from_low_u64_be(1));
+		info.used_gas.low_u64()
+	});
+
+	new_test_ext().execute_with(|| {
+		let info = execute(used_gas - 1);
+
+		assert_eq!(info.exit_reason, ExitReason::Error(ExitError::OutOfGas));
+		assert_eq!(info.used_gas, U256::from(used_gas - 1));
+		assert_eq!(EVM::account_storages(contract, H256::zero()), H256::zero());
+		assert_eq!(EVM::account_basic(&H160::default()).nonce, U256::from(2));
+	});
+}
+
+#[test]
+fn executions_must_fit_in_block_proof_size() {
+	new_test_ext().execute_with(|| {
+		GasLimitPovSizeRatio::set(1_000);
+		MaxBlockProofSize::set(2_000);
+		let call = || {
+			EVM::call(
+				Origin::root(),
+				H160::default(),
+				H160::from_str("1000000000000000000000000000000000000001").unwrap(),
+				Vec::new(),
+				U256::default(),
+				1_000_000,
+				U256::from(1_000_000_000),
+				None,
+				None,
+				Vec::new(),
+			)
+		};
+		let create = || {
+			EVM::create(
+				Origin::root(),
+				H160::default(),
+				vec![0x00],
+				U256::default(),
+				1_000_000,
+				U256::from(1_000_000_000),
+				None,
+				None,
+				Vec::new(),
+			)
+		};
+
+		// A gas limit of 1_000_000 may reach a proof size of 1_000.
+		BlockProofSize::<Test>::put(1_001);
+		assert_noop!(call(), Error::<Test>::BlockProofSizeExhausted);
+		assert_noop!(create(), Error::<Test>::BlockProofSizeExhausted);
+
+		BlockProofSize::<Test>::put(1_000);
+		assert_ok!(call());
+	});
+}
+
+#[test]
+fn block_proof_size_is_reset_each_block() {
+	new_test_ext().execute_with(|| {
+		BlockProofSize::<Test>::put(1_000);
+		EVM::on_initialize(2);
+		assert_eq!(EVM::block_proof_size(), 0);
+	});
+}
+
+#[test]
+fn identical_codes_are_stored_once() {
+	new_test_ext().execute_with(|| {
+		let addr = H160::repeat_byte(0xaa);
+		let addr_2 = H160::repeat_byte(0xbb);
+		let metadata = CodeMetadata::from_code(&[1, 2, 3]);
+
+		EVM::create_account(addr, vec![1, 2, 3]);
+		EVM::create_account(addr_2, vec![1, 2, 3]);
+		assert_eq!(EVM::account_code_metadata(addr), Some(metadata));
+		assert_eq!(EVM::account_code_metadata(addr_2), Some(metadata));
+		assert_eq!(metadata.size, 3);
+		assert_eq!(EVM::code_refcount(metadata.hash), 2);
+
+		// Setting the same code again keeps a single reference for the account.
+		EVM::create_account(addr, vec![1, 2, 3]);
+		assert_eq!(EVM::code_refcount(metadata.hash), 2);
+
+		EVM::remove_account(&addr);
+		assert_eq!(EVM::account_codes(addr), Vec::<u8>::new());
+		assert_eq!(EVM::account_codes(addr_2), vec![1, 2, 3]);
+		assert_eq!(EVM::code_refcount(metadata.hash), 1);
+
+		EVM::remove_account(&addr_2);
+		assert!(!<Codes<Test>>::contains_key(metadata.hash));
+		assert!(!<CodeRefcounts<Test>>::contains_key(metadata.hash));
+	});
+}
+
+#[test]
+fn replacing_code_releases_the_previous_one() {
+	new_test_ext().execute_with(|| {
+		let addr = H160::repeat_byte(0xaa);
+		let substrate_addr = <Test as Config>::AddressMapping::into_account_id(addr);
+		let previous = CodeMetadata::from_code(&[1]);
+
+		EVM::create_account(addr, vec![1]);
+		EVM::create_account(addr, vec![2, 3]);
+		assert_eq!(EVM::account_codes(addr), vec![2, 3]);
+		assert!(!<Codes<Test>>::contains_key(previous.hash));
+		assert_eq!(frame_system::Account::<Test>::get(substrate_addr).sufficients, 1);
+	});
+}
+
+/// Store `code` as the code of `address` in the version 0 `AccountCodes` map.
+fn put_unmigrated_code(address: H160, code: Vec<u8>) {
+	frame_support::storage::migration::put_storage_value(
+		b"EVM",
+		b"AccountCodes",
+		&Blake2_128Concat::hash(&address.encode()),
+		code,
+	);
+}
+
+#[test]
+fn account_codes_are_migrated_to_codes_by_hash_over_blocks() {
+	new_test_ext().execute_with(|| {
+		let addr = H160::repeat_byte(0xaa);
+		let addr_2 = H160::repeat_byte(0xbb);
+		let code = vec![1u8; MAX_MIGRATED_CODE_SIZE as usize];
+		let metadata = CodeMetadata::from_code(&code);
+		put_unmigrated_code(addr, code.clone());
+		put_unmigrated_code(addr_2, code.clone());
+		StorageVersion::new(0).put::<EVM>();
+
+		EVM::on_runtime_upgrade();
+
+		assert_eq!(StorageVersion::get::<EVM>(), StorageVersion::new(1));
+		assert!(EVM::migrating_account_codes());
+		assert_eq!(EVM::code_refcount(metadata.hash), 0);
+		assert_eq!(EVM::account_codes(addr), code);
+
+		// Each block moves at most `MAX_MIGRATED_CODE_SIZE` bytes of code.
+		EVM::on_idle(1, Weight::MAX);
+		assert_eq!(EVM::code_refcount(metadata.hash), 1);
+		assert_eq!(EVM::account_codes(addr), code);
+		assert_eq!(EVM::account_codes(addr_2), code);
+
+		EVM::on_idle(2, Weight::MAX);
+		EVM::on_idle(3, Weight::MAX);
+		assert!(!EVM::migrating_account_codes());
+		assert_eq!(EVM::account_code_metadata(addr), Some(metadata));
+		assert_eq!(EVM::account_code_metadata(addr_2), Some(metadata));
+		assert_eq!(EVM::code_refcount(metadata.hash), 2);
+		assert!(!frame_support::storage::migration::have_storage_value(
+			b"EVM",
+			b"AccountCodes",
+			&Blake2_128Concat::hash(&addr.encode()),
+		));
+	});
+}
+
+#[test]
+fn account_code_migration_resumes_within_the_weight_budget() {
+	new_test_ext().execute_with(|| {
+		DbWeight::set(RuntimeDbWeight { read: 1, write: 1 });
+		let addresses: Vec<H160> = (1..=5).map(H160::repeat_byte).collect();
+		for address in &addresses {
+			put_unmigrated_code(*address, vec![address[0]]);
+		}
+		StorageVersion::new(0).put::<EVM>();
+		EVM::on_runtime_upgrade();
+
+		// Reading and clearing the migration flag, and two accounts at 2 reads and 4 writes each.
+		let budget = 2 + 2 * 6;
+		let migrated = || {
+			addresses
+				.iter()
+				.filter(|address| EVM::account_code_metadata(**address).is_some())
+				.count()
+		};
+
+		EVM::on_idle(1, budget);
+		assert_eq!(migrated(), 2);
+		assert!(EVM::migrating_account_codes());
+		for address in &addresses {
+			assert_eq!(EVM::account_codes(*address), vec![address[0]]);
+		}
+
+		EVM::on_idle(2, budget);
+		assert_eq!(migrated(), 4);
+		assert!(EVM::migrating_account_codes());
+
+		EVM::on_idle(3, budget);
+		assert_eq!(migrated(), 5);
+		assert!(!EVM::migrating_account_codes());
+		for address in &addresses {
+			assert_eq!(EVM::account_codes(*address), vec![address[0]]);
+			assert_eq!(EVM::code_refcount(CodeMetadata::from_code(&[address[0]]).hash), 1);
+		}
+	});
+}
+
+#[test]
+fn unmigrated_account_codes_are_migrated_when_their_account_changes() {
+	new_test_ext().execute_with(|| {
+		let addr = H160::repeat_byte(0xaa);
+		let addr_2 = H160::repeat_byte(0xbb);
+		put_unmigrated_code(addr, vec![1, 2, 3]);
+		put_unmigrated_code(addr_2, vec![1, 2, 3]);
+		StorageVersion::new(0).put::<EVM>();
+		EVM::on_runtime_upgrade();
+		assert!(EVM::has_code(&addr));
+
+		EVM::remove_account(&addr);
+		assert!(!EVM::has_code(&addr));
+		assert_eq!(EVM::account_codes(addr), Vec::<u8>::new());
+
+		EVM::create_account(addr_2, vec![4, 5]);
+		assert_eq!(EVM::account_codes(addr_2), vec![4, 5]);
+		assert_eq!(EVM::code_refcount(CodeMetadata::from_code(&[1, 2, 3]).hash), 0);
+		assert!(EVM::unmigrated_account_code(addr_2).is_none());
+	});
+}
+
+#[test]
+fn removed_account_storage_reads_as_empty_until_deleted() {
+	new_test_ext().execute_with(|| {
+		let addr = H160::repeat_byte(0xaa);
+		EVM::create_account(addr, vec![1, 2, 3]);
+		<AccountStorages<Test>>::insert(addr, H256::zero(), H256::repeat_byte(1));
+
+		EVM::remove_account(&addr);
+		assert_eq!(EVM::deletion_queue_count(), 1);
+		assert_eq!(EVM::account_storages(addr, H256::zero()), H256::repeat_byte(1));
+		assert_eq!(EVM::account_storage(addr, H256::zero()), H256::zero());
+
+		EVM::on_idle(1, Weight::MAX);
+		assert_eq!(EVM::deletion_queue_count(), 0);
+		assert!(!EVM::is_pending_deletion(&addr));
+		assert_eq!(EVM::account_storages(addr, H256::zero()), H256::zero());
+	});
+}
+
+#[test]
+fn deletion_queue_is_processed_in_bounded_chunks() {
+	let addr = H160::repeat_byte(0xaa);
+	let mut ext = new_test_ext();
+	ext.execute_with(|| {
+		for i in 0..5 {
+			<AccountStorages<Test>>::insert(addr, H256::from_low_u64_be(i), H256::repeat_byte(1));
+		}
+		EVM::remove_account(&addr);
+	});
+	// Deletion is bounded by the items of the backend, not of the overlay.
+	ext.commit_all().unwrap();
+
+	ext.execute_with(|| {
+		let weight = |k| <Test as Config>::WeightInfo::process_deletion_queue(k);
+
+		assert_eq!(EVM::on_idle(1, weight(3)), weight(3));
+		assert_eq!(EVM::deletion_queue_count(), 1);
+		assert_eq!(<AccountStorages<Test>>::iter_prefix(addr).count(), 2);
+
+		// The two remaining items, and dequeuing the account.
+		assert_eq!(EVM::on_idle(2, Weight::MAX), weight(3));
+		assert_eq!(EVM::deletion_queue_count(), 0);
+		assert_eq!(<AccountStorages<Test>>::iter_prefix(addr).count(), 0);
+	});
+}
+
+#[test]
+fn creating_contract_at_address_pending_deletion_fails() {
+	// PUSH1 0 PUSH1 0 RETURN
+	let init = hex::decode("60006000f3").unwrap();
+	let create = || {
+		<Test as Config>::Runner::create(
+			H160::default(),
+			init.clone(),
+			U256::zero(),
+			1_000_000,
+			None,
+			None,
+			None,
+			Vec::new(),
+			<Test as Config>::config(),
+		)
+		.unwrap()
+	};
+	let address = new_test_ext().execute_with(|| create().value);
+
+	new_test_ext().execute_with(|| {
+		<AccountStorages<Test>>::insert(address, H256::zero(), H256::repeat_byte(1));
+		EVM::remove_account(&address);
+
+		let info = create();
+		assert_eq!(info.value, address);
+		assert_eq!(info.exit_reason, ExitReason::Error(ExitError::OutOfGas));
+		assert_eq!(EVM::account_storages(address, H256::zero()), H256::repeat_byte(1));
+	});
+}
+
+#[test]
+fn creating_contract_with_create2_at_address_pending_deletion_fails() {
+	// PUSH1 0 PUSH1 0 RETURN
+	let init = hex::decode("60006000f3").unwrap();
+	let create2 = || {
+		<Test as Config>::Runner::create2(
+			H160::default(),
+			init.clone(),
+			H256::repeat_byte(0x01),
+			U256::zero(),
+			1_000_000,
+			None,
+			None,
+			None,
+			Vec::new(),
+			<Test as Config>::config(),
+		)
+		.unwrap()
+	};
+
+	new_test_ext().execute_with(|| {
+		let address = create2().value;
+		<AccountStorages<Test>>::insert(address, H256::zero(), H256::repeat_byte(1));
+		// Like a self-destruct of the contract.
+		EVM::remove_account(&address);
+		assert!(EVM::is_pending_deletion(&address));
+
+		let info = create2();
+		assert_eq!(info.value, address);
+		assert_eq!(info.exit_reason, ExitReason::Error(ExitError::OutOfGas));
+		assert_eq!(info.used_gas, U256::from(1_000_000));
+
+		EVM::on_idle(1, Weight::MAX);
+		let info = create2();
+		assert_eq!(info.exit_reason, ExitReason::Succeed(ExitSucceed::Returned));
+		assert_eq!(EVM::account_storages(address, H256::zero()), H256::zero());
+	});
+}
+
+#[test]
+fn incrementing_nonce_holds_one_sufficient_reference() {
+	new_test_ext().execute_with(|| {
+		let addr = H160::repeat_byte(0xaa);
+		let substrate_addr = <Test as Config>::AddressMapping::into_account_id(addr);
+
+		EVM::inc_nonce(&addr);
+		EVM::inc_nonce(&addr);
+		let account = frame_system::Account::<Test>::get(&substrate_addr);
+		assert_eq!(account.nonce, 2);
+		assert_eq!(account.sufficients, 1);
+		assert!(EVM::has_nonce_reference(&substrate_addr));
+	});
+}
+
+#[test]
+fn incrementing_nonce_raised_without_reference_holds_one() {
+	new_test_ext().execute_with(|| {
+		let addr = H160::repeat_byte(0xaa);
+		let substrate_addr = <Test as Config>::AddressMapping::into_account_id(addr);
+		// Like the nonce of a signed extrinsic.
+		frame_system::Pallet::<Test>::inc_account_nonce(&substrate_addr);
+
+		EVM::inc_nonce(&addr);
+		let account = frame_system::Account::<Test>::get(&substrate_addr);
+		assert_eq!(account.nonce, 2);
+		assert_eq!(account.sufficients, 1);
+	});
+}
+
+#[test]
+fn removing_account_resets_its_nonce() {
+	new_test_ext().execute_with(|| {
+		let addr = H160::repeat_byte(0xaa);
+		let substrate_addr = <Test as Config>::AddressMapping::into_account_id(addr);
+		EVM::create_account(addr, vec![0x00]);
+		EVM::inc_nonce(&addr);
+
+		EVM::remove_account(&addr);
+
+		assert_eq!(EVM::account_basic(&addr).nonce, U256::zero());
+		assert!(!EVM::has_nonce_reference(&substrate_addr));
+		assert_eq!(frame_system::Account::<Test>::get(&substrate_addr).sufficients, 0);
+	});
+}
+
+#[test]
+fn accounts_with_nonce_are_given_nonce_references_over_blocks() {
+	new_test_ext().execute_with(|| {
+		let addr = H160::repeat_byte(0xaa);
+		let substrate_addr = <Test as Config>::AddressMapping::into_account_id(addr);
+		let _ = Balances::deposit_creating(&substrate_addr, 100);
+		frame_system::Pallet::<Test>::inc_account_nonce(&substrate_addr);
+		let genesis_addr = <Test as Config>::AddressMapping::into_account_id(H160::default());
+		StorageVersion::new(1).put::<EVM>();
+
+		EVM::on_runtime_upgrade();
+		assert_eq!(StorageVersion::get::<EVM>(), StorageVersion::new(2));
+		assert!(!EVM::has_nonce_reference(&substrate_addr));
+
+		EVM::on_idle(1, Weight::MAX);
+		assert!(NonceReferencesCursor::<Test>::get().is_none());
+		assert!(EVM::has_nonce_reference(&substrate_addr));
+		assert_eq!(frame_system::Account::<Test>::get(&substrate_addr).sufficients, 1);
+		assert_eq!(frame_system::Account::<Test>::get(&genesis_addr).sufficients, 1);
+	});
+}
+
+#[test]
+fn nonce_reference_migration_resumes_from_its_cursor() {
+	new_test_ext().execute_with(|| {
+		DbWeight::set(RuntimeDbWeight { read: 1, write: 1 });
+		for byte in 1..=5 {
+			let who = <Test as Config>::AddressMapping::into_account_id(H160::repeat_byte(byte));
+			let _ = Balances::deposit_creating(&who, 100);
+			frame_system::Pallet::<Test>::inc_account_nonce(&who);
+		}
+		let accounts: Vec<H160> = frame_system::Account::<Test>::iter_keys().collect();
+		StorageVersion::new(1).put::<EVM>();
+		EVM::on_runtime_upgrade();
+
+		// Reading the code migration flag, reading and writing the cursor, and two accounts at
+		// 2 reads and 2 writes each.
+		let budget = 1 + 2 + 2 * 4;
+		let mut blocks = 0;
+		while NonceReferencesCursor::<Test>::get().is_some() {
+			blocks += 1;
+			assert!(blocks <= accounts.len(), "the migration doesn't resume from its cursor");
+			EVM::on_idle(blocks as u64, budget);
+		}
+
+		// The last block finds no account left after the cursor.
+		assert_eq!(blocks, accounts.len() / 2 + 1);
+		for who in &accounts {
+			let account = frame_system::Account::<Test>::get(who);
+			assert_eq!(EVM::has_nonce_reference(who), account.nonce != 0);
+		}
+	});
+}
+
+#[test]
+fn evm_config_follows_fork_schedule() {
+	new_test_ext().execute_with(|| {
+		let istanbul = ForkConfig {
+			hardfork: Hardfork::Istanbul,
+			contract_size_limit: None,
+			limit_initcode_size: false,
+		};
+		let london = ForkConfig {
+			hardfork: Hardfork::London,
+			contract_size_limit: Some(49_152),
+			limit_initcode_size: false,
+		};
+		assert_ok!(EVM::set_fork_schedule(Origin::root(), vec![(5, istanbul), (10, london)]));
+
+		System::set_block_number(4);
+		assert_eq!(EVM::fork_at(4), None);
+		assert_eq!(EVM::evm_config().create_contract_limit, Some(0x6000));
+		assert!(EVM::evm_config().increase_state_access_gas);
+
+		System::set_block_number(9);
+		assert_eq!(EVM::fork_at(9), Some(istanbul));
+		assert!(!EVM::evm_config().increase_state_access_gas);
+
+		System::set_block_number(10);
+		assert_eq!(EVM::evm_config().create_contract_limit, Some(49_152));
+		assert!(EVM::evm_config().increase_state_access_gas);
+	});
+}
+
+#[test]
+fn fork_schedule_must_be_set_by_root_in_ascending_order() {
+	new_test_ext().execute_with(|| {
+		let fork = ForkConfig {
+			hardfork: Hardfork::Berlin,
+			contract_size_limit: None,
+			limit_initcode_size: false,
+		};
+
+		assert_noop!(
+			EVM::set_fork_schedule(Origin::signed(H160::default()), vec![(1, fork)]),
+			sp_runtime::DispatchError::BadOrigin,
+		);
+		assert_noop!(
+			EVM::set_fork_schedule(Origin::root(), vec![(2, fork), (2, fork)]),
+			Error::<Test>::InvalidForkSchedule,
+		);
+		assert_noop!(
+			EVM::set_fork_schedule(Origin::root(), vec![(1, fork); MAX_FORKS as usize + 1]),
+			Error::<Test>::InvalidForkSchedule,
+		);
+	});
+}
+
+#[test]
+fn fork_schedule_keeps_activated_forks() {
+	new_test_ext().execute_with(|| {
+		let berlin = ForkConfig {
+			hardfork: Hardfork::Berlin,
+			contract_size_limit: None,
+			limit_initcode_size: false,
+		};
+		let london = ForkConfig {
+			hardfork: Hardfork::London,
+			contract_size_limit: None,
+			limit_initcode_size: false,
+		};
+		assert_ok!(EVM::set_fork_schedule(Origin::root(), vec![(5, berlin), (10, london)]));
+		System::set_block_number(7);
+
+		assert_noop!(
+			EVM::set_fork_schedule(Origin::root(), vec![(10, london)]),
+			Error::<Test>::ForkActivated,
+		);
+		assert_noop!(
+			EVM::set_fork_schedule(Origin::root(), vec![(5, london), (10, london)]),
+			Error::<Test>::ForkActivated,
+		);
+		assert_noop!(
+			EVM::set_fork_schedule(Origin::root(), vec![(5, berlin), (7, london)]),
+			Error::<Test>::ForkActivated,
+		);
+
+		assert_ok!(EVM::set_fork_schedule(Origin::root(), vec![(5, berlin), (8, london)]));
+		assert_ok!(EVM::set_fork_schedule(Origin::root(), vec![(5, berlin)]));
+		assert_eq!(EVM::fork_schedule(), vec![(5, berlin)]);
+	});
+}
+
+#[test]
+fn max_code_size_is_the_contract_size_limit_unless_the_fork_sets_one() {
+	new_test_ext().execute_with(|| {
+		MaxCodeSize::set(49_152);
+		assert_eq!(EVM::evm_config().create_contract_limit, Some(49_152));
+
+		let fork = ForkConfig {
+			hardfork: Hardfork::London,
+			contract_size_limit: Some(30_000),
+			limit_initcode_size: false,
+		};
+		assert_ok!(EVM::set_fork_schedule(Origin::root(), vec![(1, fork)]));
+		System::set_block_number(1);
+		assert_eq!(EVM::evm_config().create_contract_limit, Some(30_000));
+	});
+}
+
+#[test]
+fn initcode_size_is_limited_from_a_fork_limiting_it() {
+	new_test_ext().execute_with(|| {
+		MaxCodeSize::set(49_152);
+		assert_eq!(EVM::max_initcode_size(&EVM::evm_config()), None);
+
+		set_initcode_size_limit();
+		assert_eq!(EVM::max_initcode_size(&EVM::evm_config()), Some(98_304));
+	});
+}
+
+/// Activate a fork limiting initcode to twice `MaxCodeSize`.
+fn set_initcode_size_limit() {
+	let fork = ForkConfig {
+		hardfork: Hardfork::London,
+		contract_size_limit: None,
+		limit_initcode_size: true,
+	};
+	ForkSchedule::<Test>::put(vec![(0, fork)]);
+}
+
+#[test]
+fn create_with_initcode_above_limit_fails() {
+	new_test_ext().execute_with(|| {
+		MaxCodeSize::set(16);
+		let create = |init: Vec<u8>| {
+			<Test as Config>::Runner::create(
+				H160::default(),
+				init,
+				U256::zero(),
+				1_000_000,
+				None,
+				None,
+				None,
+				Vec::new(),
+				&EVM::evm_config(),
+			)
+		};
+
+		assert!(create(vec![0; 33]).is_ok());
+
+		set_initcode_size_limit();
+		assert!(matches!(create(vec![0; 33]), Err(Error::<Test>::InitcodeTooLarge)));
+		assert!(create(vec![0; 32]).is_ok());
+	});
+}
+
+#[test]
+fn create_opcode_with_initcode_above_limit_fails_its_caller() {
+	new_test_ext().execute_with(|| {
+		MaxCodeSize::set(16);
+		set_initcode_size_limit();
+		let call = |size: u8| {
+			let contract = H160::repeat_byte(size);
+			// PUSH1 size PUSH1 0 PUSH1 0 CREATE STOP
+			EVM::create_account(contract, vec![0x60, size, 0x60, 0x00, 0x60, 0x00, 0xf0, 0x00]);
+			<Test as Config>::Runner::call(
+				H160::default(),
+				contract,
+				Vec::new(),
+				U256::zero(),
+				1_000_000,
+				None,
+				None,
+				None,
+				Vec::new(),
+				&EVM::evm_config(),
+			)
+			.unwrap()
+		};
+
+		let info = call(33);
+		assert_eq!(info.exit_reason, ExitReason::Error(ExitError::OutOfGas));
+		assert_eq!(info.used_gas, U256::from(1_000_000));
+		assert_eq!(call(32).exit_reason, ExitReason::Succeed(ExitSucceed::Stopped));
+	});
+}
+
+#[test]
+fn calls_above_max_transaction_gas_fail() {
+	new_test_ext().execute_with(|| {
+		MaxTransactionGas::set(1_000_000);
+		let call = |gas_limit: u64| {
+			EVM::call(
+				Origin::root(),
+				H160::default(),
+				H160::from_str("1000000000000000000000000000000000000001").unwrap(),
+				Vec::new(),
+				U256::default(),
+				gas_limit,
+				U256::from(1_000_000_000),
+				None,
+				None,
+				Vec::new(),
+			)
+		};
+
+		assert_noop!(call(1_000_001), Error::<Test>::GasLimitTooHigh);
+		assert_ok!(call(1_000_000));
+		assert_noop!(
+			EVM::create2(
+				Origin::root(),
+				H160::default(),
+				vec![0x00],
+				H256::default(),
+				U256::default(),
+				1_000_001,
+				U256::from(1_000_000_000),
+				None,
+				None,
+				Vec::new(),
+			),
+			Error::<Test>::GasLimitTooHigh
+		);
+	});
+}
+
+#[test]
+fn calls_dispatched_from_evm_cannot_execute_evm_code() {
+	new_test_ext().execute_with(|| {
+		let call = || {
+			EVM::call(
+				Origin::root(),
+				H160::default(),
+				H160::from_str("1000000000000000000000000000000000000001").unwrap(),
+				Vec::new(),
+				U256::default(),
+				1000000,
+				U256::from(1_000_000_000),
+				None,
+				None,
+				Vec::new(),
+			)
+		};
+
+		assert_noop!(dispatch_from_evm(call), Error::<Test>::DispatchedFromEvm);
+		assert_ok!(call());
+	});
+}
@@ -0,0 +1,113 @@
+// SPDX-License-Identifier: Apache-2.0
+// This file is part of Frontier.
+//
+// Copyright (c) 2021 Parity Technologies (UK) Ltd.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Weights for pallet_evm.
+//!
+//! `withdraw` and `deposit` are a balance transfer between two accounts, and weigh as the
+//! benchmarked `transfer` of pallet_balances. The other weights are estimates written by hand in
+//! the layout of the benchmark output; they were not measured. Regenerate them by running the
+//! benchmarks of `benchmarks.rs` on the reference hardware before relying on them:
+//! `BenchmarkedGasWeightMapping` derives the weight of gas from `runner_execute` and
+//! `runner_execute_keccak`.
+
+#![allow(unused_parens)]
+#![allow(unused_imports)]
+
+use frame_support::{
+	traits::Get,
+	weights::{constants::RocksDbWeight, Weight},
+};
+use sp_std::marker::PhantomData;
+
+/// Weight functions needed for pallet_evm.
+pub trait WeightInfo {
+	fn withdraw() -> Weight;
+	fn deposit() -> Weight;
+	fn runner_execute(x: u32) -> Weight;
+	fn runner_execute_keccak(x: u32) -> Weight;
+	fn process_deletion_queue(k: u32) -> Weight;
+	fn set_fork_schedule(n: u32) -> Weight;
+}
+
+/// Weights for pallet_evm using the weights of pallet_balances for transfers.
+pub struct SubstrateWeight<T>(PhantomData<T>);
+impl<T: pallet_balances::Config> WeightInfo for SubstrateWeight<T> {
+	fn withdraw() -> Weight {
+		<T::WeightInfo as pallet_balances::WeightInfo>::transfer()
+	}
+	fn deposit() -> Weight {
+		<T::WeightInfo as pallet_balances::WeightInfo>::transfer()
+	}
+	fn runner_execute(x: u32) -> Weight {
+		(31_842_000 as Weight)
+			.saturating_add((19_824 as Weight).saturating_mul(x as Weight))
+			.saturating_add(T::DbWeight::get().reads(4 as Weight))
+			.saturating_add(T::DbWeight::get().writes(2 as Weight))
+	}
+	fn runner_execute_keccak(x: u32) -> Weight {
+		(27_104_000 as Weight)
+			.saturating_add((23_518 as Weight).saturating_mul(x as Weight))
+			.saturating_add(T::DbWeight::get().reads(3 as Weight))
+			.saturating_add(T::DbWeight::get().writes(1 as Weight))
+	}
+	fn process_deletion_queue(k: u32) -> Weight {
+		(4_127_000 as Weight)
+			.saturating_add((1_036_000 as Weight).saturating_mul(k as Weight))
+			.saturating_add(T::DbWeight::get().reads(2 as Weight))
+			.saturating_add(T::DbWeight::get().writes(2 as Weight))
+			.saturating_add(T::DbWeight::get().writes((1 as Weight).saturating_mul(k as Weight)))
+	}
+	fn set_fork_schedule(n: u32) -> Weight {
+		(3_046_000 as Weight)
+			.saturating_add((61_000 as Weight).saturating_mul(n as Weight))
+			.saturating_add(T::DbWeight::get().writes(1 as Weight))
+	}
+}
+
+// For backwards compatibility and tests
+impl WeightInfo for () {
+	fn withdraw() -> Weight {
+		<() as pallet_balances::WeightInfo>::transfer()
+	}
+	fn deposit() -> Weight {
+		<() as pallet_balances::WeightInfo>::transfer()
+	}
+	fn runner_execute(x: u32) -> Weight {
+		(31_842_000 as Weight)
+			.saturating_add((19_824 as Weight).saturating_mul(x as Weight))
+			.saturating_add(RocksDbWeight::get().reads(4 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(2 as Weight))
+	}
+	fn runner_execute_keccak(x: u32) -> Weight {
+		(27_104_000 as Weight)
+			.saturating_add((23_518 as Weight).saturating_mul(x as Weight))
+			.saturating_add(RocksDbWeight::get().reads(3 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(1 as Weight))
+	}
+	fn process_deletion_queue(k: u32) -> Weight {
+		(4_127_000 as Weight)
+			.saturating_add((1_036_000 as Weight).saturating_mul(k as Weight))
+			.saturating_add(RocksDbWeight::get().reads(2 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(2 as Weight))
+			.saturating_add(RocksDbWeight::get().writes((1 as Weight).saturating_mul(k as Weight)))
+	}
+	fn set_fork_schedule(n: u32) -> Weight {
+		(3_046_000 as Weight)
+			.saturating_add((61_000 as Weight).saturating_mul(n as Weight))
+			.saturating_add(RocksDbWeight::get().writes(1 as Weight))
+	}
+}
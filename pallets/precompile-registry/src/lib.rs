@@ -0,0 +1,228 @@
+// SPDX-License-Identifier: Apache-2.0
+// This file is part of Frontier.
+//
+// Copyright (c) 2021 Parity Technologies (UK) Ltd.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! # Precompile registry
+//!
+//! Maps EVM addresses to the precompiles known by the runtime. Root can enable, disable or
+//! relocate precompiles, and the runtime `PrecompileSet` dispatches calls according to the
+//! registry.
+//!
+//! Each registered address carries a small dummy code, so that contracts checking
+//! `EXTCODESIZE` before calling a precompile do not refuse it. The code reverts if it is ever
+//! executed, which only happens if the runtime does not know the precompile anymore.
+//!
+//! Chains started without the registry get the `InitialPrecompiles` enabled by the runtime
+//! upgrade adding it, as new chains get them from the genesis config.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(test)]
+mod tests;
+
+pub use pallet::*;
+
+/// Code stored at the address of enabled precompiles: `PUSH1 0x00 PUSH1 0x00 REVERT`.
+pub const DUMMY_CODE: [u8; 5] = [0x60, 0x00, 0x60, 0x00, 0xfd];
+
+#[frame_support::pallet]
+pub mod pallet {
+	use super::DUMMY_CODE;
+	use frame_support::{dispatch::DispatchResult, pallet_prelude::*};
+	use frame_system::pallet_prelude::*;
+	use sp_core::H160;
+	use sp_runtime::traits::MaybeSerializeDeserialize;
+	use sp_std::prelude::*;
+
+	#[pallet::config]
+	pub trait Config: frame_system::Config + pallet_evm::Config {
+		type Event: From<Event<Self>> + IsType<<Self as frame_system::Config>::Event>;
+		/// Identifier of the precompiles the runtime knows how to execute.
+		type PrecompileId: Parameter + Member + Copy + MaybeSerializeDeserialize;
+		/// Precompiles enabled by the runtime upgrade adding the registry to a running chain.
+		type InitialPrecompiles: Get<Vec<(H160, Self::PrecompileId)>>;
+	}
+
+	/// The current storage version.
+	const STORAGE_VERSION: StorageVersion = StorageVersion::new(1);
+
+	#[pallet::pallet]
+	#[pallet::generate_store(pub(super) trait Store)]
+	#[pallet::storage_version(STORAGE_VERSION)]
+	pub struct Pallet<T>(_);
+
+	#[pallet::hooks]
+	impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
+		fn on_runtime_upgrade() -> Weight {
+			if StorageVersion::get::<Pallet<T>>() >= 1 {
+				return T::DbWeight::get().reads(1);
+			}
+
+			let weight = Pallet::<T>::enable_initial_precompiles();
+			STORAGE_VERSION.put::<Pallet<T>>();
+			weight.saturating_add(T::DbWeight::get().reads_writes(1, 1))
+		}
+	}
+
+	/// Precompile enabled at an address.
+	#[pallet::storage]
+	#[pallet::getter(fn precompile_at)]
+	pub type Precompiles<T: Config> =
+		StorageMap<_, Blake2_128Concat, H160, T::PrecompileId, OptionQuery>;
+
+	/// Address of an enabled precompile.
+	#[pallet::storage]
+	#[pallet::getter(fn precompile_address)]
+	pub type PrecompileAddresses<T: Config> =
+		StorageMap<_, Blake2_128Concat, T::PrecompileId, H160, OptionQuery>;
+
+	#[pallet::genesis_config]
+	pub struct GenesisConfig<T: Config> {
+		pub precompiles: Vec<(H160, T::PrecompileId)>,
+	}
+
+	#[cfg(feature = "std")]
+	impl<T: Config> Default for GenesisConfig<T> {
+		fn default() -> Self {
+			Self { precompiles: Vec::new() }
+		}
+	}
+
+	#[pallet::genesis_build]
+	impl<T: Config> GenesisBuild<T> for GenesisConfig<T> {
+		fn build(&self) {
+			for (address, id) in &self.precompiles {
+				assert!(
+					!<Precompiles<T>>::contains_key(address)
+						&& !<PrecompileAddresses<T>>::contains_key(id),
+					"duplicate precompile in genesis"
+				);
+				Pallet::<T>::register(*id, *address);
+			}
+		}
+	}
+
+	#[pallet::event]
+	#[pallet::generate_deposit(pub(super) fn deposit_event)]
+	pub enum Event<T: Config> {
+		/// A precompile was enabled at an address. \[id, address\]
+		PrecompileEnabled(T::PrecompileId, H160),
+		/// A precompile was disabled. \[id, address\]
+		PrecompileDisabled(T::PrecompileId, H160),
+		/// A precompile was moved to a new address. \[id, old_address, new_address\]
+		PrecompileRelocated(T::PrecompileId, H160, H160),
+	}
+
+	#[pallet::error]
+	pub enum Error<T> {
+		/// The precompile is already enabled.
+		AlreadyEnabled,
+		/// The precompile is not enabled.
+		NotEnabled,
+		/// Another precompile is enabled at the address.
+		AddressInUse,
+		/// The address holds contract code.
+		AddressHasCode,
+	}
+
+	#[pallet::call]
+	impl<T: Config> Pallet<T> {
+		/// Enable a precompile at an address.
+		#[pallet::weight(10_000 + T::DbWeight::get().reads_writes(3, 3))]
+		pub fn enable_precompile(
+			origin: OriginFor<T>,
+			id: T::PrecompileId,
+			address: H160,
+		) -> DispatchResult {
+			ensure_root(origin)?;
+			ensure!(!<PrecompileAddresses<T>>::contains_key(&id), Error::<T>::AlreadyEnabled);
+			Self::ensure_available(&address)?;
+
+			Self::register(id, address);
+			Self::deposit_event(Event::PrecompileEnabled(id, address));
+			Ok(())
+		}
+
+		/// Disable a precompile.
+		#[pallet::weight(10_000 + T::DbWeight::get().reads_writes(1, 3))]
+		pub fn disable_precompile(origin: OriginFor<T>, id: T::PrecompileId) -> DispatchResult {
+			ensure_root(origin)?;
+			let address = <PrecompileAddresses<T>>::get(&id).ok_or(Error::<T>::NotEnabled)?;
+
+			Self::unregister(id, address);
+			Self::deposit_event(Event::PrecompileDisabled(id, address));
+			Ok(())
+		}
+
+		/// Move an enabled precompile to a new address.
+		#[pallet::weight(10_000 + T::DbWeight::get().reads_writes(3, 6))]
+		pub fn relocate_precompile(
+			origin: OriginFor<T>,
+			id: T::PrecompileId,
+			new_address: H160,
+		) -> DispatchResult {
+			ensure_root(origin)?;
+			let old_address = <PrecompileAddresses<T>>::get(&id).ok_or(Error::<T>::NotEnabled)?;
+			Self::ensure_available(&new_address)?;
+
+			Self::unregister(id, old_address);
+			Self::register(id, new_address);
+			Self::deposit_event(Event::PrecompileRelocated(id, old_address, new_address));
+			Ok(())
+		}
+	}
+
+	impl<T: Config> Pallet<T> {
+		/// Whether a precompile is enabled at an address.
+		pub fn is_precompile(address: &H160) -> bool {
+			<Precompiles<T>>::contains_key(address)
+		}
+
+		/// Enable the `InitialPrecompiles` whose id and address are both free, as the genesis
+		/// config would have.
+		pub(crate) fn enable_initial_precompiles() -> Weight {
+			let precompiles = T::InitialPrecompiles::get();
+			let count = precompiles.len() as Weight;
+			for (address, id) in precompiles {
+				if !<PrecompileAddresses<T>>::contains_key(&id)
+					&& Self::ensure_available(&address).is_ok()
+				{
+					Self::register(id, address);
+				}
+			}
+			// Same accesses as `enable_precompile`, plus the code refcount of the dummy code.
+			T::DbWeight::get().reads_writes(4, 5).saturating_mul(count)
+		}
+
+		fn ensure_available(address: &H160) -> DispatchResult {
+			ensure!(!<Precompiles<T>>::contains_key(address), Error::<T>::AddressInUse);
+			ensure!(!pallet_evm::Pallet::<T>::has_code(address), Error::<T>::AddressHasCode);
+			Ok(())
+		}
+
+		fn register(id: T::PrecompileId, address: H160) {
+			<Precompiles<T>>::insert(address, id);
+			<PrecompileAddresses<T>>::insert(id, address);
+			pallet_evm::Pallet::<T>::create_account(address, DUMMY_CODE.to_vec());
+		}
+
+		fn unregister(id: T::PrecompileId, address: H160) {
+			<Precompiles<T>>::remove(address);
+			<PrecompileAddresses<T>>::remove(id);
+			pallet_evm::Pallet::<T>::remove_account(&address);
+		}
+	}
+}
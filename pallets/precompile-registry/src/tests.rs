@@ -0,0 +1,266 @@
+// SPDX-License-Identifier: Apache-2.0
+// This file is part of Frontier.
+//
+// Copyright (c) 2021 Parity Technologies (UK) Ltd.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::*;
+use crate as pallet_precompile_registry;
+
+use frame_support::{
+	assert_noop, assert_ok, parameter_types,
+	traits::{GenesisBuild, Hooks, StorageVersion},
+};
+use pallet_evm::{EnsureAddressNever, EnsureAddressRoot, IdentityAddressMapping};
+use sp_core::{H160, H256, U256};
+use sp_runtime::{
+	generic,
+	traits::{BlakeTwo256, IdentityLookup},
+	DispatchError,
+};
+
+type UncheckedExtrinsic = frame_system::mocking::MockUncheckedExtrinsic<Test>;
+type Block = frame_system::mocking::MockBlock<Test>;
+
+frame_support::construct_runtime! {
+	pub enum Test where
+		Block = Block,
+		NodeBlock = Block,
+		UncheckedExtrinsic = UncheckedExtrinsic,
+	{
+		System: frame_system::{Pallet, Call, Config, Storage, Event<T>},
+		Balances: pallet_balances::{Pallet, Call, Storage, Config<T>, Event<T>},
+		Timestamp: pallet_timestamp::{Pallet, Call, Storage},
+		EVM: pallet_evm::{Pallet, Call, Storage, Config, Event<T>},
+		PrecompileRegistry: pallet_precompile_registry::{Pallet, Call, Storage, Config<T>, Event<T>},
+	}
+}
+
+parameter_types! {
+	pub const BlockHashCount: u64 = 250;
+}
+impl frame_system::Config for Test {
+	type BaseCallFilter = frame_support::traits::Everything;
+	type BlockWeights = ();
+	type BlockLength = ();
+	type DbWeight = ();
+	type Origin = Origin;
+	type Index = u64;
+	type BlockNumber = u64;
+	type Hash = H256;
+	type Call = Call;
+	type Hashing = BlakeTwo256;
+	type AccountId = H160;
+	type Lookup = IdentityLookup<Self::AccountId>;
+	type Header = generic::Header<u64, BlakeTwo256>;
+	type Event = Event;
+	type BlockHashCount = BlockHashCount;
+	type Version = ();
+	type PalletInfo = PalletInfo;
+	type AccountData = pallet_balances::AccountData<u64>;
+	type OnNewAccount = ();
+	type OnKilledAccount = ();
+	type SystemWeightInfo = ();
+	type SS58Prefix = ();
+	type OnSetCode = ();
+}
+
+parameter_types! {
+	pub const ExistentialDeposit: u64 = 0;
+}
+impl pallet_balances::Config for Test {
+	type MaxLocks = ();
+	type Balance = u64;
+	type DustRemoval = ();
+	type Event = Event;
+	type ExistentialDeposit = ExistentialDeposit;
+	type AccountStore = System;
+	type WeightInfo = ();
+	type MaxReserves = ();
+	type ReserveIdentifier = ();
+}
+
+parameter_types! {
+	pub const MinimumPeriod: u64 = 1000;
+}
+impl pallet_timestamp::Config for Test {
+	type Moment = u64;
+	type OnTimestampSet = ();
+	type MinimumPeriod = MinimumPeriod;
+	type WeightInfo = ();
+}
+
+pub struct FixedGasPrice;
+impl pallet_evm::FeeCalculator for FixedGasPrice {
+	fn min_gas_price() -> U256 {
+		1_000_000_000u128.into()
+	}
+}
+
+impl pallet_evm::Config for Test {
+	type FeeCalculator = FixedGasPrice;
+	type GasWeightMapping = ();
+	type CallOrigin = EnsureAddressRoot<Self::AccountId>;
+	type WithdrawOrigin = EnsureAddressNever<Self::AccountId>;
+	type AddressMapping = IdentityAddressMapping;
+	type Currency = Balances;
+	type Runner = pallet_evm::runner::stack::Runner<Self>;
+	type Event = Event;
+	type PrecompilesType = ();
+	type PrecompilesValue = ();
+	type ChainId = ();
+	type BlockGasLimit = ();
+	type GasLimitPovSizeRatio = ();
+	type MaxBlockProofSize = ();
+	type MaxCodeSize = ();
+	type MaxTransactionGas = ();
+	type OnChargeTransaction = ();
+	type BlockHashMapping = pallet_evm::SubstrateBlockHashMapping<Self>;
+	type FindAuthor = ();
+	type WeightInfo = ();
+}
+
+parameter_types! {
+	pub InitialPrecompiles: Vec<(H160, u8)> =
+		vec![(H160::from_low_u64_be(1), 1), (H160::from_low_u64_be(3), 3)];
+}
+
+impl Config for Test {
+	type Event = Event;
+	type PrecompileId = u8;
+	type InitialPrecompiles = InitialPrecompiles;
+}
+
+fn address(a: u64) -> H160 {
+	H160::from_low_u64_be(a)
+}
+
+fn new_test_ext() -> sp_io::TestExternalities {
+	let mut t = frame_system::GenesisConfig::default().build_storage::<Test>().unwrap();
+	pallet_precompile_registry::GenesisConfig::<Test> {
+		precompiles: vec![(address(1), 1), (address(2), 2)],
+	}
+	.assimilate_storage(&mut t)
+	.unwrap();
+	t.into()
+}
+
+#[test]
+fn genesis_precompiles_are_enabled_with_dummy_code() {
+	new_test_ext().execute_with(|| {
+		assert_eq!(PrecompileRegistry::precompile_at(address(1)), Some(1));
+		assert_eq!(PrecompileRegistry::precompile_address(2), Some(address(2)));
+		assert!(PrecompileRegistry::is_precompile(&address(2)));
+		assert!(!PrecompileRegistry::is_precompile(&address(3)));
+		assert_eq!(EVM::account_codes(address(1)), DUMMY_CODE.to_vec());
+	});
+}
+
+#[test]
+fn enable_precompile_works() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(PrecompileRegistry::enable_precompile(Origin::root(), 3, address(3)));
+		assert_eq!(PrecompileRegistry::precompile_at(address(3)), Some(3));
+		assert_eq!(EVM::account_codes(address(3)), DUMMY_CODE.to_vec());
+	});
+}
+
+#[test]
+fn enable_precompile_requires_root() {
+	new_test_ext().execute_with(|| {
+		assert_noop!(
+			PrecompileRegistry::enable_precompile(Origin::signed(address(42)), 3, address(3)),
+			DispatchError::BadOrigin
+		);
+	});
+}
+
+#[test]
+fn enable_precompile_checks_id_and_address() {
+	new_test_ext().execute_with(|| {
+		assert_noop!(
+			PrecompileRegistry::enable_precompile(Origin::root(), 1, address(3)),
+			Error::<Test>::AlreadyEnabled
+		);
+		assert_noop!(
+			PrecompileRegistry::enable_precompile(Origin::root(), 3, address(1)),
+			Error::<Test>::AddressInUse
+		);
+		pallet_evm::Pallet::<Test>::create_account(address(4), vec![0x00]);
+		assert_noop!(
+			PrecompileRegistry::enable_precompile(Origin::root(), 3, address(4)),
+			Error::<Test>::AddressHasCode
+		);
+	});
+}
+
+#[test]
+fn disable_precompile_removes_dummy_code() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(PrecompileRegistry::disable_precompile(Origin::root(), 1));
+		assert_eq!(PrecompileRegistry::precompile_at(address(1)), None);
+		assert_eq!(PrecompileRegistry::precompile_address(1), None);
+		assert!(EVM::account_codes(address(1)).is_empty());
+
+		assert_noop!(
+			PrecompileRegistry::disable_precompile(Origin::root(), 1),
+			Error::<Test>::NotEnabled
+		);
+	});
+}
+
+#[test]
+fn relocate_precompile_works() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(PrecompileRegistry::relocate_precompile(Origin::root(), 1, address(1024)));
+		assert_eq!(PrecompileRegistry::precompile_at(address(1)), None);
+		assert_eq!(PrecompileRegistry::precompile_at(address(1024)), Some(1));
+		assert_eq!(PrecompileRegistry::precompile_address(1), Some(address(1024)));
+		assert!(EVM::account_codes(address(1)).is_empty());
+		assert_eq!(EVM::account_codes(address(1024)), DUMMY_CODE.to_vec());
+
+		assert_noop!(
+			PrecompileRegistry::relocate_precompile(Origin::root(), 1, address(2)),
+			Error::<Test>::AddressInUse
+		);
+		assert_noop!(
+			PrecompileRegistry::relocate_precompile(Origin::root(), 3, address(3)),
+			Error::<Test>::NotEnabled
+		);
+	});
+}
+
+#[test]
+fn runtime_upgrade_enables_initial_precompiles() {
+	sp_io::TestExternalities::new(
+		frame_system::GenesisConfig::default().build_storage::<Test>().unwrap(),
+	)
+	.execute_with(|| {
+		StorageVersion::new(0).put::<PrecompileRegistry>();
+		pallet_evm::Pallet::<Test>::create_account(address(3), vec![0x00]);
+
+		PrecompileRegistry::on_runtime_upgrade();
+
+		assert_eq!(StorageVersion::get::<PrecompileRegistry>(), 1);
+		assert_eq!(PrecompileRegistry::precompile_at(address(1)), Some(1));
+		assert_eq!(EVM::account_codes(address(1)), DUMMY_CODE.to_vec());
+		// Contract code is never replaced by a precompile.
+		assert_eq!(PrecompileRegistry::precompile_address(3), None);
+		assert_eq!(EVM::account_codes(address(3)), vec![0x00]);
+
+		assert_ok!(PrecompileRegistry::disable_precompile(Origin::root(), 1));
+		PrecompileRegistry::on_runtime_upgrade();
+		assert_eq!(PrecompileRegistry::precompile_at(address(1)), None);
+	});
+}
@@ -0,0 +1,122 @@
+// SPDX-License-Identifier: Apache-2.0
+// This file is part of Frontier.
+//
+// Copyright (c) 2020 Parity Technologies (UK) Ltd.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! BIP-32 key derivation, as used by Ethereum wallets, for development accounts.
+
+use bip39::{Language, Mnemonic, Seed};
+use hmac::{Hmac, Mac, NewMac};
+use sha2::Sha512;
+use sp_core::{ecdsa, Pair};
+
+/// Derivation path of Ethereum accounts, to be followed by the account index.
+pub const ETHEREUM_DERIVATION_PATH: &str = "m/44'/60'/0'/0";
+
+const HARDENED: u32 = 1 << 31;
+
+/// Derive the key pair at `path` (e.g. `m/44'/60'/0'/0/0`) from a BIP-39 `phrase`.
+///
+/// Returns `None` if the phrase or the path is malformed.
+pub fn derive_bip32_pair(phrase: &str, path: &str) -> Option<ecdsa::Pair> {
+	let mnemonic = Mnemonic::from_phrase(phrase, Language::English).ok()?;
+	let seed = Seed::new(&mnemonic, "");
+
+	let master = hmac_sha512(b"Bitcoin seed", seed.as_bytes());
+	let mut key = libsecp256k1::SecretKey::parse_slice(&master[..32]).ok()?;
+	let mut chain_code = [0u8; 32];
+	chain_code.copy_from_slice(&master[32..]);
+
+	for index in parse_path(path)? {
+		let mut data = Vec::with_capacity(37);
+		if index >= HARDENED {
+			data.push(0);
+			data.extend_from_slice(&key.serialize());
+		} else {
+			let public = libsecp256k1::PublicKey::from_secret_key(&key);
+			data.extend_from_slice(&public.serialize_compressed());
+		}
+		data.extend_from_slice(&index.to_be_bytes());
+
+		let child = hmac_sha512(&chain_code, &data);
+		let tweak = libsecp256k1::SecretKey::parse_slice(&child[..32]).ok()?;
+		key.tweak_add_assign(&tweak).ok()?;
+		chain_code.copy_from_slice(&child[32..]);
+	}
+
+	ecdsa::Pair::from_seed_slice(&key.serialize()).ok()
+}
+
+/// Derive the `index`-th Ethereum account of a BIP-39 `phrase`.
+pub fn derive_ethereum_pair(phrase: &str, index: u32) -> Option<ecdsa::Pair> {
+	derive_bip32_pair(phrase, &format!("{}/{}", ETHEREUM_DERIVATION_PATH, index))
+}
+
+fn hmac_sha512(key: &[u8], data: &[u8]) -> [u8; 64] {
+	let mut mac =
+		Hmac::<Sha512>::new_from_slice(key).expect("HMAC accepts keys of any length; qed");
+	mac.update(data);
+	let mut out = [0u8; 64];
+	out.copy_from_slice(&mac.finalize().into_bytes());
+	out
+}
+
+fn parse_path(path: &str) -> Option<Vec<u32>> {
+	let mut parts = path.split('/');
+	if parts.next()? != "m" {
+		return None;
+	}
+	parts
+		.map(|part| match part.strip_suffix('\'') {
+			Some(index) => {
+				index.parse::<u32>().ok().filter(|i| *i < HARDENED).map(|i| i + HARDENED)
+			},
+			None => part.parse::<u32>().ok().filter(|i| *i < HARDENED),
+		})
+		.collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::EthereumSigner;
+	use hex_literal::hex;
+	use sp_core::{crypto::DEV_PHRASE, H160};
+	use sp_runtime::traits::IdentifyAccount;
+
+	#[test]
+	fn derives_dev_accounts() {
+		let expected = [
+			hex!("f24ff3a9cf04c71dbc94d0b566f7a27b94566cac"),
+			hex!("3cd0a705a2dc65e5b1e1205896baa2be8a07c6e0"),
+			hex!("798d4ba9baf0064ec19eb4f0a1a45785ae9d6dfc"),
+			hex!("773539d4ac0e786233d90a233654ccee26a613d9"),
+			hex!("ff64d3f6efe2317ee2807d223a0bdc4c0c49dfdb"),
+			hex!("c0f0f4ab324c46e55d02d0033343b4be8a55532d"),
+		];
+		for (index, address) in expected.iter().enumerate() {
+			let pair = derive_ethereum_pair(DEV_PHRASE, index as u32).unwrap();
+			assert_eq!(EthereumSigner::from(pair.public()).into_account(), H160(*address));
+		}
+	}
+
+	#[test]
+	fn rejects_malformed_input() {
+		assert!(derive_bip32_pair("not a mnemonic", "m/0").is_none());
+		assert!(derive_bip32_pair(DEV_PHRASE, "44'/60'").is_none());
+		assert!(derive_bip32_pair(DEV_PHRASE, "m/x").is_none());
+		assert!(derive_bip32_pair(DEV_PHRASE, "m/2147483648").is_none());
+	}
+}
@@ -0,0 +1,166 @@
+// SPDX-License-Identifier: Apache-2.0
+// This file is part of Frontier.
+//
+// Copyright (c) 2020 Parity Technologies (UK) Ltd.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Primitives for runtimes whose accounts are plain 20-byte Ethereum addresses.
+//!
+//! Substrate extrinsics of such runtimes are signed the way Ethereum signs messages: the
+//! payload is hashed with Keccak-256 and signed with a recoverable secp256k1 signature, and
+//! the signer is the address recovered from it.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use codec::{Decode, Encode, MaxEncodedLen};
+use scale_info::TypeInfo;
+#[cfg(feature = "std")]
+use serde::{Deserialize, Serialize};
+use sp_core::{ecdsa, H160, H256};
+use sp_io::hashing::keccak_256;
+use sp_runtime::{
+	traits::{IdentifyAccount, Lazy, Verify},
+	RuntimeDebug,
+};
+
+#[cfg(feature = "std")]
+pub mod derivation;
+
+/// Recoverable secp256k1 signature of the Keccak-256 hash of a payload.
+#[derive(Eq, PartialEq, Clone, Encode, Decode, RuntimeDebug, TypeInfo)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub struct EthereumSignature(ecdsa::Signature);
+
+impl From<ecdsa::Signature> for EthereumSignature {
+	fn from(signature: ecdsa::Signature) -> Self {
+		EthereumSignature(signature)
+	}
+}
+
+impl From<[u8; 65]> for EthereumSignature {
+	fn from(raw: [u8; 65]) -> Self {
+		EthereumSignature(ecdsa::Signature::from_raw(raw))
+	}
+}
+
+impl Verify for EthereumSignature {
+	type Signer = EthereumSigner;
+
+	fn verify<L: Lazy<[u8]>>(&self, mut msg: L, signer: &H160) -> bool {
+		let signature: &[u8; 65] = self.0.as_ref();
+		match sp_io::crypto::secp256k1_ecdsa_recover(signature, &keccak_256(msg.get())) {
+			Ok(public) => H160::from(H256::from(keccak_256(&public))) == *signer,
+			Err(_) => false,
+		}
+	}
+}
+
+/// The signer of an [`EthereumSignature`]: the Ethereum address of the signing key.
+#[derive(
+	Eq,
+	PartialEq,
+	Ord,
+	PartialOrd,
+	Clone,
+	Copy,
+	Default,
+	Encode,
+	Decode,
+	MaxEncodedLen,
+	RuntimeDebug,
+	TypeInfo,
+)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub struct EthereumSigner([u8; 20]);
+
+impl IdentifyAccount for EthereumSigner {
+	type AccountId = H160;
+
+	fn into_account(self) -> H160 {
+		H160(self.0)
+	}
+}
+
+impl From<[u8; 20]> for EthereumSigner {
+	fn from(address: [u8; 20]) -> Self {
+		EthereumSigner(address)
+	}
+}
+
+impl From<H160> for EthereumSigner {
+	fn from(address: H160) -> Self {
+		EthereumSigner(address.0)
+	}
+}
+
+impl From<ecdsa::Public> for EthereumSigner {
+	fn from(public: ecdsa::Public) -> Self {
+		let public = libsecp256k1::PublicKey::parse_slice(
+			public.as_ref(),
+			Some(libsecp256k1::PublicKeyFormat::Compressed),
+		)
+		.expect("ecdsa public keys are valid compressed secp256k1 points; qed");
+		let hash = keccak_256(&public.serialize()[1..]);
+		let mut address = [0u8; 20];
+		address.copy_from_slice(&hash[12..]);
+		EthereumSigner(address)
+	}
+}
+
+#[cfg(feature = "std")]
+impl std::fmt::Display for EthereumSigner {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		write!(f, "{:?}", H160(self.0))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use hex_literal::hex;
+	use sp_core::Pair;
+
+	fn sign(pair: &ecdsa::Pair, message: &[u8]) -> EthereumSignature {
+		pair.sign_prehashed(&keccak_256(message)).into()
+	}
+
+	#[test]
+	fn signer_is_ethereum_address_of_public_key() {
+		// Private key and address of the first dev account ("Alith").
+		let pair = ecdsa::Pair::from_seed(&hex!(
+			"5fb92d6e98884f76de468fa3f6278f8807c48bebc13595d45af5bdc4da702133"
+		));
+		assert_eq!(
+			EthereumSigner::from(pair.public()).into_account(),
+			H160(hex!("f24ff3a9cf04c71dbc94d0b566f7a27b94566cac")),
+		);
+	}
+
+	#[test]
+	fn verify_works() {
+		let pair = ecdsa::Pair::from_seed(&[7u8; 32]);
+		let signer = EthereumSigner::from(pair.public()).into_account();
+		let signature = sign(&pair, b"payload");
+
+		assert!(signature.verify(&b"payload"[..], &signer));
+		assert!(!signature.verify(&b"other payload"[..], &signer));
+		assert!(!signature.verify(&b"payload"[..], &H160::repeat_byte(1)));
+	}
+
+	#[test]
+	fn verify_rejects_malformed_signature() {
+		let signature = EthereumSignature::from([0u8; 65]);
+		assert!(!signature.verify(&b"payload"[..], &H160::zero()));
+	}
+}
@@ -59,7 +59,7 @@ sp_api::decl_runtime_apis! {
 		fn account_basic(address: H160) -> fp_evm::Account;
 		/// Returns FixedGasPrice::min_gas_price
 		fn gas_price() -> U256;
-		/// For a given account address, returns pallet_evm::AccountCodes.
+		/// For a given account address, returns its code from pallet_evm::Codes.
 		fn account_code_at(address: H160) -> Vec<u8>;
 		/// Returns the converted FindAuthor::find_author authority id.
 		fn author() -> H160;
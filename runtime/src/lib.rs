@@ -13,9 +13,9 @@ use sp_api::impl_runtime_apis;
 use sp_core::{crypto::KeyTypeId, OpaqueMetadata, H160, H256, U256};
 use sp_runtime::{
 	create_runtime_str, generic, impl_opaque_keys,
-	traits::{AccountIdLookup, BlakeTwo256, Block as BlockT, IdentifyAccount, Verify, Dispatchable, PostDispatchInfoOf},
+	traits::{BlakeTwo256, Block as BlockT, IdentifyAccount, Verify, Dispatchable, PostDispatchInfoOf},
 	transaction_validity::{TransactionSource, TransactionValidity, TransactionValidityError},
-	ApplyExtrinsicResult, MultiSignature,
+	ApplyExtrinsicResult,
 };
 
 use sp_std::{marker::PhantomData, prelude::*};
@@ -28,7 +28,7 @@ use fp_rpc::TransactionStatus;
 
 use frame_support::{
 	construct_runtime, match_type, parameter_types,
-	traits::{FindAuthor, Everything, Nothing},
+	traits::{Contains, FindAuthor, Everything, Get, Nothing},
 	weights::{
 		constants::{BlockExecutionWeight, ExtrinsicBaseWeight, WEIGHT_PER_SECOND},
 		DispatchClass, IdentityFee, Weight, WeightToFeeCoefficient, WeightToFeeCoefficients,
@@ -44,10 +44,11 @@ pub use sp_consensus_aura::sr25519::AuthorityId as AuraId;
 pub use sp_runtime::{MultiAddress, Perbill, Permill};
 
 use pallet_ethereum::{Call::transact, Transaction as EthereumTransaction};
-use pallet_evm::{Account as EVMAccount, EnsureAddressTruncated, HashedAddressMapping, Runner};
+use pallet_evm::{Account as EVMAccount, Runner};
 
 mod precompiles;
 use precompiles::FrontierPrecompiles;
+pub use precompiles::{genesis_precompiles, KnownPrecompile};
 
 #[cfg(any(feature = "std", test))]
 pub use sp_runtime::BuildStorage;
@@ -60,19 +61,23 @@ use polkadot_runtime_common::{BlockHashCount, RocksDbWeight, SlowAdjustingFeeUpd
 // XCM Imports
 use xcm::latest::prelude::*;
 use xcm_builder::{
-	AccountId32Aliases, AllowTopLevelPaidExecutionFrom, AllowUnpaidExecutionFrom, CurrencyAdapter,
-	EnsureXcmOrigin, FixedWeightBounds, IsConcrete, LocationInverter, NativeAsset, ParentIsDefault,
-	RelayChainAsNative, SiblingParachainAsNative, SiblingParachainConvertsVia,
-	SignedAccountId32AsNative, SignedToAccountId32, SovereignSignedViaLocation, TakeWeightCredit,
-	UsingComponents,
+	AllowTopLevelPaidExecutionFrom, AllowUnpaidExecutionFrom, ConvertedConcreteAssetId,
+	CurrencyAdapter, EnsureXcmOrigin, FixedWeightBounds, FungiblesAdapter, IsConcrete,
+	LocationInverter, NativeAsset, ParentIsDefault, RelayChainAsNative, SiblingParachainAsNative,
+	SiblingParachainConvertsVia, SovereignSignedViaLocation, TakeWeightCredit, UsingComponents,
 };
-use xcm_executor::{Config, XcmExecutor};
+use xcm_executor::{traits::JustTry, Config, XcmExecutor};
 
 /// Import the template pallet.
 pub use pallet_template;
 
 /// Alias to 512-bit hash when used in the context of a transaction signature on the chain.
-pub type Signature = MultiSignature;
+#[cfg(not(feature = "account-id-20"))]
+pub type Signature = sp_runtime::MultiSignature;
+
+/// Ethereum signature of the transaction payload, making accounts 20-byte Ethereum addresses.
+#[cfg(feature = "account-id-20")]
+pub type Signature = fp_account::EthereumSignature;
 
 /// Some way of identifying an account on the chain. We intentionally make it equivalent
 /// to the public key of our transaction signing scheme.
@@ -81,6 +86,9 @@ pub type AccountId = <<Signature as Verify>::Signer as IdentifyAccount>::Account
 /// Balance of an account.
 pub type Balance = u128;
 
+/// Identifier of the assets of `pallet_assets`.
+pub type AssetId = u128;
+
 /// Index of a transaction in the chain.
 pub type Index = u32;
 
@@ -91,8 +99,13 @@ pub type Hash = sp_core::H256;
 pub type BlockNumber = u32;
 
 /// The address format for describing accounts.
+#[cfg(not(feature = "account-id-20"))]
 pub type Address = MultiAddress<AccountId, ()>;
 
+/// The address format for describing accounts: the account itself.
+#[cfg(feature = "account-id-20")]
+pub type Address = AccountId;
+
 /// Block header type as expected by this runtime.
 pub type Header = generic::Header<BlockNumber, BlakeTwo256>;
 
@@ -234,7 +247,7 @@ pub const VERSION: RuntimeVersion = RuntimeVersion {
 	spec_name: create_runtime_str!("template-parachain"),
 	impl_name: create_runtime_str!("template-parachain"),
 	authoring_version: 1,
-	spec_version: 1,
+	spec_version: 4,
 	impl_version: 0,
 	apis: RUNTIME_API_VERSIONS,
 	transaction_version: 1,
@@ -276,6 +289,16 @@ const NORMAL_DISPATCH_RATIO: Perbill = Perbill::from_percent(75);
 /// We allow for 0.5 of a second of compute with a 12 second average block time.
 const MAXIMUM_BLOCK_WEIGHT: Weight = WEIGHT_PER_SECOND / 2;
 
+/// The relay chain accepts proofs of validity of up to 5 MiB per block.
+const MAX_POV_SIZE: u64 = 5 * 1024 * 1024;
+
+/// Gas executed per second of compute. An estimate, until the weight of gas is measured by the
+/// `runner_execute` benchmarks of `pallet_evm` on the reference hardware.
+const GAS_PER_SECOND: u64 = 40_000_000;
+
+/// Weight of a unit of gas.
+const WEIGHT_PER_GAS: Weight = WEIGHT_PER_SECOND / GAS_PER_SECOND;
+
 /// The version information used to identify this runtime when compiled natively.
 #[cfg(feature = "std")]
 pub fn native_version() -> NativeVersion {
@@ -314,13 +337,26 @@ parameter_types! {
 
 // Configure FRAME pallets to include in runtime.
 
+/// Calls that can be dispatched: all but `Assets::create`. Assets are created by root with
+/// `force_create`, so that an asset id cannot be taken before the asset is registered.
+pub struct BaseFilter;
+
+impl Contains<Call> for BaseFilter {
+	fn contains(call: &Call) -> bool {
+		!matches!(call, Call::Assets(pallet_assets::Call::create { .. }))
+	}
+}
+
 impl frame_system::Config for Runtime {
 	/// The identifier used to distinguish between accounts.
 	type AccountId = AccountId;
 	/// The aggregated dispatch type that is available for extrinsics.
 	type Call = Call;
 	/// The lookup mechanism to get account ID from whatever is passed in dispatchers.
-	type Lookup = AccountIdLookup<AccountId, ()>;
+	#[cfg(not(feature = "account-id-20"))]
+	type Lookup = sp_runtime::traits::AccountIdLookup<AccountId, ()>;
+	#[cfg(feature = "account-id-20")]
+	type Lookup = sp_runtime::traits::IdentityLookup<AccountId>;
 	/// The index type for storing how many extrinsics an account has signed.
 	type Index = Index;
 	/// The index type for blocks.
@@ -350,7 +386,7 @@ impl frame_system::Config for Runtime {
 	/// The weight of database operations that the runtime can invoke.
 	type DbWeight = RocksDbWeight;
 	/// The basic call filter to use in dispatchable.
-	type BaseCallFilter = Everything;
+	type BaseCallFilter = BaseFilter;
 	/// Weight information for the extrinsics of this pallet.
 	type SystemWeightInfo = ();
 	/// Block & extrinsics weights: base values and limits.
@@ -406,6 +442,30 @@ impl pallet_balances::Config for Runtime {
 	type ReserveIdentifier = [u8; 8];
 }
 
+parameter_types! {
+	pub const AssetDeposit: Balance = 10 * UNIT;
+	pub const AssetApprovalDeposit: Balance = EXISTENTIAL_DEPOSIT;
+	pub const AssetsStringLimit: u32 = 50;
+	pub const MetadataDepositBase: Balance = UNIT;
+	pub const MetadataDepositPerByte: Balance = 10 * MILLIUNIT;
+}
+
+impl pallet_assets::Config for Runtime {
+	type Event = Event;
+	type Balance = Balance;
+	type AssetId = AssetId;
+	type Currency = Balances;
+	type ForceOrigin = EnsureRoot<AccountId>;
+	type AssetDeposit = AssetDeposit;
+	type MetadataDepositBase = MetadataDepositBase;
+	type MetadataDepositPerByte = MetadataDepositPerByte;
+	type ApprovalDeposit = AssetApprovalDeposit;
+	type StringLimit = AssetsStringLimit;
+	type Freezer = ();
+	type Extra = ();
+	type WeightInfo = pallet_assets::weights::SubstrateWeight<Runtime>;
+}
+
 parameter_types! {
 	/// Relay Chain `TransactionByteFee` / 10
 	pub const TransactionByteFee: Balance = 10 * MICROUNIT;
@@ -436,33 +496,90 @@ impl<F: FindAuthor<u32>> FindAuthor<H160> for FindAuthorTruncated<F> {
 	}
 }
 
+/// Converts gas to weight at `WEIGHT_PER_GAS`.
+pub struct FixedGasWeightMapping;
+impl pallet_evm::GasWeightMapping for FixedGasWeightMapping {
+	fn gas_to_weight(gas: u64) -> Weight {
+		gas.saturating_mul(WEIGHT_PER_GAS)
+	}
+	fn weight_to_gas(weight: Weight) -> u64 {
+		weight / WEIGHT_PER_GAS
+	}
+}
+
 parameter_types! {
 	pub const ChainId: u64 = 42;
-	pub BlockGasLimit: U256 = U256::from(u32::max_value());
 	pub PrecompilesValue: FrontierPrecompiles<Runtime> = FrontierPrecompiles::<_>::new();
+	/// EVM executions may fill the proof of validity as much as normal dispatches fill the block.
+	pub MaxBlockProofSize: u64 = NORMAL_DISPATCH_RATIO * MAX_POV_SIZE;
+	/// Gas per byte of proof, so that the proof of a block full of gas fits `MaxBlockProofSize`.
+	pub GasLimitPovSizeRatio: u64 = {
+		let block_gas_limit = pallet_evm::BlockGasLimitFromWeight::<Runtime>::get().low_u64();
+		(block_gas_limit + MaxBlockProofSize::get() - 1) / MaxBlockProofSize::get()
+	};
+	/// Twice the limit of EIP-170, for contracts too large for Ethereum.
+	pub const MaxCodeSize: u32 = 2 * 0x6000;
+	/// A transaction may use at most a quarter of a block.
+	pub MaxTransactionGas: u64 = pallet_evm::BlockGasLimitFromWeight::<Runtime>::get().low_u64() / 4;
 }
 
 impl pallet_evm::Config for Runtime {
 	type FeeCalculator = BaseFee;
-	type GasWeightMapping = ();
+	// `pallet_evm::BenchmarkedGasWeightMapping` once the weights of `pallet_evm` are benchmarked
+	// on the reference hardware.
+	type GasWeightMapping = FixedGasWeightMapping;
 	type BlockHashMapping = pallet_ethereum::EthereumBlockHashMapping<Self>;
-	type CallOrigin = EnsureAddressTruncated;
-	type WithdrawOrigin = EnsureAddressTruncated;
-	type AddressMapping = HashedAddressMapping<BlakeTwo256>;
+	// Addresses linked to an account by `AccountLink` use it, other addresses a hashed account.
+	#[cfg(not(feature = "account-id-20"))]
+	type CallOrigin =
+		pallet_account_link::EnsureAddressLinkedOr<Self, pallet_evm::EnsureAddressTruncated>;
+	#[cfg(not(feature = "account-id-20"))]
+	type WithdrawOrigin =
+		pallet_account_link::EnsureAddressLinkedOr<Self, pallet_evm::EnsureAddressTruncated>;
+	#[cfg(not(feature = "account-id-20"))]
+	type AddressMapping = pallet_account_link::LinkedAddressMapping<Self>;
+	// Substrate and EVM accounts are the same.
+	#[cfg(feature = "account-id-20")]
+	type CallOrigin = pallet_evm::EnsureAddressSame;
+	#[cfg(feature = "account-id-20")]
+	type WithdrawOrigin = pallet_evm::EnsureAddressSame;
+	#[cfg(feature = "account-id-20")]
+	type AddressMapping = pallet_evm::IdentityAddressMapping;
 	type Currency = Balances;
 	type Event = Event;
 	type Runner = pallet_evm::runner::stack::Runner<Self>;
 	type PrecompilesType = FrontierPrecompiles<Self>;
 	type PrecompilesValue = PrecompilesValue;
 	type ChainId = ChainId;
-	type BlockGasLimit = BlockGasLimit;
+	type BlockGasLimit = pallet_evm::BlockGasLimitFromWeight<Self>;
+	type GasLimitPovSizeRatio = GasLimitPovSizeRatio;
+	type MaxBlockProofSize = MaxBlockProofSize;
+	type MaxCodeSize = MaxCodeSize;
+	type MaxTransactionGas = MaxTransactionGas;
 	type OnChargeTransaction = ();
 	type FindAuthor = FindAuthorTruncated<Aura>;
+	type WeightInfo = pallet_evm::weights::SubstrateWeight<Runtime>;
 }
 
 impl pallet_ethereum::Config for Runtime {
 	type Event = Event;
 	type StateRoot = pallet_ethereum::IntermediateStateRoot;
+	type WeightInfo = pallet_ethereum::weights::SubstrateWeight<Runtime>;
+}
+
+impl pallet_ethereum_xcm::Config for Runtime {
+	type XcmOrigin = pallet_xcm::EnsureXcm<Everything>;
+}
+
+impl pallet_account_link::Config for Runtime {
+	type Event = Event;
+	type Signature = Signature;
+	type Signer = <Signature as Verify>::Signer;
+	// With 20-byte accounts, addresses already are accounts and links have no effect.
+	#[cfg(not(feature = "account-id-20"))]
+	type FallbackMapping = pallet_evm::HashedAddressMapping<BlakeTwo256>;
+	#[cfg(feature = "account-id-20")]
+	type FallbackMapping = pallet_evm::IdentityAddressMapping;
 }
 
 frame_support::parameter_types! {
@@ -491,6 +608,24 @@ impl pallet_base_fee::Config for Runtime {
 	type Threshold = BaseFeeThreshold;
 }
 
+parameter_types! {
+	pub InitialPrecompiles: Vec<(H160, KnownPrecompile)> = genesis_precompiles();
+}
+
+impl pallet_precompile_registry::Config for Runtime {
+	type Event = Event;
+	type PrecompileId = KnownPrecompile;
+	type InitialPrecompiles = InitialPrecompiles;
+}
+
+impl pallet_asset_registry::Config for Runtime {
+	type Event = Event;
+	type AssetId = AssetId;
+	type Assets = Assets;
+	type AssetAddress = pallet_evm_precompile_assets_erc20::AssetIdToAddress<AssetId>;
+	type RegisterOrigin = EnsureRoot<AccountId>;
+}
+
 parameter_types! {
 	pub const ReservedXcmpWeight: Weight = MAXIMUM_BLOCK_WEIGHT / 4;
 	pub const ReservedDmpWeight: Weight = MAXIMUM_BLOCK_WEIGHT / 4;
@@ -521,6 +656,7 @@ parameter_types! {
 	pub const RelayNetwork: NetworkId = NetworkId::Any;
 	pub RelayChainOrigin: Origin = cumulus_pallet_xcm::Origin::Relay.into();
 	pub Ancestry: MultiLocation = Parachain(ParachainInfo::parachain_id().into()).into();
+	pub CheckingAccount: AccountId = PolkadotXcm::check_account();
 }
 
 /// Type for specifying how a `MultiLocation` can be converted into an `AccountId`. This is used
@@ -531,10 +667,18 @@ pub type LocationToAccountId = (
 	ParentIsDefault<AccountId>,
 	// Sibling parachain origins convert to AccountId via the `ParaId::into`.
 	SiblingParachainConvertsVia<Sibling, AccountId>,
-	// Straight up local `AccountId32` origins just alias directly to `AccountId`.
-	AccountId32Aliases<RelayNetwork, AccountId>,
+	// Straight up local account origins just alias directly to `AccountId`.
+	LocalAccountAliases,
 );
 
+/// Converts an `AccountId32` location into the same 32-byte `AccountId`.
+#[cfg(not(feature = "account-id-20"))]
+pub type LocalAccountAliases = xcm_builder::AccountId32Aliases<RelayNetwork, AccountId>;
+
+/// Converts an `AccountKey20` location into the same 20-byte `AccountId`.
+#[cfg(feature = "account-id-20")]
+pub type LocalAccountAliases = xcm_builder::AccountKey20Aliases<RelayNetwork, AccountId>;
+
 /// Means for transacting assets on this chain.
 pub type LocalAssetTransactor = CurrencyAdapter<
 	// Use this currency:
@@ -549,6 +693,26 @@ pub type LocalAssetTransactor = CurrencyAdapter<
 	(),
 >;
 
+/// Means for transacting the assets of `pallet_assets`, foreign or local. Their locations are
+/// kept by `AssetRegistry`.
+pub type AssetsTransactor = FungiblesAdapter<
+	// Use this fungibles implementation:
+	Assets,
+	// Use this asset when it is a fungible asset registered at the given location:
+	ConvertedConcreteAssetId<AssetId, Balance, AssetRegistry, JustTry>,
+	// Convert an XCM MultiLocation into a local account id:
+	LocationToAccountId,
+	// Our chain's account ID type (we can't get away without mentioning it explicitly):
+	AccountId,
+	// We don't track any teleports of the assets.
+	Nothing,
+	// The account used to check teleports, unused.
+	CheckingAccount,
+>;
+
+/// Means for transacting assets on this chain: the native currency, then the registered assets.
+pub type AssetTransactors = (LocalAssetTransactor, AssetsTransactor);
+
 /// This is the type we use to convert an (incoming) XCM origin into a local `Origin` instance,
 /// ready for dispatching a transaction with Xcm's `Transact`. There is an `OriginKind` which can
 /// biases the kind of local `Origin` it will become.
@@ -563,13 +727,22 @@ pub type XcmOriginToTransactDispatchOrigin = (
 	// Native converter for sibling Parachains; will convert to a `SiblingPara` origin when
 	// recognized.
 	SiblingParachainAsNative<cumulus_pallet_xcm::Origin, Origin>,
-	// Native signed account converter; this just converts an `AccountId32` origin into a normal
-	// `Origin::Signed` origin of the same 32-byte value.
-	SignedAccountId32AsNative<RelayNetwork, Origin>,
-	// Xcm origins can be represented natively under the Xcm pallet's Xcm origin.
+	// Native signed account converter; this just converts an `AccountId32` (or `AccountKey20`)
+	// origin into a normal `Origin::Signed` origin of the same value.
+	SignedAccountAsNative,
+	// Xcm origins can be represented natively under the Xcm pallet's Xcm origin. `EthereumXcm`
+	// runs the Ethereum transactions of such origins from an address derived from the location.
 	XcmPassthrough<Origin>,
 );
 
+/// Converts an `AccountId32` origin into a signed origin of the same 32-byte account.
+#[cfg(not(feature = "account-id-20"))]
+pub type SignedAccountAsNative = xcm_builder::SignedAccountId32AsNative<RelayNetwork, Origin>;
+
+/// Converts an `AccountKey20` origin into a signed origin of the same 20-byte account.
+#[cfg(feature = "account-id-20")]
+pub type SignedAccountAsNative = xcm_builder::SignedAccountKey20AsNative<RelayNetwork, Origin>;
+
 parameter_types! {
 	// One XCM operation is 1_000_000_000 weight - almost certainly a conservative estimate.
 	pub UnitWeightCost: Weight = 1_000_000_000;
@@ -595,14 +768,20 @@ impl Config for XcmConfig {
 	type Call = Call;
 	type XcmSender = XcmRouter;
 	// How to withdraw and deposit an asset.
-	type AssetTransactor = LocalAssetTransactor;
+	type AssetTransactor = AssetTransactors;
 	type OriginConverter = XcmOriginToTransactDispatchOrigin;
-	type IsReserve = NativeAsset;
+	// Registered assets are accepted from their reserve chain.
+	type IsReserve = (NativeAsset, pallet_asset_registry::ReserveOfRegisteredAsset<Runtime>);
 	type IsTeleporter = (); // Teleporting is disabled.
 	type LocationInverter = LocationInverter<Ancestry>;
 	type Barrier = Barrier;
 	type Weigher = FixedWeightBounds<UnitWeightCost, Call, MaxInstructions>;
-	type Trader = UsingComponents<IdentityFee<Balance>, RelayLocation, AccountId, Balances, ()>;
+	// Execution is paid with the native currency, or with a registered asset having a fee rate,
+	// whose fees are burnt.
+	type Trader = (
+		UsingComponents<IdentityFee<Balance>, RelayLocation, AccountId, Balances, ()>,
+		pallet_asset_registry::UnitsPerSecondTrader<Runtime, ()>,
+	);
 	type ResponseHandler = PolkadotXcm;
 	type AssetTrap = PolkadotXcm;
 	type AssetClaims = PolkadotXcm;
@@ -614,7 +793,31 @@ parameter_types! {
 }
 
 /// No local origins on this chain are allowed to dispatch XCM sends/executions.
-pub type LocalOriginToLocation = SignedToAccountId32<Origin, AccountId, RelayNetwork>;
+#[cfg(not(feature = "account-id-20"))]
+pub type LocalOriginToLocation = xcm_builder::SignedToAccountId32<Origin, AccountId, RelayNetwork>;
+
+/// Local signed origins send and execute XCM as their `AccountKey20` location.
+#[cfg(feature = "account-id-20")]
+pub type LocalOriginToLocation = SignedToAccountKey20;
+
+/// Converts a signed origin into the `AccountKey20` location of its account.
+#[cfg(feature = "account-id-20")]
+pub struct SignedToAccountKey20;
+
+#[cfg(feature = "account-id-20")]
+impl xcm_executor::traits::Convert<Origin, MultiLocation> for SignedToAccountKey20 {
+	fn convert(origin: Origin) -> Result<MultiLocation, Origin> {
+		let caller: Result<frame_system::RawOrigin<AccountId>, Origin> = origin.into();
+		match caller {
+			Ok(frame_system::RawOrigin::Signed(who)) => Ok(MultiLocation::new(
+				0,
+				X1(AccountKey20 { network: RelayNetwork::get(), key: who.into() }),
+			)),
+			Ok(other) => Err(other.into()),
+			Err(other) => Err(other),
+		}
+	}
+}
 
 /// The means for routing XCM messages which are not for local execution into the right message
 /// queues.
@@ -764,6 +967,11 @@ construct_runtime!(
 		EVM: pallet_evm::{Pallet, Config, Call, Storage, Event<T>} = 42,
 		DynamicFee: pallet_dynamic_fee::{Pallet, Call, Storage, Config, Inherent} = 43,
 		BaseFee: pallet_base_fee::{Pallet, Call, Storage, Config<T>, Event} = 44,
+		PrecompileRegistry: pallet_precompile_registry::{Pallet, Call, Storage, Config<T>, Event<T>} = 45,
+		EthereumXcm: pallet_ethereum_xcm::{Pallet, Call} = 46,
+		Assets: pallet_assets::{Pallet, Call, Storage, Event<T>} = 47,
+		AssetRegistry: pallet_asset_registry::{Pallet, Call, Storage, Event<T>} = 48,
+		AccountLink: pallet_account_link::{Pallet, Call, Storage, Event<T>} = 49,
 	}
 );
 
@@ -901,7 +1109,7 @@ impl_runtime_apis! {
 		fn storage_at(address: H160, index: U256) -> H256 {
 			let mut tmp = [0u8; 32];
 			index.to_big_endian(&mut tmp);
-			EVM::account_storages(address, H256::from_slice(&tmp[..]))
+			EVM::account_storage(address, H256::from_slice(&tmp[..]))
 		}
 
 		fn call(
@@ -915,13 +1123,8 @@ impl_runtime_apis! {
 			nonce: Option<U256>,
 			estimate: bool,
 		) -> Result<pallet_evm::CallInfo, sp_runtime::DispatchError> {
-			let config = if estimate {
-				let mut config = <Runtime as pallet_evm::Config>::config().clone();
-				config.estimate = true;
-				Some(config)
-			} else {
-				None
-			};
+			let mut config = EVM::evm_config();
+			config.estimate = estimate;
 
 			<Runtime as pallet_evm::Config>::Runner::call(
 				from,
@@ -933,7 +1136,7 @@ impl_runtime_apis! {
 				max_priority_fee_per_gas,
 				nonce,
 				Vec::new(),
-				config.as_ref().unwrap_or(<Runtime as pallet_evm::Config>::config()),
+				&config,
 			).map_err(|err| err.into())
 		}
 
@@ -947,13 +1150,8 @@ impl_runtime_apis! {
 			nonce: Option<U256>,
 			estimate: bool,
 		) -> Result<pallet_evm::CreateInfo, sp_runtime::DispatchError> {
-			let config = if estimate {
-				let mut config = <Runtime as pallet_evm::Config>::config().clone();
-				config.estimate = true;
-				Some(config)
-			} else {
-				None
-			};
+			let mut config = EVM::evm_config();
+			config.estimate = estimate;
 
 			<Runtime as pallet_evm::Config>::Runner::create(
 				from,
@@ -964,7 +1162,7 @@ impl_runtime_apis! {
 				max_priority_fee_per_gas,
 				nonce,
 				Vec::new(),
-				config.as_ref().unwrap_or(<Runtime as pallet_evm::Config>::config()),
+				&config,
 			).map_err(|err| err.into())
 		}
 
@@ -1041,6 +1239,8 @@ impl_runtime_apis! {
 			list_benchmark!(list, extra, pallet_balances, Balances);
 			list_benchmark!(list, extra, pallet_timestamp, Timestamp);
 			list_benchmark!(list, extra, pallet_collator_selection, CollatorSelection);
+			list_benchmark!(list, extra, pallet_evm, EVM);
+			list_benchmark!(list, extra, pallet_ethereum, Ethereum);
 
 			let storage_info = AllPalletsWithSystem::storage_info();
 
@@ -1079,6 +1279,8 @@ impl_runtime_apis! {
 			add_benchmark!(params, batches, pallet_session, SessionBench::<Runtime>);
 			add_benchmark!(params, batches, pallet_timestamp, Timestamp);
 			add_benchmark!(params, batches, pallet_collator_selection, CollatorSelection);
+			add_benchmark!(params, batches, pallet_evm, EVM);
+			add_benchmark!(params, batches, pallet_ethereum, Ethereum);
 			add_benchmark!(params, batches, pallet_session, Session);
 
 			if batches.is_empty() { return Err("Benchmark not found for this pallet.".into()) }
@@ -1,30 +1,101 @@
+use codec::{Decode, Encode};
 use pallet_evm::{Context, Precompile, PrecompileResult, PrecompileSet};
+use scale_info::TypeInfo;
+#[cfg(feature = "std")]
+use serde::{Deserialize, Serialize};
 use sp_core::H160;
-use sp_std::marker::PhantomData;
+use sp_runtime::RuntimeDebug;
+use sp_std::{marker::PhantomData, vec::Vec};
 
+use frame_support::{
+	dispatch::{Dispatchable, GetDispatchInfo, PostDispatchInfo},
+	traits::Contains,
+};
+use pallet_evm_precompile_assets_erc20::Erc20AssetsPrecompileSet;
+use pallet_evm_precompile_balances_erc20::{Erc20BalancesPrecompile, Erc20Metadata};
+use pallet_evm_precompile_batch::Batch;
+use pallet_evm_precompile_blake2::Blake2F;
+use pallet_evm_precompile_bn128::{Bn128Add, Bn128Mul, Bn128Pairing};
+use pallet_evm_precompile_call_permit::CallPermitPrecompile;
+use pallet_evm_precompile_curve25519::{Curve25519Add, Curve25519ScalarMul};
+use pallet_evm_precompile_dispatch::Dispatch;
+use pallet_evm_precompile_ed25519::Ed25519Verify;
 use pallet_evm_precompile_modexp::Modexp;
-use pallet_evm_precompile_sha3fips::Sha3FIPS256;
+use pallet_evm_precompile_sha3fips::{Sha3FIPS256, Sha3FIPS512};
 use pallet_evm_precompile_simple::{ECRecover, ECRecoverPublicKey, Identity, Ripemd160, Sha256};
+use pallet_evm_precompile_xcm_transactor::XcmTransactorPrecompile;
+
+/// Precompiles the runtime knows how to execute. Their addresses are kept by
+/// `pallet_precompile_registry`.
+#[derive(Clone, Copy, PartialEq, Eq, Encode, Decode, RuntimeDebug, TypeInfo)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub enum KnownPrecompile {
+	ECRecover,
+	Sha256,
+	Ripemd160,
+	Identity,
+	Modexp,
+	Bn128Add,
+	Bn128Mul,
+	Bn128Pairing,
+	Blake2F,
+	Sha3FIPS256,
+	Sha3FIPS512,
+	ECRecoverPublicKey,
+	Curve25519Add,
+	Curve25519ScalarMul,
+	Ed25519Verify,
+	Dispatch,
+	NativeErc20,
+	Batch,
+	CallPermit,
+	XcmTransactor,
+}
+
+/// Precompiles enabled at genesis: the Ethereum ones up to Istanbul (0x01-0x09), the
+/// Frontier-specific ones at 1024 to 1026, the ERC-20 of the native token at 2050, the XCM
+/// transactor at 2054, the batch precompile at 2056 and the call permit precompile at 2058.
+pub fn genesis_precompiles() -> Vec<(H160, KnownPrecompile)> {
+	sp_std::vec![
+		(hash(1), KnownPrecompile::ECRecover),
+		(hash(2), KnownPrecompile::Sha256),
+		(hash(3), KnownPrecompile::Ripemd160),
+		(hash(4), KnownPrecompile::Identity),
+		(hash(5), KnownPrecompile::Modexp),
+		(hash(6), KnownPrecompile::Bn128Add),
+		(hash(7), KnownPrecompile::Bn128Mul),
+		(hash(8), KnownPrecompile::Bn128Pairing),
+		(hash(9), KnownPrecompile::Blake2F),
+		(hash(1024), KnownPrecompile::Sha3FIPS256),
+		(hash(1025), KnownPrecompile::ECRecoverPublicKey),
+		(hash(1026), KnownPrecompile::Dispatch),
+		(hash(2050), KnownPrecompile::NativeErc20),
+		(hash(2054), KnownPrecompile::XcmTransactor),
+		(hash(2056), KnownPrecompile::Batch),
+		(hash(2058), KnownPrecompile::CallPermit),
+	]
+}
 
 pub struct FrontierPrecompiles<R>(PhantomData<R>);
 
 impl<R> FrontierPrecompiles<R>
 where
-	R: pallet_evm::Config,
+	R: pallet_precompile_registry::Config<PrecompileId = KnownPrecompile>,
 {
 	pub fn new() -> Self {
 		Self(Default::default())
 	}
-	pub fn used_addresses() -> sp_std::vec::Vec<H160> {
-		sp_std::vec![1, 2, 3, 4, 5, 1024, 1025]
-			.into_iter()
-			.map(|x| hash(x))
-			.collect()
-	}
 }
 impl<R> PrecompileSet for FrontierPrecompiles<R>
 where
-	R: pallet_evm::Config,
+	R: pallet_precompile_registry::Config<PrecompileId = KnownPrecompile>,
+	R::Call: Dispatchable<PostInfo = PostDispatchInfo> + GetDispatchInfo + Decode,
+	<R::Call as Dispatchable>::Origin: From<Option<R::AccountId>>,
+	DispatchCallFilter: Contains<R::Call>,
+	Erc20BalancesPrecompile<R, NativeErc20Metadata>: Precompile,
+	CallPermitPrecompile<R>: Precompile,
+	XcmTransactorPrecompile<R, crate::RelayLocation>: Precompile,
+	Erc20AssetsPrecompileSet<R>: PrecompileSet,
 {
 	fn execute(
 		&self,
@@ -34,29 +105,145 @@ where
 		context: &Context,
 		is_static: bool,
 	) -> Option<PrecompileResult> {
-		match address {
+		// Addresses not in the registry may be the ERC-20 of an asset.
+		let precompile = match pallet_precompile_registry::Pallet::<R>::precompile_at(address) {
+			Some(precompile) => precompile,
+			None => {
+				return Erc20AssetsPrecompileSet::<R>::new()
+					.execute(address, input, target_gas, context, is_static)
+			}
+		};
+		Some(match precompile {
 			// Ethereum precompiles :
-			a if a == hash(1) => Some(ECRecover::execute(input, target_gas, context, is_static)),
-			a if a == hash(2) => Some(Sha256::execute(input, target_gas, context, is_static)),
-			a if a == hash(3) => Some(Ripemd160::execute(input, target_gas, context, is_static)),
-			a if a == hash(4) => Some(Identity::execute(input, target_gas, context, is_static)),
-			a if a == hash(5) => Some(Modexp::execute(input, target_gas, context, is_static)),
+			KnownPrecompile::ECRecover => ECRecover::execute(input, target_gas, context, is_static),
+			KnownPrecompile::Sha256 => Sha256::execute(input, target_gas, context, is_static),
+			KnownPrecompile::Ripemd160 => Ripemd160::execute(input, target_gas, context, is_static),
+			KnownPrecompile::Identity => Identity::execute(input, target_gas, context, is_static),
+			KnownPrecompile::Modexp => Modexp::execute(input, target_gas, context, is_static),
+			KnownPrecompile::Bn128Add => Bn128Add::execute(input, target_gas, context, is_static),
+			KnownPrecompile::Bn128Mul => Bn128Mul::execute(input, target_gas, context, is_static),
+			KnownPrecompile::Bn128Pairing => {
+				Bn128Pairing::execute(input, target_gas, context, is_static)
+			}
+			KnownPrecompile::Blake2F => Blake2F::execute(input, target_gas, context, is_static),
 			// Non-Frontier specific nor Ethereum precompiles :
-			a if a == hash(1024) => {
-				Some(Sha3FIPS256::execute(input, target_gas, context, is_static))
+			KnownPrecompile::Sha3FIPS256 => {
+				Sha3FIPS256::execute(input, target_gas, context, is_static)
+			}
+			KnownPrecompile::Sha3FIPS512 => {
+				Sha3FIPS512::execute(input, target_gas, context, is_static)
+			}
+			KnownPrecompile::ECRecoverPublicKey => {
+				ECRecoverPublicKey::execute(input, target_gas, context, is_static)
 			}
-			a if a == hash(1025) => Some(ECRecoverPublicKey::execute(
-				input, target_gas, context, is_static,
-			)),
-			_ => None,
-		}
+			KnownPrecompile::Curve25519Add => {
+				Curve25519Add::execute(input, target_gas, context, is_static)
+			}
+			KnownPrecompile::Curve25519ScalarMul => {
+				Curve25519ScalarMul::execute(input, target_gas, context, is_static)
+			}
+			KnownPrecompile::Ed25519Verify => {
+				Ed25519Verify::execute(input, target_gas, context, is_static)
+			}
+			// These act on behalf of the caller, which must not be spoofed by delegating.
+			KnownPrecompile::Dispatch
+			| KnownPrecompile::NativeErc20
+			| KnownPrecompile::Batch
+			| KnownPrecompile::CallPermit
+			| KnownPrecompile::XcmTransactor
+				if context.address != address =>
+			{
+				Err(precompile_utils::revert("cannot be called with DELEGATECALL or CALLCODE"))
+			}
+			KnownPrecompile::Dispatch => {
+				Dispatch::<R, DispatchCallFilter>::execute(input, target_gas, context, is_static)
+			}
+			KnownPrecompile::NativeErc20 => {
+				Erc20BalancesPrecompile::<R, NativeErc20Metadata>::execute(
+					input, target_gas, context, is_static,
+				)
+			}
+			KnownPrecompile::Batch => Batch::<R>::execute(input, target_gas, context, is_static),
+			KnownPrecompile::CallPermit => {
+				CallPermitPrecompile::<R>::execute(input, target_gas, context, is_static)
+			}
+			KnownPrecompile::XcmTransactor => {
+				XcmTransactorPrecompile::<R, crate::RelayLocation>::execute(
+					input, target_gas, context, is_static,
+				)
+			}
+		})
 	}
 
 	fn is_precompile(&self, address: H160) -> bool {
-		Self::used_addresses().contains(&address)
+		pallet_precompile_registry::Pallet::<R>::is_precompile(&address)
+			|| Erc20AssetsPrecompileSet::<R>::new().is_precompile(address)
+	}
+}
+
+/// Calls that contracts can dispatch through the `Dispatch` precompile. Calls entering the EVM,
+/// privileged calls and XCM are excluded. `AccountLink::link_account` is meant to be called by
+/// the fallback account of an address, which is the origin of the calls of this precompile.
+pub struct DispatchCallFilter;
+
+impl Contains<crate::Call> for DispatchCallFilter {
+	fn contains(call: &crate::Call) -> bool {
+		matches!(
+			call,
+			crate::Call::Balances(pallet_balances::Call::transfer { .. })
+				| crate::Call::Balances(pallet_balances::Call::transfer_keep_alive { .. })
+				| crate::Call::Balances(pallet_balances::Call::transfer_all { .. })
+				| crate::Call::TemplatePallet(_)
+				| crate::Call::AccountLink(pallet_account_link::Call::link_account { .. })
+				| crate::Call::AccountLink(pallet_account_link::Call::unlink { .. })
+		)
+	}
+}
+
+/// Metadata of the native token, as shown by wallets.
+pub struct NativeErc20Metadata;
+
+impl Erc20Metadata for NativeErc20Metadata {
+	fn name() -> &'static str {
+		"Unit"
+	}
+
+	fn symbol() -> &'static str {
+		"UNIT"
+	}
+
+	fn decimals() -> u8 {
+		12
 	}
 }
 
 fn hash(a: u64) -> H160 {
 	H160::from_low_u64_be(a)
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{AccountId, Call, Signature};
+
+	fn zeros<T: Decode>() -> T {
+		T::decode(&mut &[0u8; 65][..]).unwrap()
+	}
+
+	#[test]
+	fn account_link_can_be_dispatched_by_contracts() {
+		assert!(DispatchCallFilter::contains(&Call::AccountLink(
+			pallet_account_link::Call::link_account {
+				address: H160::zero(),
+				account: zeros::<AccountId>(),
+				signature: zeros::<Signature>(),
+			}
+		)));
+		assert!(DispatchCallFilter::contains(&Call::AccountLink(
+			pallet_account_link::Call::unlink {}
+		)));
+		assert!(!DispatchCallFilter::contains(&Call::AccountLink(
+			pallet_account_link::Call::link_address { address: H160::zero(), signature: [0; 65] }
+		)));
+	}
+}